@@ -1,34 +1,47 @@
 use byd::{
-	BasicMaterial, Camera, Color, Event, FreeCamera, Geometry, Mesh, MouseButton, Renderer, Scene,
-	SimpleVertex, Window,
+	BasicMaterial, Camera, Color, Event, FreeCamera, Geometry, Instance, InstancedMesh, Key, Mesh,
+	MouseButton, ObjectID, OrthographicCamera, RenderTarget, Renderer, Scene, SimpleVertex,
+	Texture, TextureMaterial, Viewport, Window,
 };
-use cgmath::{Euler, Matrix4, Rad, Vector3};
+use cgmath::{Euler, Matrix4, Point2, Rad, Vector3};
+use std::f32::consts::PI;
+
+/// Width and height of the monitor's render target
+const MONITOR_SIZE: u32 = 256;
 
 pub struct App {
 	window: Option<Window>,
 	scene: Scene,
 	camera: FreeCamera,
 	renderer: Renderer,
+	width: u32,
+	height: u32,
+
+	cubes_id: ObjectID,
+	/// Shows the cubes from above, on a screen behind them
+	monitor: RenderTarget,
+	monitor_camera: FreeCamera,
 
-	cube: Mesh<SimpleVertex>,
-	cube_ids: Vec<usize>,
+	/// Whether the window is split into top, front, side and perspective views
+	split: bool,
+	/// Cameras of the top, front and side views
+	view_cameras: [OrthographicCamera; 3],
 }
 
 impl App {
 	pub async fn new(width: u32, height: u32) -> Self {
 		let window = Window::new(width, height);
 		let mut renderer = Renderer::new(width, height).await;
-		renderer.attach(&window);
-		let scene = Scene::new();
+		renderer
+			.attach(&window)
+			.expect("Failed to attach renderer to window");
+		let mut scene = Scene::new();
 		let camera = FreeCamera::new();
-		let mut cube: Mesh<SimpleVertex> = Mesh::new(
-			Geometry::cube(),
-			BasicMaterial::new(Color::new(1.0, 0.0, 1.0, 1.0)),
-		);
+		let mut geometry: Geometry<SimpleVertex> = Geometry::cube();
 
 		// Calculate normals
 		// FIXME Geometry should do this
-		for tri in cube.geometry_mut().vertices_mut().chunks_mut(3) {
+		for tri in geometry.vertices_mut().chunks_mut(3) {
 			let u = tri[1].position - tri[0].position;
 			let v = tri[2].position - tri[0].position;
 
@@ -38,45 +51,117 @@ impl App {
 			tri[2].normal = normal;
 		}
 
+		// Every cube is an instance of one mesh, drawn with a single draw call
+		let cubes = InstancedMesh::new(
+			geometry,
+			BasicMaterial::new(Color::new(1.0, 1.0, 1.0, 1.0)),
+			vec![],
+		);
+		let cubes_id = scene.add(cubes);
+
+		let monitor = renderer.create_render_target(MONITOR_SIZE, MONITOR_SIZE);
+		let monitor_texture_id = scene.add_texture(Texture::from_render_target(&monitor));
+		let mut monitor_geometry: Geometry<SimpleVertex> = Geometry::cube();
+		for vertex in monitor_geometry.vertices_mut() {
+			let position = vertex.position;
+			vertex.uv = Point2::new(0.5 - position.x * 0.5, 0.5 - position.y * 0.5);
+		}
+		let mut monitor_mesh =
+			Mesh::new(monitor_geometry, TextureMaterial::new(monitor_texture_id));
+		monitor_mesh.transform = Matrix4::from_translation(Vector3::new(0.0, 0.0, 40.0))
+			* Matrix4::from_nonuniform_scale(10.0, 10.0, 0.2);
+		scene.add(monitor_mesh);
+
+		let mut monitor_camera = FreeCamera::new();
+		monitor_camera.resize(MONITOR_SIZE as _, MONITOR_SIZE as _);
+		monitor_camera.translate(0.0, 30.0, 10.0);
+		monitor_camera.rotate(1.2, 0.0, 0.0);
+
+		// Each view fits the area the cubes are added in
+		let view_camera = || OrthographicCamera::new().with_view_height(30.0);
+		let mut top_camera = view_camera();
+		top_camera.translate(0.0, 40.0, 10.0);
+		top_camera.rotate(PI / 2.0, 0.0, 0.0);
+		let mut front_camera = view_camera();
+		front_camera.translate(0.0, 0.0, -25.0);
+		let mut side_camera = view_camera();
+		side_camera.translate(-35.0, 0.0, 10.0);
+		side_camera.rotate(0.0, PI / 2.0, 0.0);
+
 		Self {
 			window: Some(window),
 			scene,
 			camera,
 			renderer,
+			width,
+			height,
 
-			cube,
-			cube_ids: vec![],
+			cubes_id,
+			monitor,
+			monitor_camera,
+
+			split: false,
+			view_cameras: [top_camera, front_camera, side_camera],
 		}
 	}
 }
 
 impl App {
 	pub fn add_cube(&mut self, x: f32, y: f32, z: f32) {
-		let mut cube = Mesh::new(
-			self.cube.geometry().clone(),
-			BasicMaterial::new(Color::new(
+		let cube =
+			Instance::new(Matrix4::from_translation(Vector3::new(x, y, z))).with_color(Color::new(
 				rand::random::<f32>(),
 				rand::random::<f32>(),
 				rand::random::<f32>(),
 				1.0,
-			)),
-		);
-		cube.transform = Matrix4::from_translation(Vector3::new(x, y, z));
-		self.cube_ids.push(self.scene.add(cube));
+			));
+		self.scene
+			.with_object_mut(self.cubes_id, |cubes: &mut InstancedMesh<SimpleVertex>| {
+				cubes.instances_mut().push(cube);
+			});
 	}
 
 	pub fn update(&mut self, dt: f32) {
-		for id in &self.cube_ids {
-			self.scene
-				.with_object_mut(*id, |cube: &mut Mesh<SimpleVertex>| {
+		self.scene
+			.with_object_mut(self.cubes_id, |cubes: &mut InstancedMesh<SimpleVertex>| {
+				for cube in cubes.instances_mut() {
 					cube.transform = cube.transform
 						* Matrix4::from(Euler::new(Rad(0.0), Rad(1.0 * dt), Rad(0.623 * dt)));
-				});
-		}
+				}
+			});
 	}
 
 	pub fn render(&mut self, _dt: f32) {
-		if let Err(error) = self.renderer.render(&mut self.scene, &self.camera) {
+		// The monitor itself is skipped when drawing its own view
+		if let Err(error) =
+			self.renderer
+				.render_to_target(&mut self.scene, &self.monitor_camera, &self.monitor)
+		{
+			log::error!("Error rendering monitor: {:?}", error);
+		}
+		if self.split {
+			self.render_split();
+		} else if let Err(error) = self.renderer.render(&mut self.scene, &self.camera) {
+			log::error!("Error rendering scene: {:?}", error);
+		}
+	}
+
+	/// Draw the top, front and side views and the perspective view in the four corners.
+	fn render_split(&mut self) {
+		let viewports = Viewport::grid(self.width, self.height, 2, 2);
+		let mut frame = self.renderer.begin_frame();
+		let cameras = self
+			.view_cameras
+			.iter_mut()
+			.map(|camera| camera as &mut dyn Camera)
+			.chain([&mut self.camera as &mut dyn Camera]);
+		for (camera, viewport) in cameras.zip(viewports) {
+			if let Err(error) = frame.render(&mut self.scene, camera, viewport) {
+				log::error!("Error rendering view: {:?}", error);
+			}
+		}
+		drop(frame);
+		if let Err(error) = self.renderer.render_to_surface() {
 			log::error!("Error rendering scene: {:?}", error);
 		}
 	}
@@ -92,6 +177,12 @@ impl App {
 
 		let window = self.window.take().unwrap();
 		window.run(move |event, _| match event {
+			Event::KeyDown(Key::V) => {
+				self.split = !self.split;
+				if !self.split {
+					self.camera.resize(self.width as _, self.height as _);
+				}
+			}
 			Event::MouseDown(MouseButton::Left, _x, _y) => {
 				self.add_cube(
 					(rand::random::<f32>() - 0.5) * 20.0,
@@ -100,9 +191,12 @@ impl App {
 				);
 			}
 			Event::MouseDown(MouseButton::Right, _x, _y) => {
-				if let Some(id) = self.cube_ids.pop() {
-					self.scene.remove(id);
-				}
+				self.scene.with_object_mut(
+					self.cubes_id,
+					|cubes: &mut InstancedMesh<SimpleVertex>| {
+						cubes.instances_mut().pop();
+					},
+				);
 			}
 			Event::Draw(elapsed) => {
 				let dt = elapsed.as_secs_f32();
@@ -113,6 +207,8 @@ impl App {
 				log::debug!("Window resized {}x{}", width, height);
 				self.renderer.resize(width, height);
 				self.camera.resize(width as _, height as _);
+				self.width = width;
+				self.height = height;
 			}
 			_ => {}
 		});
@@ -1,8 +1,10 @@
 use byd::{
-	Camera, Event, FreeCamera, Geometry, Gltf, Key, Mesh, MouseButton, PrimitiveVertex, Renderer,
-	Scene, SimpleVertex, Texture, TextureMaterial, Window,
+	Camera, Event, FreeCamera, Geometry, Gltf, Group, Key, Mesh, MouseButton, ObjectID,
+	PbrMaterial, PrimitiveVertex, Renderer, Scene, SimpleVertex, Texture, TextureMaterial,
+	Tonemapping, Window,
 };
 use cgmath::{Euler, Matrix4, Point2, Point3, Rad, Vector3};
+use image::{DynamicImage, Rgba, RgbaImage};
 use std::{collections::HashSet, error::Error};
 
 pub struct App {
@@ -13,15 +15,24 @@ pub struct App {
 	camera_dampening: Vector3<f32>,
 	renderer: Renderer,
 	held_keys: HashSet<Key>,
-	objects: Vec<usize>,
-	textures: Vec<usize>,
+	objects: Vec<ObjectID>,
+	/// Meshes of the duck, which can be selected by clicking on it
+	duck: Vec<ObjectID>,
+	selected: bool,
 }
 
 impl App {
 	pub async fn new(width: u32, height: u32) -> Self {
 		let window = Window::new(width, height);
-		let mut renderer = Renderer::new(width, height).await;
-		renderer.attach(&window);
+		// HDR, so the emissive parts of the models don't clip
+		let mut renderer = Renderer::builder(width, height)
+			.hdr()
+			.build()
+			.await
+			.expect("Failed to create renderer");
+		renderer
+			.attach(&window)
+			.expect("Failed to attach renderer to window");
 		let scene = Scene::new();
 
 		let mut camera = FreeCamera::new();
@@ -37,7 +48,8 @@ impl App {
 			renderer,
 			held_keys: HashSet::with_capacity(16),
 			objects: vec![],
-			textures: vec![],
+			duck: vec![],
+			selected: false,
 		}
 	}
 }
@@ -47,12 +59,10 @@ impl App {
 		self.update_camera(dt);
 
 		for id in &self.objects {
-			self.scene
-				.with_object_mut(*id, |cube: &mut Mesh<PrimitiveVertex>| {
-					cube.transform =
-						Matrix4::from(Euler::new(Rad(0.0), Rad(1.0 * dt), Rad(0.0 * dt)))
-							* cube.transform;
-				});
+			self.scene.with_object_mut(*id, |group: &mut Group| {
+				group.transform = Matrix4::from(Euler::new(Rad(0.0), Rad(1.0 * dt), Rad(0.0 * dt)))
+					* group.transform;
+			});
 		}
 	}
 
@@ -62,35 +72,71 @@ impl App {
 		}
 	}
 
+	/// Select the duck if it's under the cursor, and deselect it otherwise.
+	fn click(&mut self, x: f32, y: f32) {
+		let picked = match self.renderer.pick(&mut self.scene, &self.camera, x, y) {
+			Ok(picked) => picked,
+			Err(error) => {
+				log::error!("Error picking object: {:?}", error);
+				return;
+			}
+		};
+		let selected = picked.is_some_and(|id| self.duck.contains(&id));
+		if selected == self.selected {
+			return;
+		}
+		self.selected = selected;
+		log::info!("Duck {}", if selected { "selected" } else { "deselected" });
+
+		// Highlight the selected duck by making it glow
+		let emissive = if selected {
+			Vector3::new(0.3, 0.2, 0.0)
+		} else {
+			Vector3::new(0.0, 0.0, 0.0)
+		};
+		for id in &self.duck {
+			self.scene
+				.with_object_mut(*id, |mesh: &mut Mesh<PrimitiveVertex>| {
+					if let Some(material) = mesh.material.downcast_mut::<PbrMaterial>() {
+						material.emissive = emissive;
+					}
+				});
+		}
+	}
+
 	fn build_scene(&mut self) {
 		self.build_floor();
 		self.load_thingy().expect("Failed to load thingy mesh");
 	}
 
 	fn load_thingy(&mut self) -> Result<(), Box<dyn Error>> {
-		let mut thingy = Gltf::load("./examples/loading/assets/duck/Duck.gltf")?;
-
-		for texture in thingy.textures.drain(..) {
-			self.textures.push(self.scene.add_texture(texture));
-		}
+		let thingy = Gltf::load("./examples/loading/assets/duck/Duck.gltf")?;
 
-		for (i, mut mesh) in thingy.meshes.drain(..).enumerate() {
-			mesh.transform = Matrix4::from_translation(Vector3::new(0.0, 3.0, 0.0))
-				* Matrix4::from_scale(3.0)
-				* mesh.transform;
-			let texture_id = self.textures[thingy.mesh_textures.remove(&i).unwrap()];
-			mesh.set_material(TextureMaterial::new(texture_id));
-			self.objects.push(self.scene.add(mesh));
+		// Move the whole file by parenting its nodes to one group
+		let group = self.scene.add(Group::new(
+			Matrix4::from_translation(Vector3::new(0.0, 3.0, 0.0)) * Matrix4::from_scale(3.0),
+		));
+		let objects = thingy.add_to_scene(&mut self.scene);
+		for root in objects.roots {
+			self.scene.set_parent(root, Some(group))?;
 		}
+		self.objects.push(group);
+		self.duck = objects.meshes;
 
 		Ok(())
 	}
 
 	fn build_floor(&mut self) {
-		let texture_id = self.scene.add_texture(
-			Texture::from_image_bytes(include_bytes!("../../assets/checker.tif"))
-				.expect("Failed to load texture"),
-		);
+		let checker = RgbaImage::from_fn(64, 64, |x, y| {
+			if (x / 8 + y / 8) % 2 == 0 {
+				Rgba([200, 200, 200, 255])
+			} else {
+				Rgba([80, 80, 80, 255])
+			}
+		});
+		let texture_id = self
+			.scene
+			.add_texture(Texture::from_image(DynamicImage::ImageRgba8(checker)));
 		let mut floor = Mesh::new(
 			Geometry::new(vec![
 				SimpleVertex {
@@ -149,12 +195,21 @@ impl App {
 					ctx.release_mouse();
 				}
 			}
+			Event::KeyDown(Key::T) => {
+				let tonemapping = match self.renderer.tonemapping() {
+					Tonemapping::None => Tonemapping::Reinhard,
+					Tonemapping::Reinhard => Tonemapping::Aces,
+					Tonemapping::Aces => Tonemapping::None,
+				};
+				log::info!("Tonemapping {:?}", tonemapping);
+				self.renderer.set_tonemapping(tonemapping);
+			}
 			Event::KeyDown(_) => self.held_keys = ctx.held_keys().clone(),
 			Event::KeyUp(_) => self.held_keys = ctx.held_keys().clone(),
 			Event::MouseMotion(x, y) => {
 				self.camera.rotate(y / 500.0, x / 500.0, 0.0);
 			}
-			Event::MouseDown(MouseButton::Left, _x, _y) => {}
+			Event::MouseDown(MouseButton::Left, x, y) => self.click(x, y),
 			Event::MouseDown(MouseButton::Right, _x, _y) => {}
 			Event::Draw(elapsed) => {
 				let dt = elapsed.as_secs_f32();
@@ -1,9 +1,9 @@
 use byd::{
-	BasicMaterial, Camera, Color, CreateColor, CustomMaterial, Event, FreeCamera, Geometry, Mesh,
-	Renderer, Scene, SimpleProgram, SimpleVertex, Vertex, Window,
+	Camera, Color, CreateColor, CustomMaterial, Event, FreeCamera, Geometry, Key, Mesh, PostEffect,
+	PostEffectID, Renderer, Scene, SimpleProgram, Vertex, Window,
 };
 use byd_derive::CastBytes;
-use cgmath::{Euler, Matrix4, Point2, Point3, Rad, Vector3};
+use cgmath::{Euler, Matrix4, Point2, Point3, Rad, Vector3, Vector4};
 use std::mem;
 use wgpu::VertexFormat::{Float32x2, Float32x3, Float32x4};
 
@@ -26,16 +26,26 @@ pub struct App {
 	scene: Scene,
 	camera: FreeCamera,
 	renderer: Renderer,
+	film_effect_id: PostEffectID,
 }
 
 impl App {
 	pub async fn new(width: u32, height: u32) -> Self {
 		let window = Window::new(width, height);
 		let mut renderer = Renderer::new(width, height).await;
-		renderer.attach(&window);
+		renderer
+			.attach(&window)
+			.expect("Failed to attach renderer to window");
 		let mut scene = Scene::new();
 		let camera = FreeCamera::new();
 
+		// Grain and color grading, followed by a vignette
+		let film_effect_id = renderer.add_post_effect(
+			PostEffect::new(include_str!("./shaders/film.wgsl"))
+				.with_params(Vector4::new(0.15, 1.1, 0.0, 0.0)),
+		);
+		renderer.add_post_effect(PostEffect::vignette(0.5, 0.4));
+
 		let color_pipeline: SimpleProgram<ColorVertex> =
 			SimpleProgram::new().shader(include_str!("./shaders/color.wgsl"));
 		let color_pipeline_id = scene.add_program(color_pipeline);
@@ -59,6 +69,7 @@ impl App {
 			scene,
 			camera,
 			renderer,
+			film_effect_id,
 		}
 	}
 }
@@ -75,6 +86,12 @@ impl App {
 	pub fn run(mut self) {
 		let window = self.window.take().unwrap();
 		window.run(move |event, _| match event {
+			Event::KeyDown(Key::P) => {
+				if let Some(effect) = self.renderer.post_effect_mut(self.film_effect_id) {
+					effect.enabled = !effect.enabled;
+					log::info!("Film effect {}", if effect.enabled { "on" } else { "off" });
+				}
+			}
 			Event::Draw(elapsed) => {
 				let dt = elapsed.as_secs_f32();
 				self.update(dt);
@@ -91,6 +108,10 @@ impl App {
 }
 
 impl Vertex for ColorVertex {
+	fn position(&self) -> Option<Point3<f32>> {
+		Some(self.position)
+	}
+
 	fn buffer_layout<'a>() -> wgpu::VertexBufferLayout<'a> {
 		wgpu::VertexBufferLayout {
 			array_stride: mem::size_of::<Self>() as _,
@@ -112,6 +133,10 @@ impl Vertex for ColorVertex {
 }
 
 impl Vertex for TextureVertex {
+	fn position(&self) -> Option<Point3<f32>> {
+		Some(self.position)
+	}
+
 	fn buffer_layout<'a>() -> wgpu::VertexBufferLayout<'a> {
 		wgpu::VertexBufferLayout {
 			array_stride: mem::size_of::<Self>() as _,
@@ -0,0 +1,21 @@
+// Film look: a warm color grade with extra contrast, and animated grain.
+// params.x: grain strength, params.y: contrast
+
+fn random(seed: vec2<f32>) -> f32 {
+	return fract(sin(dot(seed, vec2<f32>(12.9898, 78.233))) * 43758.5453);
+}
+
+[[stage(fragment)]]
+fn fs_main(in: PostInput) -> [[location(0)]] vec4<f32> {
+	let color = sample_color(in.uv);
+
+	// Grade
+	var graded = color.rgb * vec3<f32>(1.08, 1.0, 0.88);
+	graded = (graded - vec3<f32>(0.5)) * post.params.y + vec3<f32>(0.5);
+
+	// Grain, stronger in the shadows
+	let noise = random(in.uv * post.resolution + vec2<f32>(post.time * 60.0)) - 0.5;
+	let grain = noise * post.params.x * (1.0 - luminance(graded));
+
+	return vec4<f32>(clamp(graded + vec3<f32>(grain), vec3<f32>(0.0), vec3<f32>(1.0)), color.a);
+}
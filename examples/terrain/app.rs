@@ -2,9 +2,18 @@ use std::collections::HashSet;
 
 use crate::Terrain;
 use byd::{
-	Camera, Event, FreeCamera, Key, MouseButton, Renderer, Scene, Texture, TextureMaterial, Window,
+	Camera, Color, Event, FreeCamera, Key, Light, MouseButton, ObjectID, Ray, Renderer, Scene,
+	Texture, TextureMaterial, Window,
 };
-use cgmath::{Matrix4, Vector3};
+use cgmath::{Matrix4, Point3, Vector3};
+use wgpu::PresentMode;
+
+/// Number of terrain chunks along each side of the grid
+const CHUNKS: u32 = 5;
+/// Width of each terrain chunk
+const CHUNK_SIZE: u32 = 65;
+/// Height of the camera above the ground when walking
+const EYE_HEIGHT: f32 = 2.0;
 
 pub struct App {
 	window: Option<Window>,
@@ -14,19 +23,41 @@ pub struct App {
 	camera_dampening: Vector3<f32>,
 	renderer: Renderer,
 	terrain: Terrain,
-	terrain_id: usize,
+	terrain_ids: Vec<ObjectID>,
 	held_keys: HashSet<Key>,
+	/// Whether the camera is kept on the ground
+	walking: bool,
 }
 
 impl App {
 	pub async fn new(width: u32, height: u32) -> Self {
 		let window = Window::new(width, height);
-		let mut renderer = Renderer::new(width, height).await;
-		renderer.attach(&window);
-		let scene = Scene::new();
+		// Multisampled to smooth the edges of the hills
+		let mut renderer = Renderer::builder(width, height)
+			.sample_count(4)
+			.clear_color(Color::new(0.45, 0.6, 0.8, 1.0))
+			.build()
+			.await
+			.expect("Failed to create renderer");
+		renderer
+			.attach(&window)
+			.expect("Failed to attach renderer to window");
+		let mut scene = Scene::new();
+		scene.add_light(
+			Light::directional(
+				Vector3::new(0.5, -0.6, 0.4),
+				Vector3::new(1.0, 0.95, 0.85),
+				2.5,
+			)
+			.with_shadows(),
+		);
+		scene.set_shadow_distance(300.0);
 		let terrain = Terrain::new();
 
-		let mut camera = FreeCamera::new();
+		// Reverse-Z keeps distant hills from fighting over depth, even with no far plane
+		let mut camera = FreeCamera::new()
+			.with_clip_planes(0.1, f32::INFINITY)
+			.with_reverse_z(true);
 		camera.translate(0.0, 10.0, -10.0);
 		camera.rotate(0.3, 0.0, 0.0);
 
@@ -38,8 +69,9 @@ impl App {
 			camera_dampening: Vector3::new(5.0, 5.0, 5.0),
 			renderer,
 			terrain,
-			terrain_id: 0,
+			terrain_ids: vec![],
 			held_keys: HashSet::with_capacity(16),
+			walking: false,
 		}
 	}
 }
@@ -61,15 +93,22 @@ impl App {
 				.expect("Failed to load grass texture"),
 		);
 
-		let mut terrain = self.terrain.generate_mesh(0, 0);
-		terrain.transform = Matrix4::from_translation(Vector3::new(0.0, 0.0, 50.0));
-		terrain
-			.material
-			.downcast_mut::<TextureMaterial>()
-			.unwrap()
-			.texture_id = grass_texture_id;
+		// A grid of chunks, most of which are culled at any time
+		for x in 0..CHUNKS {
+			for z in 0..CHUNKS {
+				let mut terrain = self.terrain.generate_mesh(x * CHUNK_SIZE, z * CHUNK_SIZE);
+				let offset = (CHUNKS * CHUNK_SIZE) as f32 / 2.0;
+				terrain.transform =
+					Matrix4::from_translation(Vector3::new(-offset, 0.0, 50.0 - offset));
+				terrain
+					.material
+					.downcast_mut::<TextureMaterial>()
+					.unwrap()
+					.texture_id = grass_texture_id;
 
-		self.terrain_id = self.scene.add(terrain);
+				self.terrain_ids.push(self.scene.add(terrain));
+			}
+		}
 
 		let window = self.window.take().unwrap();
 		let mut grabbed = false;
@@ -84,6 +123,31 @@ impl App {
 					ctx.release_mouse();
 				}
 			}
+			Event::KeyDown(Key::C) => {
+				log::info!(
+					"Culled {} of {} terrain chunks",
+					self.scene.culled_objects(),
+					self.terrain_ids.len()
+				);
+				let stats = self.scene.render_stats();
+				log::info!(
+					"{} draw calls, {} state changes",
+					stats.draw_calls,
+					stats.state_changes()
+				);
+			}
+			Event::KeyDown(Key::V) => {
+				let present_mode = match self.renderer.present_mode() {
+					PresentMode::Fifo => PresentMode::Immediate,
+					_ => PresentMode::Fifo,
+				};
+				log::info!("Present mode {:?}", present_mode);
+				self.renderer.set_present_mode(present_mode);
+			}
+			Event::KeyDown(Key::G) => {
+				self.walking = !self.walking;
+				log::info!("Walk mode {}", if self.walking { "on" } else { "off" });
+			}
 			Event::KeyDown(_) => self.held_keys = ctx.held_keys().clone(),
 			Event::KeyUp(_) => self.held_keys = ctx.held_keys().clone(),
 			Event::MouseMotion(x, y) => {
@@ -132,6 +196,22 @@ impl App {
 			self.camera_velocity.y * dt,
 			self.camera_velocity.z * dt,
 		);
+
+		if self.walking {
+			self.walk();
+		}
+	}
+
+	/// Put the camera on the ground below it, found with a ray cast down from above the terrain.
+	fn walk(&mut self) {
+		let position = *self.camera.position();
+		let ray = Ray::new(
+			Point3::new(position.x, 1000.0, position.z),
+			Vector3::new(0.0, -1.0, 0.0),
+		);
+		if let Some(hit) = self.scene.raycast(&ray) {
+			self.camera.position_mut().y = hit.position.y + EYE_HEIGHT;
+		}
 	}
 
 	fn dampen_camera(&mut self, dt: f32) {
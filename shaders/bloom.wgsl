@@ -0,0 +1,24 @@
+let BLOOM_TAPS: i32 = 4;
+
+// params.x: brightness threshold, params.y: intensity, params.z: radius in pixels
+//
+// Blurs the bright parts of the frame in a single pass, and adds them back on top.
+[[stage(fragment)]]
+fn fs_main(in: PostInput) -> [[location(0)]] vec4<f32> {
+	let color = sample_color(in.uv);
+	let step = post.params.z / f32(BLOOM_TAPS) / post.resolution;
+
+	var bloom = vec3<f32>(0.0);
+	var total = 0.0;
+	for (var x = -BLOOM_TAPS; x <= BLOOM_TAPS; x = x + 1) {
+		for (var y = -BLOOM_TAPS; y <= BLOOM_TAPS; y = y + 1) {
+			let offset = vec2<f32>(f32(x), f32(y));
+			let weight = exp(-dot(offset, offset) / f32(BLOOM_TAPS * BLOOM_TAPS));
+			let sample = sample_color(in.uv + offset * step).rgb;
+			bloom = bloom + max(sample - vec3<f32>(post.params.x), vec3<f32>(0.0)) * weight;
+			total = total + weight;
+		}
+	}
+
+	return vec4<f32>(color.rgb + bloom / total * post.params.y, color.a);
+}
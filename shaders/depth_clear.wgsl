@@ -0,0 +1,17 @@
+// Clears the depth buffer inside the viewport, by drawing a triangle covering it at the far plane.
+
+fn corner(index: u32, depth: f32) -> vec4<f32> {
+	let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+	return vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth, 1.0);
+}
+
+[[stage(vertex)]]
+fn vs_main([[builtin(vertex_index)]] index: u32) -> [[builtin(position)]] vec4<f32> {
+	return corner(index, 1.0);
+}
+
+// Far plane of reverse-Z cameras
+[[stage(vertex)]]
+fn vs_reverse_z([[builtin(vertex_index)]] index: u32) -> [[builtin(position)]] vec4<f32> {
+	return corner(index, 0.0);
+}
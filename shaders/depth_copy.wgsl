@@ -0,0 +1,24 @@
+// Copies the depth buffer into a color texture that post-processing passes can read, taking the
+// first sample of multisampled depth buffers. Depth is always copied as 0 at the near plane and 1
+// at the far plane, flipping it for reverse-Z cameras.
+
+[[group(0), binding(0)]]
+var t_depth: DEPTH_TEXTURE;
+
+[[stage(vertex)]]
+fn vs_main([[builtin(vertex_index)]] index: u32) -> [[builtin(position)]] vec4<f32> {
+	let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+	return vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+}
+
+[[stage(fragment)]]
+fn fs_main([[builtin(position)]] position: vec4<f32>) -> [[location(0)]] vec4<f32> {
+	let depth = textureLoad(t_depth, vec2<i32>(position.xy), 0).x;
+	return vec4<f32>(depth, 0.0, 0.0, 1.0);
+}
+
+[[stage(fragment)]]
+fn fs_reverse_z([[builtin(position)]] position: vec4<f32>) -> [[location(0)]] vec4<f32> {
+	let depth = textureLoad(t_depth, vec2<i32>(position.xy), 0).x;
+	return vec4<f32>(1.0 - depth, 0.0, 0.0, 1.0);
+}
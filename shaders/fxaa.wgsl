@@ -0,0 +1,42 @@
+let FXAA_REDUCE_MIN: f32 = 0.0078125;
+let FXAA_REDUCE_MUL: f32 = 0.125;
+let FXAA_SPAN_MAX: f32 = 8.0;
+
+// Blurs along edges found from the luminance of neighbouring pixels
+[[stage(fragment)]]
+fn fs_main(in: PostInput) -> [[location(0)]] vec4<f32> {
+	let texel = 1.0 / post.resolution;
+	let color = sample_color(in.uv);
+	let luma_nw = luminance(sample_color(in.uv + vec2<f32>(-1.0, -1.0) * texel).rgb);
+	let luma_ne = luminance(sample_color(in.uv + vec2<f32>(1.0, -1.0) * texel).rgb);
+	let luma_sw = luminance(sample_color(in.uv + vec2<f32>(-1.0, 1.0) * texel).rgb);
+	let luma_se = luminance(sample_color(in.uv + vec2<f32>(1.0, 1.0) * texel).rgb);
+	let luma_m = luminance(color.rgb);
+	let luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+	let luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+	var dir = vec2<f32>(
+		-((luma_nw + luma_ne) - (luma_sw + luma_se)),
+		(luma_nw + luma_sw) - (luma_ne + luma_se),
+	);
+	let dir_reduce = max(
+		(luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * FXAA_REDUCE_MUL,
+		FXAA_REDUCE_MIN,
+	);
+	let inverse_dir_min = 1.0 / (min(abs(dir.x), abs(dir.y)) + dir_reduce);
+	dir = clamp(dir * inverse_dir_min, vec2<f32>(-FXAA_SPAN_MAX), vec2<f32>(FXAA_SPAN_MAX)) * texel;
+
+	let rgb_a = 0.5 * (
+		sample_color(in.uv + dir * (1.0 / 3.0 - 0.5)).rgb +
+		sample_color(in.uv + dir * (2.0 / 3.0 - 0.5)).rgb
+	);
+	let rgb_b = rgb_a * 0.5 + 0.25 * (
+		sample_color(in.uv + dir * -0.5).rgb +
+		sample_color(in.uv + dir * 0.5).rgb
+	);
+	let luma_b = luminance(rgb_b);
+	if (luma_b < luma_min || luma_b > luma_max) {
+		return vec4<f32>(rgb_a, color.a);
+	}
+	return vec4<f32>(rgb_b, color.a);
+}
@@ -0,0 +1,6 @@
+// params.x: gamma
+[[stage(fragment)]]
+fn fs_main(in: PostInput) -> [[location(0)]] vec4<f32> {
+	let color = sample_color(in.uv);
+	return vec4<f32>(pow(color.rgb, vec3<f32>(1.0 / post.params.x)), color.a);
+}
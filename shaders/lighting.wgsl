@@ -0,0 +1,163 @@
+let PI: f32 = 3.14159265;
+
+let LIGHT_DIRECTIONAL: f32 = 0.0;
+let LIGHT_POINT: f32 = 1.0;
+let LIGHT_SPOT: f32 = 2.0;
+
+let SHADOW_MAP_SIZE: f32 = 1024.0;
+
+struct Light {
+	// xyz: position, w: kind
+	position: vec4<f32>;
+	// xyz: direction, w: range
+	direction: vec4<f32>;
+	// rgb: color * intensity
+	color: vec4<f32>;
+	// x: cos(inner angle), y: cos(outer angle)
+	cone: vec4<f32>;
+	// x: first shadow map layer, or -1 for no shadows, y: number of layers
+	shadow: vec4<f32>;
+};
+
+struct Lights {
+	ambient: vec4<f32>;
+	count: u32;
+	shadow_maps: u32;
+	padding0: u32;
+	padding1: u32;
+	lights: array<Light, 16>;
+	shadow_matrices: array<mat4x4<f32>, 8>;
+};
+
+[[group(0), binding(2)]]
+var<uniform> lights: Lights;
+[[group(0), binding(4)]]
+var shadow_maps: texture_depth_2d_array;
+[[group(0), binding(5)]]
+var shadow_sampler: sampler_comparison;
+
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+	var a = roughness * roughness;
+	var a2 = a * a;
+	var d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+	return a2 / (PI * d * d);
+}
+
+fn geometry_smith(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+	var r = roughness + 1.0;
+	var k = r * r / 8.0;
+	var ggx_v = n_dot_v / (n_dot_v * (1.0 - k) + k);
+	var ggx_l = n_dot_l / (n_dot_l * (1.0 - k) + k);
+	return ggx_v * ggx_l;
+}
+
+fn fresnel_schlick(cos_theta: f32, f0: vec3<f32>) -> vec3<f32> {
+	return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// Direction from a surface towards a light in xyz, and how much of the light reaches it in w.
+fn light_direction(light: Light, world_position: vec3<f32>) -> vec4<f32> {
+	if (light.position.w == LIGHT_DIRECTIONAL) {
+		return vec4<f32>(-light.direction.xyz, 1.0);
+	}
+
+	var to_light = light.position.xyz - world_position;
+	var distance2 = max(dot(to_light, to_light), 0.0001);
+	var l = to_light / sqrt(distance2);
+	var attenuation = 1.0 / distance2;
+
+	var range = light.direction.w;
+	if (range > 0.0) {
+		var ratio = distance2 / (range * range);
+		var window = clamp(1.0 - ratio * ratio, 0.0, 1.0);
+		attenuation = attenuation * window * window;
+	}
+
+	if (light.position.w == LIGHT_SPOT) {
+		var cos_angle = dot(-l, light.direction.xyz);
+		attenuation = attenuation * smoothStep(light.cone.y, light.cone.x, cos_angle);
+	}
+
+	return vec4<f32>(l, attenuation);
+}
+
+// Fraction of a light reaching a surface without being blocked, filtered over 3x3 shadow map
+// texels. Directional lights use the first cascade that covers the surface.
+fn shadow(light: Light, world_position: vec3<f32>, normal: vec3<f32>) -> f32 {
+	if (light.shadow.x < 0.0) {
+		return 1.0;
+	}
+
+	var first = i32(light.shadow.x);
+	var layers = i32(light.shadow.y);
+	for (var i = 0; i < layers; i = i + 1) {
+		var matrix = lights.shadow_matrices[first + i];
+
+		// Push the sample point off the surface by about a texel, to avoid shadow acne
+		var clip = matrix * vec4<f32>(world_position, 1.0);
+		var scale = length(vec3<f32>(matrix[0].x, matrix[1].x, matrix[2].x));
+		var texel = 2.0 * clip.w / (scale * SHADOW_MAP_SIZE);
+		clip = matrix * vec4<f32>(world_position + normal * texel * 1.5, 1.0);
+
+		var ndc = clip.xyz / clip.w;
+		var uv = ndc.xy * vec2<f32>(0.5, -0.5) + 0.5;
+		if (all(uv >= vec2<f32>(0.0)) && all(uv <= vec2<f32>(1.0)) && ndc.z >= 0.0 && ndc.z <= 1.0) {
+			var visible = 0.0;
+			for (var y = -1; y <= 1; y = y + 1) {
+				for (var x = -1; x <= 1; x = x + 1) {
+					var offset = vec2<f32>(f32(x), f32(y)) / SHADOW_MAP_SIZE;
+					visible = visible + textureSampleCompareLevel(shadow_maps, shadow_sampler, uv + offset, first + i, ndc.z);
+				}
+			}
+			return visible / 9.0;
+		}
+	}
+
+	return 1.0;
+}
+
+// Light reflected towards the viewer by a surface, using Cook-Torrance for specular and
+// Lambert for diffuse. `v` is the direction from the surface towards the camera.
+fn shade(
+	base_color: vec3<f32>,
+	metallic: f32,
+	roughness: f32,
+	occlusion: f32,
+	normal: vec3<f32>,
+	v: vec3<f32>,
+	world_position: vec3<f32>,
+	receive_shadows: bool,
+) -> vec3<f32> {
+	var f0 = mix(vec3<f32>(0.04), base_color, metallic);
+	var n_dot_v = max(dot(normal, v), 0.0001);
+	var color = lights.ambient.rgb * base_color * occlusion;
+
+	for (var i = 0u; i < lights.count; i = i + 1u) {
+		var light = lights.lights[i];
+		var dir = light_direction(light, world_position);
+		if (receive_shadows && dir.w > 0.0) {
+			dir.w = dir.w * shadow(light, world_position, normal);
+		}
+		var l = dir.xyz;
+		var h = normalize(v + l);
+		var n_dot_l = max(dot(normal, l), 0.0);
+		var n_dot_h = max(dot(normal, h), 0.0);
+
+		var f = fresnel_schlick(max(dot(h, v), 0.0), f0);
+		var d = distribution_ggx(n_dot_h, roughness);
+		var g = geometry_smith(n_dot_v, n_dot_l, roughness);
+		var specular = d * g * f / (4.0 * n_dot_v * max(n_dot_l, 0.0001));
+		var diffuse = (1.0 - f) * (1.0 - metallic) * base_color / PI;
+
+		color = color + (diffuse + specular) * light.color.rgb * dir.w * n_dot_l;
+	}
+
+	return color;
+}
+
+// Position of the camera, given a view matrix without any scaling.
+fn camera_position(view: mat4x4<f32>) -> vec3<f32> {
+	var rotation = mat3x3<f32>(view[0].xyz, view[1].xyz, view[2].xyz);
+	return -(transpose(rotation) * view[3].xyz);
+}
+
@@ -0,0 +1,169 @@
+// Requires lighting.wgsl
+
+let BASE_COLOR_TEXTURE: u32 = 1u;
+let METALLIC_ROUGHNESS_TEXTURE: u32 = 2u;
+let NORMAL_TEXTURE: u32 = 4u;
+let OCCLUSION_TEXTURE: u32 = 8u;
+let EMISSIVE_TEXTURE: u32 = 16u;
+
+let ALPHA_MASK: u32 = 1u;
+let ALPHA_BLEND: u32 = 2u;
+
+struct Camera {
+	view: mat4x4<f32>;
+	projection: mat4x4<f32>;
+};
+
+struct Actor {
+	color: vec4<f32>;
+	model: mat4x4<f32>;
+	receive_shadows: u32;
+};
+
+struct Material {
+	base_color: vec4<f32>;
+	emissive: vec4<f32>;
+	metallic: f32;
+	roughness: f32;
+	normal_scale: f32;
+	occlusion_strength: f32;
+	alpha_cutoff: f32;
+	alpha_mode: u32;
+	textures: u32;
+	padding: u32;
+};
+
+struct VertexOutput {
+	[[builtin(position)]] position: vec4<f32>;
+	[[location(0)]] world_position: vec3<f32>;
+	[[location(1)]] normal: vec3<f32>;
+	[[location(2)]] uv: vec2<f32>;
+	[[location(3)]] color: vec4<f32>;
+};
+
+struct InstanceInput {
+	[[location(8)]] model_0: vec4<f32>;
+	[[location(9)]] model_1: vec4<f32>;
+	[[location(10)]] model_2: vec4<f32>;
+	[[location(11)]] model_3: vec4<f32>;
+	[[location(12)]] color: vec4<f32>;
+};
+
+struct FragmentOutput {
+	[[location(0)]] color: vec4<f32>;
+};
+
+[[group(0), binding(0)]]
+var<uniform> camera: Camera;
+[[group(0), binding(1)]]
+var<uniform> actor: Actor;
+[[group(0), binding(3)]]
+var<uniform> material: Material;
+
+[[group(1), binding(0)]]
+var t_base_color: texture_2d<f32>;
+[[group(1), binding(1)]]
+var t_metallic_roughness: texture_2d<f32>;
+[[group(1), binding(2)]]
+var t_normal: texture_2d<f32>;
+[[group(1), binding(3)]]
+var t_occlusion: texture_2d<f32>;
+[[group(1), binding(4)]]
+var t_emissive: texture_2d<f32>;
+[[group(1), binding(5)]]
+var s_material: sampler;
+
+fn vertex(position: vec3<f32>, normal: vec3<f32>, uv: vec2<f32>, model: mat4x4<f32>) -> VertexOutput {
+	var out: VertexOutput;
+
+	var world_position = model * vec4<f32>(position, 1.0);
+	out.position = camera.projection * camera.view * world_position;
+	out.world_position = world_position.xyz;
+	out.normal = normalize((model * vec4<f32>(normal, 0.0)).xyz);
+	out.uv = uv;
+	out.color = vec4<f32>(1.0);
+
+	return out;
+}
+
+[[stage(vertex)]]
+fn vs_main(
+	[[location(0)]] position: vec3<f32>,
+	[[location(1)]] normal: vec3<f32>,
+	[[location(2)]] uv: vec2<f32>,
+) -> VertexOutput {
+	return vertex(position, normal, uv, actor.model);
+}
+
+[[stage(vertex)]]
+fn vs_instanced(
+	[[location(0)]] position: vec3<f32>,
+	[[location(1)]] normal: vec3<f32>,
+	[[location(2)]] uv: vec2<f32>,
+	instance: InstanceInput,
+) -> VertexOutput {
+	var model = actor.model * mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+	var out = vertex(position, normal, uv, model);
+	out.color = instance.color;
+	return out;
+}
+
+fn has_texture(flag: u32) -> bool {
+	return (material.textures & flag) != 0u;
+}
+
+// Apply a tangent space normal map, building the tangent frame from screen space derivatives
+// so meshes don't need tangent attributes.
+fn perturb_normal(normal: vec3<f32>, position: vec3<f32>, uv: vec2<f32>, sample: vec3<f32>) -> vec3<f32> {
+	var dp1 = dpdx(position);
+	var dp2 = dpdy(position);
+	var duv1 = dpdx(uv);
+	var duv2 = dpdy(uv);
+
+	var dp2perp = cross(dp2, normal);
+	var dp1perp = cross(normal, dp1);
+	var tangent = dp2perp * duv1.x + dp1perp * duv2.x;
+	var bitangent = dp2perp * duv1.y + dp1perp * duv2.y;
+	var scale = inverseSqrt(max(max(dot(tangent, tangent), dot(bitangent, bitangent)), 0.00000001));
+	var tbn = mat3x3<f32>(tangent * scale, bitangent * scale, normal);
+
+	var mapped = sample * 2.0 - 1.0;
+	mapped = vec3<f32>(mapped.xy * material.normal_scale, mapped.z);
+	return normalize(tbn * mapped);
+}
+
+[[stage(fragment)]]
+fn fs_main(
+	[[builtin(front_facing)]] is_front: bool,
+	in: VertexOutput
+) -> FragmentOutput {
+	// Sample everything up front, as sampling isn't allowed in non-uniform control flow
+	var base_sample = textureSample(t_base_color, s_material, in.uv);
+	var mr_sample = textureSample(t_metallic_roughness, s_material, in.uv);
+	var normal_sample = textureSample(t_normal, s_material, in.uv).xyz;
+	var occlusion_sample = textureSample(t_occlusion, s_material, in.uv).r;
+	var emissive_sample = textureSample(t_emissive, s_material, in.uv).rgb;
+
+	var base_color = in.color * material.base_color * select(vec4<f32>(1.0), base_sample, has_texture(BASE_COLOR_TEXTURE));
+	if (material.alpha_mode == ALPHA_MASK && base_color.a < material.alpha_cutoff) {
+		discard;
+	}
+	var alpha = select(1.0, base_color.a, material.alpha_mode == ALPHA_BLEND);
+
+	var metallic = material.metallic * select(1.0, mr_sample.b, has_texture(METALLIC_ROUGHNESS_TEXTURE));
+	var roughness = clamp(material.roughness * select(1.0, mr_sample.g, has_texture(METALLIC_ROUGHNESS_TEXTURE)), 0.04, 1.0);
+	var occlusion = select(1.0, 1.0 + material.occlusion_strength * (occlusion_sample - 1.0), has_texture(OCCLUSION_TEXTURE));
+	var emissive = material.emissive.rgb * select(vec3<f32>(1.0), emissive_sample, has_texture(EMISSIVE_TEXTURE));
+
+	var normal = normalize(select(in.normal * -1.0, in.normal, is_front));
+	var mapped_normal = perturb_normal(normal, in.world_position, in.uv, normal_sample);
+	normal = select(normal, mapped_normal, has_texture(NORMAL_TEXTURE));
+
+	var v = normalize(camera_position(camera.view) - in.world_position);
+	var color = shade(base_color.rgb, metallic, roughness, occlusion, normal, v, in.world_position, actor.receive_shadows != 0u);
+
+	var out: FragmentOutput;
+	out.color = vec4<f32>(color + emissive, alpha);
+
+	return out;
+}
@@ -0,0 +1,52 @@
+// Shared by every post-processing pass. Passes add an `fs_main(in: PostInput)` fragment shader.
+
+struct PostUniform {
+	resolution: vec2<f32>;
+	// Seconds since the renderer was created
+	time: f32;
+	// Settings of the pass
+	params: vec4<f32>;
+};
+
+// The frame so far
+[[group(0), binding(0)]]
+var t_color: texture_2d<f32>;
+[[group(0), binding(1)]]
+var s_color: sampler;
+// Depth buffer of the main pass, 0 at the near plane and 1 at the far plane, including for
+// reverse-Z cameras
+[[group(0), binding(2)]]
+var t_depth: texture_2d<f32>;
+[[group(0), binding(3)]]
+var<uniform> post: PostUniform;
+
+struct PostInput {
+	[[builtin(position)]] position: vec4<f32>;
+	[[location(0)]] uv: vec2<f32>;
+};
+
+// One triangle covering the screen
+[[stage(vertex)]]
+fn vs_main([[builtin(vertex_index)]] index: u32) -> PostInput {
+	let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+	var out: PostInput;
+	out.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+	out.uv = uv;
+	return out;
+}
+
+fn sample_color(uv: vec2<f32>) -> vec4<f32> {
+	return textureSample(t_color, s_color, uv);
+}
+
+fn load_depth(uv: vec2<f32>) -> f32 {
+	let size = textureDimensions(t_depth);
+	let pixel = clamp(vec2<i32>(uv * post.resolution), vec2<i32>(0), size - vec2<i32>(1));
+	return textureLoad(t_depth, pixel, 0).r;
+}
+
+fn luminance(color: vec3<f32>) -> f32 {
+	return dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
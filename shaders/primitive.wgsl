@@ -1,4 +1,10 @@
+// Requires lighting.wgsl
+
 let gamma: f32 = 2.2;
+let roughness: f32 = 0.6;
+
+let ALPHA_MASK: u32 = 1u;
+let ALPHA_BLEND: u32 = 2u;
 
 struct Camera {
 	view: mat4x4<f32>;
@@ -8,6 +14,9 @@ struct Camera {
 struct Actor {
 	color: vec4<f32>;
 	model: mat4x4<f32>;
+	receive_shadows: u32;
+	alpha_mode: u32;
+	alpha_cutoff: f32;
 };
 
 struct TextureEnabled {
@@ -38,25 +47,48 @@ var t_diffuse: texture_2d<f32>;
 [[group(1), binding(2)]]
 var s_diffuse: sampler;
 
-[[stage(vertex)]]
-fn vs_main(
-	[[location(0)]] position: vec3<f32>,
-	[[location(1)]] normal: vec3<f32>,
-	[[location(2)]] uv: vec2<f32>,
-) -> VertexOutput {
+struct InstanceInput {
+	[[location(8)]] model_0: vec4<f32>;
+	[[location(9)]] model_1: vec4<f32>;
+	[[location(10)]] model_2: vec4<f32>;
+	[[location(11)]] model_3: vec4<f32>;
+	[[location(12)]] color: vec4<f32>;
+};
+
+fn vertex(position: vec3<f32>, normal: vec3<f32>, uv: vec2<f32>, model: mat4x4<f32>, color: vec4<f32>) -> VertexOutput {
 	var out: VertexOutput;
 
-	var mvp: mat4x4<f32> = camera.projection * camera.view * actor.model;
+	var mvp: mat4x4<f32> = camera.projection * camera.view * model;
 
 	out.position = mvp * vec4<f32>(position, 1.0);
-	out.world_position = (actor.model * vec4<f32>(position, 1.0)).xyz;
-	out.normal = normalize((actor.model * vec4<f32>(normal, 0.0)).xyz);
-	out.color = actor.color;
+	out.world_position = (model * vec4<f32>(position, 1.0)).xyz;
+	out.normal = normalize((model * vec4<f32>(normal, 0.0)).xyz);
+	out.color = color;
 	out.uv = uv;
 
 	return out;
 }
 
+[[stage(vertex)]]
+fn vs_main(
+	[[location(0)]] position: vec3<f32>,
+	[[location(1)]] normal: vec3<f32>,
+	[[location(2)]] uv: vec2<f32>,
+) -> VertexOutput {
+	return vertex(position, normal, uv, actor.model, actor.color);
+}
+
+[[stage(vertex)]]
+fn vs_instanced(
+	[[location(0)]] position: vec3<f32>,
+	[[location(1)]] normal: vec3<f32>,
+	[[location(2)]] uv: vec2<f32>,
+	instance: InstanceInput,
+) -> VertexOutput {
+	var model = actor.model * mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+	return vertex(position, normal, uv, model, actor.color * instance.color);
+}
+
 
 [[stage(fragment)]]
 fn fs_main(
@@ -64,16 +96,19 @@ fn fs_main(
 	in: VertexOutput
 ) -> FragmentOutput {
 	var normal = select(in.normal * -1.0, in.normal, is_front);
-	var light_pos = vec3<f32>(-50.0, 40.0, -40.0);
-	var light_dir = normalize(light_pos - in.world_position);
-	var shade = clamp(dot(normal, light_dir), 0.0, 0.7) + 0.3;
-	var color = select(in.color, textureSample(t_diffuse, s_diffuse, in.uv), texture_enabled.enabled > 0);
-	if (color.a < 0.5) {
+	// Colours are sRGB, but textures are decoded to linear when sampled
+	var color = vec4<f32>(pow(in.color.rgb, vec3<f32>(gamma)), in.color.a);
+	var base_color = select(color, textureSample(t_diffuse, s_diffuse, in.uv), texture_enabled.enabled > 0);
+	if (actor.alpha_mode == ALPHA_MASK && base_color.a < actor.alpha_cutoff) {
 		discard;
 	}
+	var alpha = select(1.0, base_color.a, actor.alpha_mode == ALPHA_BLEND);
+
+	var v = normalize(camera_position(camera.view) - in.world_position);
+	var lit = shade(base_color.rgb, 0.0, roughness, 1.0, normal, v, in.world_position, actor.receive_shadows != 0u);
 
 	var out: FragmentOutput;
-	out.color = pow(color * shade, vec4<f32>(gamma));
+	out.color = vec4<f32>(lit, alpha);
 
 	return out;
 }
@@ -1,9 +1,16 @@
-let gamma: f32 = 2.2;
+// Added after tonemapping.wgsl
+
+struct QuadUniform {
+	exposure: f32;
+	tonemapping: u32;
+};
 
 [[group(0), binding(0)]]
 var t_diffuse: texture_2d<f32>;
 [[group(0), binding(1)]]
 var s_diffuse: sampler;
+[[group(0), binding(2)]]
+var<uniform> quad: QuadUniform;
 
 struct VertexOutput {
 	[[builtin(position)]] position: vec4<f32>;
@@ -26,5 +33,6 @@ fn vs_main(
 
 [[stage(fragment)]]
 fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
-	return textureSample(t_diffuse, s_diffuse, in.uv);
+	let color = textureSample(t_diffuse, s_diffuse, in.uv);
+	return vec4<f32>(tonemap(color.rgb * quad.exposure, quad.tonemapping), color.a);
 }
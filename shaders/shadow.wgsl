@@ -0,0 +1,32 @@
+struct Camera {
+	view: mat4x4<f32>;
+	projection: mat4x4<f32>;
+};
+
+struct Actor {
+	color: vec4<f32>;
+	model: mat4x4<f32>;
+};
+
+[[group(0), binding(0)]]
+var<uniform> light: Camera;
+[[group(0), binding(1)]]
+var<uniform> actor: Actor;
+
+[[stage(vertex)]]
+fn vs_main([[location(0)]] position: vec3<f32>) -> [[builtin(position)]] vec4<f32> {
+	return light.projection * light.view * actor.model * vec4<f32>(position, 1.0);
+}
+
+struct InstanceInput {
+	[[location(8)]] model_0: vec4<f32>;
+	[[location(9)]] model_1: vec4<f32>;
+	[[location(10)]] model_2: vec4<f32>;
+	[[location(11)]] model_3: vec4<f32>;
+};
+
+[[stage(vertex)]]
+fn vs_instanced([[location(0)]] position: vec3<f32>, instance: InstanceInput) -> [[builtin(position)]] vec4<f32> {
+	var model = actor.model * mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+	return light.projection * light.view * model * vec4<f32>(position, 1.0);
+}
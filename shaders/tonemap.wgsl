@@ -0,0 +1,7 @@
+// Added after tonemapping.wgsl
+// params.x: exposure, params.y: tonemapping mode
+[[stage(fragment)]]
+fn fs_main(in: PostInput) -> [[location(0)]] vec4<f32> {
+	let color = sample_color(in.uv);
+	return vec4<f32>(tonemap(color.rgb * post.params.x, u32(post.params.y)), color.a);
+}
@@ -0,0 +1,30 @@
+// Maps linear high dynamic range colors into the 0..1 range of the display. The modes match
+// `Tonemapping` in the renderer.
+
+let TONEMAP_NONE: u32 = 0u;
+let TONEMAP_REINHARD: u32 = 1u;
+let TONEMAP_ACES: u32 = 2u;
+
+fn reinhard(x: vec3<f32>) -> vec3<f32> {
+	return x / (vec3<f32>(1.0) + x);
+}
+
+// ACES filmic curve, fitted by Krzysztof Narkowicz
+fn aces(x: vec3<f32>) -> vec3<f32> {
+	let a = 2.51;
+	let b = 0.03;
+	let c = 2.43;
+	let d = 0.59;
+	let e = 0.14;
+	return clamp((x * (a * x + b)) / (x * (c * x + d) + e), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+fn tonemap(color: vec3<f32>, mode: u32) -> vec3<f32> {
+	if (mode == TONEMAP_REINHARD) {
+		return reinhard(color);
+	}
+	if (mode == TONEMAP_ACES) {
+		return aces(color);
+	}
+	return color;
+}
@@ -0,0 +1,10 @@
+// params.x: strength, params.y: distance from the center where darkening starts, with 1 at the
+// corners
+[[stage(fragment)]]
+fn fs_main(in: PostInput) -> [[location(0)]] vec4<f32> {
+	let color = sample_color(in.uv);
+	let from_center = length(in.uv - vec2<f32>(0.5)) * sqrt(2.0);
+	let t = clamp((from_center - post.params.y) / (1.0 - post.params.y), 0.0, 1.0);
+	let falloff = t * t * (3.0 - 2.0 * t);
+	return vec4<f32>(color.rgb * (1.0 - post.params.x * falloff), color.a);
+}
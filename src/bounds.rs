@@ -0,0 +1,110 @@
+use cgmath::{EuclideanSpace, Matrix, Matrix4, Point3, Transform, Vector4};
+
+/// Axis aligned box containing an object.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BoundingBox {
+	pub min: Point3<f32>,
+	pub max: Point3<f32>,
+}
+
+impl BoundingBox {
+	pub fn new(min: Point3<f32>, max: Point3<f32>) -> Self {
+		Self { min, max }
+	}
+
+	/// Smallest box containing every point, or `None` if there are no points.
+	pub fn from_points(points: impl IntoIterator<Item = Point3<f32>>) -> Option<Self> {
+		points.into_iter().fold(None, |bounds, point| {
+			Some(match bounds {
+				Some(bounds) => bounds.include(point),
+				None => Self::new(point, point),
+			})
+		})
+	}
+
+	/// Grow the box to contain a point.
+	pub fn include(self, point: Point3<f32>) -> Self {
+		Self {
+			min: Point3::new(
+				self.min.x.min(point.x),
+				self.min.y.min(point.y),
+				self.min.z.min(point.z),
+			),
+			max: Point3::new(
+				self.max.x.max(point.x),
+				self.max.y.max(point.y),
+				self.max.z.max(point.z),
+			),
+		}
+	}
+
+	/// Smallest box containing both boxes.
+	pub fn union(self, other: Self) -> Self {
+		self.include(other.min).include(other.max)
+	}
+
+	pub fn center(&self) -> Point3<f32> {
+		self.min.midpoint(self.max)
+	}
+
+	pub fn corners(&self) -> [Point3<f32>; 8] {
+		let (min, max) = (self.min, self.max);
+		[
+			Point3::new(min.x, min.y, min.z),
+			Point3::new(max.x, min.y, min.z),
+			Point3::new(min.x, max.y, min.z),
+			Point3::new(max.x, max.y, min.z),
+			Point3::new(min.x, min.y, max.z),
+			Point3::new(max.x, min.y, max.z),
+			Point3::new(min.x, max.y, max.z),
+			Point3::new(max.x, max.y, max.z),
+		]
+	}
+
+	/// Box containing this box after it's been transformed.
+	pub fn transform(&self, transform: &Matrix4<f32>) -> Self {
+		Self::from_points(
+			self.corners()
+				.iter()
+				.map(|&corner| transform.transform_point(corner)),
+		)
+		.unwrap_or(*self)
+	}
+}
+
+/// The volume a camera can see, as six planes facing inwards.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Frustum {
+	/// Left, right, bottom, top, near and far planes, with the normal in `xyz` and the distance
+	/// in `w`
+	pub planes: [Vector4<f32>; 6],
+}
+
+impl Frustum {
+	/// Extract the planes of a view-projection matrix.
+	///
+	/// The near plane is taken from OpenGL's -1..1 depth range, which also contains wgpu's 0..1
+	/// range and reverse-Z projections' 1..0 range, so nothing visible is ever culled.
+	pub fn from_matrix(matrix: Matrix4<f32>) -> Self {
+		let (x, y, z, w) = (matrix.row(0), matrix.row(1), matrix.row(2), matrix.row(3));
+		Self {
+			planes: [w + x, w - x, w + y, w - y, w + z, w - z],
+		}
+	}
+
+	/// Whether any part of a box might be inside the frustum. Boxes near the frustum's corners
+	/// can be reported as inside when they aren't.
+	pub fn intersects(&self, bounds: &BoundingBox) -> bool {
+		self.planes.iter().all(|plane| {
+			// The corner furthest along the plane's normal
+			let furthest = |normal: f32, min: f32, max: f32| if normal >= 0.0 { max } else { min };
+			let corner = Vector4::new(
+				furthest(plane.x, bounds.min.x, bounds.max.x),
+				furthest(plane.y, bounds.min.y, bounds.max.y),
+				furthest(plane.z, bounds.min.z, bounds.max.z),
+				1.0,
+			);
+			cgmath::dot(*plane, corner) >= 0.0
+		})
+	}
+}
@@ -0,0 +1,152 @@
+use crate::{BoundingBox, Ray, TriangleHit};
+use cgmath::{EuclideanSpace, Point3};
+
+/// Most triangles kept in one leaf of a [`Bvh`]
+const MAX_LEAF_TRIANGLES: usize = 4;
+
+#[derive(Clone, Debug)]
+enum BvhNode {
+	/// Triangles `start..end`
+	Leaf {
+		bounds: BoundingBox,
+		start: usize,
+		end: usize,
+	},
+	/// Indices of the two child nodes
+	Branch {
+		bounds: BoundingBox,
+		left: usize,
+		right: usize,
+	},
+}
+
+impl BvhNode {
+	fn bounds(&self) -> &BoundingBox {
+		match self {
+			Self::Leaf { bounds, .. } | Self::Branch { bounds, .. } => bounds,
+		}
+	}
+}
+
+/// Bounding volume hierarchy of triangles, so a ray only has to be tested against the triangles
+/// near it.
+#[derive(Clone, Debug)]
+pub struct Bvh {
+	nodes: Vec<BvhNode>,
+	triangles: Vec<[Point3<f32>; 3]>,
+	/// Index each triangle was given before they were reordered
+	indices: Vec<usize>,
+}
+
+impl Bvh {
+	/// Hits report the index of the triangle in `triangles`.
+	pub fn new(triangles: Vec<[Point3<f32>; 3]>) -> Self {
+		Self::with_indices(triangles.into_iter().enumerate().collect())
+	}
+
+	/// Hits report the index paired with the triangle.
+	pub fn with_indices(mut triangles: Vec<(usize, [Point3<f32>; 3])>) -> Self {
+		let mut nodes = Vec::new();
+		if !triangles.is_empty() {
+			let len = triangles.len();
+			Self::build(&mut nodes, &mut triangles, 0, len);
+		}
+		let (indices, triangles) = triangles.into_iter().unzip();
+		Self {
+			nodes,
+			triangles,
+			indices,
+		}
+	}
+
+	/// Add a node for triangles `start..end`, splitting them along the longest axis of their
+	/// centers until each leaf is small enough. Returns the node's index.
+	fn build(
+		nodes: &mut Vec<BvhNode>,
+		triangles: &mut [(usize, [Point3<f32>; 3])],
+		start: usize,
+		end: usize,
+	) -> usize {
+		let slice = &mut triangles[start..end];
+		let bounds = BoundingBox::from_points(slice.iter().flat_map(|(_, triangle)| *triangle))
+			.expect("BVH nodes always have triangles");
+		let index = nodes.len();
+		if slice.len() <= MAX_LEAF_TRIANGLES {
+			nodes.push(BvhNode::Leaf { bounds, start, end });
+			return index;
+		}
+
+		let center = |(_, triangle): &(usize, [Point3<f32>; 3])| Point3::centroid(triangle);
+		let centers = BoundingBox::from_points(slice.iter().map(center)).unwrap();
+		let size = centers.max - centers.min;
+		let axis = if size.x >= size.y && size.x >= size.z {
+			0
+		} else if size.y >= size.z {
+			1
+		} else {
+			2
+		};
+		let middle = slice.len() / 2;
+		slice.select_nth_unstable_by(middle, |a, b| center(a)[axis].total_cmp(&center(b)[axis]));
+
+		// Children are filled in once they've been built
+		nodes.push(BvhNode::Leaf { bounds, start, end });
+		let left = Self::build(nodes, triangles, start, start + middle);
+		let right = Self::build(nodes, triangles, start + middle, end);
+		nodes[index] = BvhNode::Branch {
+			bounds,
+			left,
+			right,
+		};
+		index
+	}
+
+	/// Box containing every triangle, or `None` if there are none.
+	pub fn bounds(&self) -> Option<BoundingBox> {
+		self.nodes.first().map(|node| *node.bounds())
+	}
+
+	/// Triangles in the order they're stored in, which isn't the order they were given in.
+	pub fn triangles(&self) -> &[[Point3<f32>; 3]] {
+		&self.triangles
+	}
+
+	/// Nearest triangle hit by a ray.
+	pub fn raycast(&self, ray: &Ray) -> Option<TriangleHit> {
+		let mut nearest: Option<TriangleHit> = None;
+		let mut stack = vec![0];
+		while let Some(index) = stack.pop() {
+			let Some(node) = self.nodes.get(index) else {
+				continue;
+			};
+
+			// Skip nodes that are missed, or only hit further away than a hit we've already found
+			let Some(distance) = ray.intersect_box(node.bounds()) else {
+				continue;
+			};
+			if nearest.is_some_and(|hit| hit.distance < distance) {
+				continue;
+			}
+
+			match *node {
+				BvhNode::Leaf { start, end, .. } => {
+					for i in start..end {
+						if let Some(hit) = ray.intersect_triangle(self.triangles[i]) {
+							if nearest.is_none_or(|nearest| hit.distance < nearest.distance) {
+								nearest = Some(TriangleHit {
+									index: self.indices[i],
+									..hit
+								});
+							}
+						}
+					}
+				}
+				BvhNode::Branch { left, right, .. } => {
+					stack.push(right);
+					stack.push(left);
+				}
+			}
+		}
+		nearest
+	}
+}
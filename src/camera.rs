@@ -1,6 +1,9 @@
 use std::f32::consts::PI;
 
-use cgmath::{Deg, EuclideanSpace, Euler, Matrix4, Point3, Rad, SquareMatrix, Transform, Vector3};
+use crate::Ray;
+use cgmath::{
+	Angle, Deg, EuclideanSpace, Euler, Matrix4, Point3, Rad, SquareMatrix, Transform, Vector3,
+};
 
 pub trait Camera {
 	fn resize(&mut self, _width: f32, _height: f32) {}
@@ -8,17 +11,34 @@ pub trait Camera {
 	fn projection(&self) -> Matrix4<f32> {
 		Matrix4::identity()
 	}
+	/// Whether the projection maps the near plane to depth 1 and the far plane to 0, which keeps
+	/// more precision in the distance. The renderer flips its depth test to match.
+	fn reverse_z(&self) -> bool {
+		false
+	}
 }
 
+/// Camera with a perspective projection, moved and turned freely.
 #[derive(Debug, Clone)]
 pub struct FreeCamera {
 	width: f32,
 	height: f32,
 	position: Point3<f32>,
 	rotation: Euler<Rad<f32>>,
+	/// Vertical field of view in degrees
+	fov: f32,
+	near: f32,
+	far: f32,
+	reverse_z: bool,
 	projection: Matrix4<f32>,
 }
 
+impl Default for FreeCamera {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
 impl FreeCamera {
 	pub fn new() -> Self {
 		let mut camera = Self {
@@ -26,6 +46,10 @@ impl FreeCamera {
 			height: 1.0,
 			position: Point3::new(0.0, 0.0, 0.0),
 			rotation: Euler::new(Rad(0.0), Rad(0.0), Rad(0.0)),
+			fov: 45.0,
+			near: 0.1,
+			far: 1000.0,
+			reverse_z: false,
 			projection: Matrix4::identity(),
 		};
 
@@ -34,21 +58,27 @@ impl FreeCamera {
 		camera
 	}
 
-	pub fn translate(&mut self, x: f32, y: f32, z: f32) {
-		let trans = Matrix4::from_translation(Vector3::new(x, y, z));
-		let rotate = self.rotation_matrix();
-		let rotate_inv = rotate.inverse_transform().unwrap();
+	pub fn with_fov(mut self, fov: f32) -> Self {
+		self.set_fov(fov);
+		self
+	}
 
-		let new_pos = (trans * rotate_inv).transform_point(self.position);
-		self.position = rotate.transform_point(new_pos);
+	pub fn with_clip_planes(mut self, near: f32, far: f32) -> Self {
+		self.set_clip_planes(near, far);
+		self
 	}
 
-	pub fn rotation_matrix(&self) -> Matrix4<f32> {
-		let x: Matrix4<f32> = Euler::new(self.rotation.x, Rad(0.0), Rad(0.0)).into();
-		let y: Matrix4<f32> = Euler::new(Rad(0.0), self.rotation.y, Rad(0.0)).into();
-		let z: Matrix4<f32> = Euler::new(Rad(0.0), Rad(0.0), self.rotation.z).into();
+	pub fn with_reverse_z(mut self, reverse_z: bool) -> Self {
+		self.set_reverse_z(reverse_z);
+		self
+	}
 
-		z * y * x
+	pub fn translate(&mut self, x: f32, y: f32, z: f32) {
+		self.position = translate(self.position, &self.rotation, x, y, z);
+	}
+
+	pub fn rotation_matrix(&self) -> Matrix4<f32> {
+		rotation_matrix(&self.rotation)
 	}
 
 	pub fn rotate(&mut self, x: f32, y: f32, z: f32) {
@@ -100,29 +130,283 @@ impl FreeCamera {
 	pub fn height(&self) -> f32 {
 		self.height
 	}
+
+	/// Vertical field of view in degrees.
+	pub fn fov(&self) -> f32 {
+		self.fov
+	}
+
+	pub fn set_fov(&mut self, fov: f32) {
+		self.fov = fov;
+		self.update_projection();
+	}
+
+	/// Distance to the near clipping plane.
+	pub fn near(&self) -> f32 {
+		self.near
+	}
+
+	/// Distance to the far clipping plane, which can be infinite.
+	pub fn far(&self) -> f32 {
+		self.far
+	}
+
+	/// Only draw what's between `near` and `far` from the camera. `far` can be
+	/// [`f32::INFINITY`], which works best with [reverse-Z](Self::set_reverse_z).
+	pub fn set_clip_planes(&mut self, near: f32, far: f32) {
+		self.near = near;
+		self.far = far;
+		self.update_projection();
+	}
+
+	/// Use a reverse-Z projection, spreading depth precision evenly enough for very distant far
+	/// planes. Depths in the depth buffer then fall from 1 at the near plane to 0 at the far
+	/// plane, so post effects reading depth need to flip it.
+	pub fn set_reverse_z(&mut self, reverse_z: bool) {
+		self.reverse_z = reverse_z;
+		self.update_projection();
+	}
+
+	/// Ray from the camera through a pixel, with `(0, 0)` at the top left of the screen.
+	pub fn screen_ray(&self, x: f32, y: f32) -> Ray {
+		Ray::from_screen(self, x, y, self.width, self.height)
+	}
+
+	fn update_projection(&mut self) {
+		let aspect = self.width / self.height;
+		let (near, far) = (self.near, self.far);
+		let f = 1.0 / Deg(self.fov / 2.0).tan();
+		self.projection = if self.reverse_z {
+			// Depth 1 at the near plane, falling to 0 at the far plane, or at infinity
+			let (a, b) = if far.is_finite() {
+				(-near / (far - near), near * far / (far - near))
+			} else {
+				(0.0, near)
+			};
+			#[rustfmt::skip]
+			let projection = Matrix4::new(
+				f / aspect, 0.0, 0.0, 0.0,
+				0.0, f, 0.0, 0.0,
+				0.0, 0.0, a, 1.0,
+				0.0, 0.0, b, 0.0,
+			);
+			projection
+		} else if far.is_finite() {
+			// cgmath returns RH matrix, but we want LH, so we invert Z to flip it
+			cgmath::perspective(Deg(self.fov), aspect, near, far)
+				* Matrix4::from_nonuniform_scale(1.0, 1.0, -1.0)
+		} else {
+			// The same matrix as the far plane goes to infinity
+			#[rustfmt::skip]
+			let projection = Matrix4::new(
+				f / aspect, 0.0, 0.0, 0.0,
+				0.0, f, 0.0, 0.0,
+				0.0, 0.0, 1.0, 1.0,
+				0.0, 0.0, -2.0 * near, 0.0,
+			);
+			projection
+		};
+	}
 }
 
 impl Camera for FreeCamera {
 	fn view(&self) -> Matrix4<f32> {
-		let translate: Matrix4<f32> = Matrix4::from_translation(self.position.to_vec());
-		let rotate = self.rotation_matrix();
+		view_matrix(self.position, &self.rotation)
+	}
+
+	fn projection(&self) -> Matrix4<f32> {
+		self.projection
+	}
+
+	fn resize(&mut self, width: f32, height: f32) {
+		self.width = width;
+		self.height = height;
+		self.update_projection();
+	}
+
+	fn reverse_z(&self) -> bool {
+		self.reverse_z
+	}
+}
+
+/// Camera with an orthographic projection, so things are the same size however far away they
+/// are. Used for 2D views and the top, front and side views of editors.
+#[derive(Debug, Clone)]
+pub struct OrthographicCamera {
+	width: f32,
+	height: f32,
+	position: Point3<f32>,
+	rotation: Euler<Rad<f32>>,
+	/// Height of the view in world units, with the width following the aspect ratio
+	view_height: f32,
+	near: f32,
+	far: f32,
+	projection: Matrix4<f32>,
+}
+
+impl Default for OrthographicCamera {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl OrthographicCamera {
+	pub fn new() -> Self {
+		let mut camera = Self {
+			width: 1.0,
+			height: 1.0,
+			position: Point3::new(0.0, 0.0, 0.0),
+			rotation: Euler::new(Rad(0.0), Rad(0.0), Rad(0.0)),
+			view_height: 10.0,
+			near: 0.0,
+			far: 1000.0,
+			projection: Matrix4::identity(),
+		};
+
+		camera.resize(1280.0, 720.0);
+
+		camera
+	}
 
-		(translate * rotate).inverse_transform().unwrap()
+	pub fn with_view_height(mut self, view_height: f32) -> Self {
+		self.set_view_height(view_height);
+		self
+	}
+
+	pub fn with_clip_planes(mut self, near: f32, far: f32) -> Self {
+		self.set_clip_planes(near, far);
+		self
+	}
+
+	pub fn translate(&mut self, x: f32, y: f32, z: f32) {
+		self.position = translate(self.position, &self.rotation, x, y, z);
+	}
+
+	/// Turn the camera. Unlike [`FreeCamera::rotate`] the tilt isn't limited, so the camera can
+	/// look straight down.
+	pub fn rotate(&mut self, x: f32, y: f32, z: f32) {
+		self.rotation = Euler::new(
+			Rad(x) + self.rotation.x,
+			Rad(y) + self.rotation.y,
+			Rad(z) + self.rotation.z,
+		);
+	}
+
+	pub fn position(&self) -> &Point3<f32> {
+		&self.position
+	}
+
+	pub fn rotation(&self) -> &Euler<Rad<f32>> {
+		&self.rotation
+	}
+
+	pub fn position_mut(&mut self) -> &mut Point3<f32> {
+		&mut self.position
+	}
+
+	pub fn rotation_mut(&mut self) -> &mut Euler<Rad<f32>> {
+		&mut self.rotation
+	}
+
+	pub fn width(&self) -> f32 {
+		self.width
+	}
+
+	pub fn height(&self) -> f32 {
+		self.height
+	}
+
+	/// Height of the view in world units. The width follows the aspect ratio.
+	pub fn view_height(&self) -> f32 {
+		self.view_height
+	}
+
+	/// Zoom by showing `view_height` world units from the bottom to the top of the view. Setting
+	/// it to the height in pixels shows one unit per pixel, e.g. for 2D tools.
+	pub fn set_view_height(&mut self, view_height: f32) {
+		self.view_height = view_height;
+		self.update_projection();
+	}
+
+	pub fn near(&self) -> f32 {
+		self.near
+	}
+
+	pub fn far(&self) -> f32 {
+		self.far
+	}
+
+	/// Only draw what's between `near` and `far` in front of the camera. `near` can be negative
+	/// to draw things behind it too.
+	pub fn set_clip_planes(&mut self, near: f32, far: f32) {
+		self.near = near;
+		self.far = far;
+		self.update_projection();
+	}
+
+	/// Ray from the camera through a pixel, with `(0, 0)` at the top left of the screen. Rays
+	/// all point the same way, starting from the near plane.
+	pub fn screen_ray(&self, x: f32, y: f32) -> Ray {
+		Ray::from_screen(self, x, y, self.width, self.height)
+	}
+
+	fn update_projection(&mut self) {
+		let view_width = self.view_height * self.width / self.height;
+		let depth = self.far - self.near;
+		#[rustfmt::skip]
+		let projection = Matrix4::new(
+			2.0 / view_width, 0.0, 0.0, 0.0,
+			0.0, 2.0 / self.view_height, 0.0, 0.0,
+			0.0, 0.0, 1.0 / depth, 0.0,
+			0.0, 0.0, -self.near / depth, 1.0,
+		);
+		self.projection = projection;
+	}
+}
+
+impl Camera for OrthographicCamera {
+	fn view(&self) -> Matrix4<f32> {
+		view_matrix(self.position, &self.rotation)
 	}
 
 	fn projection(&self) -> Matrix4<f32> {
-		self.projection.clone()
+		self.projection
 	}
 
 	fn resize(&mut self, width: f32, height: f32) {
 		self.width = width;
 		self.height = height;
-		let aspect = width / height;
-		let fov = 45.0;
-		let near = 0.1;
-		let far = 1000.0;
-		// cgmath returns RH matrix, but we want LH, so we invert Z to flip it
-		self.projection = cgmath::perspective(Deg(fov), aspect, near, far)
-			* Matrix4::from_nonuniform_scale(1.0, 1.0, -1.0);
+		self.update_projection();
 	}
 }
+
+fn rotation_matrix(rotation: &Euler<Rad<f32>>) -> Matrix4<f32> {
+	let x: Matrix4<f32> = Euler::new(rotation.x, Rad(0.0), Rad(0.0)).into();
+	let y: Matrix4<f32> = Euler::new(Rad(0.0), rotation.y, Rad(0.0)).into();
+	let z: Matrix4<f32> = Euler::new(Rad(0.0), Rad(0.0), rotation.z).into();
+
+	z * y * x
+}
+
+/// Move a camera's position relative to the way it's facing.
+fn translate(
+	position: Point3<f32>,
+	rotation: &Euler<Rad<f32>>,
+	x: f32,
+	y: f32,
+	z: f32,
+) -> Point3<f32> {
+	let trans = Matrix4::from_translation(Vector3::new(x, y, z));
+	let rotate = rotation_matrix(rotation);
+	let rotate_inv = rotate.inverse_transform().unwrap();
+
+	let new_pos = (trans * rotate_inv).transform_point(position);
+	rotate.transform_point(new_pos)
+}
+
+fn view_matrix(position: Point3<f32>, rotation: &Euler<Rad<f32>>) -> Matrix4<f32> {
+	let translate: Matrix4<f32> = Matrix4::from_translation(position.to_vec());
+	let rotate = rotation_matrix(rotation);
+
+	(translate * rotate).inverse_transform().unwrap()
+}
@@ -46,5 +46,5 @@ fn hue_to_rgb(p: f32, q: f32, mut t: f32) -> f32 {
 		return p + (q - p) * (2.0 / 3.0 - t) * 6.0;
 	}
 
-	return p;
+	p
 }
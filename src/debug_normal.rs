@@ -11,6 +11,12 @@ pub struct DebugNormals {
 	pub transform: Matrix4<f32>,
 }
 
+impl Default for DebugNormals {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
 impl DebugNormals {
 	pub fn new() -> Self {
 		Self {
@@ -28,7 +34,7 @@ impl DebugNormals {
 			.map(|v| {
 				[
 					SimpleVertex {
-						position: v.position.clone(),
+						position: v.position,
 						..Default::default()
 					},
 					SimpleVertex {
@@ -82,7 +88,7 @@ impl SceneObject for DebugNormals {
 		if let Some(buffer) = self.vertex_buffer.as_ref() {
 			let render_pass = &mut ctx.render_pass;
 			let len = self.lines.len() as u32 * 2;
-			render_pass.set_vertex_buffer(0, buffer.slice(..));
+			render_pass.set_vertex_buffer(0, buffer);
 			render_pass.draw(0..len, 0..1);
 		}
 	}
@@ -97,7 +103,7 @@ impl SceneObject for DebugNormals {
 	}
 
 	fn transform(&self) -> Matrix4<f32> {
-		self.transform.clone()
+		self.transform
 	}
 
 	fn material(&self) -> &dyn Material {
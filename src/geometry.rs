@@ -1,5 +1,6 @@
-use crate::Vertex;
-use std::{error::Error, fmt, mem::size_of_val};
+use crate::{BoundingBox, Bvh, Ray, TriangleHit, Vertex};
+use cgmath::Point3;
+use std::{cell::OnceCell, error::Error, fmt, mem::size_of_val};
 use wgpu::util::{BufferInitDescriptor, DeviceExt};
 
 #[derive(Debug)]
@@ -12,16 +13,100 @@ impl fmt::Display for GeometryError {
 	}
 }
 
+/// Index buffer contents, as 16 or 32-bit integers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Indices {
+	U16(Vec<u16>),
+	U32(Vec<u32>),
+}
+
+impl Indices {
+	/// Indices into `vertex_count` vertices, using the smallest integer type that fits them.
+	pub fn new(indices: Vec<u32>, vertex_count: usize) -> Self {
+		if vertex_count <= u16::MAX as usize + 1 {
+			Indices::U16(indices.into_iter().map(|index| index as u16).collect())
+		} else {
+			Indices::U32(indices)
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		match self {
+			Indices::U16(indices) => indices.len(),
+			Indices::U32(indices) => indices.len(),
+		}
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	pub fn format(&self) -> wgpu::IndexFormat {
+		match self {
+			Indices::U16(_) => wgpu::IndexFormat::Uint16,
+			Indices::U32(_) => wgpu::IndexFormat::Uint32,
+		}
+	}
+
+	pub fn get(&self, i: usize) -> Option<u32> {
+		match self {
+			Indices::U16(indices) => indices.get(i).map(|&index| index as u32),
+			Indices::U32(indices) => indices.get(i).copied(),
+		}
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+		(0..self.len()).filter_map(move |i| self.get(i))
+	}
+
+	fn as_bytes(&self) -> &[u8] {
+		match self {
+			Indices::U16(indices) => bytemuck::cast_slice(indices),
+			Indices::U32(indices) => bytemuck::cast_slice(indices),
+		}
+	}
+}
+
+impl From<Vec<u16>> for Indices {
+	fn from(indices: Vec<u16>) -> Self {
+		Indices::U16(indices)
+	}
+}
+
+impl From<Vec<u32>> for Indices {
+	fn from(indices: Vec<u32>) -> Self {
+		Indices::U32(indices)
+	}
+}
+
+/// Vertices and optionally indices of triangles.
+///
+/// Meshes can share one geometry through an [`Rc`](std::rc::Rc), so it's uploaded once and the
+/// meshes are drawn one after another without binding other buffers in between.
 pub struct Geometry<V: Vertex> {
 	vertices: Vec<V>,
-	vertex_buffer: Option<wgpu::Buffer>,
+	indices: Option<Indices>,
+	/// Allocated when the geometry is first drawn, and again after it changes
+	buffers: OnceCell<GeometryBuffers>,
+	/// Bounds of the vertices, worked out when first needed
+	bounds: OnceCell<Option<BoundingBox>>,
+	/// Hierarchy of the triangles for raycasting, built when first needed
+	bvh: OnceCell<Bvh>,
+}
+
+struct GeometryBuffers {
+	vertex: wgpu::Buffer,
+	index: Option<wgpu::Buffer>,
 }
 
 impl<V: Vertex> Clone for Geometry<V> {
 	fn clone(&self) -> Self {
 		Self {
 			vertices: self.vertices.clone(),
-			vertex_buffer: None,
+			indices: self.indices.clone(),
+			buffers: OnceCell::new(),
+			bounds: OnceCell::new(),
+			bvh: OnceCell::new(),
 		}
 	}
 }
@@ -30,38 +115,72 @@ impl<V: Vertex> Geometry<V> {
 	pub fn new(vertices: Vec<V>) -> Self {
 		Self {
 			vertices,
-			vertex_buffer: None,
+			indices: None,
+			buffers: OnceCell::new(),
+			bounds: OnceCell::new(),
+			bvh: OnceCell::new(),
+		}
+	}
+
+	/// Create geometry whose triangles are built from an index buffer into `vertices`.
+	pub fn indexed(vertices: Vec<V>, indices: impl Into<Indices>) -> Self {
+		Self {
+			vertices,
+			indices: Some(indices.into()),
+			buffers: OnceCell::new(),
+			bounds: OnceCell::new(),
+			bvh: OnceCell::new(),
 		}
 	}
 
-	pub fn allocate(&mut self, device: &wgpu::Device) -> Result<(), GeometryError> {
-		self.free()?;
+	/// Allocate the buffers, unless they already match the vertices and indices. Called before the
+	/// geometry is drawn.
+	pub fn allocate(&self, device: &wgpu::Device) -> Result<(), GeometryError> {
+		self.buffers.get_or_init(|| {
+			let contents = bytemuck::cast_slice(&self.vertices);
 
-		let contents = bytemuck::cast_slice(&self.vertices);
+			log::debug!(
+				"Allocating geometry vertex buffer ({} vertices / {} bytes)",
+				self.vertices.len(),
+				size_of_val(contents)
+			);
 
-		log::debug!(
-			"Allocating geometry vertex buffer ({} vertices / {} bytes)",
-			self.vertices.len(),
-			size_of_val(contents)
-		);
+			let vertex = device.create_buffer_init(&BufferInitDescriptor {
+				label: Some("Geometry Vertex Buffer"),
+				contents,
+				usage: wgpu::BufferUsages::VERTEX,
+			});
 
-		let vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
-			label: Some("Geometry Vertex Buffer"),
-			contents,
-			usage: wgpu::BufferUsages::VERTEX,
-		});
+			let index = self.indices.as_ref().map(|indices| {
+				let contents = indices.as_bytes();
+
+				log::debug!(
+					"Allocating geometry index buffer ({} indices / {} bytes)",
+					indices.len(),
+					size_of_val(contents)
+				);
 
-		self.vertex_buffer = Some(vertex_buffer);
+				device.create_buffer_init(&BufferInitDescriptor {
+					label: Some("Geometry Index Buffer"),
+					contents,
+					usage: wgpu::BufferUsages::INDEX,
+				})
+			});
+
+			GeometryBuffers { vertex, index }
+		});
 
 		Ok(())
 	}
 
 	pub fn free(&mut self) -> Result<(), GeometryError> {
-		if let Some(buffer) = self.vertex_buffer.take() {
-			log::debug!("Freeing geometry vertex buffer");
-			buffer.destroy();
+		if let Some(buffers) = self.buffers.take() {
+			log::debug!("Freeing geometry buffers");
+			buffers.vertex.destroy();
+			if let Some(index) = buffers.index {
+				index.destroy();
+			}
 		}
-
 		Ok(())
 	}
 
@@ -69,9 +188,42 @@ impl<V: Vertex> Geometry<V> {
 		self.vertices.len()
 	}
 
-	/// Get a reference to the geometry's vertex buffer.
+	/// Number of indices, or 0 if the geometry isn't indexed.
+	pub fn index_count(&self) -> usize {
+		self.indices.as_ref().map_or(0, Indices::len)
+	}
+
+	pub fn is_indexed(&self) -> bool {
+		self.indices.is_some()
+	}
+
+	/// Get a reference to the geometry's vertex buffer, if it's allocated.
 	pub fn vertex_buffer(&self) -> Option<&wgpu::Buffer> {
-		self.vertex_buffer.as_ref()
+		self.buffers.get().map(|buffers| &buffers.vertex)
+	}
+
+	/// Get a reference to the geometry's index buffer, if it's indexed and allocated.
+	pub fn index_buffer(&self) -> Option<&wgpu::Buffer> {
+		self.buffers.get()?.index.as_ref()
+	}
+
+	/// Get a reference to the geometry's indices.
+	pub fn indices(&self) -> Option<&Indices> {
+		self.indices.as_ref()
+	}
+
+	/// Get a mutable reference to the geometry's indices. The buffers are allocated again before
+	/// the geometry is next drawn.
+	pub fn indices_mut(&mut self) -> Option<&mut Indices> {
+		self.set_changed();
+		self.indices.as_mut()
+	}
+
+	/// Set the geometry's indices. The buffers are allocated again before the geometry is next
+	/// drawn.
+	pub fn set_indices(&mut self, indices: Option<Indices>) {
+		self.set_changed();
+		self.indices = indices;
 	}
 
 	/// Get a reference to the geometry's vertices.
@@ -79,10 +231,71 @@ impl<V: Vertex> Geometry<V> {
 		self.vertices.as_ref()
 	}
 
-	/// Get a mutable reference to the geometry's vertices.
+	/// Get a mutable reference to the geometry's vertices. The buffers are allocated again before
+	/// the geometry is next drawn.
 	pub fn vertices_mut(&mut self) -> &mut Vec<V> {
+		self.set_changed();
 		&mut self.vertices
 	}
+
+	/// Drop everything built from the vertices and indices. The buffers aren't destroyed, as
+	/// passes that haven't been submitted yet may still draw from them.
+	fn set_changed(&mut self) {
+		self.buffers = OnceCell::new();
+		self.bounds = OnceCell::new();
+		self.bvh = OnceCell::new();
+	}
+
+	/// Box containing every vertex, or `None` if the vertices have no position.
+	pub fn bounds(&self) -> Option<BoundingBox> {
+		*self.bounds.get_or_init(|| {
+			let positions: Option<Vec<_>> = self
+				.vertices
+				.iter()
+				.map(|vertex| vertex.position())
+				.collect();
+			BoundingBox::from_points(positions?)
+		})
+	}
+
+	/// Number of triangles, from the indices if there are any.
+	pub fn triangle_count(&self) -> usize {
+		if self.is_indexed() {
+			self.index_count() / 3
+		} else {
+			self.vertex_count() / 3
+		}
+	}
+
+	/// Corners of a triangle, or `None` if it's out of range or the vertices have no position.
+	pub fn triangle(&self, index: usize) -> Option<[Point3<f32>; 3]> {
+		let vertex = |corner: usize| {
+			let i = 3 * index + corner;
+			let i = match &self.indices {
+				Some(indices) => indices.get(i)? as usize,
+				None => i,
+			};
+			self.vertices.get(i)?.position()
+		};
+		Some([vertex(0)?, vertex(1)?, vertex(2)?])
+	}
+
+	/// Hierarchy of the triangles with a position, built the first time it's needed.
+	pub fn bvh(&self) -> &Bvh {
+		self.bvh.get_or_init(|| {
+			Bvh::with_indices(
+				(0..self.triangle_count())
+					.filter_map(|index| Some((index, self.triangle(index)?)))
+					.collect(),
+			)
+		})
+	}
+
+	/// Nearest triangle a ray hits.
+	pub fn raycast(&self, ray: &Ray) -> Option<TriangleHit> {
+		ray.intersect_box(&self.bounds()?)?;
+		self.bvh().raycast(ray)
+	}
 }
 
 impl<V: Vertex> Drop for Geometry<V> {
@@ -93,15 +306,12 @@ impl<V: Vertex> Drop for Geometry<V> {
 
 impl<V: Vertex + From<&'static [f32; 3]>> Geometry<V> {
 	pub fn cube() -> Self {
-		let vertices: Vec<V> = CUBE_VERTICES.iter().map(|vert| V::from(&vert)).collect();
-		Self {
-			vertices,
-			vertex_buffer: None,
-		}
+		let vertices: Vec<V> = CUBE_VERTICES.iter().map(V::from).collect();
+		Self::new(vertices)
 	}
 }
 
-#[cfg_attr(rustfmt, rustfmt_skip)]
+#[rustfmt::skip]
 const CUBE_VERTICES: [[f32; 3]; 36] = [
 	// Far
 	[-1.0, 1.0, -1.0],
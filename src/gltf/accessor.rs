@@ -0,0 +1,332 @@
+use super::{parse::*, GltfError};
+use cgmath::{Point2, Point3, Vector2, Vector3, Vector4};
+use std::convert::TryInto;
+
+/// Component types from the glTF spec
+const BYTE: u64 = 5120;
+const UNSIGNED_BYTE: u64 = 5121;
+const SHORT: u64 = 5122;
+const UNSIGNED_SHORT: u64 = 5123;
+const UNSIGNED_INT: u64 = 5125;
+const FLOAT: u64 = 5126;
+
+/// A value that can be read from a float (or normalized integer) glTF accessor.
+pub trait AccessorElement: Sized {
+	/// Number of components per element, e.g. 3 for a `VEC3`
+	const COMPONENTS: usize;
+
+	fn from_components(components: &[f32]) -> Self;
+}
+
+impl AccessorElement for f32 {
+	const COMPONENTS: usize = 1;
+
+	fn from_components(c: &[f32]) -> Self {
+		c[0]
+	}
+}
+
+impl AccessorElement for Point2<f32> {
+	const COMPONENTS: usize = 2;
+
+	fn from_components(c: &[f32]) -> Self {
+		Point2::new(c[0], c[1])
+	}
+}
+
+impl AccessorElement for Vector2<f32> {
+	const COMPONENTS: usize = 2;
+
+	fn from_components(c: &[f32]) -> Self {
+		Vector2::new(c[0], c[1])
+	}
+}
+
+impl AccessorElement for Point3<f32> {
+	const COMPONENTS: usize = 3;
+
+	fn from_components(c: &[f32]) -> Self {
+		Point3::new(c[0], c[1], c[2])
+	}
+}
+
+impl AccessorElement for Vector3<f32> {
+	const COMPONENTS: usize = 3;
+
+	fn from_components(c: &[f32]) -> Self {
+		Vector3::new(c[0], c[1], c[2])
+	}
+}
+
+impl AccessorElement for Vector4<f32> {
+	const COMPONENTS: usize = 4;
+
+	fn from_components(c: &[f32]) -> Self {
+		Vector4::new(c[0], c[1], c[2], c[3])
+	}
+}
+
+fn component_size(component_type: u64) -> Option<usize> {
+	match component_type {
+		BYTE | UNSIGNED_BYTE => Some(1),
+		SHORT | UNSIGNED_SHORT => Some(2),
+		UNSIGNED_INT | FLOAT => Some(4),
+		_ => None,
+	}
+}
+
+fn component_count(ty: &str) -> Option<usize> {
+	match ty {
+		"SCALAR" => Some(1),
+		"VEC2" => Some(2),
+		"VEC3" => Some(3),
+		"VEC4" | "MAT2" => Some(4),
+		"MAT3" => Some(9),
+		"MAT4" => Some(16),
+		_ => None,
+	}
+}
+
+fn read_float(component_type: u64, normalized: bool, b: &[u8]) -> f32 {
+	match (component_type, normalized) {
+		(BYTE, false) => b[0] as i8 as f32,
+		(BYTE, true) => (b[0] as i8 as f32 / 127.0).max(-1.0),
+		(UNSIGNED_BYTE, false) => b[0] as f32,
+		(UNSIGNED_BYTE, true) => b[0] as f32 / 255.0,
+		(SHORT, false) => i16::from_le_bytes([b[0], b[1]]) as f32,
+		(SHORT, true) => (i16::from_le_bytes([b[0], b[1]]) as f32 / 32767.0).max(-1.0),
+		(UNSIGNED_SHORT, false) => u16::from_le_bytes([b[0], b[1]]) as f32,
+		(UNSIGNED_SHORT, true) => u16::from_le_bytes([b[0], b[1]]) as f32 / 65535.0,
+		(UNSIGNED_INT, _) => read_uint(component_type, b) as f32,
+		_ => f32::from_le_bytes(b[..4].try_into().unwrap()),
+	}
+}
+
+fn read_uint(component_type: u64, b: &[u8]) -> u32 {
+	match component_type {
+		UNSIGNED_BYTE => b[0] as u32,
+		UNSIGNED_SHORT => u16::from_le_bytes([b[0], b[1]]) as u32,
+		_ => u32::from_le_bytes(b[..4].try_into().unwrap()),
+	}
+}
+
+impl GltfDoc {
+	/// Read every element of a float accessor, converting integer components (normalized or
+	/// not) to `f32`.
+	pub fn accessor<T: AccessorElement>(&self, index: u64) -> Result<Vec<T>, GltfError> {
+		let accessor = self.accessor_doc(index)?;
+		let components = component_count(&accessor.ty).unwrap_or(0);
+		if components != T::COMPONENTS {
+			return Err(invalid(
+				index,
+				format!(
+					"expected {} components but found {}",
+					T::COMPONENTS,
+					accessor.ty
+				),
+			));
+		}
+
+		let normalized = accessor.normalized;
+		let values = self.read_accessor(index, |ty, b| read_float(ty, normalized, b))?;
+
+		Ok(values
+			.chunks_exact(components)
+			.map(T::from_components)
+			.collect())
+	}
+
+	/// Read an accessor of unsigned integer scalars, such as a primitive's indices.
+	pub fn indices(&self, index: u64) -> Result<Vec<u32>, GltfError> {
+		let accessor = self.accessor_doc(index)?;
+		if accessor.ty != "SCALAR" {
+			return Err(invalid(
+				index,
+				format!("expected SCALAR but found {}", accessor.ty),
+			));
+		}
+		if !matches!(
+			accessor.component_type,
+			UNSIGNED_BYTE | UNSIGNED_SHORT | UNSIGNED_INT
+		) || accessor.normalized
+		{
+			return Err(invalid(
+				index,
+				format!(
+					"component type {} can't be used for indices",
+					accessor.component_type
+				),
+			));
+		}
+
+		self.read_accessor(index, read_uint)
+	}
+
+	fn accessor_doc(&self, index: u64) -> Result<&AccessorDoc, GltfError> {
+		self.accessors
+			.get(index as usize)
+			.ok_or_else(|| invalid(index, "accessor does not exist".into()))
+	}
+
+	/// Read an accessor's components in order, with any sparse substitutions applied.
+	fn read_accessor<S: Copy + Default>(
+		&self,
+		index: u64,
+		read: impl Fn(u64, &[u8]) -> S,
+	) -> Result<Vec<S>, GltfError> {
+		let accessor = self.accessor_doc(index)?;
+		let size = component_size(accessor.component_type).ok_or_else(|| {
+			invalid(
+				index,
+				format!("unknown component type {}", accessor.component_type),
+			)
+		})?;
+		let components = component_count(&accessor.ty)
+			.ok_or_else(|| invalid(index, format!("unknown type {}", accessor.ty)))?;
+		if accessor.ty.starts_with("MAT") && size < 4 {
+			// Matrix columns would need padding to 4 bytes
+			return Err(invalid(
+				index,
+				format!("{} with {} byte components", accessor.ty, size),
+			));
+		}
+		let element_size = size * components;
+		let count = accessor.count as usize;
+
+		let mut values = match accessor.buffer_view {
+			Some(view) => {
+				let stride = self
+					.buffer_views
+					.get(view as usize)
+					.ok_or(GltfError::MissingBuffer)?
+					.byte_stride
+					.map_or(element_size, |stride| stride as usize);
+				if stride < element_size {
+					return Err(invalid(
+						index,
+						format!("stride {} is smaller than an element", stride),
+					));
+				}
+				let data = self.element_data(
+					index,
+					view,
+					accessor.byte_offset,
+					count,
+					stride,
+					element_size,
+				)?;
+				// The data holds `count` elements of `components` each, so this can't overflow
+				let mut values = Vec::with_capacity(count * components);
+				for i in 0..count {
+					let element = &data[i * stride..];
+					for c in 0..components {
+						values.push(read(accessor.component_type, &element[c * size..]));
+					}
+				}
+				values
+			}
+			None => {
+				let len = count
+					.checked_mul(components)
+					.ok_or_else(|| invalid(index, format!("{} elements is too many", count)))?;
+				let mut values = Vec::new();
+				values
+					.try_reserve_exact(len)
+					.map_err(|_| invalid(index, format!("{} elements is too many", count)))?;
+				values.resize(len, S::default());
+				values
+			}
+		};
+
+		if let Some(sparse) = &accessor.sparse {
+			let count = sparse.count as usize;
+			let index_size = match sparse.indices.component_type {
+				UNSIGNED_BYTE | UNSIGNED_SHORT | UNSIGNED_INT => {
+					component_size(sparse.indices.component_type).unwrap()
+				}
+				ty => {
+					return Err(invalid(
+						index,
+						format!("sparse indices can't use component type {}", ty),
+					))
+				}
+			};
+			let indices = self.element_data(
+				index,
+				sparse.indices.buffer_view,
+				sparse.indices.byte_offset,
+				count,
+				index_size,
+				index_size,
+			)?;
+			let sparse_values = self.element_data(
+				index,
+				sparse.values.buffer_view,
+				sparse.values.byte_offset,
+				count,
+				element_size,
+				element_size,
+			)?;
+
+			for i in 0..count {
+				let target =
+					read_uint(sparse.indices.component_type, &indices[i * index_size..]) as usize;
+				if target >= accessor.count as usize {
+					return Err(invalid(
+						index,
+						format!("sparse index {} is out of range", target),
+					));
+				}
+				let element = &sparse_values[i * element_size..];
+				for c in 0..components {
+					values[target * components + c] =
+						read(accessor.component_type, &element[c * size..]);
+				}
+			}
+		}
+
+		Ok(values)
+	}
+
+	/// Get the bytes for `count` elements starting at `offset` in a buffer view, checking the
+	/// last element ends within the view. Counts too large to fit are errors rather than
+	/// overflowing.
+	fn element_data(
+		&self,
+		index: u64,
+		view: u64,
+		offset: u64,
+		count: usize,
+		stride: usize,
+		element_size: usize,
+	) -> Result<&[u8], GltfError> {
+		let data = self.buffer_view(view)?;
+		let outside = || {
+			invalid(
+				index,
+				format!(
+					"{} elements at offset {} is outside buffer view {} ({} bytes)",
+					count,
+					offset,
+					view,
+					data.len()
+				),
+			)
+		};
+		let len = match count {
+			0 => Some(0),
+			_ => (count - 1)
+				.checked_mul(stride)
+				.and_then(|len| len.checked_add(element_size)),
+		};
+		let range = len
+			.zip(usize::try_from(offset).ok())
+			.and_then(|(len, start)| Some(start..start.checked_add(len)?))
+			.ok_or_else(outside)?;
+		data.get(range).ok_or_else(outside)
+	}
+}
+
+fn invalid(index: u64, message: String) -> GltfError {
+	GltfError::InvalidAccessor(index as usize, message)
+}
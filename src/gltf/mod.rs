@@ -1,17 +1,45 @@
-use crate::{BasicMaterial, Color, Geometry, Mesh, Texture, Vertex};
+use crate::{
+	AlphaMode, Color, Geometry, Group, Handle, Indices, Mesh, ObjectID, PbrMaterial, Scene,
+	Texture, Vertex,
+};
 use byd_derive::CastBytes;
-use cgmath::{Matrix4, Point2, Point3, SquareMatrix, Vector3, Vector4};
-use std::{collections::HashMap, error, fs::File, io::BufReader, mem, path::Path};
+use cgmath::{Matrix4, Point2, Point3, SquareMatrix, Vector3};
+use std::{
+	collections::{HashMap, HashSet},
+	error, fs, mem,
+};
 use thiserror::Error;
 use wgpu::VertexFormat::{Float32x2, Float32x3};
 
+mod accessor;
 pub mod parse;
+pub use accessor::AccessorElement;
 use parse::*;
 
 #[derive(Error, Debug)]
 pub enum GltfError {
-	#[error("File not found")]
-	FileNotFound,
+	#[error("File not found: {0}")]
+	FileNotFound(String),
+	#[error("Invalid GLB container: {0}")]
+	InvalidGlb(String),
+	#[error("Unsupported URI: {0}")]
+	UnsupportedUri(String),
+	#[error("Missing buffer data")]
+	MissingBuffer,
+	#[error("Invalid buffer: {0}")]
+	InvalidBuffer(String),
+	#[error("Invalid accessor {0}: {1}")]
+	InvalidAccessor(usize, String),
+	#[error("Invalid material {0}: {1}")]
+	InvalidMaterial(usize, String),
+	#[error("Invalid mesh {0}: {1}")]
+	InvalidMesh(usize, String),
+	#[error("Invalid node {0}: {1}")]
+	InvalidNode(usize, String),
+	#[error("Invalid scene {0}: {1}")]
+	InvalidScene(usize, String),
+	#[error("Failed to load image {0}: {1}")]
+	InvalidImage(usize, String),
 	#[error("Unknown error")]
 	Unknown(String),
 }
@@ -22,159 +50,471 @@ impl From<Box<dyn error::Error>> for GltfError {
 	}
 }
 
-fn parse_gltf_json(filename: &str) -> Result<GltfDoc, Box<dyn error::Error>> {
+fn parse_gltf(filename: &str) -> Result<GltfDoc, GltfError> {
 	log::debug!("Reading glTF file: {}", filename);
-	let file = File::open(filename)?;
-	let reader = BufReader::new(file);
-	let mut doc: GltfDoc = serde_json::from_reader(reader)?;
-	doc.uri = filename.into();
-	Ok(doc)
+	let bytes = fs::read(filename).map_err(|_| GltfError::FileNotFound(filename.into()))?;
+	GltfDoc::from_slice(&bytes, filename)
 }
 
 pub struct Gltf {
 	pub meshes: Vec<Mesh<PrimitiveVertex>>,
+	/// One texture per glTF image
 	pub textures: Vec<Texture>,
+	/// Materials in the same order as the glTF `materials` array. Texture IDs are indices into
+	/// [`Gltf::textures`] until [`Gltf::add_to_scene`] maps them to scene textures.
+	pub materials: Vec<PbrMaterial>,
+	/// Index into [`Gltf::textures`] of each mesh's base color texture
 	pub mesh_textures: HashMap<usize, usize>,
+	/// Every node in the file, in the same order as the glTF `nodes` array
+	pub nodes: Vec<GltfNode>,
+}
+
+/// Scene objects created by [`Gltf::add_to_scene`].
+#[derive(Clone, Debug)]
+pub struct GltfObjects {
+	/// Objects of the nodes without a parent, to move the whole file at once
+	pub roots: Vec<ObjectID>,
+	/// Object of each node, in the same order as [`Gltf::nodes`]
+	pub nodes: Vec<ObjectID>,
+	/// Object of each mesh, in the same order as [`Gltf::meshes`]
+	pub meshes: Vec<ObjectID>,
+	names: Vec<String>,
+}
+
+impl GltfObjects {
+	/// Object of the first node with the given name.
+	pub fn find_node(&self, name: &str) -> Option<ObjectID> {
+		let index = self.names.iter().position(|node| node == name)?;
+		self.nodes.get(index).copied()
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct GltfNode {
+	pub name: String,
+	/// Transform relative to the parent node
+	pub transform: Matrix4<f32>,
+	/// Transform relative to the scene root
+	pub world_transform: Matrix4<f32>,
+	pub parent: Option<usize>,
+	pub children: Vec<usize>,
+	/// Indices into [`Gltf::meshes`] of the primitives attached to this node
+	pub meshes: Vec<usize>,
 }
 
 impl Gltf {
+	/// Load a `.gltf` or `.glb` file.
+	///
+	/// Only the meshes of the file's default scene are loaded, which is the first scene unless
+	/// the file says otherwise.
 	pub fn load(filename: &str) -> Result<Self, GltfError> {
-		let doc = parse_gltf_json(filename)?;
+		let doc = parse_gltf(filename)?;
 
 		let mut mesh_textures = HashMap::new();
 		let mut meshes = vec![];
 		let mut textures = vec![];
 
+		let materials = load_materials(&doc)?;
+
 		fn load_meshes(
 			mesh_id: u64,
 			doc: &GltfDoc,
+			materials: &[PbrMaterial],
 			meshes: &mut Vec<Mesh<PrimitiveVertex>>,
 			mesh_textures: &mut HashMap<usize, usize>,
 			transform: Matrix4<f32>,
-		) {
-			let mesh = &doc.meshes[mesh_id as usize];
+		) -> Result<(), GltfError> {
+			let mesh = doc.meshes.get(mesh_id as usize).ok_or_else(|| {
+				GltfError::InvalidMesh(mesh_id as usize, "mesh does not exist".into())
+			})?;
 			for PrimitiveDoc {
 				indices: indices_id,
 				attributes,
 				material,
 			} in &mesh.primitives
 			{
-				let positions_id = *attributes.get("POSITION").unwrap();
-				let positions: Vec<Point3<f32>> = doc.accessor(positions_id);
+				let positions_id = match attributes.get("POSITION") {
+					Some(id) => *id,
+					None => continue,
+				};
+				let positions: Vec<Point3<f32>> = doc.accessor(positions_id)?;
 
 				let normals: Vec<Vector3<f32>> = if let Some(normals_id) = attributes.get("NORMAL")
 				{
-					doc.accessor(*normals_id)
+					doc.accessor(*normals_id)?
 				} else {
 					vec![]
 				};
 
 				let texcoords: Vec<Point2<f32>> =
 					if let Some(texcoords_id) = attributes.get("TEXCOORD_0") {
-						doc.accessor(*texcoords_id)
+						doc.accessor(*texcoords_id)?
 					} else {
 						vec![]
 					};
 
-				let indices: Vec<u16> = if let Some(indices_id) = indices_id {
-					doc.accessor(*indices_id)
+				let vertices: Vec<PrimitiveVertex> = positions
+					.iter()
+					.enumerate()
+					.map(|(i, position)| PrimitiveVertex {
+						position: *position,
+						normal: *normals.get(i).unwrap_or(&Vector3::new(0.0, 0.0, 0.0)),
+						texcoord: *texcoords.get(i).unwrap_or(&Point2::new(0.0, 0.0)),
+					})
+					.collect();
+
+				let geometry = if let Some(indices_id) = indices_id {
+					let indices = doc.indices(*indices_id)?;
+					if let Some(i) = indices.iter().find(|&&i| i as usize >= vertices.len()) {
+						return Err(GltfError::InvalidAccessor(
+							*indices_id as usize,
+							format!("index {} is out of range", i),
+						));
+					}
+					let indices = if vertices.len() <= u16::MAX as usize + 1 {
+						Indices::U16(indices.into_iter().map(|i| i as u16).collect())
+					} else {
+						Indices::U32(indices)
+					};
+					Geometry::indexed(vertices, indices)
 				} else {
-					(0..positions.len() as u16).collect()
+					Geometry::new(vertices)
 				};
 
-				let mut vertices = Vec::with_capacity(indices.len());
-				for index in indices {
-					vertices.push(PrimitiveVertex {
-						position: positions[index as usize].clone(),
-						normal: normals
-							.get(index as usize)
-							.unwrap_or(&Vector3::new(0.0, 0.0, 0.0))
-							.clone(),
-						texcoord: texcoords
-							.get(index as usize)
-							.unwrap_or(&Point2::new(0.0, 0.0))
-							.clone(),
-						..Default::default()
-					});
+				let material = match material {
+					Some(id) => materials.get(*id as usize).cloned().ok_or_else(|| {
+						GltfError::InvalidMaterial(*id as usize, "material does not exist".into())
+					})?,
+					None => PbrMaterial::default(),
+				};
+				if let Some(texture) = material.base_color_texture {
+					mesh_textures.insert(meshes.len(), texture.index() as usize);
 				}
 
-				let mut mesh = Mesh::new(
-					Geometry::new(vertices),
-					BasicMaterial::new(Color::new(
-						rand::random::<f32>(),
-						rand::random::<f32>(),
-						rand::random::<f32>(),
-						1.0,
-					)),
-				);
+				let mut mesh = Mesh::new(geometry, material);
 				mesh.transform = transform;
 				meshes.push(mesh);
-
-				let texture_id = doc.textures[*material as usize].source as usize;
-				mesh_textures.insert(meshes.len() - 1, texture_id);
 			}
+
+			Ok(())
 		}
 
 		fn load_node(
 			node_id: u64,
 			doc: &GltfDoc,
+			materials: &[PbrMaterial],
 			meshes: &mut Vec<Mesh<PrimitiveVertex>>,
 			mesh_textures: &mut HashMap<usize, usize>,
-			mut transform: Matrix4<f32>,
-		) {
-			let node = &doc.nodes[node_id as usize];
-			let mat: Matrix4<f32> = if let Some(node_trans) = node.translation.as_ref() {
-				Matrix4::from_translation(Vector3::from(*node_trans))
-			} else if let Some(node_mat) = node.matrix.as_ref() {
-				// FIXME hax
-				let cols = unsafe { mem::transmute::<[f32; 16], [[f32; 4]; 4]>(*node_mat) };
-				Matrix4::from(cols)
-			} else {
-				Matrix4::identity()
-			};
-			transform = transform * mat;
-			if let Some(mesh_id) = node.mesh {
-				load_meshes(mesh_id, doc, meshes, mesh_textures, transform.clone());
+			nodes: &mut Vec<GltfNode>,
+			parent_transform: Matrix4<f32>,
+		) -> Result<(), GltfError> {
+			// Scene roots and children are checked to exist before the tree is walked
+			let node = &mut nodes[node_id as usize];
+			let transform = parent_transform * node.transform;
+			node.world_transform = transform;
+			if let Some(mesh_id) = doc.nodes[node_id as usize].mesh {
+				let first = meshes.len();
+				load_meshes(mesh_id, doc, materials, meshes, mesh_textures, transform)?;
+				nodes[node_id as usize].meshes = (first..meshes.len()).collect();
+			}
+			for child_id in nodes[node_id as usize].children.clone() {
+				load_node(
+					child_id as _,
+					doc,
+					materials,
+					meshes,
+					mesh_textures,
+					nodes,
+					transform,
+				)?;
 			}
-			if let Some(children) = node.children.as_ref() {
-				for child_id in children {
-					load_node(*child_id, doc, meshes, mesh_textures, transform.clone());
+
+			Ok(())
+		}
+
+		let mut nodes: Vec<GltfNode> = doc
+			.nodes
+			.iter()
+			.map(|node| GltfNode {
+				name: node.name.clone(),
+				transform: node.transform(),
+				world_transform: node.transform(),
+				parent: None,
+				children: node
+					.children
+					.iter()
+					.flatten()
+					.map(|&id| id as usize)
+					.collect(),
+				meshes: vec![],
+			})
+			.collect();
+		// A node with two parents could make the tree loop back on itself
+		for id in 0..nodes.len() {
+			for child_id in nodes[id].children.clone() {
+				let child = nodes.get_mut(child_id).ok_or_else(|| {
+					GltfError::InvalidNode(id, format!("child {} does not exist", child_id))
+				})?;
+				if child.parent.is_some() {
+					return Err(GltfError::InvalidNode(
+						child_id,
+						"node has more than one parent".into(),
+					));
 				}
+				child.parent = Some(id);
 			}
 		}
 
-		for scene in &doc.scenes {
+		// A file without scenes has nothing to show
+		let scene = match doc.scenes.get(doc.scene as usize) {
+			None if doc.scenes.is_empty() && doc.scene == 0 => None,
+			None => {
+				return Err(GltfError::InvalidScene(
+					doc.scene as usize,
+					"scene does not exist".into(),
+				))
+			}
+			scene => scene,
+		};
+		if let Some(scene) = scene {
 			for node_id in &scene.nodes {
+				match nodes.get(*node_id as usize) {
+					Some(node) if node.parent.is_none() => {}
+					Some(_) => {
+						return Err(GltfError::InvalidNode(
+							*node_id as usize,
+							"scene root has a parent".into(),
+						))
+					}
+					None => {
+						return Err(GltfError::InvalidNode(
+							*node_id as usize,
+							"node does not exist".into(),
+						))
+					}
+				}
 				load_node(
 					*node_id,
 					&doc,
+					&materials,
 					&mut meshes,
 					&mut mesh_textures,
+					&mut nodes,
 					Matrix4::identity(),
-				);
+				)?;
 			}
 		}
 
-		for image in &doc.images {
-			let filename = Path::new(filename)
-				.parent()
-				.unwrap()
-				.join(&image.uri)
-				.to_str()
-				.unwrap()
-				.to_string();
-
-			textures.push(
-				Texture::load(&filename).expect(&format!("Failed to open image: {}", filename)),
-			);
+		// Images only used for non-colour data shouldn't be sRGB decoded
+		let color_images: HashSet<usize> = materials
+			.iter()
+			.flat_map(|m| [m.base_color_texture, m.emissive_texture])
+			.flatten()
+			.map(|id| id.index() as usize)
+			.collect();
+		let data_images: HashSet<usize> = materials
+			.iter()
+			.flat_map(|m| {
+				[
+					m.metallic_roughness_texture,
+					m.normal_texture,
+					m.occlusion_texture,
+				]
+			})
+			.flatten()
+			.map(|id| id.index() as usize)
+			.collect();
+
+		for (i, image) in doc.images.iter().enumerate() {
+			let bytes = match (&image.uri, image.buffer_view) {
+				(Some(uri), _) => doc.read_uri(uri)?,
+				(None, Some(view)) => doc.buffer_view(view)?.to_vec(),
+				(None, None) => return Err(GltfError::InvalidImage(i, "no image data".into())),
+			};
+			let mut texture = Texture::from_image_bytes(&bytes)
+				.map_err(|e| GltfError::InvalidImage(i, e.to_string()))?;
+			texture.set_srgb(!data_images.contains(&i) || color_images.contains(&i));
+			textures.push(texture);
 		}
 
 		Ok(Self {
 			meshes,
 			textures,
+			materials,
 			mesh_textures,
+			nodes,
 		})
 	}
+
+	/// Add every texture, node and mesh to a scene, pointing the meshes' materials at the scene's
+	/// texture IDs. Nodes become [`Group`]s with the same hierarchy, and each mesh is a child of
+	/// its node.
+	pub fn add_to_scene(self, scene: &mut Scene) -> GltfObjects {
+		let texture_ids: Vec<_> = self
+			.textures
+			.into_iter()
+			.map(|texture| scene.add_texture(texture))
+			.collect();
+
+		// Parents are added before their children, and siblings in order
+		let mut nodes: Vec<Option<ObjectID>> = vec![None; self.nodes.len()];
+		let mut stack: Vec<usize> = (0..self.nodes.len())
+			.rev()
+			.filter(|&i| self.nodes[i].parent.is_none())
+			.collect();
+		while let Some(i) = stack.pop() {
+			if nodes[i].is_some() {
+				continue;
+			}
+			let group = Group::new(self.nodes[i].transform);
+			let id = match self.nodes[i].parent.and_then(|parent| nodes[parent]) {
+				Some(parent) => scene
+					.add_child(parent, group)
+					.expect("Parent was just added"),
+				None => scene.add(group),
+			};
+			nodes[i] = Some(id);
+			stack.extend(self.nodes[i].children.iter().rev());
+		}
+		// Nodes in a cycle of parents can't be reached from a root
+		let nodes: Vec<ObjectID> = nodes
+			.into_iter()
+			.zip(&self.nodes)
+			.map(|(id, node)| id.unwrap_or_else(|| scene.add(Group::new(node.transform))))
+			.collect();
+
+		let mut mesh_nodes = HashMap::new();
+		for (node, id) in self.nodes.iter().zip(&nodes) {
+			for &mesh in &node.meshes {
+				mesh_nodes.insert(mesh, *id);
+			}
+		}
+
+		let meshes = self
+			.meshes
+			.into_iter()
+			.enumerate()
+			.map(|(i, mut mesh)| {
+				if let Some(material) = mesh.material.downcast_mut::<PbrMaterial>() {
+					material.map_textures(|i| texture_ids[i.index() as usize]);
+				}
+				// Meshes are loaded with their node's world transform baked in
+				match mesh_nodes.get(&i).copied() {
+					Some(node) => {
+						mesh.transform = Matrix4::identity();
+						scene.add_child(node, mesh).expect("Node was just added")
+					}
+					None => scene.add(mesh),
+				}
+			})
+			.collect();
+
+		GltfObjects {
+			roots: nodes
+				.iter()
+				.filter(|id| scene.parent(**id).is_none())
+				.copied()
+				.collect(),
+			nodes,
+			meshes,
+			names: self.nodes.into_iter().map(|node| node.name).collect(),
+		}
+	}
+
+	/// Find the first node with the given name.
+	pub fn find_node(&self, name: &str) -> Option<&GltfNode> {
+		self.nodes.iter().find(|node| node.name == name)
+	}
+
+	/// Meshes attached to the first node with the given name.
+	pub fn node_meshes(&self, name: &str) -> impl Iterator<Item = &Mesh<PrimitiveVertex>> {
+		self.find_node(name)
+			.into_iter()
+			.flat_map(|node| node.meshes.iter())
+			.map(|&i| &self.meshes[i])
+	}
+}
+
+/// Convert every glTF material into a [`PbrMaterial`]. Texture IDs are the indices of the glTF
+/// images they sample.
+pub fn load_materials(doc: &GltfDoc) -> Result<Vec<PbrMaterial>, GltfError> {
+	doc.materials
+		.iter()
+		.enumerate()
+		.map(|(i, material)| load_material(i, material, doc))
+		.collect()
+}
+
+fn load_material(
+	index: usize,
+	doc: &MaterialDoc,
+	gltf: &GltfDoc,
+) -> Result<PbrMaterial, GltfError> {
+	// Texture IDs refer to images until the textures are added to a scene
+	let texture = |texture: u64| {
+		gltf.textures
+			.get(texture as usize)
+			.map(|texture| texture.source as usize)
+			.filter(|&image| image < gltf.images.len())
+			.map(Handle::from_index)
+			.ok_or_else(|| {
+				GltfError::InvalidMaterial(index, format!("texture {} does not exist", texture))
+			})
+	};
+
+	let pbr = &doc.pbr_metallic_roughness;
+	let [r, g, b, a] = pbr.base_color_factor;
+	let [er, eg, eb] = doc.emissive_factor;
+
+	Ok(PbrMaterial {
+		base_color: Color::new(r, g, b, a),
+		base_color_texture: pbr
+			.base_color_texture
+			.as_ref()
+			.map(|info| texture(info.index))
+			.transpose()?,
+		metallic: pbr.metallic_factor,
+		roughness: pbr.roughness_factor,
+		metallic_roughness_texture: pbr
+			.metallic_roughness_texture
+			.as_ref()
+			.map(|info| texture(info.index))
+			.transpose()?,
+		normal_texture: doc
+			.normal_texture
+			.as_ref()
+			.map(|info| texture(info.index))
+			.transpose()?,
+		normal_scale: doc.normal_texture.as_ref().map_or(1.0, |info| info.scale),
+		occlusion_texture: doc
+			.occlusion_texture
+			.as_ref()
+			.map(|info| texture(info.index))
+			.transpose()?,
+		occlusion_strength: doc
+			.occlusion_texture
+			.as_ref()
+			.map_or(1.0, |info| info.strength),
+		emissive: Vector3::new(er, eg, eb),
+		emissive_texture: doc
+			.emissive_texture
+			.as_ref()
+			.map(|info| texture(info.index))
+			.transpose()?,
+		alpha_mode: match doc.alpha_mode.as_str() {
+			"OPAQUE" => AlphaMode::Opaque,
+			"MASK" => AlphaMode::Mask,
+			"BLEND" => AlphaMode::Blend,
+			mode => {
+				return Err(GltfError::InvalidMaterial(
+					index,
+					format!("unknown alphaMode {}", mode),
+				))
+			}
+		},
+		alpha_cutoff: doc.alpha_cutoff,
+		double_sided: doc.double_sided,
+	})
 }
 
 #[derive(Copy, Clone, Debug, CastBytes)]
@@ -199,6 +539,10 @@ impl Default for PrimitiveVertex {
 }
 
 impl Vertex for PrimitiveVertex {
+	fn position(&self) -> Option<Point3<f32>> {
+		Some(self.position)
+	}
+
 	fn buffer_layout<'a>() -> wgpu::VertexBufferLayout<'a> {
 		wgpu::VertexBufferLayout {
 			array_stride: mem::size_of::<Self>() as _,
@@ -223,10 +567,3 @@ impl Vertex for PrimitiveVertex {
 		}
 	}
 }
-
-fn calculate_normal(tri: &[PrimitiveVertex]) -> Vector3<f32> {
-	let u = tri[1].position - tri[0].position;
-	let v = tri[2].position - tri[0].position;
-
-	u.cross(v)
-}
@@ -1,84 +1,200 @@
+use super::GltfError;
+use cgmath::{Matrix4, Quaternion, SquareMatrix, Vector3};
 use serde::Deserialize;
-use std::{
-	collections::HashMap,
-	fs::File,
-	io::{Read, Seek, SeekFrom},
-	mem,
-	ops::Range,
-	path::Path,
-};
+use std::{collections::HashMap, convert::TryInto, fs, ops::Range, path::Path};
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const GLB_CHUNK_JSON: u32 = 0x4E4F534A;
+const GLB_CHUNK_BIN: u32 = 0x004E4942;
 
 #[derive(Deserialize, Debug)]
 pub struct GltfDoc {
 	#[serde(skip)]
 	pub uri: String,
+	/// Contents of each buffer, filled in by [`GltfDoc::load_buffers`]
+	#[serde(skip)]
+	pub buffer_data: Vec<Vec<u8>>,
 	pub asset: AssetDoc,
 	#[serde(default, rename(deserialize = "extensionsUsed"))]
 	pub extensions_used: Vec<String>,
 	#[serde(default, rename(deserialize = "extensionsRequired"))]
 	pub extensions_required: Vec<String>,
+	#[serde(default)]
 	pub scene: u64,
+	#[serde(default)]
 	pub scenes: Vec<SceneDoc>,
+	#[serde(default)]
 	pub nodes: Vec<NodeDoc>,
+	#[serde(default)]
 	pub materials: Vec<MaterialDoc>,
+	#[serde(default)]
 	pub meshes: Vec<MeshDoc>,
+	#[serde(default)]
 	pub textures: Vec<TextureDoc>,
+	#[serde(default)]
 	pub images: Vec<ImageDoc>,
+	#[serde(default)]
 	pub accessors: Vec<AccessorDoc>,
-	#[serde(rename(deserialize = "bufferViews"))]
+	#[serde(default, rename(deserialize = "bufferViews"))]
 	pub buffer_views: Vec<BufferViewDoc>,
+	#[serde(default)]
 	pub samplers: Vec<SamplerDoc>,
+	#[serde(default)]
 	pub buffers: Vec<BufferDoc>,
 }
 
 impl GltfDoc {
-	pub fn accessor<T>(&self, index: u64) -> Vec<T> {
-		let accessor = &self.accessors[index as usize];
-		let view = &self.buffer_views[accessor.buffer_view as usize];
-		let size = mem::size_of::<T>();
-		let mut bytes = self.read_buffer_range(
-			view.buffer,
-			(accessor.byte_offset + view.byte_offset)
-				..(accessor.byte_offset + view.byte_offset + view.byte_length),
-			view.byte_stride.unwrap_or(size as u64),
-		);
-
-		assert!(bytes.len() % size == 0);
-
-		let p = bytes.as_mut_ptr();
-		let len = bytes.len() / size;
-		let cap = bytes.capacity() / size;
-		mem::forget(bytes);
-		unsafe { Vec::from_raw_parts(p as *mut T, len, cap) }
+	/// Parse a glTF file from memory, either as JSON or as a binary `.glb` container.
+	///
+	/// `uri` is the file's path, used to resolve relative buffer and image URIs.
+	pub fn from_slice(bytes: &[u8], uri: &str) -> Result<Self, GltfError> {
+		let (json, blob) = if bytes.starts_with(GLB_MAGIC) {
+			parse_glb(bytes)?
+		} else {
+			(bytes, None)
+		};
+
+		let mut doc: GltfDoc =
+			serde_json::from_slice(json).map_err(|e| GltfError::Unknown(e.to_string()))?;
+		doc.uri = uri.into();
+		doc.load_buffers(blob)?;
+
+		Ok(doc)
+	}
+
+	/// Read the contents of every buffer into memory.
+	///
+	/// A buffer without a URI refers to the binary chunk of a `.glb` file, passed in as `blob`.
+	pub fn load_buffers(&mut self, mut blob: Option<&[u8]>) -> Result<(), GltfError> {
+		let mut buffer_data = Vec::with_capacity(self.buffers.len());
+		for buffer in &self.buffers {
+			let data = match buffer.uri.as_ref() {
+				Some(uri) => self.read_uri(uri)?,
+				None => blob.take().ok_or(GltfError::MissingBuffer)?.to_vec(),
+			};
+			if (data.len() as u64) < buffer.byte_length {
+				return Err(GltfError::InvalidBuffer(format!(
+					"expected {} bytes but found {}",
+					buffer.byte_length,
+					data.len()
+				)));
+			}
+			buffer_data.push(data);
+		}
+		self.buffer_data = buffer_data;
+
+		Ok(())
+	}
+
+	/// Read the data referred to by a URI, either embedded as a `data:` URI or in a file
+	/// relative to the glTF file.
+	pub fn read_uri(&self, uri: &str) -> Result<Vec<u8>, GltfError> {
+		if uri.starts_with("data:") {
+			decode_data_uri(uri)
+		} else {
+			let filename = self.relative_filename(uri);
+			fs::read(&filename).map_err(|_| GltfError::FileNotFound(filename))
+		}
+	}
+
+	/// Get the bytes covered by a buffer view.
+	pub fn buffer_view(&self, index: u64) -> Result<&[u8], GltfError> {
+		let view = self
+			.buffer_views
+			.get(index as usize)
+			.ok_or(GltfError::MissingBuffer)?;
+		let end = view
+			.byte_offset
+			.checked_add(view.byte_length)
+			.ok_or_else(|| {
+				GltfError::InvalidBuffer(format!(
+					"buffer view {} ends past the largest possible offset",
+					index
+				))
+			})?;
+		self.buffer_range(view.buffer, view.byte_offset..end)
 	}
 
 	pub fn relative_filename(&self, filename: &str) -> String {
-		let cwd = Path::new(&self.uri).parent().unwrap();
-		cwd.join(filename).to_str().unwrap().into()
+		let cwd = Path::new(&self.uri)
+			.parent()
+			.unwrap_or_else(|| Path::new(""));
+		cwd.join(filename).to_string_lossy().into()
 	}
 
-	pub fn read_buffer_range(&self, index: u64, range: Range<u64>, stride: u64) -> Vec<u8> {
-		// FIXME use stride
-		let buffer = &self.buffers[index as usize];
-		let filename = self.relative_filename(&buffer.uri);
-		let mut file =
-			File::open(filename).expect(&format!("Failed to open buffer: {}", buffer.uri));
-
-		let mut data = vec![0; (range.end - range.start) as usize];
-		file.seek(SeekFrom::Start(range.start)).expect(&format!(
-			"Failed to seek to {} in {}",
-			range.start, buffer.uri
-		));
-		file.read_exact(&mut data).expect(&format!(
-			"Failed to read from {} to {} in {}",
-			range.start, range.end, buffer.uri
-		));
-
-		data.shrink_to_fit();
-		data
+	pub fn buffer_range(&self, index: u64, range: Range<u64>) -> Result<&[u8], GltfError> {
+		let data = self
+			.buffer_data
+			.get(index as usize)
+			.ok_or(GltfError::MissingBuffer)?;
+		data.get(range.start as usize..range.end as usize)
+			.ok_or_else(|| {
+				GltfError::InvalidBuffer(format!(
+					"range {}..{} is outside buffer {} ({} bytes)",
+					range.start,
+					range.end,
+					index,
+					data.len()
+				))
+			})
 	}
 }
 
+/// Split a `.glb` container into its JSON chunk and optional binary chunk.
+fn parse_glb(bytes: &[u8]) -> Result<(&[u8], Option<&[u8]>), GltfError> {
+	let read_u32 = |offset: usize| -> Result<u32, GltfError> {
+		bytes
+			.get(offset..offset + 4)
+			.map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+			.ok_or_else(|| GltfError::InvalidGlb("unexpected end of file".into()))
+	};
+
+	let version = read_u32(4)?;
+	if version != 2 {
+		return Err(GltfError::InvalidGlb(format!(
+			"unsupported version {}",
+			version
+		)));
+	}
+	let length = (read_u32(8)? as usize).min(bytes.len());
+
+	let mut json = None;
+	let mut blob = None;
+	let mut offset = 12;
+	while offset + 8 <= length {
+		let chunk_length = read_u32(offset)? as usize;
+		let chunk_type = read_u32(offset + 4)?;
+		let start = offset + 8;
+		let chunk = bytes
+			.get(start..start + chunk_length)
+			.ok_or_else(|| GltfError::InvalidGlb("chunk extends past end of file".into()))?;
+		match chunk_type {
+			GLB_CHUNK_JSON if json.is_none() => json = Some(chunk),
+			GLB_CHUNK_BIN if blob.is_none() => blob = Some(chunk),
+			// Unknown chunks must be ignored
+			_ => {}
+		}
+		offset = start + chunk_length;
+	}
+
+	let json = json.ok_or_else(|| GltfError::InvalidGlb("missing JSON chunk".into()))?;
+
+	Ok((json, blob))
+}
+
+/// Decode a base64 `data:` URI, e.g. `data:application/octet-stream;base64,...`
+fn decode_data_uri(uri: &str) -> Result<Vec<u8>, GltfError> {
+	let (header, data) = uri
+		.strip_prefix("data:")
+		.and_then(|uri| uri.split_once(','))
+		.ok_or_else(|| GltfError::UnsupportedUri(uri.chars().take(32).collect()))?;
+	if !header.ends_with(";base64") {
+		return Err(GltfError::UnsupportedUri(header.into()));
+	}
+
+	base64::decode(data).map_err(|e| GltfError::InvalidBuffer(e.to_string()))
+}
+
 #[derive(Deserialize, Debug)]
 pub struct AssetDoc {
 	#[serde(default)]
@@ -100,14 +216,130 @@ pub struct NodeDoc {
 	pub name: String,
 	pub mesh: Option<u64>,
 	pub children: Option<Vec<u64>>,
-	pub translation: Option<(f32, f32, f32)>,
+	pub translation: Option<[f32; 3]>,
+	/// Unit quaternion as `[x, y, z, w]`
+	pub rotation: Option<[f32; 4]>,
+	pub scale: Option<[f32; 3]>,
+	/// Column-major 4x4 matrix
 	pub matrix: Option<[f32; 16]>,
 }
 
+impl NodeDoc {
+	/// The node's transform relative to its parent.
+	///
+	/// Uses `matrix` if present, otherwise composes `translation * rotation * scale`.
+	pub fn transform(&self) -> Matrix4<f32> {
+		if let Some(m) = self.matrix.as_ref() {
+			return Matrix4::new(
+				m[0], m[1], m[2], m[3], m[4], m[5], m[6], m[7], m[8], m[9], m[10], m[11], m[12],
+				m[13], m[14], m[15],
+			);
+		}
+
+		let translation = self
+			.translation
+			.map(|t| Matrix4::from_translation(Vector3::from(t)))
+			.unwrap_or_else(Matrix4::identity);
+		let rotation = self
+			.rotation
+			.map(|[x, y, z, w]| Matrix4::from(Quaternion::new(w, x, y, z)))
+			.unwrap_or_else(Matrix4::identity);
+		let scale = self
+			.scale
+			.map(|[x, y, z]| Matrix4::from_nonuniform_scale(x, y, z))
+			.unwrap_or_else(Matrix4::identity);
+
+		translation * rotation * scale
+	}
+}
+
 #[derive(Deserialize, Debug)]
 pub struct MaterialDoc {
 	#[serde(default)]
 	pub name: String,
+	#[serde(default, rename(deserialize = "pbrMetallicRoughness"))]
+	pub pbr_metallic_roughness: PbrMetallicRoughnessDoc,
+	#[serde(rename(deserialize = "normalTexture"))]
+	pub normal_texture: Option<NormalTextureInfoDoc>,
+	#[serde(rename(deserialize = "occlusionTexture"))]
+	pub occlusion_texture: Option<OcclusionTextureInfoDoc>,
+	#[serde(rename(deserialize = "emissiveTexture"))]
+	pub emissive_texture: Option<TextureInfoDoc>,
+	#[serde(default, rename(deserialize = "emissiveFactor"))]
+	pub emissive_factor: [f32; 3],
+	#[serde(default = "default_alpha_mode", rename(deserialize = "alphaMode"))]
+	pub alpha_mode: String,
+	#[serde(default = "default_alpha_cutoff", rename(deserialize = "alphaCutoff"))]
+	pub alpha_cutoff: f32,
+	#[serde(default, rename(deserialize = "doubleSided"))]
+	pub double_sided: bool,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PbrMetallicRoughnessDoc {
+	#[serde(default = "default_color", rename(deserialize = "baseColorFactor"))]
+	pub base_color_factor: [f32; 4],
+	#[serde(rename(deserialize = "baseColorTexture"))]
+	pub base_color_texture: Option<TextureInfoDoc>,
+	#[serde(default = "default_one", rename(deserialize = "metallicFactor"))]
+	pub metallic_factor: f32,
+	#[serde(default = "default_one", rename(deserialize = "roughnessFactor"))]
+	pub roughness_factor: f32,
+	#[serde(rename(deserialize = "metallicRoughnessTexture"))]
+	pub metallic_roughness_texture: Option<TextureInfoDoc>,
+}
+
+impl Default for PbrMetallicRoughnessDoc {
+	fn default() -> Self {
+		Self {
+			base_color_factor: default_color(),
+			base_color_texture: None,
+			metallic_factor: 1.0,
+			roughness_factor: 1.0,
+			metallic_roughness_texture: None,
+		}
+	}
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TextureInfoDoc {
+	pub index: u64,
+	#[serde(default, rename(deserialize = "texCoord"))]
+	pub tex_coord: u64,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct NormalTextureInfoDoc {
+	pub index: u64,
+	#[serde(default, rename(deserialize = "texCoord"))]
+	pub tex_coord: u64,
+	#[serde(default = "default_one")]
+	pub scale: f32,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct OcclusionTextureInfoDoc {
+	pub index: u64,
+	#[serde(default, rename(deserialize = "texCoord"))]
+	pub tex_coord: u64,
+	#[serde(default = "default_one")]
+	pub strength: f32,
+}
+
+fn default_one() -> f32 {
+	1.0
+}
+
+fn default_color() -> [f32; 4] {
+	[1.0; 4]
+}
+
+fn default_alpha_mode() -> String {
+	"OPAQUE".into()
+}
+
+fn default_alpha_cutoff() -> f32 {
+	0.5
 }
 
 #[derive(Deserialize, Debug)]
@@ -119,7 +351,7 @@ pub struct MeshDoc {
 
 #[derive(Deserialize, Debug)]
 pub struct TextureDoc {
-	pub sampler: u64,
+	pub sampler: Option<u64>,
 	pub source: u64,
 }
 
@@ -129,27 +361,58 @@ pub struct ImageDoc {
 	pub mime_type: String,
 	#[serde(default)]
 	pub name: String,
-	pub uri: String,
+	pub uri: Option<String>,
+	#[serde(rename(deserialize = "bufferView"))]
+	pub buffer_view: Option<u64>,
 }
 
 #[derive(Deserialize, Debug)]
 pub struct PrimitiveDoc {
 	pub attributes: HashMap<String, u64>,
 	pub indices: Option<u64>,
-	pub material: u64,
+	pub material: Option<u64>,
 }
 
 #[derive(Deserialize, Debug)]
 pub struct AccessorDoc {
+	/// Absent when every element is zero, or only sparse values are provided
 	#[serde(rename(deserialize = "bufferView"))]
-	pub buffer_view: u64,
+	pub buffer_view: Option<u64>,
 	#[serde(default, rename(deserialize = "byteOffset"))]
 	pub byte_offset: u64,
 	#[serde(rename(deserialize = "componentType"))]
 	pub component_type: u64,
+	#[serde(default)]
+	pub normalized: bool,
 	pub count: u64,
 	#[serde(rename(deserialize = "type"))]
 	pub ty: String,
+	pub sparse: Option<SparseDoc>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SparseDoc {
+	pub count: u64,
+	pub indices: SparseIndicesDoc,
+	pub values: SparseValuesDoc,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SparseIndicesDoc {
+	#[serde(rename(deserialize = "bufferView"))]
+	pub buffer_view: u64,
+	#[serde(default, rename(deserialize = "byteOffset"))]
+	pub byte_offset: u64,
+	#[serde(rename(deserialize = "componentType"))]
+	pub component_type: u64,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SparseValuesDoc {
+	#[serde(rename(deserialize = "bufferView"))]
+	pub buffer_view: u64,
+	#[serde(default, rename(deserialize = "byteOffset"))]
+	pub byte_offset: u64,
 }
 
 #[derive(Deserialize, Debug)]
@@ -167,9 +430,9 @@ pub struct BufferViewDoc {
 #[derive(Deserialize, Debug)]
 pub struct SamplerDoc {
 	#[serde(rename(deserialize = "magFilter"))]
-	pub mag_filter: u64,
+	pub mag_filter: Option<u64>,
 	#[serde(rename(deserialize = "minFilter"))]
-	pub min_filter: u64,
+	pub min_filter: Option<u64>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -178,5 +441,6 @@ pub struct BufferDoc {
 	pub byte_length: u64,
 	#[serde(rename(deserialize = "type"))]
 	pub ty: Option<String>,
-	pub uri: String,
+	/// Absent when the buffer is the binary chunk of a `.glb` file
+	pub uri: Option<String>,
 }
@@ -0,0 +1,33 @@
+use crate::SceneObject;
+use cgmath::{Matrix4, SquareMatrix};
+
+/// An object that draws nothing, used to move its children together.
+pub struct Group {
+	pub transform: Matrix4<f32>,
+}
+
+impl Default for Group {
+	fn default() -> Self {
+		Self::new(Matrix4::identity())
+	}
+}
+
+impl Group {
+	pub fn new(transform: Matrix4<f32>) -> Self {
+		Self { transform }
+	}
+}
+
+impl SceneObject for Group {
+	fn transform(&self) -> Matrix4<f32> {
+		self.transform
+	}
+
+	fn draws(&self) -> bool {
+		false
+	}
+
+	fn cast_shadows(&self) -> bool {
+		false
+	}
+}
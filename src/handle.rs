@@ -0,0 +1,104 @@
+use std::fmt;
+
+/// Identifies something owned by a [`Scene`](crate::Scene).
+///
+/// Handles are only meaningful to the scene that created them. Once the thing they refer to is
+/// removed, its index may be reused, but with a new generation, so a stale handle never refers
+/// to something else.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Handle {
+	index: u32,
+	generation: u32,
+}
+
+impl Handle {
+	/// The first handle allocated by a new [`HandleAllocator`].
+	pub const FIRST: Self = Self {
+		index: 0,
+		generation: 0,
+	};
+
+	pub fn new(index: u32, generation: u32) -> Self {
+		Self { index, generation }
+	}
+
+	/// Handle with the first generation of `index`. Used where IDs refer to something outside a
+	/// scene, such as the images of a glTF file.
+	pub fn from_index(index: usize) -> Self {
+		Self {
+			index: index as u32,
+			generation: 0,
+		}
+	}
+
+	pub fn index(&self) -> u32 {
+		self.index
+	}
+
+	pub fn generation(&self) -> u32 {
+		self.generation
+	}
+}
+
+impl fmt::Display for Handle {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}v{}", self.index, self.generation)
+	}
+}
+
+/// Hands out [`Handle`]s, reusing the indices of freed handles with a new generation.
+#[derive(Debug, Default)]
+pub struct HandleAllocator {
+	/// Current generation of each index
+	generations: Vec<u32>,
+	free: Vec<u32>,
+}
+
+impl HandleAllocator {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn allocate(&mut self) -> Handle {
+		match self.free.pop() {
+			Some(index) => Handle {
+				index,
+				generation: self.generations[index as usize],
+			},
+			None => {
+				self.generations.push(0);
+				Handle {
+					index: self.generations.len() as u32 - 1,
+					generation: 0,
+				}
+			}
+		}
+	}
+
+	/// Free a handle so its index can be reused. Returns false if it was already stale.
+	pub fn free(&mut self, handle: Handle) -> bool {
+		if !self.contains(handle) {
+			return false;
+		}
+		// Wraps after 2^32 frees of one index, when a handle that old could match again
+		let generation = &mut self.generations[handle.index as usize];
+		*generation = generation.wrapping_add(1);
+		self.free.push(handle.index);
+		true
+	}
+
+	/// Whether a handle was allocated by this allocator and hasn't been freed.
+	pub fn contains(&self, handle: Handle) -> bool {
+		// Freeing bumps the generation, so a freed index never matches a handle handed out
+		self.generations.get(handle.index as usize) == Some(&handle.generation)
+	}
+
+	/// Number of handles in use.
+	pub fn len(&self) -> usize {
+		self.generations.len() - self.free.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+}
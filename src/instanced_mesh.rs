@@ -0,0 +1,243 @@
+use crate::{
+	BoundingBox, Color, Geometry, Material, MountContext, Ray, RenderContext, SceneObject,
+	TriangleHit, Vertex,
+};
+use byd_derive::CastBytes;
+use cgmath::{Matrix4, SquareMatrix, Transform, Vector4};
+use std::{cell::OnceCell, mem::size_of, rc::Rc};
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+
+/// Per-instance data of an [`InstancedMesh`].
+#[derive(Copy, Clone, Debug, CastBytes)]
+#[repr(C)]
+pub struct Instance {
+	/// Transform of the instance, applied before the mesh's own transform
+	pub transform: Matrix4<f32>,
+	/// Multiplied with the material's color
+	pub color: Color,
+}
+
+impl Default for Instance {
+	fn default() -> Self {
+		Self::new(Matrix4::identity())
+	}
+}
+
+impl Instance {
+	pub fn new(transform: Matrix4<f32>) -> Self {
+		Self {
+			transform,
+			color: Vector4::new(1.0, 1.0, 1.0, 1.0),
+		}
+	}
+
+	pub fn with_color(mut self, color: Color) -> Self {
+		self.color = color;
+		self
+	}
+
+	/// Layout of the instance buffer. The columns of the transform are at shader locations 8 to
+	/// 11, and the color is at location 12, leaving lower locations for vertex attributes.
+	pub fn buffer_layout<'a>() -> wgpu::VertexBufferLayout<'a> {
+		const ATTRIBUTES: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![
+			8 => Float32x4,
+			9 => Float32x4,
+			10 => Float32x4,
+			11 => Float32x4,
+			12 => Float32x4,
+		];
+		wgpu::VertexBufferLayout {
+			array_stride: size_of::<Self>() as _,
+			step_mode: wgpu::VertexStepMode::Instance,
+			attributes: &ATTRIBUTES,
+		}
+	}
+}
+
+/// Draws many copies of one [`Geometry`] with a single instanced draw call.
+pub struct InstancedMesh<V: Vertex> {
+	/// Shared with the other meshes drawing the same geometry
+	geometry: Rc<Geometry<V>>,
+	instances: Vec<Instance>,
+	instance_buffer: Option<wgpu::Buffer>,
+	/// Number of instances the instance buffer has room for
+	instance_capacity: usize,
+	instances_changed: bool,
+	/// Bounds of every instance, worked out when first needed
+	bounds: OnceCell<Option<BoundingBox>>,
+	pub material: Box<dyn Material>,
+	pub transform: Matrix4<f32>,
+	pub cast_shadows: bool,
+	pub receive_shadows: bool,
+}
+
+impl<V: Vertex> InstancedMesh<V> {
+	/// `geometry` can be an `Rc` shared with other meshes, which are then drawn from the same
+	/// buffers.
+	pub fn new(
+		geometry: impl Into<Rc<Geometry<V>>>,
+		material: impl Material,
+		instances: Vec<Instance>,
+	) -> Self {
+		Self {
+			geometry: geometry.into(),
+			instances,
+			instance_buffer: None,
+			instance_capacity: 0,
+			instances_changed: true,
+			bounds: OnceCell::new(),
+			material: Box::new(material),
+			transform: Matrix4::identity(),
+			cast_shadows: true,
+			receive_shadows: true,
+		}
+	}
+
+	/// Get a reference to the mesh's geometry.
+	pub fn geometry(&self) -> &Geometry<V> {
+		&self.geometry
+	}
+
+	pub fn instances(&self) -> &[Instance] {
+		&self.instances
+	}
+
+	/// Get a mutable reference to the instances. They're uploaded again on the next frame.
+	pub fn instances_mut(&mut self) -> &mut Vec<Instance> {
+		self.instances_changed = true;
+		self.bounds = OnceCell::new();
+		&mut self.instances
+	}
+
+	pub fn set_instances(&mut self, instances: Vec<Instance>) {
+		self.instances = instances;
+		self.instances_changed = true;
+		self.bounds = OnceCell::new();
+	}
+
+	/// Upload changed instances, growing the buffer if they no longer fit.
+	fn upload_instances(&mut self, device: &wgpu::Device, queue: &mut wgpu::Queue) {
+		if !self.instances_changed {
+			return;
+		}
+		self.instances_changed = false;
+
+		let contents = bytemuck::cast_slice(&self.instances);
+		if self.instance_buffer.is_some() && self.instances.len() <= self.instance_capacity {
+			if let Some(buffer) = &self.instance_buffer {
+				queue.write_buffer(buffer, 0, contents);
+			}
+			return;
+		}
+
+		log::debug!(
+			"Allocating instance buffer ({} instances)",
+			self.instances.len()
+		);
+		self.instance_capacity = self.instances.len().next_power_of_two();
+		let mut padded = contents.to_vec();
+		padded.resize(self.instance_capacity * size_of::<Instance>(), 0);
+		self.instance_buffer = Some(device.create_buffer_init(&BufferInitDescriptor {
+			label: Some("Instance Buffer"),
+			contents: &padded,
+			usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+		}));
+	}
+}
+
+impl<V: Vertex> SceneObject for InstancedMesh<V> {
+	fn render<'a>(&'a mut self, ctx: &mut RenderContext<'a>) {
+		if self.instances.is_empty() {
+			return;
+		}
+		self.geometry
+			.allocate(ctx.device)
+			.expect("Failed to allocate mesh geometry");
+		self.upload_instances(ctx.device, ctx.queue);
+
+		if let (Some(buffer), Some(instance_buffer)) =
+			(self.geometry.vertex_buffer(), self.instance_buffer.as_ref())
+		{
+			let render_pass = &mut ctx.render_pass;
+			let instances = 0..self.instances.len() as u32;
+			render_pass.set_vertex_buffer(0, buffer);
+			render_pass.set_vertex_buffer(1, instance_buffer);
+			if let (Some(index_buffer), Some(indices)) =
+				(self.geometry.index_buffer(), self.geometry.indices())
+			{
+				render_pass.set_index_buffer(index_buffer, indices.format());
+				render_pass.draw_indexed(0..indices.len() as u32, 0, instances);
+			} else {
+				let len = self.geometry.vertex_count() as u32;
+				render_pass.draw(0..len, instances);
+			}
+		}
+	}
+
+	fn mount(&mut self, ctx: &mut MountContext) {
+		log::debug!("Instanced mesh mounted");
+		self.geometry
+			.allocate(ctx.device)
+			.expect("Failed to allocate mesh geometry");
+	}
+
+	fn unmount(&mut self, _ctx: &mut MountContext) {
+		log::debug!("Instanced mesh unmounted");
+		// Shared geometry is freed when the last mesh drawing it is dropped
+		if let Some(geometry) = Rc::get_mut(&mut self.geometry) {
+			geometry.free().expect("Failed to free mesh geometry");
+		}
+		self.instance_buffer = None;
+		self.instances_changed = true;
+	}
+
+	fn transform(&self) -> Matrix4<f32> {
+		self.transform
+	}
+
+	fn material(&self) -> &dyn Material {
+		&*self.material
+	}
+
+	fn cast_shadows(&self) -> bool {
+		self.cast_shadows
+	}
+
+	fn receive_shadows(&self) -> bool {
+		self.receive_shadows
+	}
+
+	fn instanced(&self) -> bool {
+		true
+	}
+
+	fn bounds(&self) -> Option<BoundingBox> {
+		*self.bounds.get_or_init(|| {
+			let bounds = self.geometry.bounds()?;
+			self.instances
+				.iter()
+				.map(|instance| bounds.transform(&instance.transform))
+				.reduce(BoundingBox::union)
+		})
+	}
+
+	fn geometry_key(&self) -> Option<usize> {
+		Some(Rc::as_ptr(&self.geometry) as usize)
+	}
+
+	fn raycast(&self, ray: &Ray) -> Option<TriangleHit> {
+		self.instances
+			.iter()
+			.filter_map(|instance| {
+				let inverse = instance.transform.invert()?;
+				let hit = self.geometry.raycast(&ray.transform(&inverse))?;
+				Some(TriangleHit {
+					triangle: hit
+						.triangle
+						.map(|corner| instance.transform.transform_point(corner)),
+					..hit
+				})
+			})
+			.min_by(|a, b| a.distance.total_cmp(&b.distance))
+	}
+}
@@ -13,21 +13,48 @@ pub use camera::*;
 pub mod render_context;
 pub use render_context::*;
 
+pub mod render_target;
+pub use render_target::*;
+
+pub mod viewport;
+pub use viewport::*;
+
 pub mod mount_context;
 pub use mount_context::*;
 
+pub mod light;
+pub use light::*;
+
 pub mod material;
 pub use material::*;
 
+pub mod bounds;
+pub use bounds::*;
+
+pub mod ray;
+pub use ray::*;
+
+pub mod bvh;
+pub use bvh::*;
+
 pub mod geometry;
 pub use geometry::*;
 
 pub mod mesh;
 pub use mesh::*;
 
+pub mod instanced_mesh;
+pub use instanced_mesh::*;
+
+pub mod group;
+pub use group::*;
+
 pub mod event;
 pub use event::*;
 
+pub mod handle;
+pub use handle::*;
+
 pub mod scene;
 pub use scene::*;
 
@@ -40,6 +67,9 @@ pub use window::*;
 pub mod renderer;
 pub use renderer::*;
 
+pub mod post_effect;
+pub use post_effect::*;
+
 pub mod vertex;
 pub use vertex::*;
 
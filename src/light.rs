@@ -0,0 +1,244 @@
+use crate::{pipelines::SHADOW_MAP_SIZE, Camera};
+use cgmath::{
+	EuclideanSpace, InnerSpace, Matrix4, Point3, Rad, SquareMatrix, Transform, Vector3, Vector4,
+};
+
+/// Number of shadow maps a directional light's shadows are split across
+pub const SHADOW_CASCADES: usize = 3;
+
+/// How much cascade splits favour a logarithmic distribution over an even one
+const CASCADE_SPLIT_LAMBDA: f32 = 0.75;
+
+/// Converts OpenGL clip space depth, as produced by cgmath, to the 0..1 range used by wgpu
+#[rustfmt::skip]
+const OPENGL_TO_WGPU: Matrix4<f32> = Matrix4::new(
+	1.0, 0.0, 0.0, 0.0,
+	0.0, 1.0, 0.0, 0.0,
+	0.0, 0.0, 0.5, 0.0,
+	0.0, 0.0, 0.5, 1.0,
+);
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LightKind {
+	/// Infinitely far away light, such as the sun, shining along `direction`
+	Directional { direction: Vector3<f32> },
+	/// Light shining in every direction from `position`, fading out to nothing at `range`
+	Point { position: Point3<f32>, range: f32 },
+	/// Cone of light from `position` along `direction`, fading out between `inner_angle` and
+	/// `outer_angle` from its centre
+	Spot {
+		position: Point3<f32>,
+		direction: Vector3<f32>,
+		range: f32,
+		inner_angle: Rad<f32>,
+		outer_angle: Rad<f32>,
+	},
+}
+
+/// A light source that can be added to a [`Scene`](crate::Scene).
+///
+/// A range of `0.0` means a point or spot light's intensity falls off with the inverse square of
+/// the distance, without ever reaching zero.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Light {
+	pub kind: LightKind,
+	/// Linear RGB color
+	pub color: Vector3<f32>,
+	pub intensity: f32,
+	/// Whether objects block this light. Point lights don't cast shadows.
+	pub cast_shadows: bool,
+}
+
+impl Light {
+	pub fn directional(direction: Vector3<f32>, color: Vector3<f32>, intensity: f32) -> Self {
+		Self {
+			kind: LightKind::Directional {
+				direction: direction.normalize(),
+			},
+			color,
+			intensity,
+			cast_shadows: false,
+		}
+	}
+
+	pub fn point(position: Point3<f32>, color: Vector3<f32>, intensity: f32) -> Self {
+		Self {
+			kind: LightKind::Point {
+				position,
+				range: 0.0,
+			},
+			color,
+			intensity,
+			cast_shadows: false,
+		}
+	}
+
+	/// Spot light with a cone `angle` from its centre to its edge. The edge is softened over the
+	/// outer 10% of the cone.
+	pub fn spot(
+		position: Point3<f32>,
+		direction: Vector3<f32>,
+		angle: impl Into<Rad<f32>>,
+		color: Vector3<f32>,
+		intensity: f32,
+	) -> Self {
+		let angle = angle.into();
+		Self {
+			kind: LightKind::Spot {
+				position,
+				direction: direction.normalize(),
+				range: 0.0,
+				inner_angle: angle * 0.9,
+				outer_angle: angle,
+			},
+			color,
+			intensity,
+			cast_shadows: false,
+		}
+	}
+
+	/// Set the distance at which a point or spot light stops having any effect.
+	pub fn with_range(mut self, new_range: f32) -> Self {
+		match &mut self.kind {
+			LightKind::Point { range, .. } | LightKind::Spot { range, .. } => *range = new_range,
+			LightKind::Directional { .. } => {}
+		}
+		self
+	}
+
+	/// Make objects block this light.
+	pub fn with_shadows(mut self) -> Self {
+		self.cast_shadows = true;
+		self
+	}
+
+	/// View-projection matrices of the shadow maps needed for this light.
+	///
+	/// Directional lights get [`SHADOW_CASCADES`] maps, each covering a further slice of the
+	/// camera's view out to `distance`. Spot lights get a single map covering their cone, and
+	/// point lights get none.
+	pub fn shadow_matrices(&self, camera: &dyn Camera, distance: f32) -> Vec<Matrix4<f32>> {
+		match self.kind {
+			LightKind::Directional { direction } => {
+				cascade_matrices(direction.normalize(), camera, distance)
+			}
+			LightKind::Spot {
+				position,
+				direction,
+				range,
+				outer_angle,
+				..
+			} => {
+				let far = if range > 0.0 { range } else { distance };
+				let projection = cgmath::perspective(outer_angle * 2.0, 1.0, 0.05, far);
+				let view =
+					Matrix4::look_at_rh(position, position + direction, up_vector(direction));
+				vec![OPENGL_TO_WGPU * projection * view]
+			}
+			LightKind::Point { .. } => vec![],
+		}
+	}
+}
+
+/// An up vector that isn't parallel to `direction`.
+fn up_vector(direction: Vector3<f32>) -> Vector3<f32> {
+	if direction.normalize().y.abs() > 0.99 {
+		Vector3::unit_x()
+	} else {
+		Vector3::unit_y()
+	}
+}
+
+/// Fit an orthographic shadow map around each slice of the camera's view frustum.
+fn cascade_matrices(
+	direction: Vector3<f32>,
+	camera: &dyn Camera,
+	distance: f32,
+) -> Vec<Matrix4<f32>> {
+	let inverse = (camera.projection() * camera.view())
+		.invert()
+		.unwrap_or_else(Matrix4::identity);
+	let unproject = |x: f32, y: f32, z: f32| {
+		let point = inverse * Vector4::new(x, y, z, 1.0);
+		Point3::from_homogeneous(point)
+	};
+
+	// Depths of the near and far planes in the depth buffer, and a depth between them. With
+	// reverse-Z the far plane may be at infinity, so corners are found at the middle depth.
+	let (near_z, middle_z, far_z) = if camera.reverse_z() {
+		(1.0, 0.5, 0.0)
+	} else {
+		(0.0, 1.0, 1.0)
+	};
+
+	// Corners of the near plane and of a plane further along the frustum, and how far each
+	// plane is from the camera
+	let corners = [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)];
+	let near: Vec<_> = corners
+		.iter()
+		.map(|&(x, y)| unproject(x, y, near_z))
+		.collect();
+	let far: Vec<_> = corners
+		.iter()
+		.map(|&(x, y)| unproject(x, y, middle_z))
+		.collect();
+	let eye = camera
+		.view()
+		.invert()
+		.unwrap_or_else(Matrix4::identity)
+		.transform_point(Point3::origin());
+	let near_center = unproject(0.0, 0.0, near_z);
+	let forward = (unproject(0.0, 0.0, middle_z) - near_center).normalize();
+	let near_depth = (near_center - eye).dot(forward).max(0.001);
+	let middle_depth = (unproject(0.0, 0.0, middle_z) - eye).dot(forward);
+	// Infinite far planes unproject to infinity or NaN
+	let far_depth = (unproject(0.0, 0.0, far_z) - eye).dot(forward);
+	let far_depth = if far_depth.is_finite() {
+		far_depth
+	} else {
+		f32::INFINITY
+	};
+	let shadow_depth = distance.min(far_depth).max(near_depth);
+
+	// Blend logarithmic and even splits, so nearby cascades are small and detailed
+	let split = |i: usize| {
+		let t = i as f32 / SHADOW_CASCADES as f32;
+		let log = near_depth * (shadow_depth / near_depth).powf(t);
+		let even = near_depth + (shadow_depth - near_depth) * t;
+		let depth = CASCADE_SPLIT_LAMBDA * log + (1.0 - CASCADE_SPLIT_LAMBDA) * even;
+		(depth - near_depth) / (middle_depth - near_depth)
+	};
+
+	(0..SHADOW_CASCADES)
+		.map(|i| {
+			let (start, end) = (split(i), split(i + 1));
+			let slice: Vec<Point3<f32>> = near
+				.iter()
+				.zip(&far)
+				.flat_map(|(n, f)| [n + (f - n) * start, n + (f - n) * end])
+				.collect();
+
+			// Bounding sphere, so the map's size doesn't change as the camera turns
+			let center = Point3::centroid(&slice);
+			let radius = slice
+				.iter()
+				.map(|corner| (corner - center).magnitude())
+				.fold(0.0, f32::max)
+				.ceil();
+
+			// Pull the light back so casters between it and the slice are included
+			let back = radius + distance;
+			let view = Matrix4::look_at_rh(center - direction * back, center, up_vector(direction));
+			let projection = cgmath::ortho(-radius, radius, -radius, radius, 0.0, back + radius);
+			let mut matrix = projection * view;
+
+			// Snap to whole texels, so shadow edges don't shimmer as the camera moves
+			let texels = SHADOW_MAP_SIZE as f32 / 2.0;
+			let origin = matrix * Vector4::new(0.0, 0.0, 0.0, 1.0);
+			matrix.w.x += ((origin.x * texels).round() - origin.x * texels) / texels;
+			matrix.w.y += ((origin.y * texels).round() - origin.y * texels) / texels;
+
+			OPENGL_TO_WGPU * matrix
+		})
+		.collect()
+}
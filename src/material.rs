@@ -1,19 +1,36 @@
 use crate::{Color, ProgramID, TextureID};
+use cgmath::Vector3;
 use downcast_rs::{impl_downcast, Downcast};
 
-pub trait Material: Downcast {}
+pub trait Material: Downcast {
+	/// How the material's alpha is interpreted. Blended materials are drawn after everything
+	/// else, from back to front.
+	fn alpha_mode(&self) -> AlphaMode {
+		AlphaMode::Opaque
+	}
+}
 impl_downcast!(Material);
 
 #[derive(Clone)]
 pub struct BasicMaterial {
 	pub color: Color,
+	pub alpha_mode: AlphaMode,
+	pub alpha_cutoff: f32,
 }
 
-impl Material for BasicMaterial {}
+impl Material for BasicMaterial {
+	fn alpha_mode(&self) -> AlphaMode {
+		self.alpha_mode
+	}
+}
 
 impl BasicMaterial {
 	pub const fn new(color: Color) -> Self {
-		Self { color }
+		Self {
+			color,
+			alpha_mode: AlphaMode::Opaque,
+			alpha_cutoff: 0.5,
+		}
 	}
 }
 
@@ -22,6 +39,12 @@ pub struct LineMaterial {}
 
 impl Material for LineMaterial {}
 
+impl Default for LineMaterial {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
 impl LineMaterial {
 	pub const fn new() -> Self {
 		Self {}
@@ -31,13 +54,23 @@ impl LineMaterial {
 #[derive(Clone)]
 pub struct TextureMaterial {
 	pub texture_id: TextureID,
+	pub alpha_mode: AlphaMode,
+	pub alpha_cutoff: f32,
 }
 
-impl Material for TextureMaterial {}
+impl Material for TextureMaterial {
+	fn alpha_mode(&self) -> AlphaMode {
+		self.alpha_mode
+	}
+}
 
 impl TextureMaterial {
 	pub const fn new(texture_id: TextureID) -> Self {
-		Self { texture_id }
+		Self {
+			texture_id,
+			alpha_mode: AlphaMode::Opaque,
+			alpha_cutoff: 0.5,
+		}
 	}
 }
 
@@ -53,3 +86,101 @@ impl CustomMaterial {
 		Self { program_id }
 	}
 }
+
+/// How the alpha channel of a material is interpreted.
+///
+/// The discriminants match the `ALPHA_*` constants used by the shaders.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[repr(u32)]
+pub enum AlphaMode {
+	/// Alpha is ignored and the surface is fully opaque
+	#[default]
+	Opaque = 0,
+	/// Fragments with alpha below the material's `alpha_cutoff` are discarded
+	Mask = 1,
+	/// The surface is alpha blended over whatever is behind it
+	Blend = 2,
+}
+
+/// Metallic-roughness material, matching the glTF material model.
+///
+/// Each texture is optional and multiplied with its matching factor.
+#[derive(Clone, Debug)]
+pub struct PbrMaterial {
+	pub base_color: Color,
+	pub base_color_texture: Option<TextureID>,
+	pub metallic: f32,
+	pub roughness: f32,
+	/// Roughness in the green channel and metalness in the blue channel
+	pub metallic_roughness_texture: Option<TextureID>,
+	/// Tangent space normal map
+	pub normal_texture: Option<TextureID>,
+	pub normal_scale: f32,
+	/// Ambient occlusion in the red channel
+	pub occlusion_texture: Option<TextureID>,
+	pub occlusion_strength: f32,
+	pub emissive: Vector3<f32>,
+	pub emissive_texture: Option<TextureID>,
+	pub alpha_mode: AlphaMode,
+	pub alpha_cutoff: f32,
+	/// Render back faces too, instead of culling them
+	pub double_sided: bool,
+}
+
+impl Material for PbrMaterial {
+	fn alpha_mode(&self) -> AlphaMode {
+		self.alpha_mode
+	}
+}
+
+impl Default for PbrMaterial {
+	fn default() -> Self {
+		Self::new(Color::new(1.0, 1.0, 1.0, 1.0))
+	}
+}
+
+impl PbrMaterial {
+	pub const fn new(base_color: Color) -> Self {
+		Self {
+			base_color,
+			base_color_texture: None,
+			metallic: 1.0,
+			roughness: 1.0,
+			metallic_roughness_texture: None,
+			normal_texture: None,
+			normal_scale: 1.0,
+			occlusion_texture: None,
+			occlusion_strength: 1.0,
+			emissive: Vector3::new(0.0, 0.0, 0.0),
+			emissive_texture: None,
+			alpha_mode: AlphaMode::Opaque,
+			alpha_cutoff: 0.5,
+			double_sided: false,
+		}
+	}
+
+	/// Every texture slot, in the order base color, metallic-roughness, normal, occlusion and
+	/// emissive.
+	pub fn textures(&self) -> [Option<TextureID>; 5] {
+		[
+			self.base_color_texture,
+			self.metallic_roughness_texture,
+			self.normal_texture,
+			self.occlusion_texture,
+			self.emissive_texture,
+		]
+	}
+
+	/// Replace every texture ID, e.g. to map a glTF file's texture indices to scene textures.
+	pub fn map_textures(&mut self, mut f: impl FnMut(TextureID) -> TextureID) {
+		for texture in [
+			&mut self.base_color_texture,
+			&mut self.metallic_roughness_texture,
+			&mut self.normal_texture,
+			&mut self.occlusion_texture,
+			&mut self.emissive_texture,
+		] {
+			*texture = texture.map(&mut f);
+		}
+	}
+}
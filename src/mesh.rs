@@ -1,13 +1,19 @@
-use crate::{Geometry, Material, MountContext, RenderContext, SceneObject, Vertex};
+use crate::{
+	BoundingBox, Geometry, Material, MountContext, Ray, RenderContext, SceneObject, TriangleHit,
+	Vertex,
+};
 use byd_derive::CastBytes;
 use cgmath::{EuclideanSpace, Matrix4, Point2, Point3, SquareMatrix, Vector3};
-use std::mem::size_of;
+use std::{mem::size_of, rc::Rc};
 use wgpu::VertexFormat::{Float32x2, Float32x3};
 
 pub struct Mesh<V: Vertex> {
-	geometry: Geometry<V>,
+	/// Shared with the other meshes drawing the same geometry
+	geometry: Rc<Geometry<V>>,
 	pub material: Box<dyn Material>,
 	pub transform: Matrix4<f32>,
+	pub cast_shadows: bool,
+	pub receive_shadows: bool,
 }
 
 #[repr(C)]
@@ -46,11 +52,15 @@ impl From<&[f32; 3]> for SimpleVertex {
 }
 
 impl<V: Vertex> Mesh<V> {
-	pub fn new(geometry: Geometry<V>, material: impl Material) -> Self {
+	/// `geometry` can be an `Rc` shared with other meshes, which are then drawn from the same
+	/// buffers.
+	pub fn new(geometry: impl Into<Rc<Geometry<V>>>, material: impl Material) -> Self {
 		Self {
-			geometry,
+			geometry: geometry.into(),
 			material: Box::new(material),
 			transform: Matrix4::identity(),
+			cast_shadows: true,
+			receive_shadows: true,
 		}
 	}
 
@@ -63,9 +73,15 @@ impl<V: Vertex> Mesh<V> {
 		&self.geometry
 	}
 
-	/// Get a mutable reference to the mesh's geometry.
+	/// The mesh's geometry, to share with other meshes.
+	pub fn shared_geometry(&self) -> Rc<Geometry<V>> {
+		self.geometry.clone()
+	}
+
+	/// Get a mutable reference to the mesh's geometry. If it's shared with other meshes, the mesh
+	/// gets its own copy first.
 	pub fn geometry_mut(&mut self) -> &mut Geometry<V> {
-		&mut self.geometry
+		Rc::make_mut(&mut self.geometry)
 	}
 
 	/// Set the mesh's material.
@@ -76,11 +92,21 @@ impl<V: Vertex> Mesh<V> {
 
 impl<V: Vertex> SceneObject for Mesh<V> {
 	fn render<'a>(&'a mut self, ctx: &mut RenderContext<'a>) {
+		self.geometry
+			.allocate(ctx.device)
+			.expect("Failed to allocate mesh geometry");
 		if let Some(buffer) = self.geometry.vertex_buffer() {
 			let render_pass = &mut ctx.render_pass;
-			let len = self.geometry.vertex_count() as u32;
-			render_pass.set_vertex_buffer(0, buffer.slice(..));
-			render_pass.draw(0..len, 0..1);
+			render_pass.set_vertex_buffer(0, buffer);
+			if let (Some(index_buffer), Some(indices)) =
+				(self.geometry.index_buffer(), self.geometry.indices())
+			{
+				render_pass.set_index_buffer(index_buffer, indices.format());
+				render_pass.draw_indexed(0..indices.len() as u32, 0, 0..1);
+			} else {
+				let len = self.geometry.vertex_count() as u32;
+				render_pass.draw(0..len, 0..1);
+			}
 		}
 	}
 
@@ -93,19 +119,46 @@ impl<V: Vertex> SceneObject for Mesh<V> {
 
 	fn unmount(&mut self, _ctx: &mut MountContext) {
 		log::debug!("Mesh unmounted");
-		self.geometry.free().expect("Failed to free mesh geometry");
+		// Shared geometry is freed when the last mesh drawing it is dropped
+		if let Some(geometry) = Rc::get_mut(&mut self.geometry) {
+			geometry.free().expect("Failed to free mesh geometry");
+		}
 	}
 
 	fn transform(&self) -> Matrix4<f32> {
-		self.transform.clone()
+		self.transform
 	}
 
 	fn material(&self) -> &dyn Material {
 		&*self.material
 	}
+
+	fn cast_shadows(&self) -> bool {
+		self.cast_shadows
+	}
+
+	fn receive_shadows(&self) -> bool {
+		self.receive_shadows
+	}
+
+	fn bounds(&self) -> Option<BoundingBox> {
+		self.geometry.bounds()
+	}
+
+	fn geometry_key(&self) -> Option<usize> {
+		Some(Rc::as_ptr(&self.geometry) as usize)
+	}
+
+	fn raycast(&self, ray: &Ray) -> Option<TriangleHit> {
+		self.geometry.raycast(ray)
+	}
 }
 
 impl Vertex for SimpleVertex {
+	fn position(&self) -> Option<Point3<f32>> {
+		Some(self.position)
+	}
+
 	fn buffer_layout<'a>() -> ::wgpu::VertexBufferLayout<'a> {
 		wgpu::VertexBufferLayout {
 			array_stride: size_of::<Self>() as _,
@@ -1,7 +1,14 @@
+use crate::TrackedRenderPass;
+
 pub trait Pipeline {
 	fn bind_group_layout(&self) -> &wgpu::BindGroupLayout;
 	fn texture_bind_group_layout(&self) -> Option<&wgpu::BindGroupLayout> {
 		None
 	}
-	fn apply<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>);
+	fn apply<'a>(&'a self, render_pass: &mut TrackedRenderPass<'a>);
+	/// Use the variant of the pipeline that reads an [`Instance`](crate::Instance) buffer from
+	/// vertex buffer 1.
+	fn apply_instanced<'a>(&'a self, render_pass: &mut TrackedRenderPass<'a>) {
+		self.apply(render_pass);
+	}
 }
@@ -1,9 +1,7 @@
-use std::marker::PhantomData;
+use std::{collections::HashMap, marker::PhantomData};
 
-use super::Uniform;
-use crate::{Pipeline, PrimitiveVertex, TextureBuffer, Vertex};
-use byd_derive::CastBytes;
-use cgmath::{Matrix4, Vector4};
+use super::{LIGHTS_BINDING, SHADOW_MAP_BINDING, SHADOW_SAMPLER_BINDING};
+use crate::{Instance, Pipeline, TargetFormat, TextureBuffer, TrackedRenderPass, Vertex};
 
 pub const CAMERA_BINDING: u32 = 0;
 pub const ACTOR_BINDING: u32 = 1;
@@ -11,15 +9,32 @@ pub const TEXTURE_ENABLED_BINDING: u32 = 0;
 pub const TEXTURE_BINDING: u32 = 1;
 pub const SAMPLER_BINDING: u32 = 2;
 
+/// Renders a user supplied shader with `vs_main` and `fs_main` entry points.
+///
+/// If `instanced`, the shader also has a `vs_instanced` entry point, taking the [`Instance`]
+/// attributes at locations 8 to 12, which is used to draw
+/// [`InstancedMesh`](crate::InstancedMesh)es.
+///
+/// Pipelines are built for each target the pipeline is [prepared](Self::prepare) for, and kept so
+/// switching between targets doesn't rebuild them.
 pub struct CustomPipeline<V: Vertex> {
-	render_pipeline: wgpu::RenderPipeline,
+	/// Plain and instanced pipelines for each target
+	render_pipelines: HashMap<TargetFormat, (wgpu::RenderPipeline, Option<wgpu::RenderPipeline>)>,
+	/// Target of the pass being drawn into
+	target: TargetFormat,
+	/// Whether the shader has a `vs_instanced` entry point
+	instanced: bool,
+	shader_module: wgpu::ShaderModule,
+	pipeline_layout: wgpu::PipelineLayout,
 	bind_group_layout: wgpu::BindGroupLayout,
 	texture_bind_group_layout: wgpu::BindGroupLayout,
 	_phantom_vertex: PhantomData<V>,
 }
 
 impl<V: Vertex> CustomPipeline<V> {
-	pub fn new(device: &wgpu::Device, source: &str) -> Self {
+	/// `target` must match the targets of the pass the pipeline draws into. `instanced` is whether
+	/// the shader has a `vs_instanced` entry point.
+	pub fn new(device: &wgpu::Device, source: &str, target: TargetFormat, instanced: bool) -> Self {
 		// Uniforms
 		let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
 			label: Some("CustomPipeline Bind Group Layout"),
@@ -27,7 +42,7 @@ impl<V: Vertex> CustomPipeline<V> {
 				// Camera
 				wgpu::BindGroupLayoutEntry {
 					binding: CAMERA_BINDING,
-					visibility: wgpu::ShaderStages::VERTEX,
+					visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
 					ty: wgpu::BindingType::Buffer {
 						ty: wgpu::BufferBindingType::Uniform,
 						has_dynamic_offset: false,
@@ -38,7 +53,7 @@ impl<V: Vertex> CustomPipeline<V> {
 				// Actor
 				wgpu::BindGroupLayoutEntry {
 					binding: ACTOR_BINDING,
-					visibility: wgpu::ShaderStages::VERTEX,
+					visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
 					ty: wgpu::BindingType::Buffer {
 						ty: wgpu::BufferBindingType::Uniform,
 						has_dynamic_offset: true,
@@ -46,6 +61,35 @@ impl<V: Vertex> CustomPipeline<V> {
 					},
 					count: None,
 				},
+				// Lights
+				wgpu::BindGroupLayoutEntry {
+					binding: LIGHTS_BINDING,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Buffer {
+						ty: wgpu::BufferBindingType::Uniform,
+						has_dynamic_offset: false,
+						min_binding_size: None,
+					},
+					count: None,
+				},
+				// Shadow Maps
+				wgpu::BindGroupLayoutEntry {
+					binding: SHADOW_MAP_BINDING,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Texture {
+						sample_type: wgpu::TextureSampleType::Depth,
+						view_dimension: wgpu::TextureViewDimension::D2Array,
+						multisampled: false,
+					},
+					count: None,
+				},
+				// Shadow Sampler
+				wgpu::BindGroupLayoutEntry {
+					binding: SHADOW_SAMPLER_BINDING,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+					count: None,
+				},
 			],
 		});
 
@@ -99,61 +143,105 @@ impl<V: Vertex> CustomPipeline<V> {
 			push_constant_ranges: &[],
 		});
 
-		log::debug!("Creating pipeline");
-		let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-			label: Some("Primitive Render Pipeline"),
-			layout: Some(&pipeline_layout),
-			vertex: wgpu::VertexState {
-				module: &shader_module,
-				entry_point: "vs_main",
-				buffers: &[V::buffer_layout()],
-			},
-			fragment: Some(wgpu::FragmentState {
-				module: &shader_module,
-				entry_point: "fs_main",
-				targets: &[wgpu::ColorTargetState {
-					format: wgpu::TextureFormat::Rgba8UnormSrgb, // FIXME ctx.swapchain_format(),
-					blend: Some(wgpu::BlendState::REPLACE),
-					write_mask: wgpu::ColorWrites::ALL,
-				}],
-			}),
-			primitive: wgpu::PrimitiveState {
-				topology: wgpu::PrimitiveTopology::TriangleList,
-				strip_index_format: None,
-				front_face: wgpu::FrontFace::Cw,
-				//cull_mode: Some(wgpu::Face::Back),
-				cull_mode: None,
-				conservative: false,
-				polygon_mode: wgpu::PolygonMode::Fill,
-				unclipped_depth: false,
-			},
-			multisample: wgpu::MultisampleState {
-				count: 1,
-				mask: !0,
-				alpha_to_coverage_enabled: false,
-			},
-			depth_stencil: Some(wgpu::DepthStencilState {
-				format: TextureBuffer::DEPTH_FORMAT,
-				depth_write_enabled: true,
-				depth_compare: wgpu::CompareFunction::Less,
-				stencil: wgpu::StencilState::default(),
-				bias: wgpu::DepthBiasState::default(),
-			}),
-			multiview: None,
-		});
-
-		Self {
-			render_pipeline: pipeline,
+		let mut pipeline = Self {
+			render_pipelines: HashMap::new(),
+			target,
+			instanced,
+			shader_module,
+			pipeline_layout,
 			bind_group_layout,
 			texture_bind_group_layout,
 			_phantom_vertex: Default::default(),
+		};
+		pipeline.prepare(device, target);
+		pipeline
+	}
+
+	/// Whether the pipeline has an instanced variant, for
+	/// [`apply_instanced`](Pipeline::apply_instanced).
+	pub fn instanced(&self) -> bool {
+		self.instanced
+	}
+
+	/// Draw into passes with `target`, building the pipelines for it if needed.
+	pub fn prepare(&mut self, device: &wgpu::Device, target: TargetFormat) {
+		if !self.render_pipelines.contains_key(&target) {
+			let render_pipelines = self.create_pipelines(device, target);
+			self.render_pipelines.insert(target, render_pipelines);
 		}
+		self.target = target;
+	}
+
+	fn create_pipelines(
+		&self,
+		device: &wgpu::Device,
+		target: TargetFormat,
+	) -> (wgpu::RenderPipeline, Option<wgpu::RenderPipeline>) {
+		log::debug!("Creating custom pipelines for {:?}", target);
+		let create_pipeline = |entry_point, buffers: &[wgpu::VertexBufferLayout]| {
+			device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+				label: Some("Primitive Render Pipeline"),
+				layout: Some(&self.pipeline_layout),
+				vertex: wgpu::VertexState {
+					module: &self.shader_module,
+					entry_point,
+					buffers,
+				},
+				fragment: Some(wgpu::FragmentState {
+					module: &self.shader_module,
+					entry_point: "fs_main",
+					targets: &[wgpu::ColorTargetState {
+						format: target.format,
+						blend: Some(wgpu::BlendState::REPLACE),
+						write_mask: wgpu::ColorWrites::ALL,
+					}],
+				}),
+				primitive: wgpu::PrimitiveState {
+					topology: wgpu::PrimitiveTopology::TriangleList,
+					strip_index_format: None,
+					front_face: wgpu::FrontFace::Cw,
+					//cull_mode: Some(wgpu::Face::Back),
+					cull_mode: None,
+					conservative: false,
+					polygon_mode: wgpu::PolygonMode::Fill,
+					unclipped_depth: false,
+				},
+				multisample: wgpu::MultisampleState {
+					count: target.sample_count,
+					mask: !0,
+					alpha_to_coverage_enabled: false,
+				},
+				depth_stencil: Some(wgpu::DepthStencilState {
+					format: TextureBuffer::DEPTH_FORMAT,
+					depth_write_enabled: true,
+					depth_compare: target.depth_compare(),
+					stencil: wgpu::StencilState::default(),
+					bias: wgpu::DepthBiasState::default(),
+				}),
+				multiview: None,
+			})
+		};
+		let render_pipeline = create_pipeline("vs_main", &[V::buffer_layout()]);
+		let instanced_pipeline = self.instanced.then(|| {
+			create_pipeline(
+				"vs_instanced",
+				&[V::buffer_layout(), Instance::buffer_layout()],
+			)
+		});
+		(render_pipeline, instanced_pipeline)
 	}
 }
 
 impl<V: Vertex> Pipeline for CustomPipeline<V> {
-	fn apply<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
-		render_pass.set_pipeline(&self.render_pipeline);
+	fn apply<'a>(&'a self, render_pass: &mut TrackedRenderPass<'a>) {
+		render_pass.set_pipeline(&self.render_pipelines[&self.target].0);
+	}
+
+	/// Falls back to the plain pipeline without an instanced variant, so check
+	/// [`instanced`](CustomPipeline::instanced) first.
+	fn apply_instanced<'a>(&'a self, render_pass: &mut TrackedRenderPass<'a>) {
+		let (plain, instanced) = &self.render_pipelines[&self.target];
+		render_pass.set_pipeline(instanced.as_ref().unwrap_or(plain));
 	}
 
 	fn bind_group_layout(&self) -> &wgpu::BindGroupLayout {
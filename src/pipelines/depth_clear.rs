@@ -0,0 +1,91 @@
+use crate::{Pipeline, TextureBuffer, TrackedRenderPass};
+
+/// Clears the depth buffer inside a viewport, which a render pass's clear can't do as it always
+/// clears the whole attachment.
+///
+/// Draws a triangle at the far plane over the viewport, in a pass with only a depth attachment.
+pub struct DepthClearPipeline {
+	/// Indexed by `reverse_z as usize`
+	render_pipelines: Vec<wgpu::RenderPipeline>,
+	bind_group_layout: wgpu::BindGroupLayout,
+}
+
+impl DepthClearPipeline {
+	/// `sample_count` must match the depth buffer being cleared.
+	pub fn new(device: &wgpu::Device, sample_count: u32) -> Self {
+		let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+			label: Some("DepthClearPipeline Bind Group Layout"),
+			entries: &[],
+		});
+
+		// Shader
+		log::debug!("Creating DepthClearPipeline shader");
+		let shader_module = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+			label: Some("Depth Clear Shader"),
+			source: wgpu::ShaderSource::Wgsl(include_str!("../../shaders/depth_clear.wgsl").into()),
+		});
+
+		let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+			label: Some("Depth Clear Pipeline Layout"),
+			bind_group_layouts: &[],
+			push_constant_ranges: &[],
+		});
+
+		log::debug!("Creating DepthClearPipeline");
+		let create_pipeline = |entry_point| {
+			device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+				label: Some("Depth Clear Pipeline"),
+				layout: Some(&pipeline_layout),
+				vertex: wgpu::VertexState {
+					module: &shader_module,
+					entry_point,
+					buffers: &[],
+				},
+				fragment: None,
+				primitive: wgpu::PrimitiveState {
+					topology: wgpu::PrimitiveTopology::TriangleList,
+					strip_index_format: None,
+					front_face: wgpu::FrontFace::Ccw,
+					cull_mode: None,
+					conservative: false,
+					polygon_mode: wgpu::PolygonMode::Fill,
+					unclipped_depth: false,
+				},
+				depth_stencil: Some(wgpu::DepthStencilState {
+					format: TextureBuffer::DEPTH_FORMAT,
+					depth_write_enabled: true,
+					depth_compare: wgpu::CompareFunction::Always,
+					stencil: wgpu::StencilState::default(),
+					bias: wgpu::DepthBiasState::default(),
+				}),
+				multisample: wgpu::MultisampleState {
+					count: sample_count,
+					mask: !0,
+					alpha_to_coverage_enabled: false,
+				},
+				multiview: None,
+			})
+		};
+		let render_pipelines = vec![create_pipeline("vs_main"), create_pipeline("vs_reverse_z")];
+
+		Self {
+			render_pipelines,
+			bind_group_layout,
+		}
+	}
+
+	/// Use the variant clearing to the far plane of a camera with or without reverse-Z.
+	pub fn apply_reverse_z<'a>(&'a self, render_pass: &mut TrackedRenderPass<'a>, reverse_z: bool) {
+		render_pass.set_pipeline(&self.render_pipelines[reverse_z as usize]);
+	}
+}
+
+impl Pipeline for DepthClearPipeline {
+	fn apply<'a>(&'a self, render_pass: &mut TrackedRenderPass<'a>) {
+		render_pass.set_pipeline(&self.render_pipelines[0]);
+	}
+
+	fn bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+		&self.bind_group_layout
+	}
+}
@@ -0,0 +1,148 @@
+use super::Uniform;
+use crate::{Camera, Light, LightKind};
+use byd_derive::CastBytes;
+use cgmath::{Angle, InnerSpace, Matrix4, SquareMatrix, Vector3, Vector4};
+
+/// Binding of the light buffer in group 0 of every lit pipeline
+pub const LIGHTS_BINDING: u32 = 2;
+/// Binding of the shadow map array in group 0 of every lit pipeline
+pub const SHADOW_MAP_BINDING: u32 = 4;
+/// Binding of the shadow map comparison sampler in group 0 of every lit pipeline
+pub const SHADOW_SAMPLER_BINDING: u32 = 5;
+/// Maximum number of lights affecting a scene at once
+pub const MAX_LIGHTS: usize = 16;
+/// Number of layers in the shadow map array, shared by every shadow casting light
+pub const MAX_SHADOW_MAPS: usize = 8;
+/// Width and height of each shadow map
+pub const SHADOW_MAP_SIZE: u32 = 1024;
+
+/// WGSL declaring the light buffer and a `shade` function for Cook-Torrance lighting.
+///
+/// Prepend it to a [`SimpleProgram`](crate::SimpleProgram) shader to light custom materials
+/// with the scene's lights.
+pub const LIGHTING_SHADER: &str = include_str!("../../shaders/lighting.wgsl");
+
+const DIRECTIONAL: f32 = 0.0;
+const POINT: f32 = 1.0;
+const SPOT: f32 = 2.0;
+
+#[derive(Copy, Clone, Debug, CastBytes)]
+#[repr(C)]
+pub struct LightUniform {
+	/// Position, with the kind of light in `w`
+	pub position: Vector4<f32>,
+	/// Direction the light is shining, with its range in `w`
+	pub direction: Vector4<f32>,
+	/// Color premultiplied by intensity
+	pub color: Vector4<f32>,
+	/// Cosines of a spot light's inner and outer angles
+	pub cone: Vector4<f32>,
+	/// First shadow map layer in `x`, or -1 if the light has no shadows, and the number of layers
+	/// in `y`
+	pub shadow: Vector4<f32>,
+}
+impl Uniform for LightUniform {}
+
+impl From<&Light> for LightUniform {
+	fn from(light: &Light) -> Self {
+		let color = (light.color * light.intensity).extend(1.0);
+		let zero = Vector3::new(0.0, 0.0, 0.0);
+		let shadow = Vector4::new(-1.0, 0.0, 0.0, 0.0);
+		match light.kind {
+			LightKind::Directional { direction } => Self {
+				position: zero.extend(DIRECTIONAL),
+				direction: direction.normalize().extend(0.0),
+				color,
+				cone: Vector4::new(0.0, 0.0, 0.0, 0.0),
+				shadow,
+			},
+			LightKind::Point { position, range } => Self {
+				position: Vector4::new(position.x, position.y, position.z, POINT),
+				direction: zero.extend(range),
+				color,
+				cone: Vector4::new(0.0, 0.0, 0.0, 0.0),
+				shadow,
+			},
+			LightKind::Spot {
+				position,
+				direction,
+				range,
+				inner_angle,
+				outer_angle,
+			} => Self {
+				position: Vector4::new(position.x, position.y, position.z, SPOT),
+				direction: direction.normalize().extend(range),
+				color,
+				cone: Vector4::new(inner_angle.cos(), outer_angle.cos(), 0.0, 0.0),
+				shadow,
+			},
+		}
+	}
+}
+
+#[derive(Copy, Clone, Debug, CastBytes)]
+#[repr(C)]
+pub struct LightsUniform {
+	pub ambient: Vector4<f32>,
+	pub count: u32,
+	/// Number of shadow map layers in use
+	pub shadow_maps: u32,
+	pub _padding: [u32; 2],
+	pub lights: [LightUniform; MAX_LIGHTS],
+	/// View-projection matrix of each shadow map layer
+	pub shadow_matrices: [Matrix4<f32>; MAX_SHADOW_MAPS],
+}
+impl Uniform for LightsUniform {}
+
+impl LightsUniform {
+	/// Pack lights into a uniform, ignoring any beyond [`MAX_LIGHTS`].
+	///
+	/// Shadow casting lights are given shadow map layers in order until all
+	/// [`MAX_SHADOW_MAPS`] are used, with directional light cascades fitted to `camera`'s view
+	/// out to `shadow_distance`.
+	pub fn new<'a>(
+		ambient: Vector3<f32>,
+		lights: impl IntoIterator<Item = &'a Light>,
+		camera: &dyn Camera,
+		shadow_distance: f32,
+	) -> Self {
+		let mut uniform = Self {
+			ambient: ambient.extend(1.0),
+			count: 0,
+			shadow_maps: 0,
+			_padding: [0; 2],
+			lights: [LightUniform {
+				position: Vector4::new(0.0, 0.0, 0.0, 0.0),
+				direction: Vector4::new(0.0, 0.0, 0.0, 0.0),
+				color: Vector4::new(0.0, 0.0, 0.0, 0.0),
+				cone: Vector4::new(0.0, 0.0, 0.0, 0.0),
+				shadow: Vector4::new(-1.0, 0.0, 0.0, 0.0),
+			}; MAX_LIGHTS],
+			shadow_matrices: [Matrix4::identity(); MAX_SHADOW_MAPS],
+		};
+		for (slot, light) in uniform.lights.iter_mut().zip(lights) {
+			*slot = light.into();
+			uniform.count += 1;
+
+			if !light.cast_shadows {
+				continue;
+			}
+			let first = uniform.shadow_maps as usize;
+			let matrices = light.shadow_matrices(camera, shadow_distance);
+			if matrices.is_empty() || first + matrices.len() > MAX_SHADOW_MAPS {
+				continue;
+			}
+			uniform.shadow_matrices[first..first + matrices.len()].copy_from_slice(&matrices);
+			uniform.shadow_maps += matrices.len() as u32;
+			slot.shadow = Vector4::new(first as f32, matrices.len() as f32, 0.0, 0.0);
+		}
+		uniform
+	}
+}
+
+/// Scene-wide lighting resources bound in group 0 of every lit pipeline.
+pub struct LightResources<'a> {
+	pub buffer: &'a wgpu::Buffer,
+	pub shadow_map: &'a wgpu::TextureView,
+	pub shadow_sampler: &'a wgpu::Sampler,
+}
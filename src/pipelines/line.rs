@@ -1,33 +1,23 @@
-use super::Uniform;
-use crate::{Pipeline, SimpleVertex, TextureBuffer, Vertex};
-use byd_derive::CastBytes;
-use cgmath::{Matrix4, Vector4};
+use crate::{Pipeline, SimpleVertex, TargetFormat, TextureBuffer, TrackedRenderPass, Vertex};
+use std::collections::HashMap;
 
 pub const CAMERA_BINDING: u32 = 0;
 pub const ACTOR_BINDING: u32 = 1;
 
-#[derive(Copy, Clone, CastBytes)]
-pub struct CameraUniform {
-	pub view: Matrix4<f32>,
-	pub projection: Matrix4<f32>,
-}
-
-impl Uniform for CameraUniform {}
-
-#[derive(Copy, Clone, CastBytes)]
-pub struct ActorUniform {
-	pub color: Vector4<f32>,
-	pub model: Matrix4<f32>,
-}
-impl Uniform for ActorUniform {}
-
+/// Renders [`LineMaterial`](crate::LineMaterial)s, with a pipeline built for each target it's
+/// [prepared](Self::prepare) for.
 pub struct LinePipeline {
-	render_pipeline: wgpu::RenderPipeline,
+	render_pipelines: HashMap<TargetFormat, wgpu::RenderPipeline>,
+	/// Target of the pass being drawn into
+	target: TargetFormat,
+	shader_module: wgpu::ShaderModule,
+	pipeline_layout: wgpu::PipelineLayout,
 	bind_group_layout: wgpu::BindGroupLayout,
 }
 
 impl LinePipeline {
-	pub fn new(device: &wgpu::Device) -> Self {
+	/// `target` must match the targets of the pass the pipeline draws into.
+	pub fn new(device: &wgpu::Device, target: TargetFormat) -> Self {
 		// Uniforms
 		let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
 			label: Some("LinePipeline Bind Group Layout"),
@@ -71,20 +61,41 @@ impl LinePipeline {
 			push_constant_ranges: &[],
 		});
 
-		log::debug!("Creating pipeline");
-		let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+		let mut pipeline = Self {
+			render_pipelines: HashMap::new(),
+			target,
+			shader_module,
+			pipeline_layout,
+			bind_group_layout,
+		};
+		pipeline.prepare(device, target);
+		pipeline
+	}
+
+	/// Draw into passes with `target`, building a pipeline for it if needed.
+	pub fn prepare(&mut self, device: &wgpu::Device, target: TargetFormat) {
+		if !self.render_pipelines.contains_key(&target) {
+			let render_pipeline = self.create_pipeline(device, target);
+			self.render_pipelines.insert(target, render_pipeline);
+		}
+		self.target = target;
+	}
+
+	fn create_pipeline(&self, device: &wgpu::Device, target: TargetFormat) -> wgpu::RenderPipeline {
+		log::debug!("Creating line pipeline for {:?}", target);
+		device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
 			label: Some("Line Render Pipeline"),
-			layout: Some(&pipeline_layout),
+			layout: Some(&self.pipeline_layout),
 			vertex: wgpu::VertexState {
-				module: &shader_module,
+				module: &self.shader_module,
 				entry_point: "vs_main",
 				buffers: &[SimpleVertex::buffer_layout()],
 			},
 			fragment: Some(wgpu::FragmentState {
-				module: &shader_module,
+				module: &self.shader_module,
 				entry_point: "fs_main",
 				targets: &[wgpu::ColorTargetState {
-					format: wgpu::TextureFormat::Rgba8UnormSrgb, // FIXME ctx.swapchain_format(),
+					format: target.format,
 					blend: Some(wgpu::BlendState::REPLACE),
 					write_mask: wgpu::ColorWrites::ALL,
 				}],
@@ -99,30 +110,25 @@ impl LinePipeline {
 				unclipped_depth: false,
 			},
 			multisample: wgpu::MultisampleState {
-				count: 1,
+				count: target.sample_count,
 				mask: !0,
 				alpha_to_coverage_enabled: false,
 			},
 			depth_stencil: Some(wgpu::DepthStencilState {
 				format: TextureBuffer::DEPTH_FORMAT,
 				depth_write_enabled: true,
-				depth_compare: wgpu::CompareFunction::Less,
+				depth_compare: target.depth_compare(),
 				stencil: wgpu::StencilState::default(),
 				bias: wgpu::DepthBiasState::default(),
 			}),
 			multiview: None,
-		});
-
-		Self {
-			render_pipeline: pipeline,
-			bind_group_layout,
-		}
+		})
 	}
 }
 
 impl Pipeline for LinePipeline {
-	fn apply<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
-		render_pass.set_pipeline(&self.render_pipeline);
+	fn apply<'a>(&'a self, render_pass: &mut TrackedRenderPass<'a>) {
+		render_pass.set_pipeline(&self.render_pipelines[&self.target]);
 	}
 
 	fn bind_group_layout(&self) -> &wgpu::BindGroupLayout {
@@ -6,8 +6,20 @@ mod custom_pipeline;
 pub use custom_pipeline::CustomPipeline;
 mod line;
 pub use line::LinePipeline;
+mod lighting;
+pub use lighting::*;
+mod pbr;
+pub use pbr::*;
+mod shadow;
+pub use shadow::*;
+mod pick;
+pub use pick::*;
 mod quad;
 pub use quad::*;
+mod post;
+pub use post::*;
+mod depth_clear;
+pub use depth_clear::*;
 use std::mem::size_of_val;
 
 pub trait Uniform {
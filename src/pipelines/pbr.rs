@@ -0,0 +1,292 @@
+use super::{Uniform, LIGHTS_BINDING, SHADOW_MAP_BINDING, SHADOW_SAMPLER_BINDING};
+use crate::{
+	AlphaMode, Instance, PbrMaterial, Pipeline, PrimitiveVertex, TargetFormat, TextureBuffer,
+	TrackedRenderPass, Vertex,
+};
+use byd_derive::CastBytes;
+use cgmath::Vector4;
+use std::collections::HashMap;
+
+pub const PBR_CAMERA_BINDING: u32 = 0;
+pub const PBR_ACTOR_BINDING: u32 = 1;
+pub const PBR_MATERIAL_BINDING: u32 = 3;
+/// Bindings of the five material textures, followed by the sampler
+pub const PBR_TEXTURE_BINDINGS: [u32; 5] = [0, 1, 2, 3, 4];
+pub const PBR_SAMPLER_BINDING: u32 = 5;
+
+const TEXTURE_FLAGS: [u32; 5] = [1, 2, 4, 8, 16];
+
+#[derive(Copy, Clone, Debug, CastBytes)]
+#[repr(C)]
+pub struct MaterialUniform {
+	pub base_color: Vector4<f32>,
+	pub emissive: Vector4<f32>,
+	pub metallic: f32,
+	pub roughness: f32,
+	pub normal_scale: f32,
+	pub occlusion_strength: f32,
+	pub alpha_cutoff: f32,
+	pub alpha_mode: u32,
+	/// Bitmask of which textures are bound, in [`PbrMaterial::textures`] order
+	pub textures: u32,
+	pub _padding: u32,
+}
+impl Uniform for MaterialUniform {}
+
+impl From<&PbrMaterial> for MaterialUniform {
+	fn from(material: &PbrMaterial) -> Self {
+		let textures = material
+			.textures()
+			.iter()
+			.zip(TEXTURE_FLAGS)
+			.filter(|(texture, _)| texture.is_some())
+			.fold(0, |flags, (_, flag)| flags | flag);
+
+		Self {
+			base_color: material.base_color,
+			emissive: material.emissive.extend(0.0),
+			metallic: material.metallic,
+			roughness: material.roughness,
+			normal_scale: material.normal_scale,
+			occlusion_strength: material.occlusion_strength,
+			alpha_cutoff: material.alpha_cutoff,
+			alpha_mode: material.alpha_mode as u32,
+			textures,
+			_padding: 0,
+		}
+	}
+}
+
+/// Renders [`PbrMaterial`]s, with a variant for each combination of face culling, blending and
+/// instancing.
+///
+/// Variants are built for each target the pipeline is [prepared](Self::prepare) for, and kept so
+/// switching between targets doesn't rebuild them.
+pub struct PbrPipeline<V: Vertex = PrimitiveVertex> {
+	/// Variants for each target, indexed by
+	/// `double_sided as usize + 2 * blend as usize + 4 * instanced as usize`
+	render_pipelines: HashMap<TargetFormat, Vec<wgpu::RenderPipeline>>,
+	/// Target of the pass being drawn into
+	target: TargetFormat,
+	shader_module: wgpu::ShaderModule,
+	pipeline_layout: wgpu::PipelineLayout,
+	bind_group_layout: wgpu::BindGroupLayout,
+	texture_bind_group_layout: wgpu::BindGroupLayout,
+	_phantom_vertex: std::marker::PhantomData<V>,
+}
+
+impl<V: Vertex> PbrPipeline<V> {
+	/// `target` must match the targets of the pass the pipeline draws into.
+	pub fn new(device: &wgpu::Device, target: TargetFormat) -> Self {
+		let uniform = |binding, visibility| wgpu::BindGroupLayoutEntry {
+			binding,
+			visibility,
+			ty: wgpu::BindingType::Buffer {
+				ty: wgpu::BufferBindingType::Uniform,
+				has_dynamic_offset: binding == PBR_ACTOR_BINDING || binding == PBR_MATERIAL_BINDING,
+				min_binding_size: None,
+			},
+			count: None,
+		};
+
+		// Uniforms
+		let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+			label: Some("PbrPipeline Bind Group Layout"),
+			entries: &[
+				uniform(
+					PBR_CAMERA_BINDING,
+					wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+				),
+				uniform(
+					PBR_ACTOR_BINDING,
+					wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+				),
+				uniform(LIGHTS_BINDING, wgpu::ShaderStages::FRAGMENT),
+				uniform(PBR_MATERIAL_BINDING, wgpu::ShaderStages::FRAGMENT),
+				wgpu::BindGroupLayoutEntry {
+					binding: SHADOW_MAP_BINDING,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Texture {
+						sample_type: wgpu::TextureSampleType::Depth,
+						view_dimension: wgpu::TextureViewDimension::D2Array,
+						multisampled: false,
+					},
+					count: None,
+				},
+				wgpu::BindGroupLayoutEntry {
+					binding: SHADOW_SAMPLER_BINDING,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+					count: None,
+				},
+			],
+		});
+
+		// Textures
+		let mut entries: Vec<_> = PBR_TEXTURE_BINDINGS
+			.iter()
+			.map(|&binding| wgpu::BindGroupLayoutEntry {
+				binding,
+				visibility: wgpu::ShaderStages::FRAGMENT,
+				ty: wgpu::BindingType::Texture {
+					sample_type: wgpu::TextureSampleType::Float { filterable: true },
+					view_dimension: wgpu::TextureViewDimension::D2,
+					multisampled: false,
+				},
+				count: None,
+			})
+			.collect();
+		entries.push(wgpu::BindGroupLayoutEntry {
+			binding: PBR_SAMPLER_BINDING,
+			visibility: wgpu::ShaderStages::FRAGMENT,
+			ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+			count: None,
+		});
+		let texture_bind_group_layout =
+			device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+				label: Some("PbrPipeline Texture Bind Group Layout"),
+				entries: &entries,
+			});
+
+		// Shader
+		log::debug!("Creating PBR shader");
+		let shader_module = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+			label: Some("PBR Shader"),
+			source: wgpu::ShaderSource::Wgsl(
+				concat!(
+					include_str!("../../shaders/lighting.wgsl"),
+					include_str!("../../shaders/pbr.wgsl")
+				)
+				.into(),
+			),
+		});
+
+		let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+			label: Some("PBR Pipeline Layout"),
+			bind_group_layouts: &[&bind_group_layout, &texture_bind_group_layout],
+			push_constant_ranges: &[],
+		});
+
+		let mut pipeline = Self {
+			render_pipelines: HashMap::new(),
+			target,
+			shader_module,
+			pipeline_layout,
+			bind_group_layout,
+			texture_bind_group_layout,
+			_phantom_vertex: Default::default(),
+		};
+		pipeline.prepare(device, target);
+		pipeline
+	}
+
+	/// Draw into passes with `target`, building the pipeline variants for it if needed.
+	pub fn prepare(&mut self, device: &wgpu::Device, target: TargetFormat) {
+		if !self.render_pipelines.contains_key(&target) {
+			let render_pipelines = self.create_pipelines(device, target);
+			self.render_pipelines.insert(target, render_pipelines);
+		}
+		self.target = target;
+	}
+
+	fn create_pipelines(
+		&self,
+		device: &wgpu::Device,
+		target: TargetFormat,
+	) -> Vec<wgpu::RenderPipeline> {
+		log::debug!("Creating PBR pipelines for {:?}", target);
+		(0..8)
+			.map(|variant| {
+				let double_sided = variant & 1 != 0;
+				let blend = variant & 2 != 0;
+				let instanced = variant & 4 != 0;
+				let buffers = [V::buffer_layout(), Instance::buffer_layout()];
+				device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+					label: Some("PBR Render Pipeline"),
+					layout: Some(&self.pipeline_layout),
+					vertex: wgpu::VertexState {
+						module: &self.shader_module,
+						entry_point: if instanced { "vs_instanced" } else { "vs_main" },
+						buffers: if instanced { &buffers } else { &buffers[..1] },
+					},
+					fragment: Some(wgpu::FragmentState {
+						module: &self.shader_module,
+						entry_point: "fs_main",
+						targets: &[wgpu::ColorTargetState {
+							format: target.format,
+							blend: Some(if blend {
+								wgpu::BlendState::ALPHA_BLENDING
+							} else {
+								wgpu::BlendState::REPLACE
+							}),
+							write_mask: wgpu::ColorWrites::ALL,
+						}],
+					}),
+					primitive: wgpu::PrimitiveState {
+						topology: wgpu::PrimitiveTopology::TriangleList,
+						strip_index_format: None,
+						// The camera's projection mirrors Z, which flips the winding of CCW faces
+						front_face: wgpu::FrontFace::Cw,
+						cull_mode: if double_sided {
+							None
+						} else {
+							Some(wgpu::Face::Back)
+						},
+						conservative: false,
+						polygon_mode: wgpu::PolygonMode::Fill,
+						unclipped_depth: false,
+					},
+					multisample: wgpu::MultisampleState {
+						count: target.sample_count,
+						mask: !0,
+						alpha_to_coverage_enabled: false,
+					},
+					depth_stencil: Some(wgpu::DepthStencilState {
+						format: TextureBuffer::DEPTH_FORMAT,
+						depth_write_enabled: !blend,
+						depth_compare: target.depth_compare(),
+						stencil: wgpu::StencilState::default(),
+						bias: wgpu::DepthBiasState::default(),
+					}),
+					multiview: None,
+				})
+			})
+			.collect()
+	}
+
+	/// Variants for the target the pipeline was last prepared for.
+	fn variants(&self) -> &[wgpu::RenderPipeline] {
+		&self.render_pipelines[&self.target]
+	}
+
+	/// Use the pipeline variant matching a material's culling and blending, and whether an
+	/// [`Instance`] buffer is bound.
+	pub fn apply_material<'a>(
+		&'a self,
+		render_pass: &mut TrackedRenderPass<'a>,
+		material: &PbrMaterial,
+		instanced: bool,
+	) {
+		let blend = material.alpha_mode == AlphaMode::Blend;
+		let variant = material.double_sided as usize + 2 * blend as usize + 4 * instanced as usize;
+		render_pass.set_pipeline(&self.variants()[variant]);
+	}
+}
+
+impl Pipeline for PbrPipeline {
+	fn apply<'a>(&'a self, render_pass: &mut TrackedRenderPass<'a>) {
+		render_pass.set_pipeline(&self.variants()[0]);
+	}
+
+	fn apply_instanced<'a>(&'a self, render_pass: &mut TrackedRenderPass<'a>) {
+		render_pass.set_pipeline(&self.variants()[4]);
+	}
+
+	fn bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+		&self.bind_group_layout
+	}
+
+	fn texture_bind_group_layout(&self) -> Option<&wgpu::BindGroupLayout> {
+		Some(&self.texture_bind_group_layout)
+	}
+}
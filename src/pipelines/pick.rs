@@ -0,0 +1,176 @@
+use std::{collections::HashMap, marker::PhantomData};
+
+use super::Uniform;
+use crate::{Instance, Pipeline, PrimitiveVertex, TextureBuffer, TrackedRenderPass, Vertex};
+use byd_derive::CastBytes;
+use cgmath::Matrix4;
+
+pub const PICK_CAMERA_BINDING: u32 = 0;
+pub const PICK_ACTOR_BINDING: u32 = 1;
+/// Format of the ID buffer
+pub const PICK_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rg32Uint;
+
+#[derive(Copy, Clone, CastBytes)]
+#[repr(C)]
+pub struct PickUniform {
+	pub model: Matrix4<f32>,
+	/// Index and generation of the object's ID, with the index offset by one so zero means
+	/// nothing was drawn
+	pub object: [u32; 2],
+	pub _padding: [u32; 2],
+}
+impl Uniform for PickUniform {}
+
+/// Renders the ID of each object into an ID buffer, to find which object is under a pixel.
+///
+/// Pipelines are built for each depth test direction the pipeline is [prepared](Self::prepare)
+/// for, so cameras with and without reverse-Z can pick from the same scene.
+pub struct PickPipeline<V: Vertex = PrimitiveVertex> {
+	/// Plain and instanced pipelines for each depth test
+	render_pipelines: HashMap<wgpu::CompareFunction, (wgpu::RenderPipeline, wgpu::RenderPipeline)>,
+	/// Depth test of the pass being drawn into
+	depth_compare: wgpu::CompareFunction,
+	shader_module: wgpu::ShaderModule,
+	pipeline_layout: wgpu::PipelineLayout,
+	bind_group_layout: wgpu::BindGroupLayout,
+	_phantom_vertex: PhantomData<V>,
+}
+
+impl<V: Vertex> PickPipeline<V> {
+	/// `depth_compare` should match the [`TargetFormat`](crate::TargetFormat) the scene is
+	/// drawn with, which depends on the camera.
+	pub fn new(device: &wgpu::Device, depth_compare: wgpu::CompareFunction) -> Self {
+		// Uniforms
+		let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+			label: Some("PickPipeline Bind Group Layout"),
+			entries: &[
+				// Camera
+				wgpu::BindGroupLayoutEntry {
+					binding: PICK_CAMERA_BINDING,
+					visibility: wgpu::ShaderStages::VERTEX,
+					ty: wgpu::BindingType::Buffer {
+						ty: wgpu::BufferBindingType::Uniform,
+						has_dynamic_offset: false,
+						min_binding_size: None,
+					},
+					count: None,
+				},
+				// Actor
+				wgpu::BindGroupLayoutEntry {
+					binding: PICK_ACTOR_BINDING,
+					visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Buffer {
+						ty: wgpu::BufferBindingType::Uniform,
+						has_dynamic_offset: true,
+						min_binding_size: None,
+					},
+					count: None,
+				},
+			],
+		});
+
+		// Shader
+		log::debug!("Creating Pick shader");
+		let shader_module = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+			label: Some("Pick Shader"),
+			source: wgpu::ShaderSource::Wgsl(include_str!("../../shaders/pick.wgsl").into()),
+		});
+
+		let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+			label: Some("Pick Pipeline Layout"),
+			bind_group_layouts: &[&bind_group_layout],
+			push_constant_ranges: &[],
+		});
+
+		let mut pipeline = Self {
+			render_pipelines: HashMap::new(),
+			depth_compare,
+			shader_module,
+			pipeline_layout,
+			bind_group_layout,
+			_phantom_vertex: Default::default(),
+		};
+		pipeline.prepare(device, depth_compare);
+		pipeline
+	}
+
+	/// Draw with `depth_compare`, building the pipelines for it if needed.
+	pub fn prepare(&mut self, device: &wgpu::Device, depth_compare: wgpu::CompareFunction) {
+		if !self.render_pipelines.contains_key(&depth_compare) {
+			let render_pipelines = self.create_pipelines(device, depth_compare);
+			self.render_pipelines
+				.insert(depth_compare, render_pipelines);
+		}
+		self.depth_compare = depth_compare;
+	}
+
+	fn create_pipelines(
+		&self,
+		device: &wgpu::Device,
+		depth_compare: wgpu::CompareFunction,
+	) -> (wgpu::RenderPipeline, wgpu::RenderPipeline) {
+		log::debug!("Creating pick pipelines for {:?}", depth_compare);
+		let create_pipeline = |entry_point, buffers: &[wgpu::VertexBufferLayout]| {
+			device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+				label: Some("Pick Render Pipeline"),
+				layout: Some(&self.pipeline_layout),
+				vertex: wgpu::VertexState {
+					module: &self.shader_module,
+					entry_point,
+					buffers,
+				},
+				fragment: Some(wgpu::FragmentState {
+					module: &self.shader_module,
+					entry_point: "fs_main",
+					targets: &[wgpu::ColorTargetState {
+						format: PICK_FORMAT,
+						blend: None,
+						write_mask: wgpu::ColorWrites::ALL,
+					}],
+				}),
+				primitive: wgpu::PrimitiveState {
+					topology: wgpu::PrimitiveTopology::TriangleList,
+					strip_index_format: None,
+					front_face: wgpu::FrontFace::Cw,
+					cull_mode: None,
+					conservative: false,
+					polygon_mode: wgpu::PolygonMode::Fill,
+					unclipped_depth: false,
+				},
+				multisample: wgpu::MultisampleState {
+					count: 1,
+					mask: !0,
+					alpha_to_coverage_enabled: false,
+				},
+				depth_stencil: Some(wgpu::DepthStencilState {
+					format: TextureBuffer::DEPTH_FORMAT,
+					depth_write_enabled: true,
+					depth_compare,
+					stencil: wgpu::StencilState::default(),
+					bias: wgpu::DepthBiasState::default(),
+				}),
+				multiview: None,
+			})
+		};
+		let render_pipeline = create_pipeline("vs_main", &[V::buffer_layout()]);
+		let instanced_pipeline = create_pipeline(
+			"vs_instanced",
+			&[V::buffer_layout(), Instance::buffer_layout()],
+		);
+		(render_pipeline, instanced_pipeline)
+	}
+}
+
+impl Pipeline for PickPipeline {
+	fn apply<'a>(&'a self, render_pass: &mut TrackedRenderPass<'a>) {
+		render_pass.set_pipeline(&self.render_pipelines[&self.depth_compare].0);
+	}
+
+	fn apply_instanced<'a>(&'a self, render_pass: &mut TrackedRenderPass<'a>) {
+		render_pass.set_pipeline(&self.render_pipelines[&self.depth_compare].1);
+	}
+
+	fn bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+		&self.bind_group_layout
+	}
+}
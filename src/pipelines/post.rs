@@ -0,0 +1,286 @@
+use super::Uniform;
+use crate::{Pipeline, TrackedRenderPass};
+use byd_derive::CastBytes;
+use cgmath::Vector4;
+
+pub const POST_COLOR_BINDING: u32 = 0;
+pub const POST_SAMPLER_BINDING: u32 = 1;
+pub const POST_DEPTH_BINDING: u32 = 2;
+pub const POST_UNIFORM_BINDING: u32 = 3;
+
+/// Format of the copy of the depth buffer that post-processing passes read.
+pub const POST_DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Float;
+
+#[derive(Copy, Clone, CastBytes)]
+#[repr(C)]
+pub struct PostUniform {
+	/// Size of the screen in pixels
+	pub resolution: [f32; 2],
+	/// Seconds since the renderer was created
+	pub time: f32,
+	pub _padding: u32,
+	/// Settings of the pass
+	pub params: Vector4<f32>,
+}
+
+impl Uniform for PostUniform {}
+
+/// Draws a fullscreen pass of a [`PostEffect`](crate::PostEffect).
+///
+/// The fragment shader is added to `shaders/post.wgsl`, which declares the bindings and the
+/// vertex shader.
+pub struct PostPipeline {
+	render_pipeline: wgpu::RenderPipeline,
+	bind_group_layout: wgpu::BindGroupLayout,
+	sampler: wgpu::Sampler,
+}
+
+impl PostPipeline {
+	/// `format` is the format of the screen texture the pass draws into.
+	pub fn new(device: &wgpu::Device, source: &str, format: wgpu::TextureFormat) -> Self {
+		// Uniforms
+		let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+			label: Some("PostPipeline Bind Group Layout"),
+			entries: &[
+				// Color
+				wgpu::BindGroupLayoutEntry {
+					binding: POST_COLOR_BINDING,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Texture {
+						sample_type: wgpu::TextureSampleType::Float { filterable: true },
+						view_dimension: wgpu::TextureViewDimension::D2,
+						multisampled: false,
+					},
+					count: None,
+				},
+				wgpu::BindGroupLayoutEntry {
+					binding: POST_SAMPLER_BINDING,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+					count: None,
+				},
+				// Depth
+				wgpu::BindGroupLayoutEntry {
+					binding: POST_DEPTH_BINDING,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Texture {
+						sample_type: wgpu::TextureSampleType::Float { filterable: false },
+						view_dimension: wgpu::TextureViewDimension::D2,
+						multisampled: false,
+					},
+					count: None,
+				},
+				// Post
+				wgpu::BindGroupLayoutEntry {
+					binding: POST_UNIFORM_BINDING,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Buffer {
+						ty: wgpu::BufferBindingType::Uniform,
+						has_dynamic_offset: false,
+						min_binding_size: None,
+					},
+					count: None,
+				},
+			],
+		});
+
+		// Linear filtering for effects that sample between pixels, e.g. bloom
+		let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+			label: Some("PostPipeline Sampler"),
+			address_mode_u: wgpu::AddressMode::ClampToEdge,
+			address_mode_v: wgpu::AddressMode::ClampToEdge,
+			address_mode_w: wgpu::AddressMode::ClampToEdge,
+			mag_filter: wgpu::FilterMode::Linear,
+			min_filter: wgpu::FilterMode::Linear,
+			mipmap_filter: wgpu::FilterMode::Nearest,
+			..Default::default()
+		});
+
+		// Shader
+		log::debug!("Creating PostPipeline shader");
+		let source = format!("{}\n{}", include_str!("../../shaders/post.wgsl"), source);
+		let shader_module = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+			label: Some("Post Shader"),
+			source: wgpu::ShaderSource::Wgsl(source.into()),
+		});
+
+		log::debug!("Creating PostPipeline layout");
+		let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+			label: Some("Post Pipeline Layout"),
+			bind_group_layouts: &[&bind_group_layout],
+			push_constant_ranges: &[],
+		});
+
+		log::debug!("Creating PostPipeline");
+		let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+			label: Some("Post Pipeline"),
+			layout: Some(&pipeline_layout),
+			vertex: wgpu::VertexState {
+				module: &shader_module,
+				entry_point: "vs_main",
+				buffers: &[],
+			},
+			fragment: Some(wgpu::FragmentState {
+				module: &shader_module,
+				entry_point: "fs_main",
+				targets: &[wgpu::ColorTargetState {
+					format,
+					blend: Some(wgpu::BlendState::REPLACE),
+					write_mask: wgpu::ColorWrites::ALL,
+				}],
+			}),
+			primitive: wgpu::PrimitiveState {
+				topology: wgpu::PrimitiveTopology::TriangleList,
+				strip_index_format: None,
+				front_face: wgpu::FrontFace::Ccw,
+				cull_mode: None,
+				conservative: false,
+				polygon_mode: wgpu::PolygonMode::Fill,
+				unclipped_depth: false,
+			},
+			depth_stencil: None,
+			multisample: wgpu::MultisampleState {
+				count: 1,
+				mask: !0,
+				alpha_to_coverage_enabled: false,
+			},
+			multiview: None,
+		});
+
+		Self {
+			render_pipeline,
+			bind_group_layout,
+			sampler,
+		}
+	}
+
+	/// Sampler bound alongside the color texture.
+	pub fn sampler(&self) -> &wgpu::Sampler {
+		&self.sampler
+	}
+}
+
+impl Pipeline for PostPipeline {
+	fn apply<'a>(&'a self, render_pass: &mut TrackedRenderPass<'a>) {
+		render_pass.set_pipeline(&self.render_pipeline);
+	}
+
+	fn bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+		&self.bind_group_layout
+	}
+}
+
+/// Copies the depth buffer into a [`POST_DEPTH_FORMAT`] texture, as depth textures can't be
+/// sampled like color textures and multisampled ones can't be bound as plain 2D textures.
+///
+/// The copy always runs from 0 at the near plane to 1 at the far plane, with a variant that flips
+/// the depth of reverse-Z cameras.
+pub struct DepthCopyPipeline {
+	/// Indexed by `reverse_z as usize`
+	render_pipelines: Vec<wgpu::RenderPipeline>,
+	bind_group_layout: wgpu::BindGroupLayout,
+}
+
+impl DepthCopyPipeline {
+	/// `multisampled` is whether the depth buffer has more than one sample per pixel.
+	pub fn new(device: &wgpu::Device, multisampled: bool) -> Self {
+		// Uniforms
+		let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+			label: Some("DepthCopyPipeline Bind Group Layout"),
+			entries: &[
+				// Depth
+				wgpu::BindGroupLayoutEntry {
+					binding: 0,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Texture {
+						// Loaded as a float texture, which the GL backend can translate
+						sample_type: wgpu::TextureSampleType::Float { filterable: false },
+						view_dimension: wgpu::TextureViewDimension::D2,
+						multisampled,
+					},
+					count: None,
+				},
+			],
+		});
+
+		// Shader
+		log::debug!("Creating DepthCopyPipeline shader");
+		let texture_type = if multisampled {
+			"texture_multisampled_2d<f32>"
+		} else {
+			"texture_2d<f32>"
+		};
+		let source =
+			include_str!("../../shaders/depth_copy.wgsl").replace("DEPTH_TEXTURE", texture_type);
+		let shader_module = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+			label: Some("Depth Copy Shader"),
+			source: wgpu::ShaderSource::Wgsl(source.into()),
+		});
+
+		log::debug!("Creating DepthCopyPipeline layout");
+		let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+			label: Some("Depth Copy Pipeline Layout"),
+			bind_group_layouts: &[&bind_group_layout],
+			push_constant_ranges: &[],
+		});
+
+		log::debug!("Creating DepthCopyPipeline");
+		let create_pipeline = |entry_point| {
+			device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+				label: Some("Depth Copy Pipeline"),
+				layout: Some(&pipeline_layout),
+				vertex: wgpu::VertexState {
+					module: &shader_module,
+					entry_point: "vs_main",
+					buffers: &[],
+				},
+				fragment: Some(wgpu::FragmentState {
+					module: &shader_module,
+					entry_point,
+					targets: &[wgpu::ColorTargetState {
+						format: POST_DEPTH_FORMAT,
+						blend: None,
+						write_mask: wgpu::ColorWrites::ALL,
+					}],
+				}),
+				primitive: wgpu::PrimitiveState {
+					topology: wgpu::PrimitiveTopology::TriangleList,
+					strip_index_format: None,
+					front_face: wgpu::FrontFace::Ccw,
+					cull_mode: None,
+					conservative: false,
+					polygon_mode: wgpu::PolygonMode::Fill,
+					unclipped_depth: false,
+				},
+				depth_stencil: None,
+				multisample: wgpu::MultisampleState {
+					count: 1,
+					mask: !0,
+					alpha_to_coverage_enabled: false,
+				},
+				multiview: None,
+			})
+		};
+		let render_pipelines = vec![create_pipeline("fs_main"), create_pipeline("fs_reverse_z")];
+
+		Self {
+			render_pipelines,
+			bind_group_layout,
+		}
+	}
+
+	/// Use the variant copying the depth of a camera with or without reverse-Z.
+	pub fn apply_reverse_z<'a>(&'a self, render_pass: &mut TrackedRenderPass<'a>, reverse_z: bool) {
+		render_pass.set_pipeline(&self.render_pipelines[reverse_z as usize]);
+	}
+}
+
+impl Pipeline for DepthCopyPipeline {
+	fn apply<'a>(&'a self, render_pass: &mut TrackedRenderPass<'a>) {
+		render_pass.set_pipeline(&self.render_pipelines[0]);
+	}
+
+	fn bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+		&self.bind_group_layout
+	}
+}
@@ -1,9 +1,10 @@
-use std::marker::PhantomData;
+use std::{collections::HashMap, marker::PhantomData};
 
-use super::Uniform;
-use crate::{Pipeline, PrimitiveVertex, TextureBuffer, Vertex};
-use byd_derive::CastBytes;
-use cgmath::{Matrix4, Vector4};
+use super::{LIGHTS_BINDING, SHADOW_MAP_BINDING, SHADOW_SAMPLER_BINDING};
+use crate::{
+	AlphaMode, Instance, Pipeline, PrimitiveVertex, TargetFormat, TextureBuffer, TrackedRenderPass,
+	Vertex,
+};
 
 pub const CAMERA_BINDING: u32 = 0;
 pub const ACTOR_BINDING: u32 = 1;
@@ -11,30 +12,27 @@ pub const TEXTURE_ENABLED_BINDING: u32 = 0;
 pub const TEXTURE_BINDING: u32 = 1;
 pub const SAMPLER_BINDING: u32 = 2;
 
-#[derive(Copy, Clone, CastBytes)]
-pub struct CameraUniform {
-	pub view: Matrix4<f32>,
-	pub projection: Matrix4<f32>,
-}
-
-impl Uniform for CameraUniform {}
-
-#[derive(Copy, Clone, CastBytes)]
-pub struct ActorUniform {
-	pub color: Vector4<f32>,
-	pub model: Matrix4<f32>,
-}
-impl Uniform for ActorUniform {}
-
+/// Renders [`BasicMaterial`](crate::BasicMaterial)s and
+/// [`TextureMaterial`](crate::TextureMaterial)s, with a variant for each combination of blending
+/// and instancing.
+///
+/// Variants are built for each target the pipeline is [prepared](Self::prepare) for, and kept so
+/// switching between targets doesn't rebuild them.
 pub struct PrimitivePipeline<V: Vertex = PrimitiveVertex> {
-	render_pipeline: wgpu::RenderPipeline,
+	/// Variants for each target, indexed by `blend as usize + 2 * instanced as usize`
+	render_pipelines: HashMap<TargetFormat, Vec<wgpu::RenderPipeline>>,
+	/// Target of the pass being drawn into
+	target: TargetFormat,
+	shader_module: wgpu::ShaderModule,
+	pipeline_layout: wgpu::PipelineLayout,
 	bind_group_layout: wgpu::BindGroupLayout,
 	texture_bind_group_layout: wgpu::BindGroupLayout,
 	_phantom_vertex: PhantomData<V>,
 }
 
 impl<V: Vertex> PrimitivePipeline<V> {
-	pub fn new(device: &wgpu::Device) -> Self {
+	/// `target` must match the targets of the pass the pipeline draws into.
+	pub fn new(device: &wgpu::Device, target: TargetFormat) -> Self {
 		// Uniforms
 		let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
 			label: Some("PrimitivePipeline Bind Group Layout"),
@@ -42,7 +40,7 @@ impl<V: Vertex> PrimitivePipeline<V> {
 				// Camera
 				wgpu::BindGroupLayoutEntry {
 					binding: CAMERA_BINDING,
-					visibility: wgpu::ShaderStages::VERTEX,
+					visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
 					ty: wgpu::BindingType::Buffer {
 						ty: wgpu::BufferBindingType::Uniform,
 						has_dynamic_offset: false,
@@ -53,7 +51,7 @@ impl<V: Vertex> PrimitivePipeline<V> {
 				// Actor
 				wgpu::BindGroupLayoutEntry {
 					binding: ACTOR_BINDING,
-					visibility: wgpu::ShaderStages::VERTEX,
+					visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
 					ty: wgpu::BindingType::Buffer {
 						ty: wgpu::BufferBindingType::Uniform,
 						has_dynamic_offset: true,
@@ -61,6 +59,35 @@ impl<V: Vertex> PrimitivePipeline<V> {
 					},
 					count: None,
 				},
+				// Lights
+				wgpu::BindGroupLayoutEntry {
+					binding: LIGHTS_BINDING,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Buffer {
+						ty: wgpu::BufferBindingType::Uniform,
+						has_dynamic_offset: false,
+						min_binding_size: None,
+					},
+					count: None,
+				},
+				// Shadow Maps
+				wgpu::BindGroupLayoutEntry {
+					binding: SHADOW_MAP_BINDING,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Texture {
+						sample_type: wgpu::TextureSampleType::Depth,
+						view_dimension: wgpu::TextureViewDimension::D2Array,
+						multisampled: false,
+					},
+					count: None,
+				},
+				// Shadow Sampler
+				wgpu::BindGroupLayoutEntry {
+					binding: SHADOW_SAMPLER_BINDING,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+					count: None,
+				},
 			],
 		});
 
@@ -104,7 +131,13 @@ impl<V: Vertex> PrimitivePipeline<V> {
 		log::debug!("Creating Primitive shader");
 		let shader_module = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
 			label: Some("Primitive Shader"),
-			source: wgpu::ShaderSource::Wgsl(include_str!("../../shaders/primitive.wgsl").into()),
+			source: wgpu::ShaderSource::Wgsl(
+				concat!(
+					include_str!("../../shaders/lighting.wgsl"),
+					include_str!("../../shaders/primitive.wgsl")
+				)
+				.into(),
+			),
 		});
 
 		log::debug!("Creating pipeline layout");
@@ -114,61 +147,123 @@ impl<V: Vertex> PrimitivePipeline<V> {
 			push_constant_ranges: &[],
 		});
 
-		log::debug!("Creating pipeline");
-		let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-			label: Some("Primitive Render Pipeline"),
-			layout: Some(&pipeline_layout),
-			vertex: wgpu::VertexState {
-				module: &shader_module,
-				entry_point: "vs_main",
-				buffers: &[V::buffer_layout()],
-			},
-			fragment: Some(wgpu::FragmentState {
-				module: &shader_module,
-				entry_point: "fs_main",
-				targets: &[wgpu::ColorTargetState {
-					format: wgpu::TextureFormat::Rgba8UnormSrgb, // FIXME ctx.swapchain_format(),
-					blend: Some(wgpu::BlendState::REPLACE),
-					write_mask: wgpu::ColorWrites::ALL,
-				}],
-			}),
-			primitive: wgpu::PrimitiveState {
-				topology: wgpu::PrimitiveTopology::TriangleList,
-				strip_index_format: None,
-				front_face: wgpu::FrontFace::Cw,
-				//cull_mode: Some(wgpu::Face::Back),
-				cull_mode: None,
-				conservative: false,
-				polygon_mode: wgpu::PolygonMode::Fill,
-				unclipped_depth: false,
-			},
-			multisample: wgpu::MultisampleState {
-				count: 1,
-				mask: !0,
-				alpha_to_coverage_enabled: false,
-			},
-			depth_stencil: Some(wgpu::DepthStencilState {
-				format: TextureBuffer::DEPTH_FORMAT,
-				depth_write_enabled: true,
-				depth_compare: wgpu::CompareFunction::Less,
-				stencil: wgpu::StencilState::default(),
-				bias: wgpu::DepthBiasState::default(),
-			}),
-			multiview: None,
-		});
-
-		Self {
-			render_pipeline: pipeline,
+		let mut pipeline = Self {
+			render_pipelines: HashMap::new(),
+			target,
+			shader_module,
+			pipeline_layout,
 			bind_group_layout,
 			texture_bind_group_layout,
 			_phantom_vertex: Default::default(),
+		};
+		pipeline.prepare(device, target);
+		pipeline
+	}
+
+	/// Draw into passes with `target`, building the pipeline variants for it if needed.
+	pub fn prepare(&mut self, device: &wgpu::Device, target: TargetFormat) {
+		if !self.render_pipelines.contains_key(&target) {
+			let render_pipelines = self.create_pipelines(device, target);
+			self.render_pipelines.insert(target, render_pipelines);
 		}
+		self.target = target;
+	}
+
+	fn create_pipelines(
+		&self,
+		device: &wgpu::Device,
+		target: TargetFormat,
+	) -> Vec<wgpu::RenderPipeline> {
+		log::debug!("Creating primitive pipelines for {:?}", target);
+		let create_pipeline = |blend, entry_point, buffers: &[wgpu::VertexBufferLayout]| {
+			device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+				label: Some("Primitive Render Pipeline"),
+				layout: Some(&self.pipeline_layout),
+				vertex: wgpu::VertexState {
+					module: &self.shader_module,
+					entry_point,
+					buffers,
+				},
+				fragment: Some(wgpu::FragmentState {
+					module: &self.shader_module,
+					entry_point: "fs_main",
+					targets: &[wgpu::ColorTargetState {
+						format: target.format,
+						blend: Some(if blend {
+							wgpu::BlendState::ALPHA_BLENDING
+						} else {
+							wgpu::BlendState::REPLACE
+						}),
+						write_mask: wgpu::ColorWrites::ALL,
+					}],
+				}),
+				primitive: wgpu::PrimitiveState {
+					topology: wgpu::PrimitiveTopology::TriangleList,
+					strip_index_format: None,
+					front_face: wgpu::FrontFace::Cw,
+					//cull_mode: Some(wgpu::Face::Back),
+					cull_mode: None,
+					conservative: false,
+					polygon_mode: wgpu::PolygonMode::Fill,
+					unclipped_depth: false,
+				},
+				multisample: wgpu::MultisampleState {
+					count: target.sample_count,
+					mask: !0,
+					alpha_to_coverage_enabled: false,
+				},
+				depth_stencil: Some(wgpu::DepthStencilState {
+					format: TextureBuffer::DEPTH_FORMAT,
+					depth_write_enabled: !blend,
+					depth_compare: target.depth_compare(),
+					stencil: wgpu::StencilState::default(),
+					bias: wgpu::DepthBiasState::default(),
+				}),
+				multiview: None,
+			})
+		};
+		(0..4)
+			.map(|variant| {
+				let blend = variant & 1 != 0;
+				if variant & 2 != 0 {
+					create_pipeline(
+						blend,
+						"vs_instanced",
+						&[V::buffer_layout(), Instance::buffer_layout()],
+					)
+				} else {
+					create_pipeline(blend, "vs_main", &[V::buffer_layout()])
+				}
+			})
+			.collect()
+	}
+
+	/// Variants for the target the pipeline was last prepared for.
+	fn variants(&self) -> &[wgpu::RenderPipeline] {
+		&self.render_pipelines[&self.target]
+	}
+
+	/// Use the pipeline variant matching a material's alpha mode, and whether an [`Instance`]
+	/// buffer is bound.
+	pub fn apply_alpha_mode<'a>(
+		&'a self,
+		render_pass: &mut TrackedRenderPass<'a>,
+		alpha_mode: AlphaMode,
+		instanced: bool,
+	) {
+		let blend = alpha_mode == AlphaMode::Blend;
+		let variant = blend as usize + 2 * instanced as usize;
+		render_pass.set_pipeline(&self.variants()[variant]);
 	}
 }
 
 impl Pipeline for PrimitivePipeline {
-	fn apply<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
-		render_pass.set_pipeline(&self.render_pipeline);
+	fn apply<'a>(&'a self, render_pass: &mut TrackedRenderPass<'a>) {
+		render_pass.set_pipeline(&self.variants()[0]);
+	}
+
+	fn apply_instanced<'a>(&'a self, render_pass: &mut TrackedRenderPass<'a>) {
+		render_pass.set_pipeline(&self.variants()[2]);
 	}
 
 	fn bind_group_layout(&self) -> &wgpu::BindGroupLayout {
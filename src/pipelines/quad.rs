@@ -3,7 +3,8 @@ use cgmath::Point3;
 use std::mem::size_of;
 use wgpu::VertexFormat::Float32x3;
 
-use crate::Pipeline;
+use super::Uniform;
+use crate::{Pipeline, TrackedRenderPass};
 
 pub struct QuadPipeline {
 	render_pipeline: wgpu::RenderPipeline,
@@ -16,6 +17,18 @@ pub struct Vertex {
 	pub position: Point3<f32>,
 }
 
+/// How the screen texture is mapped to the display.
+#[derive(Copy, Clone, CastBytes)]
+#[repr(C)]
+pub struct QuadUniform {
+	pub exposure: f32,
+	/// [`Tonemapping`](crate::Tonemapping) mode
+	pub tonemapping: u32,
+	pub _padding: [u32; 2],
+}
+
+impl Uniform for QuadUniform {}
+
 impl Vertex {
 	pub const fn new(x: f32, y: f32, z: f32) -> Self {
 		Self {
@@ -37,7 +50,8 @@ impl Vertex {
 }
 
 impl QuadPipeline {
-	pub fn new(device: &wgpu::Device) -> Self {
+	/// `format` is the format of the surface the quad is drawn to.
+	pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
 		// Uniforms
 		let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
 			label: Some("QuadPipeline Bind Group Layout"),
@@ -58,6 +72,17 @@ impl QuadPipeline {
 					ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
 					count: None,
 				},
+				// Quad
+				wgpu::BindGroupLayoutEntry {
+					binding: 2,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Buffer {
+						ty: wgpu::BufferBindingType::Uniform,
+						has_dynamic_offset: false,
+						min_binding_size: None,
+					},
+					count: None,
+				},
 			],
 		});
 
@@ -65,7 +90,13 @@ impl QuadPipeline {
 		log::debug!("Creating QuadPipeline shader");
 		let shader_module = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
 			label: Some("Quad Shader"),
-			source: wgpu::ShaderSource::Wgsl(include_str!("../../shaders/quad.wgsl").into()),
+			source: wgpu::ShaderSource::Wgsl(
+				concat!(
+					include_str!("../../shaders/tonemapping.wgsl"),
+					include_str!("../../shaders/quad.wgsl")
+				)
+				.into(),
+			),
 		});
 
 		log::debug!("Creating QuadPipeline layout");
@@ -88,7 +119,7 @@ impl QuadPipeline {
 				module: &shader_module,
 				entry_point: "fs_main",
 				targets: &[wgpu::ColorTargetState {
-					format: wgpu::TextureFormat::Bgra8UnormSrgb, // FIXME ctx.swapchain_format(),
+					format,
 					blend: Some(wgpu::BlendState::REPLACE),
 					write_mask: wgpu::ColorWrites::ALL,
 				}],
@@ -119,7 +150,7 @@ impl QuadPipeline {
 }
 
 impl Pipeline for QuadPipeline {
-	fn apply<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+	fn apply<'a>(&'a self, render_pass: &mut TrackedRenderPass<'a>) {
 		render_pass.set_pipeline(&self.render_pipeline);
 	}
 
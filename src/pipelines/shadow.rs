@@ -0,0 +1,127 @@
+use std::marker::PhantomData;
+
+use crate::{Instance, Pipeline, PrimitiveVertex, TextureBuffer, TrackedRenderPass, Vertex};
+
+pub const SHADOW_LIGHT_BINDING: u32 = 0;
+pub const SHADOW_ACTOR_BINDING: u32 = 1;
+
+/// Renders the depth of shadow casters into a shadow map, as seen from a light.
+pub struct ShadowPipeline<V: Vertex = PrimitiveVertex> {
+	render_pipeline: wgpu::RenderPipeline,
+	instanced_pipeline: wgpu::RenderPipeline,
+	bind_group_layout: wgpu::BindGroupLayout,
+	_phantom_vertex: PhantomData<V>,
+}
+
+impl<V: Vertex> ShadowPipeline<V> {
+	pub fn new(device: &wgpu::Device) -> Self {
+		// Uniforms
+		let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+			label: Some("ShadowPipeline Bind Group Layout"),
+			entries: &[
+				// Light
+				wgpu::BindGroupLayoutEntry {
+					binding: SHADOW_LIGHT_BINDING,
+					visibility: wgpu::ShaderStages::VERTEX,
+					ty: wgpu::BindingType::Buffer {
+						ty: wgpu::BufferBindingType::Uniform,
+						has_dynamic_offset: true,
+						min_binding_size: None,
+					},
+					count: None,
+				},
+				// Actor
+				wgpu::BindGroupLayoutEntry {
+					binding: SHADOW_ACTOR_BINDING,
+					visibility: wgpu::ShaderStages::VERTEX,
+					ty: wgpu::BindingType::Buffer {
+						ty: wgpu::BufferBindingType::Uniform,
+						has_dynamic_offset: true,
+						min_binding_size: None,
+					},
+					count: None,
+				},
+			],
+		});
+
+		// Shader
+		log::debug!("Creating Shadow shader");
+		let shader_module = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+			label: Some("Shadow Shader"),
+			source: wgpu::ShaderSource::Wgsl(include_str!("../../shaders/shadow.wgsl").into()),
+		});
+
+		let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+			label: Some("Shadow Pipeline Layout"),
+			bind_group_layouts: &[&bind_group_layout],
+			push_constant_ranges: &[],
+		});
+
+		log::debug!("Creating shadow pipeline");
+		let create_pipeline = |entry_point, buffers: &[wgpu::VertexBufferLayout]| {
+			device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+				label: Some("Shadow Render Pipeline"),
+				layout: Some(&pipeline_layout),
+				vertex: wgpu::VertexState {
+					module: &shader_module,
+					entry_point,
+					buffers,
+				},
+				fragment: None,
+				primitive: wgpu::PrimitiveState {
+					topology: wgpu::PrimitiveTopology::TriangleList,
+					strip_index_format: None,
+					front_face: wgpu::FrontFace::Ccw,
+					// Meshes aren't guaranteed to be closed, so both sides cast shadows
+					cull_mode: None,
+					conservative: false,
+					polygon_mode: wgpu::PolygonMode::Fill,
+					unclipped_depth: false,
+				},
+				multisample: wgpu::MultisampleState {
+					count: 1,
+					mask: !0,
+					alpha_to_coverage_enabled: false,
+				},
+				depth_stencil: Some(wgpu::DepthStencilState {
+					format: TextureBuffer::DEPTH_FORMAT,
+					depth_write_enabled: true,
+					depth_compare: wgpu::CompareFunction::LessEqual,
+					stencil: wgpu::StencilState::default(),
+					bias: wgpu::DepthBiasState {
+						constant: 2,
+						slope_scale: 2.0,
+						clamp: 0.0,
+					},
+				}),
+				multiview: None,
+			})
+		};
+		let render_pipeline = create_pipeline("vs_main", &[V::buffer_layout()]);
+		let instanced_pipeline = create_pipeline(
+			"vs_instanced",
+			&[V::buffer_layout(), Instance::buffer_layout()],
+		);
+
+		Self {
+			render_pipeline,
+			instanced_pipeline,
+			bind_group_layout,
+			_phantom_vertex: Default::default(),
+		}
+	}
+}
+
+impl Pipeline for ShadowPipeline {
+	fn apply<'a>(&'a self, render_pass: &mut TrackedRenderPass<'a>) {
+		render_pass.set_pipeline(&self.render_pipeline);
+	}
+
+	fn apply_instanced<'a>(&'a self, render_pass: &mut TrackedRenderPass<'a>) {
+		render_pass.set_pipeline(&self.instanced_pipeline);
+	}
+
+	fn bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+		&self.bind_group_layout
+	}
+}
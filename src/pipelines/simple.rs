@@ -1,5 +1,5 @@
 use super::Uniform;
-use crate::{Pipeline, SimpleVertex, TextureBuffer, Vertex};
+use crate::{Pipeline, SimpleVertex, TextureBuffer, TrackedRenderPass, Vertex};
 use byd_derive::CastBytes;
 use cgmath::{Matrix4, Vector4};
 
@@ -18,9 +18,17 @@ pub struct CameraUniform {
 impl Uniform for CameraUniform {}
 
 #[derive(Copy, Clone, CastBytes)]
+#[repr(C)]
 pub struct ActorUniform {
 	pub color: Vector4<f32>,
 	pub model: Matrix4<f32>,
+	/// Whether lit shaders should darken the object where shadows fall on it
+	pub receive_shadows: u32,
+	/// [`AlphaMode`](crate::AlphaMode) of the object's material
+	pub alpha_mode: u32,
+	/// Alpha below which masked fragments are discarded
+	pub alpha_cutoff: f32,
+	pub _padding: u32,
 }
 impl Uniform for ActorUniform {}
 
@@ -166,7 +174,7 @@ impl SimplePipeline {
 }
 
 impl Pipeline for SimplePipeline {
-	fn apply<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+	fn apply<'a>(&'a self, render_pass: &mut TrackedRenderPass<'a>) {
 		render_pass.set_pipeline(&self.render_pipeline);
 	}
 
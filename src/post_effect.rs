@@ -0,0 +1,187 @@
+use crate::{
+	pipelines::{
+		PostPipeline, PostUniform, Uniform, POST_COLOR_BINDING, POST_DEPTH_BINDING,
+		POST_SAMPLER_BINDING, POST_UNIFORM_BINDING,
+	},
+	Handle, Pipeline, TrackedRenderPass,
+};
+use cgmath::Vector4;
+use std::{borrow::Cow, mem::size_of};
+
+pub type PostEffectID = Handle;
+
+/// How high dynamic range colors are mapped into the range the display can show.
+///
+/// The discriminants match the `TONEMAP_*` constants used by the shaders.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[repr(u32)]
+pub enum Tonemapping {
+	/// Colors are clipped at 1
+	#[default]
+	None = 0,
+	/// `x / (1 + x)`, which never quite reaches white
+	Reinhard = 1,
+	/// The ACES filmic curve, with more contrast than Reinhard
+	Aces = 2,
+}
+
+/// A fullscreen pass over the frame, run after the scene is drawn.
+///
+/// Effects are added to a [`Renderer`](crate::Renderer) and run in the order they were added,
+/// each reading the output of the one before. Custom effects are WGSL fragment shaders with an
+/// `fs_main(in: PostInput)` entry point, added to `shaders/post.wgsl`, which gives them the
+/// color and depth textures, the screen resolution, the time and [`PostEffect::params`].
+pub struct PostEffect {
+	source: Cow<'static, str>,
+	/// Settings of the effect, available to the shader as `post.params`
+	pub params: Vector4<f32>,
+	/// Disabled effects are skipped
+	pub enabled: bool,
+	state: Option<PostEffectState>,
+}
+
+/// Pipeline of an effect, built for the format of the screen.
+struct PostEffectState {
+	format: wgpu::TextureFormat,
+	pipeline: PostPipeline,
+	uniform_buffer: wgpu::Buffer,
+}
+
+impl PostEffect {
+	/// Effect running a fragment shader. See [`PostEffect`] for what the shader has access to.
+	pub fn new(source: impl Into<Cow<'static, str>>) -> Self {
+		Self {
+			source: source.into(),
+			params: Vector4::new(0.0, 0.0, 0.0, 0.0),
+			enabled: true,
+			state: None,
+		}
+	}
+
+	pub fn with_params(mut self, params: Vector4<f32>) -> Self {
+		self.params = params;
+		self
+	}
+
+	/// Map high dynamic range colors into the displayable range after scaling them by
+	/// `exposure`. Effects after this one then work on displayable colors, unlike the
+	/// [`Renderer`](crate::Renderer)'s own tonemapping, which runs after every effect.
+	pub fn tonemap(tonemapping: Tonemapping, exposure: f32) -> Self {
+		Self::new(concat!(
+			include_str!("../shaders/tonemapping.wgsl"),
+			include_str!("../shaders/tonemap.wgsl")
+		))
+		.with_params(Vector4::new(exposure, tonemapping as u32 as f32, 0.0, 0.0))
+	}
+
+	/// Raise colors to the power of `1 / gamma`. Only needed when the screen format isn't sRGB,
+	/// as sRGB formats are gamma corrected when written.
+	pub fn gamma(gamma: f32) -> Self {
+		Self::new(include_str!("../shaders/gamma.wgsl"))
+			.with_params(Vector4::new(gamma, 0.0, 0.0, 0.0))
+	}
+
+	/// Fast approximate anti-aliasing, which blurs edges found by their contrast. Cheaper than
+	/// multisampling, and also smooths edges within textures and shaders.
+	pub fn fxaa() -> Self {
+		Self::new(include_str!("../shaders/fxaa.wgsl"))
+	}
+
+	/// Glow around parts of the frame brighter than `threshold`, blurred over `radius` pixels
+	/// and added back scaled by `intensity`.
+	pub fn bloom(threshold: f32, intensity: f32, radius: f32) -> Self {
+		Self::new(include_str!("../shaders/bloom.wgsl"))
+			.with_params(Vector4::new(threshold, intensity, radius, 0.0))
+	}
+
+	/// Darken the edges of the frame by up to `strength`, starting `start` of the way from the
+	/// centre to the corners.
+	pub fn vignette(strength: f32, start: f32) -> Self {
+		Self::new(include_str!("../shaders/vignette.wgsl"))
+			.with_params(Vector4::new(strength, start, 0.0, 0.0))
+	}
+
+	/// The effect's fragment shader.
+	pub fn source(&self) -> &str {
+		&self.source
+	}
+
+	/// Draw the effect from `input` into `output`, building its pipeline first if it hasn't been
+	/// built for `format` yet.
+	#[allow(clippy::too_many_arguments)]
+	pub(crate) fn draw(
+		&mut self,
+		device: &wgpu::Device,
+		queue: &mut wgpu::Queue,
+		encoder: &mut wgpu::CommandEncoder,
+		format: wgpu::TextureFormat,
+		input: &wgpu::TextureView,
+		depth: &wgpu::TextureView,
+		output: &wgpu::TextureView,
+		mut uniform: PostUniform,
+	) {
+		if self
+			.state
+			.as_ref()
+			.is_none_or(|state| state.format != format)
+		{
+			log::debug!("Compiling post effect for {:?}", format);
+			self.state = Some(PostEffectState {
+				format,
+				pipeline: PostPipeline::new(device, &self.source, format),
+				uniform_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+					label: Some("Post Effect Uniform Buffer"),
+					size: size_of::<PostUniform>() as wgpu::BufferAddress,
+					usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+					mapped_at_creation: false,
+				}),
+			});
+		}
+		let state = self.state.as_ref().unwrap();
+
+		uniform.params = self.params;
+		queue.write_buffer(&state.uniform_buffer, 0, uniform.as_bytes());
+		let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+			label: Some("PostPipeline Bind Group"),
+			layout: state.pipeline.bind_group_layout(),
+			entries: &[
+				// Color
+				wgpu::BindGroupEntry {
+					binding: POST_COLOR_BINDING,
+					resource: wgpu::BindingResource::TextureView(input),
+				},
+				wgpu::BindGroupEntry {
+					binding: POST_SAMPLER_BINDING,
+					resource: wgpu::BindingResource::Sampler(state.pipeline.sampler()),
+				},
+				// Depth
+				wgpu::BindGroupEntry {
+					binding: POST_DEPTH_BINDING,
+					resource: wgpu::BindingResource::TextureView(depth),
+				},
+				// Post
+				wgpu::BindGroupEntry {
+					binding: POST_UNIFORM_BINDING,
+					resource: state.uniform_buffer.as_entire_binding(),
+				},
+			],
+		});
+
+		let render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+			label: Some("Post Pass"),
+			color_attachments: &[wgpu::RenderPassColorAttachment {
+				view: output,
+				resolve_target: None,
+				ops: wgpu::Operations {
+					load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+					store: true,
+				},
+			}],
+			depth_stencil_attachment: None,
+		});
+		let mut render_pass = TrackedRenderPass::new(render_pass);
+		state.pipeline.apply(&mut render_pass);
+		render_pass.set_bind_group(0, &bind_group, &[]);
+		render_pass.draw(0..3, 0..1);
+	}
+}
@@ -2,21 +2,34 @@ use std::{collections::HashMap, mem::size_of};
 
 use crate::{
 	pipelines::{
-		ActorUniform, CameraUniform, CustomPipeline, ACTOR_BINDING, CAMERA_BINDING,
-		SAMPLER_BINDING, TEXTURE_BINDING, TEXTURE_ENABLED_BINDING,
+		ActorUniform, CameraUniform, CustomPipeline, LightResources, LightsUniform, ACTOR_BINDING,
+		CAMERA_BINDING, LIGHTS_BINDING, SAMPLER_BINDING, SHADOW_MAP_BINDING,
+		SHADOW_SAMPLER_BINDING, TEXTURE_BINDING, TEXTURE_ENABLED_BINDING,
 	},
-	Camera, Pipeline, RenderContext, TextureBuffer, TextureID, Vertex,
+	scene::{create_slot_buffer, slot_capacity, INITIAL_SLOTS},
+	Camera, Pipeline, RenderContext, TargetFormat, TextureBuffer, TextureID, Vertex,
+	DEFAULT_TEXTURE,
 };
 
-const MAX_OBJECTS: u64 = 2048;
-
 pub trait Program {
-	fn compile(&mut self, ctx: &mut RenderContext);
+	/// Build the program's pipeline. `lights` are the scene's light buffer and shadow maps, shared
+	/// by every program.
+	fn compile(&mut self, ctx: &mut RenderContext, lights: &LightResources);
+	/// Draw into passes with `target`, building the pipeline for it if needed. Called before each
+	/// frame, after [`compile`](Self::compile), and whenever the scene is drawn into a different
+	/// target. Bind groups must be kept, as textures aren't added again.
+	fn prepare(&mut self, device: &wgpu::Device, target: TargetFormat);
+	/// Make room for the uniforms of `slots` objects.
+	fn reserve(&mut self, device: &wgpu::Device, slots: u64, lights: &LightResources);
 	fn set_camera(&self, ctx: &mut RenderContext, camera: &dyn Camera);
 	fn set_actor(&self, ctx: &mut RenderContext, index: u64, contents: ActorUniform);
-	fn bind_actor<'a>(&'a self, ctx: &mut RenderContext<'a>, index: u64);
+	/// Bind the pipeline and uniforms for an object. `instanced` objects use the pipeline variant
+	/// that reads an [`Instance`](crate::Instance) buffer. Returns false if the object can't be
+	/// drawn, e.g. an instanced object without an instanced variant.
+	fn bind_actor<'a>(&'a self, ctx: &mut RenderContext<'a>, index: u64, instanced: bool) -> bool;
 	fn bind_texture<'a>(&'a self, ctx: &mut RenderContext<'a>, id: TextureID);
 	fn add_texture(&mut self, id: TextureID, device: &wgpu::Device, texture: &TextureBuffer);
+	fn remove_texture(&mut self, id: TextureID);
 }
 
 struct ProgramState<P: Pipeline> {
@@ -26,9 +39,80 @@ struct ProgramState<P: Pipeline> {
 	camera_buffer: wgpu::Buffer,
 	actor_buffer: wgpu::Buffer,
 	enabled_buffer: wgpu::Buffer,
+	/// Number of objects the actor buffer has room for
+	capacity: u64,
 }
 
 impl<P: Pipeline> ProgramState<P> {
+	fn create_bind_group(
+		device: &wgpu::Device,
+		pipeline: &P,
+		camera_buffer: &wgpu::Buffer,
+		actor_buffer: &wgpu::Buffer,
+		lights: &LightResources,
+	) -> wgpu::BindGroup {
+		let camera_size = size_of::<CameraUniform>() as wgpu::BufferAddress;
+		let actor_size = size_of::<ActorUniform>() as wgpu::BufferAddress;
+		let lights_size = size_of::<LightsUniform>() as wgpu::BufferAddress;
+
+		device.create_bind_group(&wgpu::BindGroupDescriptor {
+			label: Some("Program Bind Group"),
+			layout: pipeline.bind_group_layout(),
+			entries: &[
+				// Camera
+				wgpu::BindGroupEntry {
+					binding: CAMERA_BINDING,
+					resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+						buffer: camera_buffer,
+						size: wgpu::BufferSize::new(camera_size),
+						offset: 0,
+					}),
+				},
+				// Actors
+				wgpu::BindGroupEntry {
+					binding: ACTOR_BINDING,
+					resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+						buffer: actor_buffer,
+						size: wgpu::BufferSize::new(actor_size),
+						offset: 0,
+					}),
+				},
+				// Lights
+				wgpu::BindGroupEntry {
+					binding: LIGHTS_BINDING,
+					resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+						buffer: lights.buffer,
+						size: wgpu::BufferSize::new(lights_size),
+						offset: 0,
+					}),
+				},
+				// Shadow Maps
+				wgpu::BindGroupEntry {
+					binding: SHADOW_MAP_BINDING,
+					resource: wgpu::BindingResource::TextureView(lights.shadow_map),
+				},
+				wgpu::BindGroupEntry {
+					binding: SHADOW_SAMPLER_BINDING,
+					resource: wgpu::BindingResource::Sampler(lights.shadow_sampler),
+				},
+			],
+		})
+	}
+
+	fn reserve(&mut self, device: &wgpu::Device, slots: u64, lights: &LightResources) {
+		if slots > self.capacity {
+			self.capacity = slot_capacity(slots);
+			self.actor_buffer = create_slot_buffer(device, "Actor Buffer", self.capacity);
+			self.bind_group = Self::create_bind_group(
+				device,
+				&self.pipeline,
+				&self.camera_buffer,
+				&self.actor_buffer,
+				lights,
+			);
+		}
+	}
+
 	pub fn set_camera(&self, ctx: &mut RenderContext, camera: &dyn Camera) {
 		let contents = CameraUniform {
 			view: camera.view(),
@@ -49,12 +133,16 @@ impl<P: Pipeline> ProgramState<P> {
 		);
 	}
 
-	pub fn bind_actor<'a>(&'a self, ctx: &mut RenderContext<'a>, index: u64) {
+	pub fn bind_actor<'a>(&'a self, ctx: &mut RenderContext<'a>, index: u64, instanced: bool) {
 		let render_pass = &mut ctx.render_pass;
 		let uniform_alignment =
 			ctx.device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
 		let offset = (index * uniform_alignment) as wgpu::DynamicOffset;
-		self.pipeline.apply(render_pass);
+		if instanced {
+			self.pipeline.apply_instanced(render_pass);
+		} else {
+			self.pipeline.apply(render_pass);
+		}
 		render_pass.set_bind_group(0, &self.bind_group, &[offset]);
 	}
 
@@ -62,8 +150,11 @@ impl<P: Pipeline> ProgramState<P> {
 		if let Some(texture) = self.texture_bind_groups.get(&id) {
 			let uniform_alignment =
 				ctx.device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
-			let is_enabled_offset =
-				(if id == 0 { 0 } else { uniform_alignment }) as wgpu::DynamicOffset;
+			let is_enabled_offset = (if id == DEFAULT_TEXTURE {
+				0
+			} else {
+				uniform_alignment
+			}) as wgpu::DynamicOffset;
 
 			ctx.render_pass
 				.set_bind_group(1, texture, &[is_enabled_offset]);
@@ -108,6 +199,13 @@ impl<P: Pipeline> ProgramState<P> {
 pub struct SimpleProgram<V: Vertex> {
 	state: Option<ProgramState<CustomPipeline<V>>>,
 	source: String,
+	instanced: bool,
+}
+
+impl<V: Vertex> Default for SimpleProgram<V> {
+	fn default() -> Self {
+		Self::new()
+	}
 }
 
 impl<V: Vertex> SimpleProgram<V> {
@@ -115,19 +213,28 @@ impl<V: Vertex> SimpleProgram<V> {
 		Self {
 			state: None,
 			source: "".into(),
+			instanced: false,
 		}
 	}
 	pub fn shader(mut self, source: &str) -> Self {
 		self.source = source.into();
 		self
 	}
+
+	/// The shader also has a `vs_instanced` entry point, taking the [`Instance`](crate::Instance)
+	/// attributes at locations 8 to 12, to draw [`InstancedMesh`](crate::InstancedMesh)es with.
+	/// Without it, instanced meshes using the program aren't drawn.
+	pub fn instanced(mut self) -> Self {
+		self.instanced = true;
+		self
+	}
 }
 
 impl<V: Vertex> Program for SimpleProgram<V> {
-	fn compile(&mut self, ctx: &mut RenderContext) {
+	fn compile(&mut self, ctx: &mut RenderContext, lights: &LightResources) {
 		let device = ctx.device;
 		let queue = &mut ctx.queue;
-		let pipeline = CustomPipeline::new(device, &self.source);
+		let pipeline = CustomPipeline::new(device, &self.source, ctx.target, self.instanced);
 		let uniform_alignment =
 			device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
 
@@ -138,12 +245,7 @@ impl<V: Vertex> Program for SimpleProgram<V> {
 			mapped_at_creation: false,
 		});
 
-		let actor_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-			label: Some("Actor Buffer"),
-			usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-			size: MAX_OBJECTS * uniform_alignment,
-			mapped_at_creation: false,
-		});
+		let actor_buffer = create_slot_buffer(device, "Actor Buffer", INITIAL_SLOTS);
 
 		let enabled_buffer = device.create_buffer(&wgpu::BufferDescriptor {
 			label: Some("Texture Enabled Buffer"),
@@ -152,44 +254,20 @@ impl<V: Vertex> Program for SimpleProgram<V> {
 			mapped_at_creation: false,
 		});
 
+		queue.write_buffer(&enabled_buffer, 0, bytemuck::cast_slice(&[0]));
 		queue.write_buffer(
 			&enabled_buffer,
-			0 * uniform_alignment,
+			uniform_alignment,
 			bytemuck::cast_slice(&[0]),
 		);
-		queue.write_buffer(
-			&enabled_buffer,
-			1 * uniform_alignment,
-			bytemuck::cast_slice(&[0]),
-		);
-
-		let camera_size = size_of::<CameraUniform>() as wgpu::BufferAddress;
-		let actor_size = size_of::<ActorUniform>() as wgpu::BufferAddress;
 
-		let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-			label: Some("Program Bind Group"),
-			layout: pipeline.bind_group_layout(),
-			entries: &[
-				// Camera
-				wgpu::BindGroupEntry {
-					binding: CAMERA_BINDING,
-					resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-						buffer: &camera_buffer,
-						size: wgpu::BufferSize::new(camera_size),
-						offset: 0,
-					}),
-				},
-				// Actors
-				wgpu::BindGroupEntry {
-					binding: ACTOR_BINDING,
-					resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-						buffer: &actor_buffer,
-						size: wgpu::BufferSize::new(actor_size),
-						offset: 0,
-					}),
-				},
-			],
-		});
+		let bind_group = ProgramState::create_bind_group(
+			device,
+			&pipeline,
+			&camera_buffer,
+			&actor_buffer,
+			lights,
+		);
 
 		self.state = Some(ProgramState {
 			pipeline,
@@ -198,31 +276,63 @@ impl<V: Vertex> Program for SimpleProgram<V> {
 			camera_buffer,
 			actor_buffer,
 			enabled_buffer,
+			capacity: INITIAL_SLOTS,
 		});
 	}
 
+	fn prepare(&mut self, device: &wgpu::Device, target: TargetFormat) {
+		if let Some(s) = self.state.as_mut() {
+			s.pipeline.prepare(device, target);
+		}
+	}
+
+	fn reserve(&mut self, device: &wgpu::Device, slots: u64, lights: &LightResources) {
+		if let Some(s) = self.state.as_mut() {
+			s.reserve(device, slots, lights);
+		}
+	}
+
 	fn set_camera(&self, ctx: &mut RenderContext, camera: &dyn Camera) {
-		self.state.as_ref().map(|s| s.set_camera(ctx, camera));
+		if let Some(s) = self.state.as_ref() {
+			s.set_camera(ctx, camera);
+		}
 	}
 
 	fn set_actor(&self, ctx: &mut RenderContext, index: u64, contents: ActorUniform) {
-		self.state
-			.as_ref()
-			.map(|s| s.set_actor(ctx, index, contents));
+		if let Some(s) = self.state.as_ref() {
+			s.set_actor(ctx, index, contents);
+		}
 	}
 
-	fn bind_actor<'a>(&'a self, ctx: &mut RenderContext<'a>, index: u64) {
-		self.state.as_ref().map(|s| s.bind_actor(ctx, index));
+	fn bind_actor<'a>(&'a self, ctx: &mut RenderContext<'a>, index: u64, instanced: bool) -> bool {
+		let s = match self.state.as_ref() {
+			Some(s) => s,
+			None => return false,
+		};
+		if instanced && !s.pipeline.instanced() {
+			log::warn!("Skipping an instanced object, as its program has no vs_instanced");
+			return false;
+		}
+		s.bind_actor(ctx, index, instanced);
+		true
 	}
 
 	fn bind_texture<'a>(&'a self, ctx: &mut RenderContext<'a>, id: TextureID) {
-		self.state.as_ref().map(|s| s.bind_texture(ctx, id));
+		if let Some(s) = self.state.as_ref() {
+			s.bind_texture(ctx, id);
+		}
 	}
 
 	fn add_texture(&mut self, id: TextureID, device: &wgpu::Device, texture: &TextureBuffer) {
-		self.state
-			.as_mut()
-			.map(|s| s.add_texture(id, device, texture));
+		if let Some(s) = self.state.as_mut() {
+			s.add_texture(id, device, texture);
+		}
+	}
+
+	fn remove_texture(&mut self, id: TextureID) {
+		if let Some(s) = self.state.as_mut() {
+			s.texture_bind_groups.remove(&id);
+		}
 	}
 }
 
@@ -269,7 +379,7 @@ impl<P: Pipeline> Program for CustomProgram<P> {
 				wgpu::BindGroupEntry {
 					binding: CAMERA_BINDING,
 					resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-						buffer: &camera_buffer,
+						buffer: camera_buffer,
 						size: wgpu::BufferSize::new(camera_size),
 						offset: 0,
 					}),
@@ -278,7 +388,7 @@ impl<P: Pipeline> Program for CustomProgram<P> {
 				wgpu::BindGroupEntry {
 					binding: ACTOR_BINDING,
 					resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-						buffer: &actor_buffer,
+						buffer: actor_buffer,
 						size: wgpu::BufferSize::new(actor_size),
 						offset: 0,
 					}),
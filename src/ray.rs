@@ -0,0 +1,152 @@
+use crate::{BoundingBox, Camera, ObjectID};
+use cgmath::{InnerSpace, Matrix4, Point3, SquareMatrix, Vector3, Vector4, Zero};
+
+/// A half-line from `origin` along `direction`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Ray {
+	pub origin: Point3<f32>,
+	pub direction: Vector3<f32>,
+}
+
+impl Ray {
+	/// Ray with a normalized direction, so distances along it are in world units.
+	///
+	/// A zero direction, which can't be normalized, is kept as zero rather than becoming NaN, so
+	/// the ray doesn't hit any triangles.
+	pub fn new(origin: Point3<f32>, direction: Vector3<f32>) -> Self {
+		let length = direction.magnitude();
+		Self {
+			origin,
+			direction: if length > 0.0 {
+				direction / length
+			} else {
+				Vector3::zero()
+			},
+		}
+	}
+
+	/// Ray from the camera through a pixel of a `width` by `height` viewport, with `(0, 0)` at
+	/// its top left.
+	pub fn from_screen(camera: &dyn Camera, x: f32, y: f32, width: f32, height: f32) -> Self {
+		let inverse = (camera.projection() * camera.view())
+			.invert()
+			.unwrap_or_else(Matrix4::identity);
+		let unproject = |z: f32| {
+			let ndc = Vector4::new(2.0 * x / width - 1.0, 1.0 - 2.0 * y / height, z, 1.0);
+			Point3::from_homogeneous(inverse * ndc)
+		};
+
+		// Depths 0 and 1 are the near and far planes of the depth buffer. With reverse-Z the near
+		// plane is at 1 and the far plane at 0, which may be at infinity, so a nearer depth is
+		// used to find the direction.
+		if camera.reverse_z() {
+			let near = unproject(1.0);
+			Self::new(near, unproject(0.5) - near)
+		} else {
+			let near = unproject(0.0);
+			Self::new(near, unproject(1.0) - near)
+		}
+	}
+
+	pub fn at(&self, distance: f32) -> Point3<f32> {
+		self.origin + self.direction * distance
+	}
+
+	/// The ray in another space, e.g. an object's local space from its inverse world transform.
+	///
+	/// The direction isn't renormalized, so distances along the transformed ray match distances
+	/// along this one.
+	pub fn transform(&self, transform: &Matrix4<f32>) -> Self {
+		Self {
+			origin: Point3::from_homogeneous(transform * self.origin.to_homogeneous()),
+			direction: (transform * self.direction.extend(0.0)).truncate(),
+		}
+	}
+
+	/// Distance to where the ray enters a box, or zero if it starts inside it.
+	pub fn intersect_box(&self, bounds: &BoundingBox) -> Option<f32> {
+		let mut near = 0.0f32;
+		let mut far = f32::INFINITY;
+		for axis in 0..3 {
+			// Parallel to the slab, which would otherwise give NaN when starting on its edge
+			if self.direction[axis] == 0.0 {
+				if self.origin[axis] < bounds.min[axis] || self.origin[axis] > bounds.max[axis] {
+					return None;
+				}
+				continue;
+			}
+			let inverse = 1.0 / self.direction[axis];
+			let a = (bounds.min[axis] - self.origin[axis]) * inverse;
+			let b = (bounds.max[axis] - self.origin[axis]) * inverse;
+			near = near.max(a.min(b));
+			far = far.min(a.max(b));
+		}
+		(near <= far).then_some(near)
+	}
+
+	/// Where the ray hits a triangle, from either side.
+	pub fn intersect_triangle(&self, triangle: [Point3<f32>; 3]) -> Option<TriangleHit> {
+		// Möller–Trumbore
+		let edge1 = triangle[1] - triangle[0];
+		let edge2 = triangle[2] - triangle[0];
+		let p = self.direction.cross(edge2);
+		let determinant = edge1.dot(p);
+		// The determinant scales with the triangle's size and the ray's length, so compare it
+		// relative to them to skip rays parallel to the triangle and degenerate triangles
+		let scale = edge1.magnitude() * edge2.magnitude() * self.direction.magnitude();
+		if determinant.abs() <= f32::EPSILON * scale {
+			return None;
+		}
+
+		let inverse = 1.0 / determinant;
+		let s = self.origin - triangle[0];
+		let u = s.dot(p) * inverse;
+		if !(0.0..=1.0).contains(&u) {
+			return None;
+		}
+		let q = s.cross(edge1);
+		let v = self.direction.dot(q) * inverse;
+		if v < 0.0 || u + v > 1.0 {
+			return None;
+		}
+
+		let distance = edge2.dot(q) * inverse;
+		(distance >= 0.0).then_some(TriangleHit {
+			distance,
+			index: 0,
+			triangle,
+			barycentric: Vector3::new(1.0 - u - v, u, v),
+		})
+	}
+}
+
+/// Where a ray hit a triangle.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TriangleHit {
+	/// Distance along the ray
+	pub distance: f32,
+	/// Index of the triangle in the list it was tested from, e.g. the one passed to
+	/// [`Geometry::triangle`](crate::Geometry::triangle). 0 for a single triangle.
+	pub index: usize,
+	/// Corners of the triangle that was hit
+	pub triangle: [Point3<f32>; 3],
+	/// Weight of each corner at the point that was hit
+	pub barycentric: Vector3<f32>,
+}
+
+/// Where a ray hit an object in a [`Scene`](crate::Scene).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Hit {
+	pub object: ObjectID,
+	/// Distance along the ray
+	pub distance: f32,
+	/// World space position
+	pub position: Point3<f32>,
+	/// World space normal of the triangle that was hit, facing back along the ray
+	pub normal: Vector3<f32>,
+	/// Index of the triangle that was hit in the object's geometry, see
+	/// [`Geometry::triangle`](crate::Geometry::triangle)
+	pub triangle: usize,
+	/// Weight of each corner of the triangle that was hit
+	pub barycentric: Vector3<f32>,
+}
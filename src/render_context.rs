@@ -1,8 +1,290 @@
-use crate::Camera;
+use crate::{Camera, TextureBuffer};
+use std::ops::{Bound, Range, RangeBounds};
 
 pub struct RenderContext<'a> {
 	pub device: &'a wgpu::Device,
 	pub queue: &'a mut wgpu::Queue,
-	pub render_pass: wgpu::RenderPass<'a>,
+	/// The pass being drawn into. Objects that used the [`wgpu::RenderPass`] directly can reach it
+	/// through [`TrackedRenderPass::raw`].
+	pub render_pass: TrackedRenderPass<'a>,
 	pub camera: &'a dyn Camera,
+	/// Targets of the pass, which pipelines drawing into it must be built for
+	pub target: TargetFormat,
+	/// Color texture of the [`RenderTarget`](crate::RenderTarget) being drawn into, if it can also
+	/// be shown in scenes. Objects textured with it are skipped, as a texture can't be drawn into
+	/// and sampled at once.
+	pub render_target: Option<&'a TextureBuffer>,
+}
+
+impl<'a> RenderContext<'a> {
+	pub fn new(
+		device: &'a wgpu::Device,
+		queue: &'a mut wgpu::Queue,
+		render_pass: wgpu::RenderPass<'a>,
+		camera: &'a dyn Camera,
+		target: TargetFormat,
+	) -> Self {
+		Self {
+			device,
+			queue,
+			render_pass: TrackedRenderPass::new(render_pass),
+			camera,
+			target,
+			render_target: None,
+		}
+	}
+}
+
+/// Color format and samples per pixel of a render pass's targets, and which way its depth
+/// test runs.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TargetFormat {
+	pub format: wgpu::TextureFormat,
+	pub sample_count: u32,
+	/// Whether depth is cleared to 0 and nearer fragments have greater depths, for cameras with
+	/// a [reverse-Z](crate::Camera::reverse_z) projection. Scene pipelines keep a variant for
+	/// each direction, so cameras with and without reverse-Z can share a scene.
+	pub reverse_z: bool,
+}
+
+impl TargetFormat {
+	/// Screen format that keeps colors brighter than white, for tonemapping.
+	pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+	pub fn new(format: wgpu::TextureFormat, sample_count: u32) -> Self {
+		Self {
+			format,
+			sample_count,
+			reverse_z: false,
+		}
+	}
+
+	pub fn with_reverse_z(mut self, reverse_z: bool) -> Self {
+		self.reverse_z = reverse_z;
+		self
+	}
+
+	/// Depth test passing fragments nearer than what's already been drawn.
+	pub fn depth_compare(&self) -> wgpu::CompareFunction {
+		if self.reverse_z {
+			wgpu::CompareFunction::Greater
+		} else {
+			wgpu::CompareFunction::Less
+		}
+	}
+
+	/// Depth of the far plane, which the depth buffer is cleared to.
+	pub fn clear_depth(&self) -> f32 {
+		if self.reverse_z {
+			0.0
+		} else {
+			1.0
+		}
+	}
+}
+
+impl Default for TargetFormat {
+	/// The default screen format, without multisampling.
+	fn default() -> Self {
+		Self::new(wgpu::TextureFormat::Rgba8UnormSrgb, 1)
+	}
+}
+
+/// Number of draws and state changes recorded in a render pass.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RenderStats {
+	pub draw_calls: usize,
+	pub pipeline_changes: usize,
+	pub bind_group_changes: usize,
+	/// Vertex and index buffer changes
+	pub buffer_changes: usize,
+}
+
+impl RenderStats {
+	/// Total number of pipeline, bind group and buffer changes.
+	pub fn state_changes(&self) -> usize {
+		self.pipeline_changes + self.bind_group_changes + self.buffer_changes
+	}
+}
+
+/// Start and end of the bytes of a buffer that are bound, with no end meaning the rest of the
+/// buffer.
+type BufferRange = (wgpu::BufferAddress, Option<wgpu::BufferAddress>);
+
+fn buffer_range(bounds: impl RangeBounds<wgpu::BufferAddress>) -> BufferRange {
+	let start = match bounds.start_bound() {
+		Bound::Included(&start) => start,
+		Bound::Excluded(&start) => start + 1,
+		Bound::Unbounded => 0,
+	};
+	let end = match bounds.end_bound() {
+		Bound::Included(&end) => Some(end + 1),
+		Bound::Excluded(&end) => Some(end),
+		Bound::Unbounded => None,
+	};
+	(start, end)
+}
+
+fn buffer_slice(buffer: &wgpu::Buffer, (start, end): BufferRange) -> wgpu::BufferSlice<'_> {
+	match end {
+		Some(end) => buffer.slice(start..end),
+		None => buffer.slice(start..),
+	}
+}
+
+/// A [`wgpu::RenderPass`] that skips calls which wouldn't change its state, and counts the rest.
+///
+/// This replaced the plain render pass in [`RenderContext`]. Anything it doesn't wrap is still
+/// available through [`raw`](Self::raw).
+pub struct TrackedRenderPass<'a> {
+	render_pass: wgpu::RenderPass<'a>,
+	pipeline: Option<&'a wgpu::RenderPipeline>,
+	bind_groups: Vec<Option<(&'a wgpu::BindGroup, Vec<wgpu::DynamicOffset>)>>,
+	vertex_buffers: Vec<Option<(&'a wgpu::Buffer, BufferRange)>>,
+	index_buffer: Option<(&'a wgpu::Buffer, BufferRange, wgpu::IndexFormat)>,
+	stats: RenderStats,
+}
+
+impl<'a> TrackedRenderPass<'a> {
+	pub fn new(render_pass: wgpu::RenderPass<'a>) -> Self {
+		Self {
+			render_pass,
+			pipeline: None,
+			bind_groups: Vec::new(),
+			vertex_buffers: Vec::new(),
+			index_buffer: None,
+			stats: RenderStats::default(),
+		}
+	}
+
+	/// Draws and state changes recorded so far.
+	pub fn stats(&self) -> RenderStats {
+		self.stats
+	}
+
+	/// The wrapped render pass, for calls this doesn't wrap.
+	///
+	/// State set through it isn't tracked, so the tracked state is forgotten and the next call to
+	/// each setter is always passed on.
+	pub fn raw(&mut self) -> &mut wgpu::RenderPass<'a> {
+		self.pipeline = None;
+		self.bind_groups.clear();
+		self.vertex_buffers.clear();
+		self.index_buffer = None;
+		&mut self.render_pass
+	}
+
+	pub fn set_pipeline(&mut self, pipeline: &'a wgpu::RenderPipeline) {
+		if self
+			.pipeline
+			.is_some_and(|current| std::ptr::eq(current, pipeline))
+		{
+			return;
+		}
+		self.pipeline = Some(pipeline);
+		self.stats.pipeline_changes += 1;
+		self.render_pass.set_pipeline(pipeline);
+	}
+
+	pub fn set_bind_group(
+		&mut self,
+		index: u32,
+		bind_group: &'a wgpu::BindGroup,
+		offsets: &[wgpu::DynamicOffset],
+	) {
+		let index = index as usize;
+		if self.bind_groups.len() <= index {
+			self.bind_groups.resize(index + 1, None);
+		}
+		if let Some((current, current_offsets)) = &self.bind_groups[index] {
+			if std::ptr::eq(*current, bind_group) && current_offsets[..] == *offsets {
+				return;
+			}
+		}
+		self.bind_groups[index] = Some((bind_group, offsets.to_vec()));
+		self.stats.bind_group_changes += 1;
+		self.render_pass
+			.set_bind_group(index as u32, bind_group, offsets);
+	}
+
+	/// Bind a whole buffer to a vertex buffer slot.
+	pub fn set_vertex_buffer(&mut self, slot: u32, buffer: &'a wgpu::Buffer) {
+		self.set_vertex_buffer_range(slot, buffer, ..);
+	}
+
+	/// Bind the bytes of a buffer in `bounds` to a vertex buffer slot.
+	pub fn set_vertex_buffer_range(
+		&mut self,
+		slot: u32,
+		buffer: &'a wgpu::Buffer,
+		bounds: impl RangeBounds<wgpu::BufferAddress>,
+	) {
+		let range = buffer_range(bounds);
+		let slot = slot as usize;
+		if self.vertex_buffers.len() <= slot {
+			self.vertex_buffers.resize(slot + 1, None);
+		}
+		if let Some((current, current_range)) = self.vertex_buffers[slot] {
+			if std::ptr::eq(current, buffer) && current_range == range {
+				return;
+			}
+		}
+		self.vertex_buffers[slot] = Some((buffer, range));
+		self.stats.buffer_changes += 1;
+		self.render_pass
+			.set_vertex_buffer(slot as u32, buffer_slice(buffer, range));
+	}
+
+	/// Bind a whole buffer as the index buffer.
+	pub fn set_index_buffer(&mut self, buffer: &'a wgpu::Buffer, format: wgpu::IndexFormat) {
+		self.set_index_buffer_range(buffer, .., format);
+	}
+
+	/// Bind the bytes of a buffer in `bounds` as the index buffer.
+	pub fn set_index_buffer_range(
+		&mut self,
+		buffer: &'a wgpu::Buffer,
+		bounds: impl RangeBounds<wgpu::BufferAddress>,
+		format: wgpu::IndexFormat,
+	) {
+		let range = buffer_range(bounds);
+		if let Some((current, current_range, current_format)) = self.index_buffer {
+			if std::ptr::eq(current, buffer) && current_range == range && current_format == format {
+				return;
+			}
+		}
+		self.index_buffer = Some((buffer, range, format));
+		self.stats.buffer_changes += 1;
+		self.render_pass
+			.set_index_buffer(buffer_slice(buffer, range), format);
+	}
+
+	pub fn set_scissor_rect(&mut self, x: u32, y: u32, width: u32, height: u32) {
+		self.render_pass.set_scissor_rect(x, y, width, height);
+	}
+
+	/// Draw into part of the target. Depths from 0 to 1 are mapped to `min_depth` to `max_depth`.
+	pub fn set_viewport(
+		&mut self,
+		x: f32,
+		y: f32,
+		width: f32,
+		height: f32,
+		min_depth: f32,
+		max_depth: f32,
+	) {
+		self.render_pass
+			.set_viewport(x, y, width, height, min_depth, max_depth);
+	}
+
+	pub fn draw(&mut self, vertices: Range<u32>, instances: Range<u32>) {
+		self.stats.draw_calls += 1;
+		self.render_pass.draw(vertices, instances);
+	}
+
+	pub fn draw_indexed(&mut self, indices: Range<u32>, base_vertex: i32, instances: Range<u32>) {
+		self.stats.draw_calls += 1;
+		self.render_pass
+			.draw_indexed(indices, base_vertex, instances);
+	}
 }
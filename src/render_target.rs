@@ -0,0 +1,134 @@
+use crate::{Color, TargetFormat, TextureBuffer};
+use std::rc::Rc;
+
+/// Textures a scene is drawn into: a color texture, its multisampled counterpart when
+/// multisampling, and a depth buffer.
+///
+/// Besides the screen, render targets can be created with
+/// [`Renderer::create_render_target`](crate::Renderer::create_render_target) and drawn into with
+/// [`Renderer::render_to_target`](crate::Renderer::render_to_target). Their color texture can be
+/// added to a scene with [`Texture::from_render_target`](crate::Texture::from_render_target), e.g.
+/// for in-world monitors, mirrors and minimaps.
+pub struct RenderTarget {
+	width: u32,
+	height: u32,
+	target: TargetFormat,
+	/// Shared with the scene textures showing the target
+	color: Rc<TextureBuffer>,
+	/// Drawn into when multisampling, and resolved into `color`
+	msaa: Option<TextureBuffer>,
+	depth: TextureBuffer,
+}
+
+impl RenderTarget {
+	pub fn new(device: &wgpu::Device, width: u32, height: u32, target: TargetFormat) -> Self {
+		let TargetFormat {
+			format,
+			sample_count,
+			..
+		} = target;
+		let mut color = TextureBuffer::with_format(device, width, height, format, "Render Target");
+		// Shown on objects at any size, so filtered rather than nearest
+		color.sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+			label: Some("Render Target sampler"),
+			address_mode_u: wgpu::AddressMode::ClampToEdge,
+			address_mode_v: wgpu::AddressMode::ClampToEdge,
+			address_mode_w: wgpu::AddressMode::ClampToEdge,
+			mag_filter: wgpu::FilterMode::Linear,
+			min_filter: wgpu::FilterMode::Linear,
+			mipmap_filter: wgpu::FilterMode::Nearest,
+			..Default::default()
+		});
+		let msaa = (sample_count > 1).then(|| {
+			TextureBuffer::new_multisampled(
+				device,
+				width,
+				height,
+				format,
+				sample_count,
+				"Multisampled Render Target",
+			)
+		});
+		let depth = TextureBuffer::new_depth_texture(device, width, height, sample_count);
+
+		Self {
+			width,
+			height,
+			target,
+			color: Rc::new(color),
+			msaa,
+			depth,
+		}
+	}
+
+	pub fn width(&self) -> u32 {
+		self.width
+	}
+
+	pub fn height(&self) -> u32 {
+		self.height
+	}
+
+	/// Format and samples per pixel the scene's pipelines are built for.
+	pub fn target(&self) -> TargetFormat {
+		self.target
+	}
+
+	/// The single sampled color texture, holding the finished drawing.
+	pub fn color(&self) -> &TextureBuffer {
+		&self.color
+	}
+
+	pub(crate) fn shared_color(&self) -> Rc<TextureBuffer> {
+		self.color.clone()
+	}
+
+	pub fn depth(&self) -> &TextureBuffer {
+		&self.depth
+	}
+
+	/// Begin a pass drawing into the target, clearing the color to `clear_color` and the depth to
+	/// `clear_depth` first. Either is kept as it is if `None`, to draw over it.
+	pub fn begin_render_pass<'a>(
+		&'a self,
+		encoder: &'a mut wgpu::CommandEncoder,
+		clear_color: Option<Color>,
+		clear_depth: Option<f32>,
+	) -> wgpu::RenderPass<'a> {
+		// When multisampling, draw into the multisampled texture and resolve it into the color
+		let (view, resolve_target) = match &self.msaa {
+			Some(msaa) => (&msaa.view, Some(&self.color.view)),
+			None => (&self.color.view, None),
+		};
+		encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+			label: Some("Render Pass"),
+			color_attachments: &[wgpu::RenderPassColorAttachment {
+				view,
+				resolve_target,
+				ops: wgpu::Operations {
+					load: match clear_color {
+						Some(color) => wgpu::LoadOp::Clear(wgpu::Color {
+							r: color.x as f64,
+							g: color.y as f64,
+							b: color.z as f64,
+							a: color.w as f64,
+						}),
+						None => wgpu::LoadOp::Load,
+					},
+					store: true,
+				},
+			}],
+			depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+				view: &self.depth.view,
+				depth_ops: Some(wgpu::Operations {
+					load: match clear_depth {
+						Some(depth) => wgpu::LoadOp::Clear(depth),
+						None => wgpu::LoadOp::Load,
+					},
+					store: true,
+				}),
+				stencil_ops: None,
+			}),
+		})
+	}
+}
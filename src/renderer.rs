@@ -1,109 +1,719 @@
 use crate::{
-	pipelines::{QuadPipeline, Vertex as QuadVertex},
-	Camera, Pipeline, RenderContext, Scene, TextureBuffer, Window,
+	pipelines::{
+		DepthClearPipeline, DepthCopyPipeline, PostUniform, QuadPipeline, QuadUniform, Uniform,
+		Vertex as QuadVertex, PICK_FORMAT, POST_DEPTH_FORMAT,
+	},
+	Camera, Color, Handle, HandleAllocator, ObjectID, Pipeline, PostEffect, PostEffectID,
+	RenderContext, RenderTarget, Scene, TargetFormat, TextureBuffer, Tonemapping,
+	TrackedRenderPass, Viewport, Window,
 };
+use cgmath::Vector4;
+use futures::executor::block_on;
+use image::RgbaImage;
 use std::{
 	error::Error,
+	mem::size_of,
+	num::NonZeroU32,
 	ops::{Deref, DerefMut},
+	time::Instant,
 };
+use thiserror::Error;
 use wgpu::util::{BufferInitDescriptor, DeviceExt};
 
-pub struct Renderer {
-	surface: Option<wgpu::Surface>,
-	adapter: wgpu::Adapter,
-	instance: wgpu::Instance,
-	size: wgpu::Extent3d,
-	device: wgpu::Device,
-	queue: wgpu::Queue,
-	quad: Quad,
-
-	depth_texture: TextureBuffer,
-	screen_texture: TextureBuffer,
+/// Why a [`Renderer`] couldn't be created as requested.
+#[derive(Error, Debug)]
+pub enum RendererError {
+	#[error("No adapter is available for backends {0:?}")]
+	NoAdapter(wgpu::Backends),
+	#[error("Adapter {0} doesn't support features {1:?}")]
+	MissingFeatures(String, wgpu::Features),
+	#[error("Adapter {0} doesn't support the requested limits: {1}")]
+	UnsupportedLimits(String, String),
+	#[error("Adapter {0} can't render to screen format {1:?}")]
+	UnsupportedFormat(String, wgpu::TextureFormat),
+	#[error("Adapter {0} doesn't support {1}x multisampling")]
+	UnsupportedSampleCount(String, u32),
+	#[error("Window surface isn't supported by adapter {0}")]
+	IncompatibleSurface(String),
+	#[error("Failed to create device: {0}")]
+	RequestDevice(#[from] wgpu::RequestDeviceError),
 }
 
-struct Quad {
-	buffer: wgpu::Buffer,
-	pipeline: QuadPipeline,
-	bind_group: wgpu::BindGroup,
+/// Chooses the adapter, device and targets of a [`Renderer`].
+#[derive(Clone, Debug)]
+pub struct RendererBuilder {
+	width: u32,
+	height: u32,
+	backends: wgpu::Backends,
+	power_preference: wgpu::PowerPreference,
+	force_fallback_adapter: bool,
+	features: wgpu::Features,
+	limits: Option<wgpu::Limits>,
+	present_mode: wgpu::PresentMode,
+	clear_color: Color,
+	screen_format: wgpu::TextureFormat,
+	sample_count: u32,
+	tonemapping: Tonemapping,
+	exposure: f32,
 }
 
-impl Renderer {
-	pub async fn new(width: u32, height: u32) -> Self {
-		let instance = wgpu::Instance::new(wgpu::Backends::all());
+impl RendererBuilder {
+	pub fn new(width: u32, height: u32) -> Self {
+		Self {
+			width,
+			height,
+			backends: wgpu::Backends::all(),
+			power_preference: wgpu::PowerPreference::default(),
+			force_fallback_adapter: false,
+			features: wgpu::Features::empty(),
+			limits: None,
+			present_mode: wgpu::PresentMode::Fifo,
+			clear_color: Color::new(0.05, 0.05, 0.05, 1.0),
+			screen_format: TargetFormat::default().format,
+			sample_count: 1,
+			tonemapping: Tonemapping::None,
+			exposure: 1.0,
+		}
+	}
+
+	/// Graphics APIs to look for an adapter on.
+	pub fn backends(mut self, backends: wgpu::Backends) -> Self {
+		self.backends = backends;
+		self
+	}
+
+	pub fn power_preference(mut self, power_preference: wgpu::PowerPreference) -> Self {
+		self.power_preference = power_preference;
+		self
+	}
+
+	/// Use the fallback (software) adapter, e.g. for rendering on CI without a GPU.
+	pub fn fallback_adapter(mut self, force_fallback_adapter: bool) -> Self {
+		self.force_fallback_adapter = force_fallback_adapter;
+		self
+	}
+
+	/// Features the device must have.
+	pub fn features(mut self, features: wgpu::Features) -> Self {
+		self.features = features;
+		self
+	}
+
+	/// Limits the device must meet. Defaults to wgpu's default limits, or the downlevel ones on
+	/// the fallback adapter.
+	pub fn limits(mut self, limits: wgpu::Limits) -> Self {
+		self.limits = Some(limits);
+		self
+	}
+
+	/// How frames are presented to the window. `Fifo` waits for vsync.
+	pub fn present_mode(mut self, present_mode: wgpu::PresentMode) -> Self {
+		self.present_mode = present_mode;
+		self
+	}
+
+	/// Background color of each frame.
+	pub fn clear_color(mut self, clear_color: Color) -> Self {
+		self.clear_color = clear_color;
+		self
+	}
+
+	/// Format of the texture scenes are drawn into.
+	pub fn screen_format(mut self, screen_format: wgpu::TextureFormat) -> Self {
+		self.screen_format = screen_format;
+		self
+	}
+
+	/// Samples per pixel of the main pass, see [`Renderer::set_sample_count`].
+	pub fn sample_count(mut self, sample_count: u32) -> Self {
+		self.sample_count = sample_count;
+		self
+	}
+
+	/// Draw scenes into a [`TargetFormat::HDR_FORMAT`] screen texture, so bright lights and
+	/// emissive materials aren't clipped, and tonemap it with ACES unless another curve was
+	/// chosen.
+	pub fn hdr(mut self) -> Self {
+		self.screen_format = TargetFormat::HDR_FORMAT;
+		if self.tonemapping == Tonemapping::None {
+			self.tonemapping = Tonemapping::Aces;
+		}
+		self
+	}
+
+	/// How the frame is mapped to the display, see [`Renderer::set_tonemapping`].
+	pub fn tonemapping(mut self, tonemapping: Tonemapping) -> Self {
+		self.tonemapping = tonemapping;
+		self
+	}
+
+	/// Scale of colors before tonemapping.
+	pub fn exposure(mut self, exposure: f32) -> Self {
+		self.exposure = exposure;
+		self
+	}
+
+	pub async fn build(self) -> Result<Renderer, RendererError> {
+		let instance = wgpu::Instance::new(self.backends);
 		let adapter = instance
 			.request_adapter(&wgpu::RequestAdapterOptions {
-				power_preference: wgpu::PowerPreference::default(),
+				power_preference: self.power_preference,
 				compatible_surface: None,
-				force_fallback_adapter: false,
+				force_fallback_adapter: self.force_fallback_adapter,
 			})
 			.await
-			.expect("Failed to request adapter");
+			.ok_or(RendererError::NoAdapter(self.backends))?;
+		let wgpu::AdapterInfo { name, backend, .. } = adapter.get_info();
+
+		// Check what we can up front, as the device's own errors don't say what's missing
+		let features = self.features | self.screen_format.describe().required_features;
+		let missing = features - adapter.features();
+		if !missing.is_empty() {
+			return Err(RendererError::MissingFeatures(name, missing));
+		}
+		// Software adapters often can't meet the default limits
+		let limits = self.limits.unwrap_or_else(|| {
+			if self.force_fallback_adapter {
+				wgpu::Limits::downlevel_defaults()
+			} else {
+				wgpu::Limits::default()
+			}
+		});
+		let unsupported = unsupported_limits(&limits, &adapter.limits());
+		if !unsupported.is_empty() {
+			return Err(RendererError::UnsupportedLimits(
+				name,
+				unsupported.join(", "),
+			));
+		}
+		// The device is created without TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES, so only the
+		// guaranteed usages of the format can be relied on
+		let usages = wgpu::TextureUsages::RENDER_ATTACHMENT
+			| wgpu::TextureUsages::TEXTURE_BINDING
+			| wgpu::TextureUsages::COPY_SRC;
+		if !self
+			.screen_format
+			.describe()
+			.guaranteed_format_features
+			.allowed_usages
+			.contains(usages)
+		{
+			return Err(RendererError::UnsupportedFormat(name, self.screen_format));
+		}
+		if !sample_count_supported(backend, self.sample_count) {
+			return Err(RendererError::UnsupportedSampleCount(
+				name,
+				self.sample_count,
+			));
+		}
+
 		let (device, queue) = adapter
 			.request_device(
 				&wgpu::DeviceDescriptor {
 					label: Some("Device Descriptor"),
-					features: wgpu::Features::empty(),
-					limits: wgpu::Limits::default(),
+					features,
+					limits,
 				},
 				None, // Trace path
 			)
-			.await
-			.expect("Failed to request device");
-
-		let depth_texture = TextureBuffer::new_depth_texture(&device, width, height);
-		let screen_texture = TextureBuffer::new(&device, width, height, "Screen");
-
-		let quad = Quad::new(&device, &screen_texture);
+			.await?;
 
 		let size = wgpu::Extent3d {
-			width,
-			height,
+			width: self.width,
+			height: self.height,
 			depth_or_array_layers: 1,
 		};
+		let screen = RenderTarget::new(
+			&device,
+			self.width,
+			self.height,
+			TargetFormat::new(self.screen_format, self.sample_count),
+		);
+		let depth_clear = DepthClearPipeline::new(&device, self.sample_count);
 
-		Self {
+		Ok(Renderer {
 			surface: None,
-			quad,
+			quad: None,
 			adapter,
 			instance,
 			size,
 			device,
 			queue,
-			depth_texture,
-			screen_texture,
-		}
+			present_mode: self.present_mode,
+			clear_color: self.clear_color,
+			tonemapping: self.tonemapping,
+			exposure: self.exposure,
+			screen,
+			depth_clear,
+			post_effect_ids: HandleAllocator::new(),
+			post_effects: Vec::new(),
+			post_targets: None,
+			post_processed: false,
+			started: Instant::now(),
+		})
+	}
+}
+
+/// Restrict drawing to `viewport`, with the full range of depths.
+fn set_viewport(render_pass: &mut TrackedRenderPass, viewport: Viewport) {
+	let Viewport {
+		x,
+		y,
+		width,
+		height,
+	} = viewport;
+	render_pass.set_viewport(x as _, y as _, width as _, height as _, 0.0, 1.0);
+	render_pass.set_scissor_rect(x, y, width, height);
+}
+
+/// Names of the limits in `requested` that `supported` doesn't meet.
+fn unsupported_limits(requested: &wgpu::Limits, supported: &wgpu::Limits) -> Vec<&'static str> {
+	let mut unsupported = Vec::new();
+	macro_rules! check {
+		($($max:ident),*; $($min:ident),*) => {
+			$(if requested.$max > supported.$max {
+				unsupported.push(stringify!($max));
+			})*
+			$(if requested.$min < supported.$min {
+				unsupported.push(stringify!($min));
+			})*
+		};
+	}
+	check!(
+		max_texture_dimension_1d,
+		max_texture_dimension_2d,
+		max_texture_dimension_3d,
+		max_texture_array_layers,
+		max_bind_groups,
+		max_dynamic_uniform_buffers_per_pipeline_layout,
+		max_dynamic_storage_buffers_per_pipeline_layout,
+		max_sampled_textures_per_shader_stage,
+		max_samplers_per_shader_stage,
+		max_storage_buffers_per_shader_stage,
+		max_storage_textures_per_shader_stage,
+		max_uniform_buffers_per_shader_stage,
+		max_uniform_buffer_binding_size,
+		max_storage_buffer_binding_size,
+		max_vertex_buffers,
+		max_vertex_attributes,
+		max_vertex_buffer_array_stride,
+		max_push_constant_size,
+		max_inter_stage_shader_components,
+		max_compute_workgroup_storage_size,
+		max_compute_invocations_per_workgroup,
+		max_compute_workgroup_size_x,
+		max_compute_workgroup_size_y,
+		max_compute_workgroup_size_z,
+		max_compute_workgroups_per_dimension;
+		min_uniform_buffer_offset_alignment,
+		min_storage_buffer_offset_alignment
+	);
+	unsupported
+}
+
+/// WebGPU guarantees 1 and 4 samples, and wgpu's render passes don't accept any other count
+/// yet, even on adapters that could draw with them.
+///
+/// The GL backend can't create multisampled textures that are also sampled, which the depth
+/// buffer is for post effects, so it only draws with 1 sample.
+fn sample_count_supported(backend: wgpu::Backend, count: u32) -> bool {
+	match backend {
+		wgpu::Backend::Gl => count == 1,
+		_ => matches!(count, 1 | 4),
+	}
+}
+
+pub struct Renderer {
+	surface: Option<wgpu::Surface>,
+	adapter: wgpu::Adapter,
+	instance: wgpu::Instance,
+	size: wgpu::Extent3d,
+	device: wgpu::Device,
+	queue: wgpu::Queue,
+	/// Draws the screen texture to the window, once attached
+	quad: Option<Quad>,
+	present_mode: wgpu::PresentMode,
+	clear_color: Color,
+	tonemapping: Tonemapping,
+	exposure: f32,
+	/// Target of the main pass
+	screen: RenderTarget,
+	/// Clears the depth of a single view, built for the screen's sample count
+	depth_clear: DepthClearPipeline,
+
+	post_effect_ids: HandleAllocator,
+	post_effects: Vec<(PostEffectID, PostEffect)>,
+	/// Created by the first frame with post-processing
+	post_targets: Option<PostTargets>,
+	/// Whether the last frame was post-processed, so it ended up in the post targets
+	post_processed: bool,
+	/// Start of the time given to post effects
+	started: Instant,
+}
+
+/// A frame drawn as several views, from [`Renderer::begin_frame`].
+pub struct Frame<'a> {
+	renderer: &'a mut Renderer,
+}
+
+impl Frame<'_> {
+	/// Draw a scene through a camera into part of the screen, after resizing the camera to the
+	/// viewport.
+	///
+	/// Views are drawn in order, each with its own depth, so later views are drawn over earlier
+	/// ones where they overlap. Viewports reaching outside the screen are cut to fit.
+	pub fn render(
+		&mut self,
+		scene: &mut Scene,
+		camera: &mut dyn Camera,
+		viewport: Viewport,
+	) -> Result<(), Box<dyn Error>> {
+		let size = self.renderer.size;
+		let viewport = match viewport.clamp(size.width, size.height) {
+			Some(viewport) => viewport,
+			None => return Ok(()),
+		};
+		camera.resize(viewport.width as f32, viewport.height as f32);
+		let mut encoder = self
+			.renderer
+			.draw_scene(scene, camera, Some(viewport), false);
+		self.renderer
+			.copy_depth(&mut encoder, Some(viewport), camera.reverse_z());
+		self.renderer
+			.queue
+			.submit(std::iter::once(encoder.finish()));
+
+		Ok(())
+	}
+}
+
+impl Drop for Frame<'_> {
+	fn drop(&mut self) {
+		let mut encoder =
+			self.renderer
+				.device
+				.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+					label: Some("Post Encoder"),
+				});
+		self.renderer.post_process(&mut encoder);
+		self.renderer
+			.queue
+			.submit(std::iter::once(encoder.finish()));
 	}
+}
+
+/// Textures the post-processing passes read and write.
+struct PostTargets {
+	/// Passes alternate between the two, with the last pass always drawing into the first
+	color: [TextureBuffer; 2],
+	/// Copy of the depth buffer
+	depth: TextureBuffer,
+	depth_copy: DepthCopyPipeline,
+	depth_bind_group: wgpu::BindGroup,
+}
+
+/// Draws the finished frame to the display, tonemapping it on the way.
+struct Quad {
+	/// Format of the window's surface
+	format: wgpu::TextureFormat,
+	buffer: wgpu::Buffer,
+	uniform_buffer: wgpu::Buffer,
+	pipeline: QuadPipeline,
+	bind_group: wgpu::BindGroup,
+}
 
-	pub fn attach(&mut self, window: &Window) {
+impl Renderer {
+	/// Create a renderer with the default configuration.
+	///
+	/// Panics if it can't be created, use [`Renderer::builder`] to handle the error instead.
+	pub async fn new(width: u32, height: u32) -> Self {
+		Self::builder(width, height)
+			.build()
+			.await
+			.expect("Failed to create renderer")
+	}
+
+	pub fn builder(width: u32, height: u32) -> RendererBuilder {
+		RendererBuilder::new(width, height)
+	}
+
+	/// Create a renderer on the fallback (software) adapter.
+	///
+	/// Intended for rendering without a GPU, e.g. screenshots and image tests on CI. Returns an
+	/// error if the platform doesn't provide a fallback adapter.
+	pub async fn headless(width: u32, height: u32) -> Result<Self, RendererError> {
+		Self::builder(width, height)
+			.fallback_adapter(true)
+			.build()
+			.await
+	}
+
+	/// Present frames to a window. Fails if the adapter can't draw to the window's surface.
+	pub fn attach(&mut self, window: &Window) -> Result<(), RendererError> {
 		let surface = unsafe { self.instance.create_surface(&window.winit) };
+		let format = surface
+			.get_preferred_format(&self.adapter)
+			.ok_or_else(|| RendererError::IncompatibleSurface(self.adapter.get_info().name))?;
+		self.quad = Some(Quad::new(&self.device, format, self.screen.color()));
 		self.surface = Some(surface);
 		self.resize(self.size.width, self.size.height);
+		Ok(())
 	}
 
 	pub fn resize(&mut self, width: u32, height: u32) {
 		self.size.width = width;
 		self.size.height = height;
+		self.configure_surface();
 
-		if let Some(surface) = self.surface.as_ref() {
-			log::debug!("Resizing renderer surface {}x{}", width, height);
+		log::debug!("Resizing renderer textures {}x{}", width, height);
+		self.screen = RenderTarget::new(&self.device, width, height, self.screen.target());
+		self.reset_post_targets();
+	}
+
+	fn configure_surface(&self) {
+		if let (Some(surface), Some(quad)) = (self.surface.as_ref(), self.quad.as_ref()) {
+			log::debug!(
+				"Configuring renderer surface {}x{}",
+				self.size.width,
+				self.size.height
+			);
 			let config = wgpu::SurfaceConfiguration {
 				usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-				format: surface
-					.get_preferred_format(&self.adapter)
-					.expect("Failed to get preferred surface format"),
+				format: quad.format,
 				width: self.size.width,
 				height: self.size.height,
-				present_mode: wgpu::PresentMode::Fifo,
+				present_mode: self.present_mode,
 			};
 			surface.configure(&self.device, &config);
 		}
+	}
+
+	/// How frames are presented to the window.
+	pub fn present_mode(&self) -> wgpu::PresentMode {
+		self.present_mode
+	}
+
+	/// Switch how frames are presented, e.g. to turn vsync on or off.
+	pub fn set_present_mode(&mut self, present_mode: wgpu::PresentMode) {
+		self.present_mode = present_mode;
+		self.configure_surface();
+	}
+
+	/// Background color of each frame.
+	pub fn clear_color(&self) -> Color {
+		self.clear_color
+	}
+
+	pub fn set_clear_color(&mut self, clear_color: Color) {
+		self.clear_color = clear_color;
+	}
+
+	/// How the frame is mapped to the display.
+	pub fn tonemapping(&self) -> Tonemapping {
+		self.tonemapping
+	}
+
+	/// Choose how the frame is mapped to the display. Tonemapping runs after every post effect,
+	/// when the frame is drawn to the window or captured.
+	pub fn set_tonemapping(&mut self, tonemapping: Tonemapping) {
+		self.tonemapping = tonemapping;
+	}
+
+	/// Scale of colors before tonemapping.
+	pub fn exposure(&self) -> f32 {
+		self.exposure
+	}
+
+	pub fn set_exposure(&mut self, exposure: f32) {
+		self.exposure = exposure;
+	}
+
+	/// Format of the texture scenes are drawn into.
+	pub fn screen_format(&self) -> wgpu::TextureFormat {
+		self.screen.target().format
+	}
+
+	/// Samples per pixel of the main pass.
+	pub fn sample_count(&self) -> u32 {
+		self.screen.target().sample_count
+	}
+
+	/// Whether the renderer can draw with `count` samples per pixel, which is only 1 or 4, and
+	/// only 1 on the GL backend.
+	pub fn supports_sample_count(&self, count: u32) -> bool {
+		sample_count_supported(self.adapter.get_info().backend, count)
+	}
+
+	/// Draw the main pass with `count` samples per pixel and resolve it into the screen texture,
+	/// to smooth jagged edges. A count of 1 turns multisampling off.
+	pub fn set_sample_count(&mut self, count: u32) -> Result<(), RendererError> {
+		if !self.supports_sample_count(count) {
+			let name = self.adapter.get_info().name;
+			return Err(RendererError::UnsupportedSampleCount(name, count));
+		}
+		let target = TargetFormat::new(self.screen_format(), count);
+		self.screen = RenderTarget::new(&self.device, self.size.width, self.size.height, target);
+		self.depth_clear = DepthClearPipeline::new(&self.device, count);
+		self.reset_post_targets();
+		Ok(())
+	}
+
+	/// Create an offscreen target with the screen's format and sample count, for
+	/// [`render_to_target`](Self::render_to_target).
+	///
+	/// Scenes drawn into both the screen and the target reuse the pipelines built for the screen.
+	pub fn create_render_target(&self, width: u32, height: u32) -> RenderTarget {
+		RenderTarget::new(&self.device, width, height, self.screen.target())
+	}
+
+	/// Add a post-processing pass, run after the passes added before it.
+	pub fn add_post_effect(&mut self, effect: PostEffect) -> PostEffectID {
+		let id = self.post_effect_ids.allocate();
+		self.post_effects.push((id, effect));
+		id
+	}
+
+	pub fn remove_post_effect(&mut self, id: PostEffectID) -> Option<PostEffect> {
+		let index = self
+			.post_effects
+			.iter()
+			.position(|(effect_id, _)| *effect_id == id)?;
+		self.post_effect_ids.free(id);
+		Some(self.post_effects.remove(index).1)
+	}
+
+	pub fn post_effect(&self, id: PostEffectID) -> Option<&PostEffect> {
+		self.post_effects
+			.iter()
+			.find(|(effect_id, _)| *effect_id == id)
+			.map(|(_, effect)| effect)
+	}
+
+	pub fn post_effect_mut(&mut self, id: PostEffectID) -> Option<&mut PostEffect> {
+		self.post_effects
+			.iter_mut()
+			.find(|(effect_id, _)| *effect_id == id)
+			.map(|(_, effect)| effect)
+	}
+
+	/// Every post effect, in the order they run.
+	pub fn post_effects(&self) -> impl Iterator<Item = (PostEffectID, &PostEffect)> {
+		self.post_effects.iter().map(|(id, effect)| (*id, effect))
+	}
+
+	/// Drop the post targets, which depend on the size and sample count, and show the screen
+	/// texture until a post-processed frame is drawn.
+	fn reset_post_targets(&mut self) {
+		self.post_targets = None;
+		self.post_processed = false;
+		if let Some(quad) = self.quad.as_mut() {
+			quad.set_texture(&self.device, self.screen.color());
+		}
+	}
+
+	/// The texture holding the finished frame.
+	fn output_texture(&self) -> &TextureBuffer {
+		match (&self.post_targets, self.post_processed) {
+			(Some(targets), true) => &targets.color[0],
+			_ => self.screen.color(),
+		}
+	}
+
+	/// The post targets, created the first time they're needed, or `None` if no post effects are
+	/// enabled.
+	fn post_targets(&mut self) -> Option<&PostTargets> {
+		if !self.post_effects.iter().any(|(_, effect)| effect.enabled) {
+			return None;
+		}
+		Some(
+			self.post_targets
+				.get_or_insert_with(|| PostTargets::new(&self.device, self.size, &self.screen)),
+		)
+	}
+
+	/// Copy the depth buffer, or the part of it inside `viewport`, for the post effects to read.
+	/// Depth is flipped for reverse-Z cameras, so post effects always see 0 at the near plane and
+	/// 1 at the far plane.
+	fn copy_depth(
+		&mut self,
+		encoder: &mut wgpu::CommandEncoder,
+		viewport: Option<Viewport>,
+		reverse_z: bool,
+	) {
+		let targets = match self.post_targets() {
+			Some(targets) => targets,
+			None => return,
+		};
+		let render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+			label: Some("Depth Copy Pass"),
+			color_attachments: &[wgpu::RenderPassColorAttachment {
+				view: &targets.depth.view,
+				resolve_target: None,
+				ops: wgpu::Operations {
+					load: match viewport {
+						Some(_) => wgpu::LoadOp::Load,
+						None => wgpu::LoadOp::Clear(wgpu::Color::WHITE),
+					},
+					store: true,
+				},
+			}],
+			depth_stencil_attachment: None,
+		});
+		let mut render_pass = TrackedRenderPass::new(render_pass);
+		if let Some(viewport) = viewport {
+			set_viewport(&mut render_pass, viewport);
+		}
+		targets
+			.depth_copy
+			.apply_reverse_z(&mut render_pass, reverse_z);
+		render_pass.set_bind_group(0, &targets.depth_bind_group, &[]);
+		render_pass.draw(0..3, 0..1);
+	}
 
-		log::debug!("Resizing renderer texture {}x{}", width, height);
-		self.screen_texture = TextureBuffer::new(&self.device, width, height, "Screen");
-		self.quad.set_texture(&self.device, &self.screen_texture);
+	/// Run the enabled post effects over the screen texture.
+	fn post_process(&mut self, encoder: &mut wgpu::CommandEncoder) {
+		let count = self.post_effects.iter().filter(|(_, e)| e.enabled).count();
+		let post_processed = count > 0;
+		if post_processed {
+			let targets = self
+				.post_targets
+				.get_or_insert_with(|| PostTargets::new(&self.device, self.size, &self.screen));
 
-		log::debug!("Resizing depth texture");
-		self.depth_texture = TextureBuffer::new_depth_texture(&self.device, width, height);
+			let uniform = PostUniform {
+				resolution: [self.size.width as f32, self.size.height as f32],
+				time: self.started.elapsed().as_secs_f32(),
+				_padding: 0,
+				params: Vector4::new(0.0, 0.0, 0.0, 0.0),
+			};
+			let effects = self.post_effects.iter_mut().map(|(_, e)| e);
+			for (i, effect) in effects.filter(|e| e.enabled).enumerate() {
+				let input = match i {
+					0 => self.screen.color(),
+					_ => &targets.color[(count - i) % 2],
+				};
+				effect.draw(
+					&self.device,
+					&mut self.queue,
+					encoder,
+					self.screen.target().format,
+					&input.view,
+					&targets.depth.view,
+					&targets.color[(count - 1 - i) % 2].view,
+					uniform,
+				);
+			}
+		}
+
+		if post_processed != self.post_processed {
+			self.post_processed = post_processed;
+			let output = match (&self.post_targets, post_processed) {
+				(Some(targets), true) => &targets.color[0],
+				_ => self.screen.color(),
+			};
+			if let Some(quad) = self.quad.as_mut() {
+				quad.set_texture(&self.device, output);
+			}
+		}
 	}
 
 	pub fn render<SR, CR, C>(&mut self, mut scene: SR, camera: CR) -> Result<(), Box<dyn Error>>
@@ -112,7 +722,7 @@ impl Renderer {
 		CR: Deref<Target = C>,
 		C: Camera,
 	{
-		self.render_to_buffer(&mut *scene, &*camera)?;
+		self.render_to_buffer(&mut scene, &*camera)?;
 		self.render_to_surface()?;
 
 		Ok(())
@@ -123,46 +733,132 @@ impl Renderer {
 		scene: &mut Scene,
 		camera: &dyn Camera,
 	) -> Result<(), Box<dyn Error>> {
+		let mut encoder = self.draw_scene(scene, camera, None, true);
+		self.copy_depth(&mut encoder, None, camera.reverse_z());
+		self.post_process(&mut encoder);
+		self.queue.submit(std::iter::once(encoder.finish()));
+
+		Ok(())
+	}
+
+	/// Start a frame made of several views, e.g. split-screen or the panes of an editor.
+	///
+	/// Clears the screen texture, which each [`Frame::render`] then draws a viewport of. The
+	/// post effects run when the frame is dropped, after which it can be shown with
+	/// [`render_to_surface`](Self::render_to_surface).
+	pub fn begin_frame(&mut self) -> Frame<'_> {
 		let mut encoder = self
 			.device
 			.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-				label: Some("Render Encoder"),
+				label: Some("Clear Encoder"),
 			});
-		{
-			let render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-				label: Some("Render Pass"),
+		self.screen
+			.begin_render_pass(&mut encoder, Some(self.clear_color), None);
+		// Parts of the screen outside every view are at the far plane for post effects
+		if let Some(targets) = self.post_targets() {
+			encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+				label: Some("Post Depth Clear Pass"),
 				color_attachments: &[wgpu::RenderPassColorAttachment {
-					view: &self.screen_texture.view,
+					view: &targets.depth.view,
 					resolve_target: None,
 					ops: wgpu::Operations {
-						load: wgpu::LoadOp::Clear(wgpu::Color {
-							r: 0.05,
-							g: 0.05,
-							b: 0.05,
-							a: 1.0,
-						}),
+						load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
 						store: true,
 					},
 				}],
+				depth_stencil_attachment: None,
+			});
+		}
+		self.queue.submit(std::iter::once(encoder.finish()));
+
+		Frame { renderer: self }
+	}
+
+	/// Draw a scene into the screen texture, or the part of it inside `viewport`, clearing the
+	/// color first if `clear_color` is set. The depth is always cleared, but only inside the
+	/// viewport. Returns the encoder so more passes can be added before it's submitted.
+	fn draw_scene(
+		&mut self,
+		scene: &mut Scene,
+		camera: &dyn Camera,
+		viewport: Option<Viewport>,
+		clear_color: bool,
+	) -> wgpu::CommandEncoder {
+		let mut encoder = self
+			.device
+			.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+				label: Some("Render Encoder"),
+			});
+		scene.process_texture_queue(&self.device, &mut self.queue);
+		scene.render_lighting(&self.device, &mut self.queue, &mut encoder, camera);
+		let target = self.screen.target().with_reverse_z(camera.reverse_z());
+		// A pass's clear covers the whole depth buffer, so views are cleared by drawing over them
+		if let Some(viewport) = viewport {
+			let render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+				label: Some("Depth Clear Pass"),
+				color_attachments: &[],
 				depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-					view: &self.depth_texture.view,
+					view: &self.screen.depth().view,
 					depth_ops: Some(wgpu::Operations {
-						load: wgpu::LoadOp::Clear(1.0),
+						load: wgpu::LoadOp::Load,
 						store: true,
 					}),
 					stencil_ops: None,
 				}),
 			});
-
-			scene.process_texture_queue(&self.device, &mut self.queue);
+			let mut render_pass = TrackedRenderPass::new(render_pass);
+			set_viewport(&mut render_pass, viewport);
+			self.depth_clear
+				.apply_reverse_z(&mut render_pass, target.reverse_z);
+			render_pass.draw(0..3, 0..1);
+		}
+		{
+			let render_pass = self.screen.begin_render_pass(
+				&mut encoder,
+				clear_color.then_some(self.clear_color),
+				viewport.is_none().then_some(target.clear_depth()),
+			);
 
 			// Draw everything
-			let mut ctx = RenderContext {
-				device: &self.device,
-				queue: &mut self.queue,
-				render_pass,
-				camera,
-			};
+			let mut ctx =
+				RenderContext::new(&self.device, &mut self.queue, render_pass, camera, target);
+			if let Some(viewport) = viewport {
+				set_viewport(&mut ctx.render_pass, viewport);
+			}
+			scene.render(&mut ctx);
+		}
+		encoder
+	}
+
+	/// Draw a scene into an offscreen target, cleared to the clear color first.
+	///
+	/// The target's color texture can then be shown in scenes with
+	/// [`Texture::from_render_target`](crate::Texture::from_render_target). Objects showing the
+	/// target itself are skipped, so a scene can contain a monitor showing its own view. Post
+	/// effects and tonemapping only apply to the screen.
+	pub fn render_to_target(
+		&mut self,
+		scene: &mut Scene,
+		camera: &dyn Camera,
+		target: &RenderTarget,
+	) -> Result<(), Box<dyn Error>> {
+		let mut encoder = self
+			.device
+			.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+				label: Some("Render Target Encoder"),
+			});
+		scene.process_texture_queue(&self.device, &mut self.queue);
+		scene.render_lighting(&self.device, &mut self.queue, &mut encoder, camera);
+		{
+			let format = target.target().with_reverse_z(camera.reverse_z());
+			let render_pass = target.begin_render_pass(
+				&mut encoder,
+				Some(self.clear_color),
+				Some(format.clear_depth()),
+			);
+			let mut ctx =
+				RenderContext::new(&self.device, &mut self.queue, render_pass, camera, format);
+			ctx.render_target = Some(target.color());
 			scene.render(&mut ctx);
 		}
 		self.queue.submit(std::iter::once(encoder.finish()));
@@ -171,7 +867,7 @@ impl Renderer {
 	}
 
 	pub fn render_to_surface(&mut self) -> Result<(), Box<dyn Error>> {
-		if let Some(surface) = &self.surface {
+		if let (Some(surface), Some(quad)) = (&self.surface, &self.quad) {
 			let frame = surface.get_current_texture()?;
 			let view = frame
 				.texture
@@ -182,28 +878,13 @@ impl Renderer {
 				.create_command_encoder(&wgpu::CommandEncoderDescriptor {
 					label: Some("Quad Render Encoder"),
 				});
-			{
-				let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-					label: Some("Quad Render Pass"),
-					color_attachments: &[wgpu::RenderPassColorAttachment {
-						view: &view,
-						resolve_target: None,
-						ops: wgpu::Operations {
-							load: wgpu::LoadOp::Clear(wgpu::Color {
-								r: 0.1,
-								g: 0.1,
-								b: 0.3,
-								a: 1.0,
-							}),
-							store: true,
-						},
-					}],
-					depth_stencil_attachment: None,
-				});
-
-				// Draw the quad
-				self.quad.render(&mut render_pass);
-			}
+			quad.draw(
+				&self.queue,
+				&mut encoder,
+				&view,
+				self.tonemapping,
+				self.exposure,
+			);
 
 			// submit will accept anything that implements IntoIter
 			self.queue.submit(std::iter::once(encoder.finish()));
@@ -212,49 +893,307 @@ impl Renderer {
 
 		Ok(())
 	}
+
+	/// Find the object drawn at a pixel, with `(0, 0)` at the top left of the screen.
+	///
+	/// Draws the ID of each object into an offscreen buffer and reads back the pixel, so only
+	/// objects drawn with the built in materials can be picked. [`Scene::raycast`] finds objects
+	/// on the CPU instead.
+	pub fn pick(
+		&mut self,
+		scene: &mut Scene,
+		camera: &dyn Camera,
+		x: f32,
+		y: f32,
+	) -> Result<Option<ObjectID>, Box<dyn Error>> {
+		if x < 0.0 || y < 0.0 || x >= self.size.width as f32 || y >= self.size.height as f32 {
+			return Ok(None);
+		}
+		let (x, y) = (x as u32, y as u32);
+
+		let id_texture = self.device.create_texture(&wgpu::TextureDescriptor {
+			label: Some("Pick Texture"),
+			size: self.size,
+			mip_level_count: 1,
+			sample_count: 1,
+			dimension: wgpu::TextureDimension::D2,
+			format: PICK_FORMAT,
+			usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+		});
+		let id_view = id_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+		// Kept apart from the screen's depth buffer, which post effects may still read, and which
+		// is multisampled when the ID buffer isn't
+		let depth_texture =
+			TextureBuffer::new_depth_texture(&self.device, self.size.width, self.size.height, 1);
+		let target = TargetFormat::new(PICK_FORMAT, 1).with_reverse_z(camera.reverse_z());
+
+		// A single row still has to be padded to the copy alignment
+		let output_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("Pick Buffer"),
+			size: wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as wgpu::BufferAddress,
+			usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+			mapped_at_creation: false,
+		});
+
+		let mut encoder = self
+			.device
+			.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+				label: Some("Pick Encoder"),
+			});
+		{
+			let render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+				label: Some("Pick Pass"),
+				color_attachments: &[wgpu::RenderPassColorAttachment {
+					view: &id_view,
+					resolve_target: None,
+					ops: wgpu::Operations {
+						load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+						store: true,
+					},
+				}],
+				depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+					view: &depth_texture.view,
+					depth_ops: Some(wgpu::Operations {
+						load: wgpu::LoadOp::Clear(target.clear_depth()),
+						store: true,
+					}),
+					stencil_ops: None,
+				}),
+			});
+
+			// Only the picked pixel needs drawing
+			let mut ctx =
+				RenderContext::new(&self.device, &mut self.queue, render_pass, camera, target);
+			ctx.render_pass.set_scissor_rect(x, y, 1, 1);
+			scene.render_picking(&mut ctx);
+		}
+		encoder.copy_texture_to_buffer(
+			wgpu::ImageCopyTexture {
+				texture: &id_texture,
+				mip_level: 0,
+				origin: wgpu::Origin3d { x, y, z: 0 },
+				aspect: wgpu::TextureAspect::All,
+			},
+			wgpu::ImageCopyBuffer {
+				buffer: &output_buffer,
+				layout: wgpu::ImageDataLayout {
+					offset: 0,
+					bytes_per_row: NonZeroU32::new(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT),
+					rows_per_image: None,
+				},
+			},
+			wgpu::Extent3d {
+				width: 1,
+				height: 1,
+				depth_or_array_layers: 1,
+			},
+		);
+		self.queue.submit(std::iter::once(encoder.finish()));
+
+		let buffer_slice = output_buffer.slice(..);
+		let mapping = buffer_slice.map_async(wgpu::MapMode::Read);
+		self.device.poll(wgpu::Maintain::Wait);
+		block_on(mapping)?;
+		let [index, generation]: [u32; 2] = {
+			let data = buffer_slice.get_mapped_range();
+			bytemuck::pod_read_unaligned(&data[..8])
+		};
+		output_buffer.unmap();
+
+		// Indices are offset by one, so zero means nothing was drawn there
+		if index == 0 {
+			return Ok(None);
+		}
+		let id = Handle::new(index - 1, generation);
+		Ok(scene.contains(id).then_some(id))
+	}
+
+	/// Read the finished frame back from the GPU, after any post effects.
+	///
+	/// Works without an attached [`Window`], so it can be used with a headless renderer.
+	pub fn capture_frame(&self) -> Result<RgbaImage, Box<dyn Error>> {
+		let width = self.size.width;
+		let height = self.size.height;
+
+		// HDR and tonemapped frames are captured as they'd be displayed
+		let displayed = match self.screen_format() {
+			wgpu::TextureFormat::Rgba8Unorm
+			| wgpu::TextureFormat::Rgba8UnormSrgb
+			| wgpu::TextureFormat::Bgra8Unorm
+			| wgpu::TextureFormat::Bgra8UnormSrgb => {
+				self.tonemapping != Tonemapping::None || self.exposure != 1.0
+			}
+			_ => true,
+		};
+		let displayed_texture = displayed.then(|| self.draw_displayed_frame());
+		let (texture, bgra) = match &displayed_texture {
+			Some(texture) => (texture, false),
+			// The GL backend stores Bgra8UnormSrgb as RGBA, and copies it out in that order
+			None => (
+				self.output_texture(),
+				match self.screen_format() {
+					wgpu::TextureFormat::Bgra8Unorm => true,
+					wgpu::TextureFormat::Bgra8UnormSrgb => {
+						self.adapter.get_info().backend != wgpu::Backend::Gl
+					}
+					_ => false,
+				},
+			),
+		};
+
+		// Rows copied out of a texture must be padded to a multiple of 256 bytes
+		let unpadded_bytes_per_row = 4 * width;
+		let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+		let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(align) * align;
+
+		let output_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("Capture Buffer"),
+			size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+			usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+			mapped_at_creation: false,
+		});
+
+		let mut encoder = self
+			.device
+			.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+				label: Some("Capture Encoder"),
+			});
+		encoder.copy_texture_to_buffer(
+			wgpu::ImageCopyTexture {
+				texture: &texture.texture,
+				mip_level: 0,
+				origin: wgpu::Origin3d::ZERO,
+				aspect: wgpu::TextureAspect::All,
+			},
+			wgpu::ImageCopyBuffer {
+				buffer: &output_buffer,
+				layout: wgpu::ImageDataLayout {
+					offset: 0,
+					bytes_per_row: NonZeroU32::new(padded_bytes_per_row),
+					rows_per_image: NonZeroU32::new(height),
+				},
+			},
+			self.size,
+		);
+		self.queue.submit(std::iter::once(encoder.finish()));
+
+		let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * height) as usize);
+		{
+			let buffer_slice = output_buffer.slice(..);
+
+			// NOTE: We have to create the mapping THEN device.poll() before await
+			// the future. Otherwise the application will freeze.
+			let mapping = buffer_slice.map_async(wgpu::MapMode::Read);
+			self.device.poll(wgpu::Maintain::Wait);
+			block_on(mapping)?;
+
+			let data = buffer_slice.get_mapped_range();
+			for row in data.chunks(padded_bytes_per_row as usize) {
+				pixels.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+			}
+		}
+		output_buffer.unmap();
+
+		if bgra {
+			for pixel in pixels.chunks_exact_mut(4) {
+				pixel.swap(0, 2);
+			}
+		}
+
+		let image = RgbaImage::from_raw(width, height, pixels)
+			.ok_or("Captured frame doesn't match the screen size")?;
+
+		Ok(image)
+	}
+
+	/// Draw the finished frame into an 8-bit sRGB texture, as it would be drawn to the window.
+	fn draw_displayed_frame(&self) -> TextureBuffer {
+		let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+		let (width, height) = (self.size.width, self.size.height);
+		let texture = TextureBuffer::with_format(&self.device, width, height, format, "Capture");
+		let quad = Quad::new(&self.device, format, self.output_texture());
+
+		let mut encoder = self
+			.device
+			.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+				label: Some("Capture Quad Encoder"),
+			});
+		quad.draw(
+			&self.queue,
+			&mut encoder,
+			&texture.view,
+			self.tonemapping,
+			self.exposure,
+		);
+		self.queue.submit(std::iter::once(encoder.finish()));
+		texture
+	}
+}
+
+impl PostTargets {
+	fn new(device: &wgpu::Device, size: wgpu::Extent3d, screen: &RenderTarget) -> Self {
+		let target = screen.target();
+		let (width, height) = (size.width, size.height);
+		let color = [
+			TextureBuffer::with_format(device, width, height, target.format, "Post 0"),
+			TextureBuffer::with_format(device, width, height, target.format, "Post 1"),
+		];
+		let depth =
+			TextureBuffer::with_format(device, width, height, POST_DEPTH_FORMAT, "Post Depth");
+		let depth_copy = DepthCopyPipeline::new(device, target.sample_count > 1);
+		let depth_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+			label: Some("DepthCopyPipeline Bind Group"),
+			layout: depth_copy.bind_group_layout(),
+			entries: &[wgpu::BindGroupEntry {
+				binding: 0,
+				resource: wgpu::BindingResource::TextureView(&screen.depth().view),
+			}],
+		});
+
+		Self {
+			color,
+			depth,
+			depth_copy,
+			depth_bind_group,
+		}
+	}
 }
 
 impl Quad {
-	fn new(device: &wgpu::Device, texture: &TextureBuffer) -> Self {
-		let pipeline = QuadPipeline::new(device);
+	fn new(device: &wgpu::Device, format: wgpu::TextureFormat, texture: &TextureBuffer) -> Self {
+		let pipeline = QuadPipeline::new(device, format);
 		let buffer = device.create_buffer_init(&BufferInitDescriptor {
 			label: Some("Quad Vertex Buffer"),
 			usage: wgpu::BufferUsages::VERTEX,
 			contents: bytemuck::cast_slice(&QUAD_VERTICES),
 		});
-		let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-			label: Some("QuadPipeline Bind Group"),
-			layout: pipeline.bind_group_layout(),
-			entries: &[
-				wgpu::BindGroupEntry {
-					binding: 0,
-					resource: wgpu::BindingResource::TextureView(&texture.view),
-				},
-				wgpu::BindGroupEntry {
-					binding: 1,
-					resource: wgpu::BindingResource::Sampler(&texture.sampler),
-				},
-			],
+		let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("Quad Uniform Buffer"),
+			size: size_of::<QuadUniform>() as wgpu::BufferAddress,
+			usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+			mapped_at_creation: false,
 		});
+		let bind_group = Self::create_bind_group(device, &pipeline, &uniform_buffer, texture);
 
 		Self {
+			format,
 			pipeline,
 			buffer,
+			uniform_buffer,
 			bind_group,
 		}
 	}
 
-	fn render<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
-		self.pipeline.apply(render_pass);
-		render_pass.set_bind_group(0, &self.bind_group, &[]);
-		render_pass.set_vertex_buffer(0, self.buffer.slice(..));
-		render_pass.draw(0..QUAD_VERTICES.len() as _, 0..1);
-	}
-
-	fn set_texture(&mut self, device: &wgpu::Device, texture: &TextureBuffer) {
-		self.bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+	fn create_bind_group(
+		device: &wgpu::Device,
+		pipeline: &QuadPipeline,
+		uniform_buffer: &wgpu::Buffer,
+		texture: &TextureBuffer,
+	) -> wgpu::BindGroup {
+		device.create_bind_group(&wgpu::BindGroupDescriptor {
 			label: Some("QuadPipeline Bind Group"),
-			layout: self.pipeline.bind_group_layout(),
+			layout: pipeline.bind_group_layout(),
 			entries: &[
 				wgpu::BindGroupEntry {
 					binding: 0,
@@ -264,12 +1203,62 @@ impl Quad {
 					binding: 1,
 					resource: wgpu::BindingResource::Sampler(&texture.sampler),
 				},
+				// Quad
+				wgpu::BindGroupEntry {
+					binding: 2,
+					resource: uniform_buffer.as_entire_binding(),
+				},
 			],
+		})
+	}
+
+	/// Draw the texture over the whole of `view`.
+	fn draw(
+		&self,
+		queue: &wgpu::Queue,
+		encoder: &mut wgpu::CommandEncoder,
+		view: &wgpu::TextureView,
+		tonemapping: Tonemapping,
+		exposure: f32,
+	) {
+		let uniform = QuadUniform {
+			exposure,
+			tonemapping: tonemapping as u32,
+			_padding: [0; 2],
+		};
+		queue.write_buffer(&self.uniform_buffer, 0, uniform.as_bytes());
+
+		let render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+			label: Some("Quad Render Pass"),
+			color_attachments: &[wgpu::RenderPassColorAttachment {
+				view,
+				resolve_target: None,
+				ops: wgpu::Operations {
+					load: wgpu::LoadOp::Clear(wgpu::Color {
+						r: 0.1,
+						g: 0.1,
+						b: 0.3,
+						a: 1.0,
+					}),
+					store: true,
+				},
+			}],
+			depth_stencil_attachment: None,
 		});
+		let mut render_pass = TrackedRenderPass::new(render_pass);
+		self.pipeline.apply(&mut render_pass);
+		render_pass.set_bind_group(0, &self.bind_group, &[]);
+		render_pass.set_vertex_buffer(0, &self.buffer);
+		render_pass.draw(0..QUAD_VERTICES.len() as _, 0..1);
+	}
+
+	fn set_texture(&mut self, device: &wgpu::Device, texture: &TextureBuffer) {
+		self.bind_group =
+			Self::create_bind_group(device, &self.pipeline, &self.uniform_buffer, texture);
 	}
 }
 
-#[cfg_attr(rustfmt, rustfmt_skip)]
+#[rustfmt::skip]
 const QUAD_VERTICES: [QuadVertex; 6] = [
 	QuadVertex::new( 1.0,  1.0, 0.5),
 	QuadVertex::new(-1.0,  1.0, 0.5),
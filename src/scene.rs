@@ -1,56 +1,286 @@
 use crate::{
 	pipelines::{
-		ActorUniform, CameraUniform, LinePipeline, PrimitivePipeline, SimplePipeline,
-		ACTOR_BINDING, CAMERA_BINDING, SAMPLER_BINDING, TEXTURE_BINDING, TEXTURE_ENABLED_BINDING,
+		ActorUniform, CameraUniform, LightResources, LightsUniform, LinePipeline, MaterialUniform,
+		PbrPipeline, PickPipeline, PickUniform, PrimitivePipeline, ShadowPipeline, ACTOR_BINDING,
+		CAMERA_BINDING, LIGHTS_BINDING, MAX_SHADOW_MAPS, PBR_ACTOR_BINDING, PBR_CAMERA_BINDING,
+		PBR_MATERIAL_BINDING, PBR_SAMPLER_BINDING, PBR_TEXTURE_BINDINGS, PICK_ACTOR_BINDING,
+		PICK_CAMERA_BINDING, SAMPLER_BINDING, SHADOW_ACTOR_BINDING, SHADOW_LIGHT_BINDING,
+		SHADOW_MAP_BINDING, SHADOW_MAP_SIZE, SHADOW_SAMPLER_BINDING, TEXTURE_BINDING,
+		TEXTURE_ENABLED_BINDING,
 	},
-	BasicMaterial, Camera, Color, CustomMaterial, LineMaterial, MountContext, Pipeline, Program,
-	RenderContext, SceneObject, Texture, TextureBuffer, TextureMaterial, Vertex,
+	AlphaMode, BasicMaterial, Camera, Color, CustomMaterial, Frustum, Handle, HandleAllocator, Hit,
+	Light, LineMaterial, Material, MountContext, PbrMaterial, Pipeline, Program, Ray,
+	RenderContext, RenderStats, SceneObject, TargetFormat, Texture, TextureBuffer, TextureMaterial,
+};
+use cgmath::{
+	EuclideanSpace, InnerSpace, Matrix4, Point3, SquareMatrix, Transform, Vector3, Vector4,
 };
-use cgmath::Vector4;
 use std::{
 	collections::{HashMap, HashSet},
 	mem::size_of,
-	sync::atomic::{AtomicUsize, Ordering},
+	ptr,
 };
+use thiserror::Error;
+
+/// Number of per-object uniform slots allocated up front
+pub(crate) const INITIAL_SLOTS: u64 = 256;
+
+pub type ObjectID = Handle;
+pub type TextureID = Handle;
+pub type ProgramID = Handle;
+pub type LightID = Handle;
+
+/// The 1x1 texture every scene starts with, used in place of missing textures
+pub const DEFAULT_TEXTURE: TextureID = Handle::FIRST;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum SceneError {
+	#[error("Object {0} is not in the scene")]
+	MissingObject(ObjectID),
+	#[error("Object {0} can't be parented to itself or its descendants")]
+	Cycle(ObjectID),
+}
+
+/// Sorts objects by the state they need, so objects sharing state are drawn one after another.
+///
+/// Fields are compared in order, from the most to the least expensive to change.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct DrawKey {
+	blend: bool,
+	/// Which uniforms the material uses: custom programs, primitive, line or PBR
+	pipeline: u8,
+	program: Option<ProgramID>,
+	instanced: bool,
+	double_sided: bool,
+	textures: [TextureID; 5],
+	geometry: Option<usize>,
+}
+
+impl DrawKey {
+	fn new(object: &dyn SceneObject) -> Self {
+		let material = object.material();
+		let mut key = Self {
+			blend: material.alpha_mode() == AlphaMode::Blend,
+			pipeline: 0,
+			program: None,
+			instanced: object.instanced(),
+			double_sided: false,
+			textures: [DEFAULT_TEXTURE; 5],
+			geometry: object.geometry_key(),
+		};
+		if let Some(material) = material.downcast_ref::<CustomMaterial>() {
+			key.program = Some(material.program_id);
+		} else if material.is::<BasicMaterial>() {
+			key.pipeline = 1;
+		} else if let Some(material) = material.downcast_ref::<TextureMaterial>() {
+			key.pipeline = 1;
+			key.textures[0] = material.texture_id;
+		} else if material.is::<LineMaterial>() {
+			key.pipeline = 2;
+		} else if let Some(material) = material.downcast_ref::<PbrMaterial>() {
+			key.pipeline = 3;
+			key.double_sided = material.double_sided;
+			key.textures = PbrUniforms::texture_key(material);
+		}
+		key
+	}
+}
+
+/// Whether a material's objects are drawn with the built in pipelines, and so share the vertex
+/// layout the shadow and pick pipelines expect.
+fn has_builtin_layout(material: &dyn Material) -> bool {
+	material.is::<BasicMaterial>()
+		|| material.is::<TextureMaterial>()
+		|| material.is::<PbrMaterial>()
+}
+
+/// Objects in the camera's view in the order they're drawn, and how many were culled. Objects
+/// textured with any of `hidden_textures` aren't drawn.
+fn draw_order(
+	objects: &HashMap<ObjectID, Box<dyn SceneObject>>,
+	world_transforms: &HashMap<ObjectID, Matrix4<f32>>,
+	camera: &dyn Camera,
+	hidden_textures: &[TextureID],
+) -> (Vec<ObjectID>, usize) {
+	let frustum = Frustum::from_matrix(camera.projection() * camera.view());
+	let eye = camera
+		.view()
+		.invert()
+		.unwrap_or_else(Matrix4::identity)
+		.transform_point(Point3::origin());
+	let mut culled_objects = 0;
+
+	// Skip objects outside the camera's view, and find how far away the rest are
+	let mut visible = Vec::with_capacity(objects.len());
+	for (id, object) in objects {
+		if !object.draws() {
+			continue;
+		}
+		let bounds = object
+			.bounds()
+			.map(|bounds| bounds.transform(&world_transforms[id]));
+		if let Some(bounds) = &bounds {
+			if !frustum.intersects(bounds) {
+				culled_objects += 1;
+				continue;
+			}
+		}
+
+		let key = DrawKey::new(&**object);
+		if key.textures.iter().any(|id| hidden_textures.contains(id)) {
+			continue;
+		}
+
+		let center = bounds.map_or_else(
+			|| world_transforms[id].transform_point(Point3::origin()),
+			|bounds| bounds.center(),
+		);
+		visible.push((key, (center - eye).magnitude2(), *id));
+	}
+
+	// Draw opaque objects first, grouped so objects sharing a pipeline, textures or geometry are
+	// drawn together. Blended objects come last, from back to front so they blend over
+	// everything behind them.
+	visible.sort_by(|(a_key, a_distance, _), (b_key, b_distance, _)| {
+		match (a_key.blend, b_key.blend) {
+			(false, false) => a_key.cmp(b_key),
+			(true, true) => b_distance.total_cmp(a_distance),
+			(a_blend, b_blend) => a_blend.cmp(&b_blend),
+		}
+	});
+
+	let order = visible.into_iter().map(|(_, _, id)| id).collect();
+	(order, culled_objects)
+}
+
+/// Hands out slots in per-object uniform buffers, reusing the slots of removed objects.
+#[derive(Debug, Default)]
+pub struct SlotAllocator {
+	free: Vec<u64>,
+	len: u64,
+}
 
-const MAX_OBJECTS: u64 = 2048;
+impl SlotAllocator {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Take a free slot, or add one to the end if none are free.
+	pub fn allocate(&mut self) -> u64 {
+		self.free.pop().unwrap_or_else(|| {
+			self.len += 1;
+			self.len - 1
+		})
+	}
+
+	pub fn free(&mut self, slot: u64) {
+		self.free.push(slot);
+	}
+
+	/// Number of slots a buffer needs room for, including free ones.
+	pub fn len(&self) -> u64 {
+		self.len
+	}
 
-pub type ObjectID = usize;
-pub type TextureID = usize;
-pub type ProgramID = usize;
-pub static NEXT_OBJECT_ID: AtomicUsize = AtomicUsize::new(1);
-pub static NEXT_TEXTURE_ID: AtomicUsize = AtomicUsize::new(0);
-pub static NEXT_PROGRAM_ID: AtomicUsize = AtomicUsize::new(0);
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+}
+
+/// Number of slots to allocate for at least `slots` objects. Grows in powers of two so buffers
+/// are rarely rebuilt.
+pub(crate) fn slot_capacity(slots: u64) -> u64 {
+	slots.max(INITIAL_SLOTS).next_power_of_two()
+}
+
+/// Create a uniform buffer with room for `slots` dynamically offset uniforms.
+pub(crate) fn create_slot_buffer(device: &wgpu::Device, label: &str, slots: u64) -> wgpu::Buffer {
+	let uniform_alignment =
+		device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
+	device.create_buffer(&wgpu::BufferDescriptor {
+		label: Some(label),
+		usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+		size: slots * uniform_alignment,
+		mapped_at_creation: false,
+	})
+}
+
+/// Lights a scene that hasn't had any lights added
+fn default_light() -> Light {
+	Light::directional(
+		Vector3::new(50.0, -40.0, 40.0),
+		Vector3::new(1.0, 1.0, 1.0),
+		2.5,
+	)
+}
 
 pub struct Scene {
 	objects: HashMap<ObjectID, Box<dyn SceneObject>>,
+	parents: HashMap<ObjectID, ObjectID>,
+	children: HashMap<ObjectID, Vec<ObjectID>>,
 	textures: HashMap<TextureID, Texture>,
 	programs: HashMap<ProgramID, Box<dyn Program>>,
+	/// Each object's slot in the per-object uniform buffers
+	object_slots: HashMap<ObjectID, u64>,
+	slots: SlotAllocator,
+	object_ids: HandleAllocator,
+	texture_ids: HandleAllocator,
+	program_ids: HandleAllocator,
+	light_ids: HandleAllocator,
+	/// Lights in the order they were added
+	lights: Vec<(LightID, Light)>,
+	ambient_light: Vector3<f32>,
+	shadow_distance: f32,
+	/// Number of objects outside the camera's view in the last frame
+	culled_objects: usize,
+	render_stats: RenderStats,
+	light_uniforms: Option<LightUniforms>,
 	uniforms: Option<SceneUniforms>,
 	debug_uniforms: Option<DebugUniforms>,
+	pbr_uniforms: Option<PbrUniforms>,
+	pick_uniforms: Option<PickUniforms>,
 
 	added_objects: HashSet<ObjectID>,
-	removed_objects: HashSet<ObjectID>,
+	/// Removed objects waiting to be unmounted
+	removed_objects: Vec<Box<dyn SceneObject>>,
 	added_textures: HashSet<TextureID>,
-	removed_textures: HashSet<TextureID>,
 	added_programs: HashSet<ProgramID>,
-	removed_programs: HashSet<ProgramID>,
+}
+
+impl Default for Scene {
+	fn default() -> Self {
+		Self::new()
+	}
 }
 
 impl Scene {
 	pub fn new() -> Self {
 		let mut scene = Self {
 			objects: HashMap::new(),
+			parents: HashMap::new(),
+			children: HashMap::new(),
 			textures: HashMap::new(),
 			programs: HashMap::new(),
+			object_slots: HashMap::new(),
+			slots: SlotAllocator::new(),
+			object_ids: HandleAllocator::new(),
+			texture_ids: HandleAllocator::new(),
+			program_ids: HandleAllocator::new(),
+			light_ids: HandleAllocator::new(),
+			lights: Vec::new(),
+			ambient_light: Vector3::new(0.3, 0.3, 0.3),
+			shadow_distance: 100.0,
+			culled_objects: 0,
+			render_stats: RenderStats::default(),
+			light_uniforms: None,
 			uniforms: None,
 			debug_uniforms: None,
+			pbr_uniforms: None,
+			pick_uniforms: None,
 			added_objects: HashSet::new(),
-			removed_objects: HashSet::new(),
+			removed_objects: Vec::new(),
 			added_textures: HashSet::new(),
-			removed_textures: HashSet::new(),
 			added_programs: HashSet::new(),
-			removed_programs: HashSet::new(),
 		};
 
 		// Add a default texture
@@ -58,25 +288,118 @@ impl Scene {
 			Texture::from_image_bytes(include_bytes!("../assets/pixel.png"))
 				.expect("Failed to load default texture"),
 		);
-		assert!(id == 0);
+		debug_assert_eq!(id, DEFAULT_TEXTURE);
 
 		scene
 	}
 
 	pub fn add_texture(&mut self, texture: Texture) -> TextureID {
-		let id = NEXT_TEXTURE_ID.fetch_add(1, Ordering::Relaxed);
+		let id = self.texture_ids.allocate();
 		self.textures.insert(id, texture);
 		self.added_textures.insert(id);
 		id
 	}
 
+	/// Remove a texture, freeing its ID. Objects still using it are drawn with the default
+	/// texture, or not at all for [`PbrMaterial`]s. The default texture can't be removed.
+	pub fn remove_texture(&mut self, id: TextureID) -> Option<Texture> {
+		if id == DEFAULT_TEXTURE {
+			return None;
+		}
+		let texture = self.textures.remove(&id)?;
+		self.texture_ids.free(id);
+		self.added_textures.remove(&id);
+		if let Some(uniforms) = self.uniforms.as_mut() {
+			uniforms.remove_texture(id);
+		}
+		if let Some(pbr_uniforms) = self.pbr_uniforms.as_mut() {
+			pbr_uniforms.remove_texture(id);
+		}
+		for program in self.programs.values_mut() {
+			program.remove_texture(id);
+		}
+		Some(texture)
+	}
+
 	pub fn add_program(&mut self, program: impl Program + 'static) -> ProgramID {
-		let id = NEXT_PROGRAM_ID.fetch_add(1, Ordering::Relaxed);
+		let id = self.program_ids.allocate();
 		self.programs.insert(id, Box::new(program));
 		self.added_programs.insert(id);
 		id
 	}
 
+	/// Remove a program, freeing its ID. Objects still using it aren't drawn.
+	pub fn remove_program(&mut self, id: ProgramID) -> Option<Box<dyn Program>> {
+		let program = self.programs.remove(&id)?;
+		self.program_ids.free(id);
+		self.added_programs.remove(&id);
+		Some(program)
+	}
+
+	/// Add a light to the scene. Only the first [`MAX_LIGHTS`](crate::pipelines::MAX_LIGHTS)
+	/// lights are used. Until a light is added, the scene is lit by a default directional light.
+	pub fn add_light(&mut self, light: Light) -> LightID {
+		let id = self.light_ids.allocate();
+		self.lights.push((id, light));
+		id
+	}
+
+	pub fn remove_light(&mut self, id: LightID) -> Option<Light> {
+		let index = self
+			.lights
+			.iter()
+			.position(|(light_id, _)| *light_id == id)?;
+		self.light_ids.free(id);
+		Some(self.lights.remove(index).1)
+	}
+
+	pub fn light(&self, id: LightID) -> Option<&Light> {
+		self.lights
+			.iter()
+			.find(|(light_id, _)| *light_id == id)
+			.map(|(_, light)| light)
+	}
+
+	pub fn light_mut(&mut self, id: LightID) -> Option<&mut Light> {
+		self.lights
+			.iter_mut()
+			.find(|(light_id, _)| *light_id == id)
+			.map(|(_, light)| light)
+	}
+
+	/// Every light in the scene, in the order they were added.
+	pub fn lights(&self) -> impl Iterator<Item = (LightID, &Light)> {
+		self.lights.iter().map(|(id, light)| (*id, light))
+	}
+
+	/// Light added to every surface regardless of direction.
+	pub fn ambient_light(&self) -> Vector3<f32> {
+		self.ambient_light
+	}
+
+	pub fn set_ambient_light(&mut self, color: Vector3<f32>) {
+		self.ambient_light = color;
+	}
+
+	/// How far from the camera directional lights cast shadows.
+	pub fn shadow_distance(&self) -> f32 {
+		self.shadow_distance
+	}
+
+	pub fn set_shadow_distance(&mut self, distance: f32) {
+		self.shadow_distance = distance;
+	}
+
+	/// Number of objects skipped in the last frame because they were outside the camera's view.
+	pub fn culled_objects(&self) -> usize {
+		self.culled_objects
+	}
+
+	/// Draw calls and state changes in the last frame's main pass.
+	pub fn render_stats(&self) -> RenderStats {
+		self.render_stats
+	}
+
 	pub fn process_texture_queue(&mut self, device: &wgpu::Device, queue: &mut wgpu::Queue) {
 		if let Some(uniforms) = self.uniforms.as_mut() {
 			// Add flagged objects
@@ -85,37 +408,154 @@ impl Scene {
 					texture.allocate(device, "Some Texture");
 					texture.upload(queue);
 					uniforms.add_texture(id, device, texture.buffer().unwrap());
-					for (_, program) in &mut self.programs {
+					for program in self.programs.values_mut() {
 						program.add_texture(id, device, texture.buffer().unwrap());
 					}
 				}
 			}
+		}
+	}
 
-			// Remove flagged objects
-			for id in self.removed_textures.drain() {
-				self.textures.remove(&id);
+	/// Update the scene's lights and draw the shadow maps of shadow casting lights.
+	///
+	/// Must be called each frame before [`render`](Self::render), outside of its render pass.
+	pub fn render_lighting(
+		&mut self,
+		device: &wgpu::Device,
+		queue: &mut wgpu::Queue,
+		encoder: &mut wgpu::CommandEncoder,
+		camera: &dyn Camera,
+	) {
+		let world_transforms = self.world_transforms();
+		let light_uniforms = self
+			.light_uniforms
+			.get_or_insert_with(|| LightUniforms::new(device));
+
+		// Update lights
+		let lights = if self.lights.is_empty() {
+			LightsUniform::new(
+				self.ambient_light,
+				&[default_light()],
+				camera,
+				self.shadow_distance,
+			)
+		} else {
+			LightsUniform::new(
+				self.ambient_light,
+				self.lights.iter().map(|(_, light)| light),
+				camera,
+				self.shadow_distance,
+			)
+		};
+		light_uniforms.set_lights(device, queue, &lights);
+		light_uniforms.reserve(device, self.slots.len());
+		let light_uniforms: &LightUniforms = light_uniforms;
+
+		let casters: HashSet<ObjectID> = self
+			.objects
+			.iter()
+			.filter(|(_, object)| object.cast_shadows() && has_builtin_layout(object.material()))
+			.map(|(id, _)| *id)
+			.collect();
+		for id in &casters {
+			light_uniforms.set_actor(
+				device,
+				queue,
+				self.object_slots[id],
+				ActorUniform {
+					color: Color::new(0.0, 0.0, 0.0, 1.0),
+					model: world_transforms[id],
+					receive_shadows: 0,
+					alpha_mode: AlphaMode::Opaque as u32,
+					alpha_cutoff: 0.0,
+					_padding: 0,
+				},
+			);
+		}
+
+		// Draw each shadow map from its light's point of view
+		for layer in 0..lights.shadow_maps {
+			let render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+				label: Some("Shadow Pass"),
+				color_attachments: &[],
+				depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+					view: &light_uniforms.layer_views[layer as usize],
+					depth_ops: Some(wgpu::Operations {
+						load: wgpu::LoadOp::Clear(1.0),
+						store: true,
+					}),
+					stencil_ops: None,
+				}),
+			});
+			let mut ctx = RenderContext::new(
+				device,
+				&mut *queue,
+				render_pass,
+				camera,
+				TargetFormat::default(),
+			);
+
+			for (id, object) in &mut self.objects {
+				if casters.contains(id) {
+					light_uniforms.bind_actor(
+						&mut ctx,
+						layer as _,
+						self.object_slots[id],
+						object.instanced(),
+					);
+					object.render(&mut ctx);
+				}
 			}
 		}
 	}
 
+	/// Objects `camera` can see, in the order [`render`](Self::render) draws them: opaque objects
+	/// first, then blended objects from back to front.
+	pub fn draw_order(&self, camera: &dyn Camera) -> Vec<ObjectID> {
+		draw_order(&self.objects, &self.world_transforms(), camera, &[]).0
+	}
+
 	pub fn render<'a>(&'a mut self, ctx: &mut RenderContext<'a>) {
+		let world_transforms = self.world_transforms();
+		let light_uniforms = self
+			.light_uniforms
+			.get_or_insert_with(|| LightUniforms::new(ctx.device));
+		let lights = light_uniforms.resources();
 		let uniforms = self
 			.uniforms
-			.get_or_insert_with(|| SceneUniforms::new(ctx.device, ctx.queue));
+			.get_or_insert_with(|| SceneUniforms::new(ctx.device, ctx.queue, &lights, ctx.target));
 		let debug_uniforms = self
 			.debug_uniforms
-			.get_or_insert_with(|| DebugUniforms::new(ctx.device));
+			.get_or_insert_with(|| DebugUniforms::new(ctx.device, ctx.target));
+		let pbr_uniforms = self
+			.pbr_uniforms
+			.get_or_insert_with(|| PbrUniforms::new(ctx.device, &lights, ctx.target));
+
+		// Pipelines keep a variant for each target they've drawn into, so textures and bind groups
+		// survive switching between targets
+		uniforms.pipeline.prepare(ctx.device, ctx.target);
+		debug_uniforms.pipeline.prepare(ctx.device, ctx.target);
+		pbr_uniforms.pipeline.prepare(ctx.device, ctx.target);
 
 		let mut mount_ctx = MountContext { device: ctx.device };
 
 		for id in self.added_programs.drain() {
 			if let Some(program) = self.programs.get_mut(&id) {
-				program.compile(ctx);
+				program.compile(ctx, &lights);
+				// Textures uploaded before the program was added
+				for (&texture_id, texture) in &self.textures {
+					if let Some(buffer) = texture.buffer() {
+						program.add_texture(texture_id, ctx.device, buffer);
+					}
+				}
 			}
 		}
+		for program in self.programs.values_mut() {
+			program.prepare(ctx.device, ctx.target);
+		}
 
 		// Default image bind group hasn't been created yet.
-		if uniforms.texture_bind_groups.len() == 0 {
+		if uniforms.texture_bind_groups.is_empty() {
 			return;
 		}
 
@@ -127,95 +567,327 @@ impl Scene {
 		}
 
 		// Remove flagged objects
-		for id in self.removed_objects.drain() {
-			if let Some(mut object) = self.objects.remove(&id) {
-				object.unmount(&mut mount_ctx);
-			}
+		for mut object in self.removed_objects.drain(..) {
+			object.unmount(&mut mount_ctx);
+		}
+
+		// Make room for every object's uniforms
+		let slots = self.slots.len();
+		uniforms.reserve(ctx.device, slots, &lights);
+		debug_uniforms.reserve(ctx.device, slots);
+		pbr_uniforms.reserve(ctx.device, slots, &lights);
+		for program in self.programs.values_mut() {
+			program.reserve(ctx.device, slots, &lights);
 		}
 
 		// Update camera position
 		uniforms.set_camera(ctx, ctx.camera);
 		debug_uniforms.set_camera(ctx, ctx.camera);
-		for (_, program) in &mut self.programs {
+		pbr_uniforms.set_camera(ctx, ctx.camera);
+		for program in self.programs.values_mut() {
 			program.set_camera(ctx, ctx.camera);
 		}
 
-		for (id, object) in &mut self.objects {
+		// Create any texture bind groups needed by PBR materials
+		for object in self.objects.values() {
+			if let Some(material) = object.material().downcast_ref::<PbrMaterial>() {
+				pbr_uniforms.add_material_textures(ctx.device, &self.textures, material);
+			}
+		}
+		let pbr_uniforms: &PbrUniforms = pbr_uniforms;
+
+		// A render target can't be sampled while it's drawn into
+		let hidden_textures: Vec<TextureID> = match ctx.render_target {
+			Some(color) => self
+				.textures
+				.iter()
+				.filter(|(_, texture)| texture.buffer().is_some_and(|b| ptr::eq(b, color)))
+				.map(|(id, _)| *id)
+				.collect(),
+			None => Vec::new(),
+		};
+
+		let (order, culled_objects) = draw_order(
+			&self.objects,
+			&world_transforms,
+			ctx.camera,
+			&hidden_textures,
+		);
+		let mut objects: HashMap<_, _> = self.objects.iter_mut().collect();
+
+		for id in &order {
+			let object = objects.remove(id).expect("Missing object");
+			let slot = self.object_slots[id];
 			let material = object.material();
 			if let Some(material) = material.downcast_ref::<CustomMaterial>() {
-				let program = self
-					.programs
-					.get(&material.program_id)
-					.expect("Missing program");
+				// Objects of removed programs aren't drawn
+				let program = match self.programs.get(&material.program_id) {
+					Some(program) => program,
+					None => continue,
+				};
 				// Update object position
 				program.set_actor(
 					ctx,
-					*id as _,
+					slot,
 					ActorUniform {
 						color: Color::new(0.0, 0.0, 0.0, 1.0),
-						model: object.transform(),
+						model: world_transforms[id],
+						receive_shadows: object.receive_shadows() as u32,
+						alpha_mode: AlphaMode::Opaque as u32,
+						alpha_cutoff: 0.0,
+						_padding: 0,
 					},
 				);
 
 				// Render object
-				program.bind_actor(ctx, *id as _);
-				program.bind_texture(ctx, 0);
+				if !program.bind_actor(ctx, slot, object.instanced()) {
+					continue;
+				}
+				program.bind_texture(ctx, DEFAULT_TEXTURE);
 				object.render(ctx);
 			} else if let Some(material) = material.downcast_ref::<BasicMaterial>() {
 				// Update object position
 				uniforms.set_actor(
 					ctx,
-					*id as _,
+					slot,
 					ActorUniform {
 						color: material.color,
-						model: object.transform(),
+						model: world_transforms[id],
+						receive_shadows: object.receive_shadows() as u32,
+						alpha_mode: material.alpha_mode as u32,
+						alpha_cutoff: material.alpha_cutoff,
+						_padding: 0,
 					},
 				);
 
 				// Render object
-				uniforms.bind_actor(ctx, *id as _);
-				uniforms.bind_texture(ctx, 0);
+				uniforms.bind_actor(ctx, slot, material.alpha_mode, object.instanced());
+				uniforms.bind_texture(ctx, DEFAULT_TEXTURE);
 				object.render(ctx);
 			} else if let Some(material) = material.downcast_ref::<TextureMaterial>() {
 				// Update object position
 				uniforms.set_actor(
 					ctx,
-					*id as _,
+					slot,
 					ActorUniform {
 						color: Vector4::new(0.0, 0.0, 0.0, 1.0),
-						model: object.transform(),
+						model: world_transforms[id],
+						receive_shadows: object.receive_shadows() as u32,
+						alpha_mode: material.alpha_mode as u32,
+						alpha_cutoff: material.alpha_cutoff,
+						_padding: 0,
 					},
 				);
 
 				// Render object
-				uniforms.bind_actor(ctx, *id as _);
+				uniforms.bind_actor(ctx, slot, material.alpha_mode, object.instanced());
 				uniforms.bind_texture(ctx, material.texture_id);
 				object.render(ctx);
 			} else if let Some(_material) = material.downcast_ref::<LineMaterial>() {
 				// Update object position
 				debug_uniforms.set_actor(
 					ctx,
-					*id as _,
+					slot,
 					ActorUniform {
 						color: Color::new(1.0, 0.0, 1.0, 1.0),
-						model: object.transform(),
+						model: world_transforms[id],
+						receive_shadows: 0,
+						alpha_mode: AlphaMode::Opaque as u32,
+						alpha_cutoff: 0.0,
+						_padding: 0,
 					},
 				);
 
 				// Render object
-				debug_uniforms.bind_actor(ctx, *id as _);
+				debug_uniforms.bind_actor(ctx, slot);
 				object.render(ctx);
+			} else if let Some(material) = material.downcast_ref::<PbrMaterial>() {
+				// Update object position and material
+				pbr_uniforms.set_actor(
+					ctx,
+					slot,
+					ActorUniform {
+						color: material.base_color,
+						model: world_transforms[id],
+						receive_shadows: object.receive_shadows() as u32,
+						alpha_mode: material.alpha_mode as u32,
+						alpha_cutoff: material.alpha_cutoff,
+						_padding: 0,
+					},
+				);
+				pbr_uniforms.set_material(ctx, slot, material);
+
+				// Render object, once its textures are ready
+				if pbr_uniforms.bind_actor(ctx, slot, material, object.instanced()) {
+					object.render(ctx);
+				}
+			}
+		}
+		self.culled_objects = culled_objects;
+		self.render_stats = ctx.render_pass.stats();
+	}
+
+	/// Draw the ID of every object into a [`PICK_FORMAT`](crate::pipelines::PICK_FORMAT) target, as used by
+	/// [`Renderer::pick`](crate::Renderer::pick).
+	///
+	/// Only objects drawn with the built in materials are drawn. Objects are mounted by
+	/// [`render`](Self::render), so objects added since the last frame are missing.
+	pub fn render_picking<'a>(&'a mut self, ctx: &mut RenderContext<'a>) {
+		let world_transforms = self.world_transforms();
+		let depth_compare = ctx.target.depth_compare();
+		let pick_uniforms = self
+			.pick_uniforms
+			.get_or_insert_with(|| PickUniforms::new(ctx.device, depth_compare));
+		pick_uniforms.pipeline.prepare(ctx.device, depth_compare);
+		pick_uniforms.reserve(ctx.device, self.slots.len());
+		pick_uniforms.set_camera(ctx, ctx.camera);
+		let pick_uniforms: &PickUniforms = pick_uniforms;
+
+		for (id, object) in &mut self.objects {
+			if !object.draws() || !has_builtin_layout(object.material()) {
+				continue;
 			}
+
+			let slot = self.object_slots[id];
+			pick_uniforms.set_actor(
+				ctx,
+				slot,
+				PickUniform {
+					model: world_transforms[id],
+					object: [id.index() + 1, id.generation()],
+					_padding: [0; 2],
+				},
+			);
+			pick_uniforms.bind_actor(ctx, slot, object.instanced());
+			object.render(ctx);
 		}
 	}
 
+	/// Nearest object hit by a ray, tested against the triangles of every mesh.
+	///
+	/// Works on the CPU, without needing a [`Renderer`](crate::Renderer).
+	pub fn raycast(&self, ray: &Ray) -> Option<Hit> {
+		self.objects
+			.iter()
+			.filter_map(|(&id, object)| {
+				let transform = self.world_transform(id)?;
+				let hit = object.raycast(&ray.transform(&transform.invert()?))?;
+				let [a, b, c] = hit.triangle.map(|corner| transform.transform_point(corner));
+				let normal = (b - a).cross(c - a).normalize();
+				Some(Hit {
+					object: id,
+					distance: hit.distance,
+					position: ray.at(hit.distance),
+					normal: if normal.dot(ray.direction) > 0.0 {
+						-normal
+					} else {
+						normal
+					},
+					triangle: hit.index,
+					barycentric: hit.barycentric,
+				})
+			})
+			.min_by(|a, b| a.distance.total_cmp(&b.distance))
+	}
+
 	pub fn add(&mut self, object: impl SceneObject + 'static) -> ObjectID {
-		let id = NEXT_OBJECT_ID.fetch_add(1, Ordering::Relaxed);
+		let id = self.object_ids.allocate();
 		self.objects.insert(id, Box::new(object));
+		self.object_slots.insert(id, self.slots.allocate());
 		self.added_objects.insert(id);
 		id
 	}
 
+	/// Add an object whose transform is relative to `parent`.
+	pub fn add_child(
+		&mut self,
+		parent: ObjectID,
+		object: impl SceneObject + 'static,
+	) -> Result<ObjectID, SceneError> {
+		if !self.contains(parent) {
+			return Err(SceneError::MissingObject(parent));
+		}
+		let id = self.add(object);
+		self.set_parent(id, Some(parent))?;
+		Ok(id)
+	}
+
+	/// Move an object under a new parent, or to the root of the scene. Its transform is kept, so
+	/// it's now relative to the new parent.
+	pub fn set_parent(&mut self, id: ObjectID, parent: Option<ObjectID>) -> Result<(), SceneError> {
+		if !self.contains(id) {
+			return Err(SceneError::MissingObject(id));
+		}
+		if let Some(parent) = parent {
+			if !self.contains(parent) {
+				return Err(SceneError::MissingObject(parent));
+			}
+			if self.ancestors(parent).any(|ancestor| ancestor == id) {
+				return Err(SceneError::Cycle(id));
+			}
+		}
+
+		if let Some(old_parent) = self.parents.remove(&id) {
+			if let Some(siblings) = self.children.get_mut(&old_parent) {
+				siblings.retain(|&child| child != id);
+			}
+		}
+		if let Some(parent) = parent {
+			self.parents.insert(id, parent);
+			self.children.entry(parent).or_default().push(id);
+		}
+		Ok(())
+	}
+
+	/// Move an object to the root of the scene.
+	pub fn detach(&mut self, id: ObjectID) -> Result<(), SceneError> {
+		self.set_parent(id, None)
+	}
+
+	pub fn parent(&self, id: ObjectID) -> Option<ObjectID> {
+		self.parents.get(&id).copied()
+	}
+
+	/// Children of an object, in the order they were added.
+	pub fn children(&self, id: ObjectID) -> &[ObjectID] {
+		self.children.get(&id).map_or(&[], |children| children)
+	}
+
+	/// An object itself, followed by its parent, grandparent and so on.
+	fn ancestors(&self, id: ObjectID) -> impl Iterator<Item = ObjectID> + '_ {
+		std::iter::successors(Some(id), |id| self.parent(*id))
+	}
+
+	/// Transform of an object relative to the scene, combining the transforms of its ancestors.
+	pub fn world_transform(&self, id: ObjectID) -> Option<Matrix4<f32>> {
+		self.get(id)?;
+		Some(
+			self.ancestors(id)
+				.filter_map(|id| self.get(id))
+				.fold(Matrix4::identity(), |transform, object| {
+					object.transform() * transform
+				}),
+		)
+	}
+
+	/// World transform of every object, working down from the roots of the scene.
+	fn world_transforms(&self) -> HashMap<ObjectID, Matrix4<f32>> {
+		let mut transforms = HashMap::with_capacity(self.objects.len());
+		let mut stack: Vec<(ObjectID, Matrix4<f32>)> = self
+			.objects
+			.keys()
+			.filter(|id| !self.parents.contains_key(id))
+			.map(|id| (*id, Matrix4::identity()))
+			.collect();
+		while let Some((id, parent_transform)) = stack.pop() {
+			let transform = parent_transform * self.objects[&id].transform();
+			transforms.insert(id, transform);
+			stack.extend(self.children(id).iter().map(|child| (*child, transform)));
+		}
+		transforms
+	}
+
+	#[allow(clippy::borrowed_box)]
 	pub fn get(&self, id: ObjectID) -> Option<&Box<dyn SceneObject>> {
 		self.objects.get(&id)
 	}
@@ -224,9 +896,32 @@ impl Scene {
 		self.objects.get_mut(&id)
 	}
 
+	/// Whether `id` refers to an object in the scene. IDs of removed objects are never reused.
+	pub fn contains(&self, id: ObjectID) -> bool {
+		self.objects.contains_key(&id)
+	}
+
+	/// Remove an object and all of its descendants from the scene. They're unmounted on the next
+	/// frame, unless they were never mounted.
 	pub fn remove(&mut self, id: ObjectID) {
-		if self.objects.contains_key(&id) {
-			self.removed_objects.insert(id);
+		if let Some(parent) = self.parents.remove(&id) {
+			if let Some(siblings) = self.children.get_mut(&parent) {
+				siblings.retain(|&child| child != id);
+			}
+		}
+		for child in self.children.remove(&id).unwrap_or_default() {
+			self.parents.remove(&child);
+			self.remove(child);
+		}
+
+		if let Some(object) = self.objects.remove(&id) {
+			self.object_ids.free(id);
+			if let Some(slot) = self.object_slots.remove(&id) {
+				self.slots.free(slot);
+			}
+			if !self.added_objects.remove(&id) {
+				self.removed_objects.push(object);
+			}
 		}
 	}
 
@@ -251,17 +946,226 @@ impl Scene {
 	}
 }
 
+pub struct LightUniforms {
+	pipeline: ShadowPipeline,
+	bind_group: wgpu::BindGroup,
+	lights_buffer: wgpu::Buffer,
+	light_buffer: wgpu::Buffer,
+	actor_buffer: wgpu::Buffer,
+	shadow_view: wgpu::TextureView,
+	layer_views: Vec<wgpu::TextureView>,
+	shadow_sampler: wgpu::Sampler,
+	/// Number of objects the actor buffer has room for
+	capacity: u64,
+}
+
+impl LightUniforms {
+	pub fn new(device: &wgpu::Device) -> Self {
+		log::debug!("Building Light Uniforms");
+		let pipeline = ShadowPipeline::new(device);
+
+		let uniform_alignment =
+			device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
+
+		let lights_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("Lights Buffer"),
+			usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+			size: size_of::<LightsUniform>() as wgpu::BufferAddress,
+			mapped_at_creation: false,
+		});
+
+		let light_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("Shadow Light Buffer"),
+			usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+			size: MAX_SHADOW_MAPS as u64 * uniform_alignment,
+			mapped_at_creation: false,
+		});
+
+		let actor_buffer = create_slot_buffer(device, "Shadow Actor Buffer", INITIAL_SLOTS);
+
+		let shadow_texture = device.create_texture(&wgpu::TextureDescriptor {
+			label: Some("Shadow Map Texture"),
+			size: wgpu::Extent3d {
+				width: SHADOW_MAP_SIZE,
+				height: SHADOW_MAP_SIZE,
+				depth_or_array_layers: MAX_SHADOW_MAPS as u32,
+			},
+			mip_level_count: 1,
+			sample_count: 1,
+			dimension: wgpu::TextureDimension::D2,
+			format: TextureBuffer::DEPTH_FORMAT,
+			usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+		});
+		let shadow_view = shadow_texture.create_view(&wgpu::TextureViewDescriptor {
+			label: Some("Shadow Map View"),
+			dimension: Some(wgpu::TextureViewDimension::D2Array),
+			..Default::default()
+		});
+		let layer_views = (0..MAX_SHADOW_MAPS as u32)
+			.map(|layer| {
+				shadow_texture.create_view(&wgpu::TextureViewDescriptor {
+					label: Some("Shadow Map Layer View"),
+					dimension: Some(wgpu::TextureViewDimension::D2),
+					base_array_layer: layer,
+					array_layer_count: std::num::NonZeroU32::new(1),
+					..Default::default()
+				})
+			})
+			.collect();
+		let shadow_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+			label: Some("Shadow Map Sampler"),
+			address_mode_u: wgpu::AddressMode::ClampToEdge,
+			address_mode_v: wgpu::AddressMode::ClampToEdge,
+			address_mode_w: wgpu::AddressMode::ClampToEdge,
+			mag_filter: wgpu::FilterMode::Linear,
+			min_filter: wgpu::FilterMode::Linear,
+			mipmap_filter: wgpu::FilterMode::Nearest,
+			compare: Some(wgpu::CompareFunction::LessEqual),
+			..Default::default()
+		});
+
+		let bind_group = Self::create_bind_group(device, &pipeline, &light_buffer, &actor_buffer);
+
+		Self {
+			pipeline,
+			bind_group,
+			lights_buffer,
+			light_buffer,
+			actor_buffer,
+			shadow_view,
+			layer_views,
+			shadow_sampler,
+			capacity: INITIAL_SLOTS,
+		}
+	}
+
+	fn create_bind_group(
+		device: &wgpu::Device,
+		pipeline: &ShadowPipeline,
+		light_buffer: &wgpu::Buffer,
+		actor_buffer: &wgpu::Buffer,
+	) -> wgpu::BindGroup {
+		let light_size = size_of::<CameraUniform>() as wgpu::BufferAddress;
+		let actor_size = size_of::<ActorUniform>() as wgpu::BufferAddress;
+
+		device.create_bind_group(&wgpu::BindGroupDescriptor {
+			label: Some("ShadowPipeline Bind Group"),
+			layout: pipeline.bind_group_layout(),
+			entries: &[
+				// Light
+				wgpu::BindGroupEntry {
+					binding: SHADOW_LIGHT_BINDING,
+					resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+						buffer: light_buffer,
+						size: wgpu::BufferSize::new(light_size),
+						offset: 0,
+					}),
+				},
+				// Actors
+				wgpu::BindGroupEntry {
+					binding: SHADOW_ACTOR_BINDING,
+					resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+						buffer: actor_buffer,
+						size: wgpu::BufferSize::new(actor_size),
+						offset: 0,
+					}),
+				},
+			],
+		})
+	}
+
+	/// Make room for `slots` objects, rebuilding the actor buffer if it's too small.
+	fn reserve(&mut self, device: &wgpu::Device, slots: u64) {
+		if slots > self.capacity {
+			self.capacity = slot_capacity(slots);
+			self.actor_buffer = create_slot_buffer(device, "Shadow Actor Buffer", self.capacity);
+			self.bind_group = Self::create_bind_group(
+				device,
+				&self.pipeline,
+				&self.light_buffer,
+				&self.actor_buffer,
+			);
+		}
+	}
+
+	/// Resources shared with every lit pipeline
+	fn resources(&self) -> LightResources<'_> {
+		LightResources {
+			buffer: &self.lights_buffer,
+			shadow_map: &self.shadow_view,
+			shadow_sampler: &self.shadow_sampler,
+		}
+	}
+
+	fn set_lights(&self, device: &wgpu::Device, queue: &mut wgpu::Queue, lights: &LightsUniform) {
+		queue.write_buffer(&self.lights_buffer, 0, bytemuck::cast_slice(&[*lights]));
+
+		let uniform_alignment =
+			device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
+		for layer in 0..lights.shadow_maps as usize {
+			let contents = CameraUniform {
+				view: Matrix4::identity(),
+				projection: lights.shadow_matrices[layer],
+			};
+			queue.write_buffer(
+				&self.light_buffer,
+				layer as u64 * uniform_alignment,
+				bytemuck::cast_slice(&[contents]),
+			);
+		}
+	}
+
+	fn set_actor(
+		&self,
+		device: &wgpu::Device,
+		queue: &mut wgpu::Queue,
+		index: u64,
+		contents: ActorUniform,
+	) {
+		let uniform_alignment =
+			device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
+		let offset = (index * uniform_alignment) as wgpu::DynamicOffset;
+		queue.write_buffer(
+			&self.actor_buffer,
+			offset as _,
+			bytemuck::cast_slice(&[contents]),
+		);
+	}
+
+	fn bind_actor<'a>(
+		&'a self,
+		ctx: &mut RenderContext<'a>,
+		layer: u64,
+		index: u64,
+		instanced: bool,
+	) {
+		let render_pass = &mut ctx.render_pass;
+		let uniform_alignment =
+			ctx.device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
+		let light_offset = (layer * uniform_alignment) as wgpu::DynamicOffset;
+		let actor_offset = (index * uniform_alignment) as wgpu::DynamicOffset;
+		if instanced {
+			self.pipeline.apply_instanced(render_pass);
+		} else {
+			self.pipeline.apply(render_pass);
+		}
+		render_pass.set_bind_group(0, &self.bind_group, &[light_offset, actor_offset]);
+	}
+}
+
 pub struct DebugUniforms {
 	pipeline: LinePipeline,
 	bind_group: wgpu::BindGroup,
 	camera_buffer: wgpu::Buffer,
 	actor_buffer: wgpu::Buffer,
+	/// Number of objects the actor buffer has room for
+	capacity: u64,
 }
 
 impl DebugUniforms {
-	pub fn new(device: &wgpu::Device) -> Self {
+	pub fn new(device: &wgpu::Device, target: TargetFormat) -> Self {
 		log::debug!("Building Debug Uniforms");
-		let pipeline = LinePipeline::new(device);
+		let pipeline = LinePipeline::new(device, target);
 
 		let uniform_alignment =
 			device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
@@ -273,17 +1177,29 @@ impl DebugUniforms {
 			mapped_at_creation: false,
 		});
 
-		let actor_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-			label: Some("Actor Buffer"),
-			usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-			size: MAX_OBJECTS * uniform_alignment,
-			mapped_at_creation: false,
-		});
+		let actor_buffer = create_slot_buffer(device, "Actor Buffer", INITIAL_SLOTS);
+
+		let bind_group = Self::create_bind_group(device, &pipeline, &camera_buffer, &actor_buffer);
 
+		Self {
+			pipeline,
+			bind_group,
+			camera_buffer,
+			actor_buffer,
+			capacity: INITIAL_SLOTS,
+		}
+	}
+
+	fn create_bind_group(
+		device: &wgpu::Device,
+		pipeline: &LinePipeline,
+		camera_buffer: &wgpu::Buffer,
+		actor_buffer: &wgpu::Buffer,
+	) -> wgpu::BindGroup {
 		let camera_size = size_of::<CameraUniform>() as wgpu::BufferAddress;
 		let actor_size = size_of::<ActorUniform>() as wgpu::BufferAddress;
 
-		let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+		device.create_bind_group(&wgpu::BindGroupDescriptor {
 			label: Some("LinePipeline Bind Group"),
 			layout: pipeline.bind_group_layout(),
 			entries: &[
@@ -291,7 +1207,7 @@ impl DebugUniforms {
 				wgpu::BindGroupEntry {
 					binding: CAMERA_BINDING,
 					resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-						buffer: &camera_buffer,
+						buffer: camera_buffer,
 						size: wgpu::BufferSize::new(camera_size),
 						offset: 0,
 					}),
@@ -300,19 +1216,142 @@ impl DebugUniforms {
 				wgpu::BindGroupEntry {
 					binding: ACTOR_BINDING,
 					resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-						buffer: &actor_buffer,
+						buffer: actor_buffer,
 						size: wgpu::BufferSize::new(actor_size),
 						offset: 0,
 					}),
 				},
 			],
+		})
+	}
+
+	/// Make room for `slots` objects, rebuilding the actor buffer if it's too small.
+	fn reserve(&mut self, device: &wgpu::Device, slots: u64) {
+		if slots > self.capacity {
+			self.capacity = slot_capacity(slots);
+			self.actor_buffer = create_slot_buffer(device, "Actor Buffer", self.capacity);
+			self.bind_group = Self::create_bind_group(
+				device,
+				&self.pipeline,
+				&self.camera_buffer,
+				&self.actor_buffer,
+			);
+		}
+	}
+
+	fn set_camera(&self, ctx: &mut RenderContext, camera: &dyn Camera) {
+		let contents = CameraUniform {
+			view: camera.view(),
+			projection: camera.projection(),
+		};
+		ctx.queue
+			.write_buffer(&self.camera_buffer, 0, bytemuck::cast_slice(&[contents]));
+	}
+
+	fn set_actor(&self, ctx: &mut RenderContext, index: u64, contents: ActorUniform) {
+		let uniform_alignment =
+			ctx.device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
+		let offset = (index * uniform_alignment) as wgpu::DynamicOffset;
+		ctx.queue.write_buffer(
+			&self.actor_buffer,
+			offset as _,
+			bytemuck::cast_slice(&[contents]),
+		);
+	}
+
+	fn bind_actor<'a>(&'a self, ctx: &mut RenderContext<'a>, index: u64) {
+		let render_pass = &mut ctx.render_pass;
+		let uniform_alignment =
+			ctx.device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
+		let offset = (index * uniform_alignment) as wgpu::DynamicOffset;
+		self.pipeline.apply(render_pass);
+		render_pass.set_bind_group(0, &self.bind_group, &[offset]);
+	}
+}
+
+pub struct PickUniforms {
+	pipeline: PickPipeline,
+	bind_group: wgpu::BindGroup,
+	camera_buffer: wgpu::Buffer,
+	actor_buffer: wgpu::Buffer,
+	/// Number of objects the actor buffer has room for
+	capacity: u64,
+}
+
+impl PickUniforms {
+	pub fn new(device: &wgpu::Device, depth_compare: wgpu::CompareFunction) -> Self {
+		log::debug!("Building Pick Uniforms");
+		let pipeline = PickPipeline::new(device, depth_compare);
+
+		let uniform_alignment =
+			device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
+
+		let camera_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("Pick Camera Buffer"),
+			usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+			size: uniform_alignment,
+			mapped_at_creation: false,
 		});
 
+		let actor_buffer = create_slot_buffer(device, "Pick Actor Buffer", INITIAL_SLOTS);
+
+		let bind_group = Self::create_bind_group(device, &pipeline, &camera_buffer, &actor_buffer);
+
 		Self {
 			pipeline,
 			bind_group,
 			camera_buffer,
 			actor_buffer,
+			capacity: INITIAL_SLOTS,
+		}
+	}
+
+	fn create_bind_group(
+		device: &wgpu::Device,
+		pipeline: &PickPipeline,
+		camera_buffer: &wgpu::Buffer,
+		actor_buffer: &wgpu::Buffer,
+	) -> wgpu::BindGroup {
+		let camera_size = size_of::<CameraUniform>() as wgpu::BufferAddress;
+		let actor_size = size_of::<PickUniform>() as wgpu::BufferAddress;
+
+		device.create_bind_group(&wgpu::BindGroupDescriptor {
+			label: Some("PickPipeline Bind Group"),
+			layout: pipeline.bind_group_layout(),
+			entries: &[
+				// Camera
+				wgpu::BindGroupEntry {
+					binding: PICK_CAMERA_BINDING,
+					resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+						buffer: camera_buffer,
+						size: wgpu::BufferSize::new(camera_size),
+						offset: 0,
+					}),
+				},
+				// Actors
+				wgpu::BindGroupEntry {
+					binding: PICK_ACTOR_BINDING,
+					resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+						buffer: actor_buffer,
+						size: wgpu::BufferSize::new(actor_size),
+						offset: 0,
+					}),
+				},
+			],
+		})
+	}
+
+	/// Make room for `slots` objects, rebuilding the actor buffer if it's too small.
+	fn reserve(&mut self, device: &wgpu::Device, slots: u64) {
+		if slots > self.capacity {
+			self.capacity = slot_capacity(slots);
+			self.actor_buffer = create_slot_buffer(device, "Pick Actor Buffer", self.capacity);
+			self.bind_group = Self::create_bind_group(
+				device,
+				&self.pipeline,
+				&self.camera_buffer,
+				&self.actor_buffer,
+			);
 		}
 	}
 
@@ -325,7 +1364,7 @@ impl DebugUniforms {
 			.write_buffer(&self.camera_buffer, 0, bytemuck::cast_slice(&[contents]));
 	}
 
-	fn set_actor(&self, ctx: &mut RenderContext, index: u64, contents: ActorUniform) {
+	fn set_actor(&self, ctx: &mut RenderContext, index: u64, contents: PickUniform) {
 		let uniform_alignment =
 			ctx.device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
 		let offset = (index * uniform_alignment) as wgpu::DynamicOffset;
@@ -336,12 +1375,16 @@ impl DebugUniforms {
 		);
 	}
 
-	fn bind_actor<'a>(&'a self, ctx: &mut RenderContext<'a>, index: u64) {
+	fn bind_actor<'a>(&'a self, ctx: &mut RenderContext<'a>, index: u64, instanced: bool) {
 		let render_pass = &mut ctx.render_pass;
 		let uniform_alignment =
 			ctx.device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
 		let offset = (index * uniform_alignment) as wgpu::DynamicOffset;
-		self.pipeline.apply(render_pass);
+		if instanced {
+			self.pipeline.apply_instanced(render_pass);
+		} else {
+			self.pipeline.apply(render_pass);
+		}
 		render_pass.set_bind_group(0, &self.bind_group, &[offset]);
 	}
 }
@@ -353,12 +1396,19 @@ pub struct SceneUniforms {
 	camera_buffer: wgpu::Buffer,
 	actor_buffer: wgpu::Buffer,
 	enabled_buffer: wgpu::Buffer,
+	/// Number of objects the actor buffer has room for
+	capacity: u64,
 }
 
 impl SceneUniforms {
-	pub fn new(device: &wgpu::Device, queue: &mut wgpu::Queue) -> Self {
+	pub fn new(
+		device: &wgpu::Device,
+		queue: &mut wgpu::Queue,
+		lights: &LightResources,
+		target: TargetFormat,
+	) -> Self {
 		log::debug!("Building Scene Uniforms");
-		let pipeline = PrimitivePipeline::new(device);
+		let pipeline = PrimitivePipeline::new(device, target);
 
 		let uniform_alignment =
 			device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
@@ -370,12 +1420,7 @@ impl SceneUniforms {
 			mapped_at_creation: false,
 		});
 
-		let actor_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-			label: Some("Actor Buffer"),
-			usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-			size: MAX_OBJECTS * uniform_alignment,
-			mapped_at_creation: false,
-		});
+		let actor_buffer = create_slot_buffer(device, "Actor Buffer", INITIAL_SLOTS);
 
 		let enabled_buffer = device.create_buffer(&wgpu::BufferDescriptor {
 			label: Some("Texture Enabled Buffer"),
@@ -391,10 +1436,32 @@ impl SceneUniforms {
 			bytemuck::cast_slice(&[1]),
 		);
 
+		let bind_group =
+			Self::create_bind_group(device, &pipeline, &camera_buffer, &actor_buffer, lights);
+
+		Self {
+			pipeline,
+			bind_group,
+			texture_bind_groups: HashMap::new(),
+			camera_buffer,
+			actor_buffer,
+			enabled_buffer,
+			capacity: INITIAL_SLOTS,
+		}
+	}
+
+	fn create_bind_group(
+		device: &wgpu::Device,
+		pipeline: &PrimitivePipeline,
+		camera_buffer: &wgpu::Buffer,
+		actor_buffer: &wgpu::Buffer,
+		lights: &LightResources,
+	) -> wgpu::BindGroup {
 		let camera_size = size_of::<CameraUniform>() as wgpu::BufferAddress;
 		let actor_size = size_of::<ActorUniform>() as wgpu::BufferAddress;
+		let lights_size = size_of::<LightsUniform>() as wgpu::BufferAddress;
 
-		let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+		device.create_bind_group(&wgpu::BindGroupDescriptor {
 			label: Some("PrimitivePipeline Bind Group"),
 			layout: pipeline.bind_group_layout(),
 			entries: &[
@@ -402,7 +1469,7 @@ impl SceneUniforms {
 				wgpu::BindGroupEntry {
 					binding: CAMERA_BINDING,
 					resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-						buffer: &camera_buffer,
+						buffer: camera_buffer,
 						size: wgpu::BufferSize::new(camera_size),
 						offset: 0,
 					}),
@@ -411,21 +1478,45 @@ impl SceneUniforms {
 				wgpu::BindGroupEntry {
 					binding: ACTOR_BINDING,
 					resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-						buffer: &actor_buffer,
+						buffer: actor_buffer,
 						size: wgpu::BufferSize::new(actor_size),
 						offset: 0,
 					}),
 				},
+				// Lights
+				wgpu::BindGroupEntry {
+					binding: LIGHTS_BINDING,
+					resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+						buffer: lights.buffer,
+						size: wgpu::BufferSize::new(lights_size),
+						offset: 0,
+					}),
+				},
+				// Shadow Maps
+				wgpu::BindGroupEntry {
+					binding: SHADOW_MAP_BINDING,
+					resource: wgpu::BindingResource::TextureView(lights.shadow_map),
+				},
+				wgpu::BindGroupEntry {
+					binding: SHADOW_SAMPLER_BINDING,
+					resource: wgpu::BindingResource::Sampler(lights.shadow_sampler),
+				},
 			],
-		});
+		})
+	}
 
-		Self {
-			pipeline,
-			bind_group,
-			texture_bind_groups: HashMap::new(),
-			camera_buffer,
-			actor_buffer,
-			enabled_buffer,
+	/// Make room for `slots` objects, rebuilding the actor buffer if it's too small.
+	fn reserve(&mut self, device: &wgpu::Device, slots: u64, lights: &LightResources) {
+		if slots > self.capacity {
+			self.capacity = slot_capacity(slots);
+			self.actor_buffer = create_slot_buffer(device, "Actor Buffer", self.capacity);
+			self.bind_group = Self::create_bind_group(
+				device,
+				&self.pipeline,
+				&self.camera_buffer,
+				&self.actor_buffer,
+				lights,
+			);
 		}
 	}
 
@@ -449,21 +1540,36 @@ impl SceneUniforms {
 		);
 	}
 
-	fn bind_actor<'a>(&'a self, ctx: &mut RenderContext<'a>, index: u64) {
+	fn bind_actor<'a>(
+		&'a self,
+		ctx: &mut RenderContext<'a>,
+		index: u64,
+		alpha_mode: AlphaMode,
+		instanced: bool,
+	) {
 		let render_pass = &mut ctx.render_pass;
 		let uniform_alignment =
 			ctx.device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
 		let offset = (index * uniform_alignment) as wgpu::DynamicOffset;
-		self.pipeline.apply(render_pass);
+		self.pipeline
+			.apply_alpha_mode(render_pass, alpha_mode, instanced);
 		render_pass.set_bind_group(0, &self.bind_group, &[offset]);
 	}
 
 	fn bind_texture<'a>(&'a self, ctx: &mut RenderContext<'a>, id: TextureID) {
-		if let Some(texture) = self.texture_bind_groups.get(&id) {
+		// Textures that haven't been uploaded yet or have been removed are drawn as the default
+		let texture = self
+			.texture_bind_groups
+			.get_key_value(&id)
+			.or_else(|| self.texture_bind_groups.get_key_value(&DEFAULT_TEXTURE));
+		if let Some((&id, texture)) = texture {
 			let uniform_alignment =
 				ctx.device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
-			let is_enabled_offset =
-				(if id == 0 { 0 } else { uniform_alignment }) as wgpu::DynamicOffset;
+			let is_enabled_offset = (if id == DEFAULT_TEXTURE {
+				0
+			} else {
+				uniform_alignment
+			}) as wgpu::DynamicOffset;
 
 			ctx.render_pass
 				.set_bind_group(1, texture, &[is_enabled_offset]);
@@ -498,4 +1604,253 @@ impl SceneUniforms {
 			}),
 		);
 	}
+
+	fn remove_texture(&mut self, id: TextureID) {
+		self.texture_bind_groups.remove(&id);
+	}
+}
+
+pub struct PbrUniforms {
+	pipeline: PbrPipeline,
+	bind_group: wgpu::BindGroup,
+	texture_bind_groups: HashMap<[TextureID; 5], wgpu::BindGroup>,
+	camera_buffer: wgpu::Buffer,
+	actor_buffer: wgpu::Buffer,
+	material_buffer: wgpu::Buffer,
+	/// Number of objects the actor buffer has room for
+	capacity: u64,
+}
+
+impl PbrUniforms {
+	pub fn new(device: &wgpu::Device, lights: &LightResources, target: TargetFormat) -> Self {
+		log::debug!("Building PBR Uniforms");
+		let pipeline = PbrPipeline::new(device, target);
+
+		let uniform_alignment =
+			device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
+
+		let camera_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("Camera Buffer"),
+			usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+			size: uniform_alignment,
+			mapped_at_creation: false,
+		});
+
+		let actor_buffer = create_slot_buffer(device, "Actor Buffer", INITIAL_SLOTS);
+
+		let material_buffer = create_slot_buffer(device, "Material Buffer", INITIAL_SLOTS);
+
+		let bind_group = Self::create_bind_group(
+			device,
+			&pipeline,
+			&camera_buffer,
+			&actor_buffer,
+			&material_buffer,
+			lights,
+		);
+
+		Self {
+			pipeline,
+			bind_group,
+			texture_bind_groups: HashMap::new(),
+			camera_buffer,
+			actor_buffer,
+			material_buffer,
+			capacity: INITIAL_SLOTS,
+		}
+	}
+
+	fn create_bind_group(
+		device: &wgpu::Device,
+		pipeline: &PbrPipeline,
+		camera_buffer: &wgpu::Buffer,
+		actor_buffer: &wgpu::Buffer,
+		material_buffer: &wgpu::Buffer,
+		lights: &LightResources,
+	) -> wgpu::BindGroup {
+		let camera_size = size_of::<CameraUniform>() as wgpu::BufferAddress;
+		let actor_size = size_of::<ActorUniform>() as wgpu::BufferAddress;
+		let material_size = size_of::<MaterialUniform>() as wgpu::BufferAddress;
+		let lights_size = size_of::<LightsUniform>() as wgpu::BufferAddress;
+
+		device.create_bind_group(&wgpu::BindGroupDescriptor {
+			label: Some("PbrPipeline Bind Group"),
+			layout: pipeline.bind_group_layout(),
+			entries: &[
+				// Camera
+				wgpu::BindGroupEntry {
+					binding: PBR_CAMERA_BINDING,
+					resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+						buffer: camera_buffer,
+						size: wgpu::BufferSize::new(camera_size),
+						offset: 0,
+					}),
+				},
+				// Actors
+				wgpu::BindGroupEntry {
+					binding: PBR_ACTOR_BINDING,
+					resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+						buffer: actor_buffer,
+						size: wgpu::BufferSize::new(actor_size),
+						offset: 0,
+					}),
+				},
+				// Lights
+				wgpu::BindGroupEntry {
+					binding: LIGHTS_BINDING,
+					resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+						buffer: lights.buffer,
+						size: wgpu::BufferSize::new(lights_size),
+						offset: 0,
+					}),
+				},
+				// Shadow Maps
+				wgpu::BindGroupEntry {
+					binding: SHADOW_MAP_BINDING,
+					resource: wgpu::BindingResource::TextureView(lights.shadow_map),
+				},
+				wgpu::BindGroupEntry {
+					binding: SHADOW_SAMPLER_BINDING,
+					resource: wgpu::BindingResource::Sampler(lights.shadow_sampler),
+				},
+				// Materials
+				wgpu::BindGroupEntry {
+					binding: PBR_MATERIAL_BINDING,
+					resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+						buffer: material_buffer,
+						size: wgpu::BufferSize::new(material_size),
+						offset: 0,
+					}),
+				},
+			],
+		})
+	}
+
+	/// Make room for `slots` objects, rebuilding the actor and material buffers if they're too
+	/// small.
+	fn reserve(&mut self, device: &wgpu::Device, slots: u64, lights: &LightResources) {
+		if slots > self.capacity {
+			self.capacity = slot_capacity(slots);
+			self.actor_buffer = create_slot_buffer(device, "Actor Buffer", self.capacity);
+			self.material_buffer = create_slot_buffer(device, "Material Buffer", self.capacity);
+			self.bind_group = Self::create_bind_group(
+				device,
+				&self.pipeline,
+				&self.camera_buffer,
+				&self.actor_buffer,
+				&self.material_buffer,
+				lights,
+			);
+		}
+	}
+
+	fn set_camera(&self, ctx: &mut RenderContext, camera: &dyn Camera) {
+		let contents = CameraUniform {
+			view: camera.view(),
+			projection: camera.projection(),
+		};
+		ctx.queue
+			.write_buffer(&self.camera_buffer, 0, bytemuck::cast_slice(&[contents]));
+	}
+
+	fn set_actor(&self, ctx: &mut RenderContext, index: u64, contents: ActorUniform) {
+		let uniform_alignment =
+			ctx.device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
+		let offset = (index * uniform_alignment) as wgpu::DynamicOffset;
+		ctx.queue.write_buffer(
+			&self.actor_buffer,
+			offset as _,
+			bytemuck::cast_slice(&[contents]),
+		);
+	}
+
+	fn set_material(&self, ctx: &mut RenderContext, index: u64, material: &PbrMaterial) {
+		let uniform_alignment =
+			ctx.device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
+		let offset = (index * uniform_alignment) as wgpu::DynamicOffset;
+		ctx.queue.write_buffer(
+			&self.material_buffer,
+			offset as _,
+			bytemuck::cast_slice(&[MaterialUniform::from(material)]),
+		);
+	}
+
+	/// Bind the pipeline and uniforms for an object. Returns false if the material's textures
+	/// haven't been uploaded yet.
+	fn bind_actor<'a>(
+		&'a self,
+		ctx: &mut RenderContext<'a>,
+		index: u64,
+		material: &PbrMaterial,
+		instanced: bool,
+	) -> bool {
+		let textures = match self.texture_bind_groups.get(&Self::texture_key(material)) {
+			Some(textures) => textures,
+			None => return false,
+		};
+
+		let render_pass = &mut ctx.render_pass;
+		let uniform_alignment =
+			ctx.device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
+		let offset = (index * uniform_alignment) as wgpu::DynamicOffset;
+		self.pipeline
+			.apply_material(render_pass, material, instanced);
+		render_pass.set_bind_group(0, &self.bind_group, &[offset, offset]);
+		render_pass.set_bind_group(1, textures, &[]);
+
+		true
+	}
+
+	/// Texture IDs to bind for a material, using the default texture for empty slots.
+	fn texture_key(material: &PbrMaterial) -> [TextureID; 5] {
+		material.textures().map(|id| id.unwrap_or(DEFAULT_TEXTURE))
+	}
+
+	fn add_material_textures(
+		&mut self,
+		device: &wgpu::Device,
+		textures: &HashMap<TextureID, Texture>,
+		material: &PbrMaterial,
+	) {
+		let key = Self::texture_key(material);
+		if self.texture_bind_groups.contains_key(&key) {
+			return;
+		}
+
+		let buffers: Option<Vec<&TextureBuffer>> = key
+			.iter()
+			.map(|id| textures.get(id).and_then(|texture| texture.buffer()))
+			.collect();
+		let buffers = match buffers {
+			Some(buffers) => buffers,
+			None => return,
+		};
+
+		log::debug!("Creating BindGroup for PBR textures {:?}", key);
+		let mut entries: Vec<_> = PBR_TEXTURE_BINDINGS
+			.iter()
+			.zip(&buffers)
+			.map(|(&binding, buffer)| wgpu::BindGroupEntry {
+				binding,
+				resource: wgpu::BindingResource::TextureView(&buffer.view),
+			})
+			.collect();
+		entries.push(wgpu::BindGroupEntry {
+			binding: PBR_SAMPLER_BINDING,
+			resource: wgpu::BindingResource::Sampler(&buffers[0].sampler),
+		});
+
+		self.texture_bind_groups.insert(
+			key,
+			device.create_bind_group(&wgpu::BindGroupDescriptor {
+				label: Some("PbrPipeline Texture Bind Group"),
+				layout: self.pipeline.texture_bind_group_layout().unwrap(),
+				entries: &entries,
+			}),
+		);
+	}
+
+	fn remove_texture(&mut self, id: TextureID) {
+		self.texture_bind_groups.retain(|key, _| !key.contains(&id));
+	}
 }
@@ -1,4 +1,6 @@
-use crate::{BasicMaterial, Color, Material, MountContext, RenderContext};
+use crate::{
+	BasicMaterial, BoundingBox, Color, Material, MountContext, Ray, RenderContext, TriangleHit,
+};
 use cgmath::{Matrix4, SquareMatrix};
 use downcast_rs::{impl_downcast, Downcast};
 
@@ -8,11 +10,45 @@ pub trait SceneObject: Downcast {
 	fn render<'a>(&'a mut self, _ctx: &mut RenderContext<'a>) {}
 	fn mount(&mut self, _ctx: &mut MountContext) {}
 	fn unmount(&mut self, _ctx: &mut MountContext) {}
+	/// Transform relative to the object's parent, or to the scene if it has none
 	fn transform(&self) -> Matrix4<f32> {
 		Matrix4::identity()
 	}
 	fn material(&self) -> &dyn Material {
 		&DEFAULT_MATERIAL
 	}
+	/// Whether the object draws anything. Objects that don't are skipped entirely when drawing
+	/// and picking, so no uniforms are written or pipelines bound for them.
+	fn draws(&self) -> bool {
+		true
+	}
+	/// Box containing everything the object draws, before its transform is applied. Objects
+	/// without bounds are never culled.
+	fn bounds(&self) -> Option<BoundingBox> {
+		None
+	}
+	/// Whether the object blocks light from shadow casting lights
+	fn cast_shadows(&self) -> bool {
+		true
+	}
+	/// Whether shadows from other objects fall on the object
+	fn receive_shadows(&self) -> bool {
+		true
+	}
+	/// Whether the object draws instances from an [`Instance`](crate::Instance) buffer, and needs
+	/// the instanced variant of its material's pipeline
+	fn instanced(&self) -> bool {
+		false
+	}
+	/// Identifies the buffers the object draws from, so objects sharing them can be drawn one
+	/// after another
+	fn geometry_key(&self) -> Option<usize> {
+		None
+	}
+	/// Nearest triangle of the object hit by a ray, in the object's local space. Objects that
+	/// can't be hit return `None`.
+	fn raycast(&self, _ray: &Ray) -> Option<TriangleHit> {
+		None
+	}
 }
 impl_downcast!(SceneObject);
@@ -1,13 +1,16 @@
-use image::{
-	io::Reader as ImageReader, DynamicImage, GenericImageView, ImageBuffer, ImageError, Rgba,
-};
-use std::{error, num::NonZeroU32};
+use crate::RenderTarget;
+use image::{io::Reader as ImageReader, DynamicImage, ImageBuffer, ImageError, Rgba};
+use std::{error, num::NonZeroU32, rc::Rc};
 
 pub struct Texture {
 	width: u32,
 	height: u32,
 	pixels: ImageBuffer<Rgba<u8>, Vec<u8>>,
-	buffer: Option<TextureBuffer>,
+	srgb: bool,
+	buffer: Option<Rc<TextureBuffer>>,
+	/// Whether the buffer is the color texture of a [`RenderTarget`], drawn into rather than
+	/// uploaded
+	rendered: bool,
 }
 
 pub struct TextureBuffer {
@@ -32,7 +35,9 @@ impl Texture {
 			width,
 			height,
 			pixels: img,
+			srgb: true,
 			buffer: None,
+			rendered: false,
 		}
 	}
 
@@ -47,17 +52,63 @@ impl Texture {
 			width,
 			height,
 			pixels: ImageBuffer::new(width, height),
+			srgb: true,
 			buffer: None,
+			rendered: false,
 		}
 	}
 
+	/// Texture showing whatever was last drawn into a render target, with
+	/// [`Renderer::render_to_target`](crate::Renderer::render_to_target).
+	pub fn from_render_target(target: &RenderTarget) -> Self {
+		Self {
+			width: target.width(),
+			height: target.height(),
+			pixels: ImageBuffer::new(0, 0),
+			srgb: target.target().format.describe().srgb,
+			buffer: Some(target.shared_color()),
+			rendered: true,
+		}
+	}
+
+	/// Whether the texture shows a [`RenderTarget`] rather than pixels of its own.
+	pub fn is_rendered(&self) -> bool {
+		self.rendered
+	}
+
+	/// Whether the pixels are sRGB encoded colours, rather than linear data such as normals.
+	pub fn is_srgb(&self) -> bool {
+		self.srgb
+	}
+
+	/// Mark the pixels as sRGB colours (the default) or linear data. Takes effect when the
+	/// texture is next allocated.
+	pub fn set_srgb(&mut self, srgb: bool) {
+		self.srgb = srgb;
+	}
+
 	pub fn is_allocated(&self) -> bool {
 		self.buffer.is_some()
 	}
 
 	pub fn allocate(&mut self, device: &wgpu::Device, label: &str) {
+		// Render targets own their texture
+		if self.rendered {
+			return;
+		}
 		self.destroy();
-		self.buffer = Some(TextureBuffer::new(device, self.width, self.height, label));
+		let format = if self.srgb {
+			wgpu::TextureFormat::Rgba8UnormSrgb
+		} else {
+			wgpu::TextureFormat::Rgba8Unorm
+		};
+		self.buffer = Some(Rc::new(TextureBuffer::with_format(
+			device,
+			self.width,
+			self.height,
+			format,
+			label,
+		)));
 	}
 
 	pub fn destroy(&mut self) {
@@ -65,6 +116,9 @@ impl Texture {
 	}
 
 	pub fn upload(&self, queue: &mut wgpu::Queue) {
+		if self.rendered {
+			return;
+		}
 		if let Some(buffer) = self.buffer.as_ref() {
 			buffer.write(queue, &self.pixels);
 		}
@@ -79,7 +133,7 @@ impl Texture {
 
 	/// Get a reference to the texture's buffer.
 	pub fn buffer(&self) -> Option<&TextureBuffer> {
-		self.buffer.as_ref()
+		self.buffer.as_deref()
 	}
 }
 
@@ -87,6 +141,22 @@ impl TextureBuffer {
 	pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
 
 	pub fn new(device: &wgpu::Device, width: u32, height: u32, label: &str) -> Self {
+		Self::with_format(
+			device,
+			width,
+			height,
+			wgpu::TextureFormat::Rgba8UnormSrgb,
+			label,
+		)
+	}
+
+	pub fn with_format(
+		device: &wgpu::Device,
+		width: u32,
+		height: u32,
+		format: wgpu::TextureFormat,
+		label: &str,
+	) -> Self {
 		let label = format!("{} texture", label);
 		let desc = wgpu::TextureDescriptor {
 			label: Some(&label),
@@ -98,7 +168,7 @@ impl TextureBuffer {
 			mip_level_count: 1,
 			sample_count: 1,
 			dimension: wgpu::TextureDimension::D2,
-			format: wgpu::TextureFormat::Rgba8UnormSrgb,
+			format,
 			usage: wgpu::TextureUsages::TEXTURE_BINDING
 				| wgpu::TextureUsages::COPY_SRC
 				| wgpu::TextureUsages::COPY_DST
@@ -125,7 +195,49 @@ impl TextureBuffer {
 		}
 	}
 
-	pub fn new_depth_texture(device: &wgpu::Device, width: u32, height: u32) -> Self {
+	/// Color target with several samples per pixel, to be resolved into a single sampled texture.
+	pub fn new_multisampled(
+		device: &wgpu::Device,
+		width: u32,
+		height: u32,
+		format: wgpu::TextureFormat,
+		sample_count: u32,
+		label: &str,
+	) -> Self {
+		let label = format!("{} texture", label);
+		let texture = device.create_texture(&wgpu::TextureDescriptor {
+			label: Some(&label),
+			size: wgpu::Extent3d {
+				width,
+				height,
+				depth_or_array_layers: 1,
+			},
+			mip_level_count: 1,
+			sample_count,
+			dimension: wgpu::TextureDimension::D2,
+			format,
+			usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+		});
+
+		let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+		let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+			label: Some(&format!("{} sampler", label)),
+			..Default::default()
+		});
+
+		Self {
+			texture,
+			view,
+			sampler,
+		}
+	}
+
+	pub fn new_depth_texture(
+		device: &wgpu::Device,
+		width: u32,
+		height: u32,
+		sample_count: u32,
+	) -> Self {
 		let desc = wgpu::TextureDescriptor {
 			label: Some("Depth Texture"),
 			size: wgpu::Extent3d {
@@ -134,7 +246,7 @@ impl TextureBuffer {
 				depth_or_array_layers: 1,
 			},
 			mip_level_count: 1,
-			sample_count: 1,
+			sample_count,
 			dimension: wgpu::TextureDimension::D2,
 			format: Self::DEPTH_FORMAT,
 			usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
@@ -1,3 +1,10 @@
+use cgmath::Point3;
+
 pub trait Vertex: bytemuck::Pod {
 	fn buffer_layout<'a>() -> wgpu::VertexBufferLayout<'a>;
+	/// Position of the vertex, used to work out the bounds of geometry. Geometry made of vertices
+	/// without a position is never culled.
+	fn position(&self) -> Option<Point3<f32>> {
+		None
+	}
 }
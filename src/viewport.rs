@@ -0,0 +1,59 @@
+/// A rectangle of the screen a scene is drawn into, in pixels from the top left.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Viewport {
+	pub x: u32,
+	pub y: u32,
+	pub width: u32,
+	pub height: u32,
+}
+
+impl Viewport {
+	pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+		Self {
+			x,
+			y,
+			width,
+			height,
+		}
+	}
+
+	/// Split a `width` by `height` screen into `columns` by `rows` equal viewports, row by row
+	/// from the top left. The last column and row take up any remainder.
+	pub fn grid(width: u32, height: u32, columns: u32, rows: u32) -> Vec<Self> {
+		let (cell_width, cell_height) = (width / columns.max(1), height / rows.max(1));
+		let mut viewports = Vec::with_capacity((columns * rows) as usize);
+		for row in 0..rows {
+			for column in 0..columns {
+				let (x, y) = (column * cell_width, row * cell_height);
+				let right = if column + 1 == columns {
+					width
+				} else {
+					x + cell_width
+				};
+				let bottom = if row + 1 == rows {
+					height
+				} else {
+					y + cell_height
+				};
+				viewports.push(Self::new(x, y, right - x, bottom - y));
+			}
+		}
+		viewports
+	}
+
+	/// Whether a pixel is inside the viewport, e.g. to find which view was clicked.
+	pub fn contains(&self, x: f32, y: f32) -> bool {
+		x >= self.x as f32
+			&& y >= self.y as f32
+			&& x < self.x.saturating_add(self.width) as f32
+			&& y < self.y.saturating_add(self.height) as f32
+	}
+
+	/// The part of the viewport inside a `width` by `height` target, if any.
+	pub fn clamp(&self, width: u32, height: u32) -> Option<Self> {
+		let right = self.x.saturating_add(self.width).min(width);
+		let bottom = self.y.saturating_add(self.height).min(height);
+		(self.x < right && self.y < bottom)
+			.then(|| Self::new(self.x, self.y, right - self.x, bottom - self.y))
+	}
+}
@@ -60,6 +60,7 @@ impl Window {
 		}
 	}
 
+	#[allow(clippy::collapsible_match, clippy::single_match)]
 	pub fn run<F>(self, mut event_handler: F)
 	where
 		F: 'static + FnMut(Event, &mut WindowContext),
@@ -106,7 +107,7 @@ impl Window {
 						}
 						for button in &held_buttons {
 							event_proxy
-								.send_event(Event::MouseDrag(button.clone(), x as _, y as _))
+								.send_event(Event::MouseDrag(*button, x as _, y as _))
 								.expect("Failed to send event");
 						}
 					}
@@ -0,0 +1,95 @@
+use byd::{BoundingBox, Camera, FreeCamera, Frustum, OrthographicCamera};
+use cgmath::{InnerSpace, Point3, Vector3, Vector4};
+
+/// Where a point lands in normalized device coordinates.
+fn project(camera: &dyn Camera, point: Point3<f32>) -> Vector3<f32> {
+	let clip = camera.projection() * camera.view() * point.to_homogeneous();
+	clip.truncate() / clip.w
+}
+
+#[test]
+fn free_camera_fov_and_clip_planes() {
+	let mut camera = FreeCamera::new()
+		.with_fov(90.0)
+		.with_clip_planes(1.0, 100.0);
+	camera.resize(100.0, 100.0);
+	assert_eq!(camera.fov(), 90.0);
+	assert_eq!((camera.near(), camera.far()), (1.0, 100.0));
+	assert!(!camera.reverse_z());
+
+	// A 90 degree view reaches as far up as it does forwards
+	let top = project(&camera, Point3::new(0.0, 10.0, 10.0));
+	assert!((top.y - 1.0).abs() < 1e-5, "{:?}", top);
+	assert!((project(&camera, Point3::new(0.0, 0.0, 100.0)).z - 1.0).abs() < 1e-5);
+}
+
+#[test]
+fn free_camera_reverse_z() {
+	let mut camera = FreeCamera::new()
+		.with_clip_planes(0.5, 200.0)
+		.with_reverse_z(true);
+	camera.resize(160.0, 120.0);
+	assert!(camera.reverse_z());
+	assert!((project(&camera, Point3::new(0.0, 0.0, 0.5)).z - 1.0).abs() < 1e-5);
+	assert!(project(&camera, Point3::new(0.0, 0.0, 200.0)).z.abs() < 1e-5);
+
+	// With an infinite far plane, distant points approach 0 without reaching it
+	camera.set_clip_planes(0.5, f32::INFINITY);
+	assert!((project(&camera, Point3::new(0.0, 0.0, 0.5)).z - 1.0).abs() < 1e-5);
+	let distant = project(&camera, Point3::new(0.0, 0.0, 1e6)).z;
+	assert!(distant > 0.0 && distant < 1e-5, "{}", distant);
+	let nearer = project(&camera, Point3::new(0.0, 0.0, 10.0)).z;
+	assert!(nearer > distant);
+
+	// Nothing in front of the camera is culled however far away it is
+	let frustum = Frustum::from_matrix(camera.projection() * camera.view());
+	let far_box = BoundingBox::new(
+		Point3::new(-1.0, -1.0, 1e5),
+		Point3::new(1.0, 1.0, 1e5 + 2.0),
+	);
+	assert!(frustum.intersects(&far_box));
+	let behind = BoundingBox::new(Point3::new(-1.0, -1.0, -3.0), Point3::new(1.0, 1.0, -1.0));
+	assert!(!frustum.intersects(&behind));
+
+	let ray = camera.screen_ray(80.0, 60.0);
+	assert!((ray.origin - Point3::new(0.0, 0.0, 0.5)).magnitude() < 1e-3);
+	assert!((ray.direction - Vector3::unit_z()).magnitude() < 1e-4);
+}
+
+#[test]
+fn orthographic_camera() {
+	let mut camera = OrthographicCamera::new()
+		.with_view_height(20.0)
+		.with_clip_planes(-10.0, 10.0);
+	camera.resize(200.0, 100.0);
+	assert_eq!(camera.view_height(), 20.0);
+
+	// Sizes don't change with distance, and the width follows the aspect ratio
+	for z in [-5.0, 0.0, 5.0] {
+		let corner = project(&camera, Point3::new(20.0, 10.0, z));
+		assert!((corner.x - 1.0).abs() < 1e-5 && (corner.y - 1.0).abs() < 1e-5);
+	}
+	assert!(project(&camera, Point3::new(0.0, 0.0, -10.0)).z.abs() < 1e-5);
+	assert!((project(&camera, Point3::new(0.0, 0.0, 10.0)).z - 1.0).abs() < 1e-5);
+
+	// Looking straight down, screen rays are parallel
+	camera.translate(0.0, 50.0, 0.0);
+	camera.rotate(std::f32::consts::FRAC_PI_2, 0.0, 0.0);
+	let centre = camera.screen_ray(100.0, 50.0);
+	let corner = camera.screen_ray(0.0, 0.0);
+	assert!((centre.direction + Vector3::unit_y()).magnitude() < 1e-4);
+	assert!((corner.direction - centre.direction).magnitude() < 1e-4);
+	assert!((centre.origin - Point3::new(0.0, 60.0, 0.0)).magnitude() < 1e-3);
+	assert!((corner.origin.x + 20.0).abs() < 1e-3);
+
+	let frustum = Frustum::from_matrix(camera.projection() * camera.view());
+	let below = |x: f32| Vector4::new(x, 45.0, 0.0, 1.0);
+	assert!(frustum
+		.planes
+		.iter()
+		.all(|plane| plane.dot(below(0.0)) >= 0.0));
+	assert!(!frustum
+		.planes
+		.iter()
+		.all(|plane| plane.dot(below(30.0)) >= 0.0));
+}
@@ -0,0 +1,159 @@
+//! Golden-image test harness.
+//!
+//! Scenes are rendered with a headless [`Renderer`] on the fallback adapter and compared against
+//! the reference PNGs in `tests/references`. These tests are ignored by default, as they need an
+//! adapter; run them with `cargo test -- --ignored`. Run with `BYD_BLESS=1` to (re)generate the
+//! references. When a comparison fails, the actual output and a diff image are written to
+//! `target/golden`.
+
+use byd::{Camera, FreeCamera, Renderer, Scene};
+use futures::executor::block_on;
+use image::{Rgba, RgbaImage};
+use std::{env, fs, path::PathBuf};
+
+pub const WIDTH: u32 = 160;
+pub const HEIGHT: u32 = 120;
+
+/// Maximum difference allowed in any channel before a pixel counts as mismatched.
+pub const TOLERANCE: u8 = 4;
+
+/// Create a headless renderer on the fallback adapter, of the backends in `WGPU_BACKEND` if set.
+///
+/// Tests using it need an adapter, so they're `#[ignore]`d and run with `cargo test -- --ignored`.
+/// Without an adapter they fail rather than passing without rendering anything.
+pub fn renderer() -> Renderer {
+	let backends = wgpu::util::backend_bits_from_env().unwrap_or_else(wgpu::Backends::all);
+	block_on(
+		Renderer::builder(WIDTH, HEIGHT)
+			.backends(backends)
+			.fallback_adapter(true)
+			.build(),
+	)
+	.unwrap_or_else(|error| panic!("Golden image tests need a fallback adapter: {}", error))
+}
+
+/// Camera at the origin looking down +Z, sized to match the renderer.
+pub fn camera() -> FreeCamera {
+	let mut camera = FreeCamera::new();
+	camera.resize(WIDTH as _, HEIGHT as _);
+	camera
+}
+
+/// Render a scene and read the frame back.
+pub fn render(renderer: &mut Renderer, scene: &mut Scene, camera: &FreeCamera) -> RgbaImage {
+	// Scene resources are created lazily, so nothing is drawn until the first few frames have
+	// uploaded the textures, compiled the programs and mounted the objects.
+	for _ in 0..3 {
+		renderer
+			.render_to_buffer(scene, camera)
+			.expect("Failed to render scene");
+	}
+	renderer.capture_frame().expect("Failed to capture frame")
+}
+
+pub struct Mismatch {
+	pub count: usize,
+	pub diff: RgbaImage,
+}
+
+/// Compare two images pixel by pixel, producing a diff image if they differ.
+pub fn compare(expected: &RgbaImage, actual: &RgbaImage, tolerance: u8) -> Result<(), Mismatch> {
+	if expected.dimensions() != actual.dimensions() {
+		let (width, height) = actual.dimensions();
+		return Err(Mismatch {
+			count: (width * height) as usize,
+			diff: RgbaImage::from_pixel(width, height, Rgba([255, 0, 0, 255])),
+		});
+	}
+
+	let mut count = 0;
+	let diff = RgbaImage::from_fn(actual.width(), actual.height(), |x, y| {
+		let a = expected.get_pixel(x, y);
+		let b = actual.get_pixel(x, y);
+		let differs =
+			a.0.iter()
+				.zip(b.0.iter())
+				.any(|(a, b)| a.abs_diff(*b) > tolerance);
+		if differs {
+			count += 1;
+			Rgba([255, 0, 0, 255])
+		} else {
+			// Faded copy of the expected image, so the mismatches stand out
+			let luma = (a[0] as u32 + a[1] as u32 + a[2] as u32) / 3;
+			let faded = (luma / 4) as u8;
+			Rgba([faded, faded, faded, 255])
+		}
+	});
+
+	if count == 0 {
+		Ok(())
+	} else {
+		Err(Mismatch { count, diff })
+	}
+}
+
+/// Check a rendered frame against `tests/references/<name>.png`.
+pub fn assert_golden(name: &str, actual: &RgbaImage) {
+	let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+	let references = root.join("tests/references");
+	let reference = references.join(format!("{}.png", name));
+
+	if env::var_os("BYD_BLESS").is_some() {
+		fs::create_dir_all(&references).expect("Failed to create reference directory");
+		actual
+			.save(&reference)
+			.expect("Failed to save reference image");
+		return;
+	}
+
+	let expected = image::open(&reference)
+		.unwrap_or_else(|_| {
+			panic!(
+				"Missing reference image {}, run with BYD_BLESS=1 to create it",
+				reference.display()
+			)
+		})
+		.to_rgba8();
+
+	if let Err(mismatch) = compare(&expected, actual, TOLERANCE) {
+		let output = root.join("target/golden");
+		fs::create_dir_all(&output).expect("Failed to create golden output directory");
+		let actual_path = output.join(format!("{}.actual.png", name));
+		let diff_path = output.join(format!("{}.diff.png", name));
+		actual
+			.save(&actual_path)
+			.expect("Failed to save actual image");
+		mismatch
+			.diff
+			.save(&diff_path)
+			.expect("Failed to save diff image");
+
+		panic!(
+			"{}: {} pixels differ from {} (actual: {}, diff: {})",
+			name,
+			mismatch.count,
+			reference.display(),
+			actual_path.display(),
+			diff_path.display()
+		);
+	}
+}
+
+#[test]
+fn compare_reports_mismatched_pixels() {
+	let expected = RgbaImage::from_pixel(4, 4, Rgba([100, 100, 100, 255]));
+	let mut actual = expected.clone();
+	actual.put_pixel(1, 2, Rgba([100, 100 + TOLERANCE, 100, 255]));
+	assert!(compare(&expected, &actual, TOLERANCE).is_ok());
+
+	actual.put_pixel(3, 0, Rgba([0, 100, 100, 255]));
+	let mismatch = compare(&expected, &actual, TOLERANCE).unwrap_err();
+	assert_eq!(mismatch.count, 1);
+	assert_eq!(*mismatch.diff.get_pixel(3, 0), Rgba([255, 0, 0, 255]));
+
+	let smaller = RgbaImage::from_pixel(2, 2, Rgba([100, 100, 100, 255]));
+	assert_eq!(
+		compare(&expected, &smaller, TOLERANCE).unwrap_err().count,
+		4
+	);
+}
@@ -0,0 +1,84 @@
+use byd::{
+	BasicMaterial, BoundingBox, Camera, Color, FreeCamera, Frustum, Geometry, Instance,
+	InstancedMesh, Mesh, SceneObject, SimpleVertex,
+};
+use cgmath::{Matrix4, Point3, Vector3};
+
+fn frustum() -> Frustum {
+	let mut camera = FreeCamera::new();
+	camera.resize(800.0, 600.0);
+	Frustum::from_matrix(camera.projection() * camera.view())
+}
+
+fn unit_box(center: Point3<f32>) -> BoundingBox {
+	BoundingBox::new(
+		center - Vector3::new(0.5, 0.5, 0.5),
+		center + Vector3::new(0.5, 0.5, 0.5),
+	)
+}
+
+#[test]
+fn bounding_box_of_points() {
+	let points = vec![
+		Point3::new(1.0, -2.0, 0.0),
+		Point3::new(-1.0, 3.0, 2.0),
+		Point3::new(0.0, 0.0, -4.0),
+	];
+	let bounds = BoundingBox::from_points(points).unwrap();
+	assert_eq!(bounds.min, Point3::new(-1.0, -2.0, -4.0));
+	assert_eq!(bounds.max, Point3::new(1.0, 3.0, 2.0));
+	assert_eq!(BoundingBox::from_points(vec![]), None);
+
+	let moved = bounds.transform(&Matrix4::from_translation(Vector3::new(10.0, 0.0, 0.0)));
+	assert_eq!(moved.min, Point3::new(9.0, -2.0, -4.0));
+	assert_eq!(moved.max, Point3::new(11.0, 3.0, 2.0));
+}
+
+#[test]
+fn frustum_keeps_boxes_in_view() {
+	let frustum = frustum();
+
+	// The camera looks down +Z
+	assert!(frustum.intersects(&unit_box(Point3::new(0.0, 0.0, 10.0))));
+	assert!(!frustum.intersects(&unit_box(Point3::new(0.0, 0.0, -10.0))));
+	assert!(!frustum.intersects(&unit_box(Point3::new(100.0, 0.0, 10.0))));
+	assert!(!frustum.intersects(&unit_box(Point3::new(0.0, 0.0, 2000.0))));
+
+	// Partly visible boxes aren't culled
+	let straddling = BoundingBox::new(Point3::new(-1.0, -1.0, -5.0), Point3::new(1.0, 1.0, 5.0));
+	assert!(frustum.intersects(&straddling));
+}
+
+#[test]
+fn mesh_bounds_follow_geometry() {
+	let material = BasicMaterial::new(Color::new(1.0, 1.0, 1.0, 1.0));
+	let mut mesh: Mesh<SimpleVertex> = Mesh::new(Geometry::cube(), material);
+	let bounds = mesh.bounds().unwrap();
+	assert_eq!(bounds.min, Point3::new(-1.0, -1.0, -1.0));
+	assert_eq!(bounds.max, Point3::new(1.0, 1.0, 1.0));
+
+	for vertex in mesh.geometry_mut().vertices_mut() {
+		vertex.position.x *= 3.0;
+	}
+	assert_eq!(mesh.bounds().unwrap().max.x, 3.0);
+}
+
+#[test]
+fn instanced_mesh_bounds_cover_every_instance() {
+	let material = BasicMaterial::new(Color::new(1.0, 1.0, 1.0, 1.0));
+	let instance = |x: f32| Instance::new(Matrix4::from_translation(Vector3::new(x, 0.0, 0.0)));
+	let mut mesh: InstancedMesh<SimpleVertex> = InstancedMesh::new(
+		Geometry::cube(),
+		material,
+		vec![instance(-5.0), instance(5.0)],
+	);
+	let bounds = mesh.bounds().unwrap();
+	assert_eq!(bounds.min.x, -6.0);
+	assert_eq!(bounds.max.x, 6.0);
+
+	mesh.instances_mut().push(instance(20.0));
+	assert_eq!(mesh.bounds().unwrap().max.x, 21.0);
+
+	mesh.set_instances(vec![]);
+	assert_eq!(mesh.bounds(), None);
+}
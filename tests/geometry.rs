@@ -0,0 +1,60 @@
+use byd::{BasicMaterial, Color, Geometry, Indices, Mesh, Ray, SceneObject, SimpleVertex};
+use cgmath::{Point3, Vector3};
+use std::rc::Rc;
+
+fn quad() -> Geometry<SimpleVertex> {
+	let corners = [
+		[-1.0, -1.0, 0.0],
+		[-1.0, 1.0, 0.0],
+		[1.0, -1.0, 0.0],
+		[1.0, 1.0, 0.0],
+	];
+	let vertices = corners.iter().map(SimpleVertex::from).collect();
+	Geometry::indexed(vertices, vec![0u16, 1, 2, 3, 2, 1])
+}
+
+#[test]
+fn indices_fit_the_vertex_count() {
+	let indices = vec![0, 1, 65_535];
+	assert_eq!(
+		Indices::new(indices.clone(), 65_536),
+		Indices::U16(vec![0, 1, 65_535])
+	);
+	assert_eq!(Indices::new(indices.clone(), 65_537), Indices::U32(indices));
+}
+
+#[test]
+fn changing_indices_rebuilds_the_geometry() {
+	let mut quad = quad();
+	// Through the upper right triangle
+	let ray = Ray::new(Point3::new(0.5, 0.5, -1.0), Vector3::new(0.0, 0.0, 1.0));
+	assert!(quad.raycast(&ray).is_some());
+
+	// Only the lower left triangle is left
+	quad.set_indices(Some(Indices::new(vec![0, 1, 2], quad.vertex_count())));
+	assert_eq!(quad.triangle_count(), 1);
+	assert!(quad.raycast(&ray).is_none());
+
+	// Moving the vertices moves the bounds
+	for vertex in quad.vertices_mut() {
+		vertex.position.x += 10.0;
+	}
+	assert_eq!(quad.bounds().unwrap().min.x, 9.0);
+}
+
+#[test]
+fn meshes_sharing_geometry_share_a_key() {
+	let material = || BasicMaterial::new(Color::new(1.0, 1.0, 1.0, 1.0));
+	let geometry = Rc::new(quad());
+	let a = Mesh::new(geometry.clone(), material());
+	let mut b = Mesh::new(a.shared_geometry(), material());
+	let c = Mesh::new(quad(), material());
+	assert_eq!(a.geometry_key(), b.geometry_key());
+	assert_ne!(a.geometry_key(), c.geometry_key());
+
+	// Changing shared geometry gives the mesh its own copy
+	b.geometry_mut().set_indices(None);
+	assert_ne!(a.geometry_key(), b.geometry_key());
+	assert!(a.geometry().is_indexed());
+	assert!(Rc::ptr_eq(&geometry, &a.shared_geometry()));
+}
@@ -0,0 +1,610 @@
+use byd::{
+	parse::{GltfDoc, NodeDoc},
+	AlphaMode, Color, Gltf, GltfError, Handle, PbrMaterial, Scene,
+};
+use cgmath::{
+	assert_relative_eq, Deg, Matrix4, Point2, Point3, Quaternion, Rotation3, SquareMatrix, Vector3,
+};
+use image::{ImageOutputFormat, Rgba, RgbaImage};
+use std::{fs, path::PathBuf};
+
+fn node(json: &str) -> NodeDoc {
+	serde_json::from_str(json).expect("Failed to parse node")
+}
+
+#[test]
+fn node_without_transform_is_identity() {
+	assert_eq!(node("{}").transform(), Matrix4::identity());
+}
+
+#[test]
+fn node_composes_translation_rotation_scale() {
+	let rotation = Quaternion::from_angle_y(Deg(90.0));
+	let doc = node(&format!(
+		r#"{{
+			"translation": [1.0, 2.0, 3.0],
+			"rotation": [{}, {}, {}, {}],
+			"scale": [2.0, 3.0, 4.0]
+		}}"#,
+		rotation.v.x, rotation.v.y, rotation.v.z, rotation.s
+	));
+
+	let expected = Matrix4::from_translation(Vector3::new(1.0, 2.0, 3.0))
+		* Matrix4::from(rotation)
+		* Matrix4::from_nonuniform_scale(2.0, 3.0, 4.0);
+	assert_relative_eq!(doc.transform(), expected, epsilon = 1e-6);
+}
+
+#[test]
+fn node_matrix_is_column_major() {
+	let doc = node(
+		r#"{
+			"matrix": [1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0, 5, 6, 7, 1],
+			"translation": [100.0, 100.0, 100.0]
+		}"#,
+	);
+
+	assert_eq!(
+		doc.transform(),
+		Matrix4::from_translation(Vector3::new(5.0, 6.0, 7.0))
+	);
+}
+
+#[test]
+fn load_keeps_node_tree() {
+	let duck = Gltf::load("./examples/loading/assets/duck/Duck.gltf").expect("Failed to load duck");
+
+	assert_eq!(duck.nodes.len(), 3);
+	let root = &duck.nodes[0];
+	assert_eq!(root.parent, None);
+	assert_eq!(root.children, vec![2, 1]);
+
+	let mesh_node = &duck.nodes[2];
+	assert_eq!(mesh_node.parent, Some(0));
+	assert_eq!(mesh_node.meshes, vec![0]);
+	assert_relative_eq!(
+		mesh_node.world_transform,
+		Matrix4::from_scale(0.01),
+		epsilon = 1e-6
+	);
+	assert_relative_eq!(duck.meshes[0].transform, mesh_node.world_transform);
+}
+
+#[test]
+fn add_to_scene_keeps_node_tree() {
+	let duck = Gltf::load("./examples/loading/assets/duck/Duck.gltf").expect("Failed to load duck");
+	let world_transform = duck.nodes[2].world_transform;
+	let mut scene = Scene::new();
+	let objects = duck.add_to_scene(&mut scene);
+
+	assert_eq!(objects.nodes.len(), 3);
+	assert_eq!(objects.roots, vec![objects.nodes[0]]);
+	assert_eq!(
+		scene.children(objects.nodes[0]),
+		&[objects.nodes[2], objects.nodes[1]]
+	);
+	assert_eq!(scene.parent(objects.meshes[0]), Some(objects.nodes[2]));
+	assert_relative_eq!(
+		scene.world_transform(objects.meshes[0]).unwrap(),
+		world_transform,
+		epsilon = 1e-6
+	);
+}
+
+fn load_nodes(name: &str, scenes: &str, nodes: &str) -> Result<Gltf, GltfError> {
+	let json = format!(
+		r#"{{ "asset": {{ "version": "2.0" }}, "scenes": {}, "nodes": {} }}"#,
+		scenes, nodes
+	);
+	Gltf::load(temp_file(name, json.as_bytes()).to_str().unwrap())
+}
+
+#[test]
+fn out_of_range_indices_are_errors() {
+	let missing_root = load_nodes("missing-root.gltf", r#"[{ "nodes": [1] }]"#, "[{}]");
+	assert!(matches!(missing_root, Err(GltfError::InvalidNode(1, _))));
+
+	let missing_child = load_nodes(
+		"missing-child.gltf",
+		r#"[{ "nodes": [0] }]"#,
+		r#"[{ "children": [5] }]"#,
+	);
+	assert!(matches!(missing_child, Err(GltfError::InvalidNode(0, _))));
+
+	let missing_mesh = load_nodes(
+		"missing-mesh.gltf",
+		r#"[{ "nodes": [0] }]"#,
+		r#"[{ "mesh": 2 }]"#,
+	);
+	assert!(matches!(missing_mesh, Err(GltfError::InvalidMesh(2, _))));
+}
+
+#[test]
+fn cyclic_node_trees_are_errors() {
+	let shared_child = load_nodes(
+		"shared-child.gltf",
+		r#"[{ "nodes": [0, 1] }]"#,
+		r#"[{ "children": [2] }, { "children": [2] }, {}]"#,
+	);
+	assert!(matches!(shared_child, Err(GltfError::InvalidNode(2, _))));
+
+	let cycle = load_nodes(
+		"cycle.gltf",
+		r#"[{ "nodes": [0] }]"#,
+		r#"[{ "children": [1] }, { "children": [0] }]"#,
+	);
+	assert!(matches!(cycle, Err(GltfError::InvalidNode(0, _))));
+}
+
+#[test]
+fn only_the_default_scene_is_loaded() {
+	let load = |name: &str, scene: &str| {
+		// The second scene's node has a mesh that doesn't exist
+		let json = format!(
+			r#"{{
+				"asset": {{ "version": "2.0" }},
+				{}
+				"scenes": [{{ "nodes": [0] }}, {{ "nodes": [1] }}],
+				"nodes": [{{ "translation": [1, 2, 3] }}, {{ "mesh": 2 }}]
+			}}"#,
+			scene
+		);
+		Gltf::load(temp_file(name, json.as_bytes()).to_str().unwrap())
+	};
+
+	let gltf = load("first-scene.gltf", "").unwrap();
+	assert_relative_eq!(
+		gltf.nodes[0].world_transform,
+		Matrix4::from_translation(Vector3::new(1.0, 2.0, 3.0))
+	);
+	assert!(matches!(
+		load("second-scene.gltf", r#""scene": 1,"#),
+		Err(GltfError::InvalidMesh(2, _))
+	));
+	assert!(matches!(
+		load("missing-scene.gltf", r#""scene": 2,"#),
+		Err(GltfError::InvalidScene(2, _))
+	));
+}
+
+/// One triangle: three `VEC3` positions followed by three `u16` indices, padded to 4 bytes.
+fn triangle_buffer() -> Vec<u8> {
+	let positions: [f32; 9] = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+	let indices: [u16; 4] = [0, 1, 2, 0];
+	let mut data: Vec<u8> = positions.iter().flat_map(|v| v.to_le_bytes()).collect();
+	data.extend(indices.iter().flat_map(|v| v.to_le_bytes()));
+	data
+}
+
+fn png() -> Vec<u8> {
+	let mut bytes = vec![];
+	image::DynamicImage::ImageRgba8(RgbaImage::from_pixel(2, 2, Rgba([255, 0, 0, 255])))
+		.write_to(&mut bytes, ImageOutputFormat::Png)
+		.expect("Failed to encode PNG");
+	bytes
+}
+
+fn triangle_json(buffer: &str, buffer_length: usize, image: &str) -> String {
+	format!(
+		r#"{{
+			"asset": {{ "version": "2.0" }},
+			"scene": 0,
+			"scenes": [{{ "nodes": [0] }}],
+			"nodes": [{{ "name": "Triangle", "mesh": 0 }}],
+			"meshes": [{{
+				"primitives": [{{ "attributes": {{ "POSITION": 0 }}, "indices": 1, "material": 0 }}]
+			}}],
+			"materials": [{{ "pbrMetallicRoughness": {{ "baseColorTexture": {{ "index": 0 }} }} }}],
+			"textures": [{{ "source": 0 }}],
+			"images": [{}],
+			"accessors": [
+				{{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" }},
+				{{ "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" }}
+			],
+			"bufferViews": [
+				{{ "buffer": 0, "byteOffset": 0, "byteLength": 36 }},
+				{{ "buffer": 0, "byteOffset": 36, "byteLength": 6 }},
+				{{ "buffer": 0, "byteOffset": 44, "byteLength": {} }}
+			],
+			"buffers": [{{ "byteLength": {}{} }}]
+		}}"#,
+		image,
+		buffer_length - 44,
+		buffer_length,
+		buffer
+	)
+}
+
+fn glb(json: &str, bin: &[u8]) -> Vec<u8> {
+	fn chunk(out: &mut Vec<u8>, ty: &[u8; 4], data: &[u8], pad: u8) {
+		let len = data.len().div_ceil(4) * 4;
+		out.extend((len as u32).to_le_bytes());
+		out.extend(ty);
+		out.extend(data);
+		out.resize(out.len() + len - data.len(), pad);
+	}
+
+	let mut chunks = vec![];
+	chunk(&mut chunks, b"JSON", json.as_bytes(), b' ');
+	chunk(&mut chunks, b"BIN\0", bin, 0);
+
+	let mut out = b"glTF".to_vec();
+	out.extend(2u32.to_le_bytes());
+	out.extend(((12 + chunks.len()) as u32).to_le_bytes());
+	out.extend(chunks);
+	out
+}
+
+fn temp_file(name: &str, contents: &[u8]) -> PathBuf {
+	let dir = std::env::temp_dir().join(format!("byd-gltf-{}", std::process::id()));
+	fs::create_dir_all(&dir).expect("Failed to create temp dir");
+	let path = dir.join(name);
+	fs::write(&path, contents).expect("Failed to write temp file");
+	path
+}
+
+fn assert_triangle(gltf: &Gltf) {
+	assert_eq!(gltf.meshes.len(), 1);
+	let geometry = gltf.meshes[0].geometry();
+	assert_eq!(geometry.vertex_count(), 3);
+	assert_eq!(geometry.index_count(), 3);
+	assert_eq!(gltf.textures.len(), 1);
+	assert_eq!(gltf.mesh_textures.get(&0), Some(&0));
+}
+
+#[test]
+fn load_glb_with_embedded_image() {
+	let mut bin = triangle_buffer();
+	bin.extend(png());
+	let json = triangle_json(
+		"",
+		bin.len(),
+		r#"{ "bufferView": 2, "mimeType": "image/png" }"#,
+	);
+
+	let path = temp_file("triangle.glb", &glb(&json, &bin));
+	let gltf = Gltf::load(path.to_str().unwrap()).expect("Failed to load GLB");
+
+	assert_triangle(&gltf);
+	assert_eq!(gltf.find_node("Triangle").unwrap().meshes, vec![0]);
+}
+
+#[test]
+fn load_gltf_with_data_uris() {
+	let bin = triangle_buffer();
+	let json = triangle_json(
+		&format!(
+			r#", "uri": "data:application/octet-stream;base64,{}""#,
+			base64::encode(&bin)
+		),
+		bin.len(),
+		&format!(
+			r#"{{ "uri": "data:image/png;base64,{}" }}"#,
+			base64::encode(png())
+		),
+	);
+
+	let path = temp_file("triangle.gltf", json.as_bytes());
+	let gltf = Gltf::load(path.to_str().unwrap()).expect("Failed to load glTF");
+
+	assert_triangle(&gltf);
+}
+
+#[test]
+fn glb_without_json_chunk_is_an_error() {
+	let mut bytes = b"glTF".to_vec();
+	bytes.extend(2u32.to_le_bytes());
+	bytes.extend(12u32.to_le_bytes());
+
+	assert!(matches!(
+		GltfDoc::from_slice(&bytes, "empty.glb"),
+		Err(GltfError::InvalidGlb(_))
+	));
+}
+
+#[test]
+fn buffer_shorter_than_declared_is_an_error() {
+	let json = r#"{
+		"asset": { "version": "2.0" },
+		"buffers": [{ "byteLength": 16, "uri": "data:application/octet-stream;base64,AAAA" }]
+	}"#;
+
+	assert!(matches!(
+		GltfDoc::from_slice(json.as_bytes(), "short.gltf"),
+		Err(GltfError::InvalidBuffer(_))
+	));
+}
+
+fn accessor_doc(buffer: &[u8], buffer_views: &str, accessors: &str) -> GltfDoc {
+	let json = format!(
+		r#"{{
+			"asset": {{ "version": "2.0" }},
+			"accessors": {},
+			"bufferViews": {},
+			"buffers": [{{
+				"byteLength": {},
+				"uri": "data:application/octet-stream;base64,{}"
+			}}]
+		}}"#,
+		accessors,
+		buffer_views,
+		buffer.len(),
+		base64::encode(buffer)
+	);
+	GltfDoc::from_slice(json.as_bytes(), "accessors.gltf").expect("Failed to parse glTF")
+}
+
+#[test]
+fn accessor_honours_byte_stride() {
+	// Interleaved VEC3 position and VEC2 texcoord
+	let data: Vec<u8> = [1.0f32, 2.0, 3.0, 0.25, 0.5, 4.0, 5.0, 6.0, 0.75, 1.0]
+		.iter()
+		.flat_map(|v| v.to_le_bytes())
+		.collect();
+	let doc = accessor_doc(
+		&data,
+		r#"[{ "buffer": 0, "byteLength": 40, "byteStride": 20 }]"#,
+		r#"[
+			{ "bufferView": 0, "componentType": 5126, "count": 2, "type": "VEC3" },
+			{ "bufferView": 0, "byteOffset": 12, "componentType": 5126, "count": 2, "type": "VEC2" }
+		]"#,
+	);
+
+	let positions: Vec<Point3<f32>> = doc.accessor(0).unwrap();
+	assert_eq!(
+		positions,
+		vec![Point3::new(1.0, 2.0, 3.0), Point3::new(4.0, 5.0, 6.0)]
+	);
+	let texcoords: Vec<Point2<f32>> = doc.accessor(1).unwrap();
+	assert_eq!(
+		texcoords,
+		vec![Point2::new(0.25, 0.5), Point2::new(0.75, 1.0)]
+	);
+}
+
+#[test]
+fn accessor_converts_normalized_integers() {
+	let mut data = vec![0u8, 255, 51, 0];
+	data.extend([0u16, 65535].iter().flat_map(|v| v.to_le_bytes()));
+	data.extend([-128i8 as u8, 127, 0, 0]);
+	let doc = accessor_doc(
+		&data,
+		r#"[{ "buffer": 0, "byteLength": 12 }]"#,
+		r#"[
+			{ "bufferView": 0, "componentType": 5121, "normalized": true, "count": 1, "type": "VEC3" },
+			{ "bufferView": 0, "byteOffset": 4, "componentType": 5123, "normalized": true, "count": 1, "type": "VEC2" },
+			{ "bufferView": 0, "byteOffset": 8, "componentType": 5120, "normalized": true, "count": 2, "type": "SCALAR" },
+			{ "bufferView": 0, "componentType": 5121, "count": 3, "type": "SCALAR" }
+		]"#,
+	);
+
+	let a: Vec<Vector3<f32>> = doc.accessor(0).unwrap();
+	assert_relative_eq!(a[0], Vector3::new(0.0, 1.0, 0.2));
+	let b: Vec<Point2<f32>> = doc.accessor(1).unwrap();
+	assert_eq!(b, vec![Point2::new(0.0, 1.0)]);
+	let c: Vec<f32> = doc.accessor(2).unwrap();
+	assert_eq!(c, vec![-1.0, 1.0]);
+	let d: Vec<f32> = doc.accessor(3).unwrap();
+	assert_eq!(d, vec![0.0, 255.0, 51.0]);
+}
+
+#[test]
+fn indices_support_all_unsigned_types() {
+	let mut data = vec![1u8, 2, 3, 0];
+	data.extend([4u16, 5].iter().flat_map(|v| v.to_le_bytes()));
+	data.extend([70000u32, 6].iter().flat_map(|v| v.to_le_bytes()));
+	let doc = accessor_doc(
+		&data,
+		r#"[{ "buffer": 0, "byteLength": 16 }]"#,
+		r#"[
+			{ "bufferView": 0, "componentType": 5121, "count": 3, "type": "SCALAR" },
+			{ "bufferView": 0, "byteOffset": 4, "componentType": 5123, "count": 2, "type": "SCALAR" },
+			{ "bufferView": 0, "byteOffset": 8, "componentType": 5125, "count": 2, "type": "SCALAR" },
+			{ "bufferView": 0, "componentType": 5126, "count": 1, "type": "SCALAR" }
+		]"#,
+	);
+
+	assert_eq!(doc.indices(0).unwrap(), vec![1, 2, 3]);
+	assert_eq!(doc.indices(1).unwrap(), vec![4, 5]);
+	assert_eq!(doc.indices(2).unwrap(), vec![70000, 6]);
+	assert!(matches!(
+		doc.indices(3),
+		Err(GltfError::InvalidAccessor(3, _))
+	));
+}
+
+#[test]
+fn sparse_accessor_replaces_values() {
+	let mut data: Vec<u8> = (0..4).flat_map(|v| (v as f32).to_le_bytes()).collect();
+	data.extend([3u16, 1].iter().flat_map(|v| v.to_le_bytes()));
+	data.extend([30.0f32, 10.0].iter().flat_map(|v| v.to_le_bytes()));
+	let sparse = r#""sparse": {
+		"count": 2,
+		"indices": { "bufferView": 1, "componentType": 5123 },
+		"values": { "bufferView": 2 }
+	}"#;
+	let doc = accessor_doc(
+		&data,
+		r#"[
+			{ "buffer": 0, "byteLength": 16 },
+			{ "buffer": 0, "byteOffset": 16, "byteLength": 4 },
+			{ "buffer": 0, "byteOffset": 20, "byteLength": 8 }
+		]"#,
+		&format!(
+			r#"[
+				{{ "bufferView": 0, "componentType": 5126, "count": 4, "type": "SCALAR", {} }},
+				{{ "componentType": 5126, "count": 4, "type": "SCALAR", {} }}
+			]"#,
+			sparse, sparse
+		),
+	);
+
+	let dense: Vec<f32> = doc.accessor(0).unwrap();
+	assert_eq!(dense, vec![0.0, 10.0, 2.0, 30.0]);
+	let zeroed: Vec<f32> = doc.accessor(1).unwrap();
+	assert_eq!(zeroed, vec![0.0, 10.0, 0.0, 30.0]);
+}
+
+#[test]
+fn malformed_accessors_are_errors() {
+	let data = [0u8; 12];
+	let doc = accessor_doc(
+		&data,
+		r#"[{ "buffer": 0, "byteLength": 12 }]"#,
+		r#"[
+			{ "bufferView": 0, "componentType": 5126, "count": 2, "type": "VEC3" },
+			{ "bufferView": 0, "componentType": 5126, "count": 1, "type": "VEC2" },
+			{ "bufferView": 0, "componentType": 1234, "count": 1, "type": "VEC3" }
+		]"#,
+	);
+
+	let out_of_range: Result<Vec<Point3<f32>>, _> = doc.accessor(0);
+	assert!(matches!(
+		out_of_range,
+		Err(GltfError::InvalidAccessor(0, _))
+	));
+	let wrong_type: Result<Vec<Point3<f32>>, _> = doc.accessor(1);
+	assert!(matches!(wrong_type, Err(GltfError::InvalidAccessor(1, _))));
+	let bad_component: Result<Vec<Point3<f32>>, _> = doc.accessor(2);
+	assert!(matches!(
+		bad_component,
+		Err(GltfError::InvalidAccessor(2, _))
+	));
+	let missing: Result<Vec<f32>, _> = doc.accessor(3);
+	assert!(matches!(missing, Err(GltfError::InvalidAccessor(3, _))));
+}
+
+#[test]
+fn overflowing_buffer_views_are_errors() {
+	let data = [0u8; 12];
+	let doc = accessor_doc(
+		&data,
+		r#"[{ "buffer": 0, "byteOffset": 8, "byteLength": 18446744073709551615 }]"#,
+		"[]",
+	);
+	assert!(matches!(
+		doc.buffer_view(0),
+		Err(GltfError::InvalidBuffer(_))
+	));
+}
+
+#[test]
+fn huge_accessor_counts_are_errors() {
+	let data = [0u8; 12];
+	let doc = accessor_doc(
+		&data,
+		r#"[{ "buffer": 0, "byteLength": 12, "byteStride": 12 }]"#,
+		r#"[
+			{ "bufferView": 0, "componentType": 5126, "count": 4611686018427387904, "type": "VEC3" },
+			{ "bufferView": 0, "byteOffset": 18446744073709551615, "componentType": 5126, "count": 1, "type": "VEC3" },
+			{ "componentType": 5126, "count": 4611686018427387904, "type": "VEC3" }
+		]"#,
+	);
+
+	for index in 0..3 {
+		let values: Result<Vec<Point3<f32>>, _> = doc.accessor(index);
+		assert!(matches!(values, Err(GltfError::InvalidAccessor(i, _)) if i == index as usize));
+	}
+}
+
+#[test]
+fn materials_are_imported() {
+	let json = r#"{
+		"asset": { "version": "2.0" },
+		"materials": [
+			{
+				"pbrMetallicRoughness": {
+					"baseColorFactor": [0.5, 0.25, 1.0, 0.75],
+					"baseColorTexture": { "index": 0 },
+					"metallicFactor": 0.2,
+					"roughnessFactor": 0.6,
+					"metallicRoughnessTexture": { "index": 1 }
+				},
+				"normalTexture": { "index": 1, "scale": 0.5 },
+				"occlusionTexture": { "index": 1, "strength": 0.25 },
+				"emissiveTexture": { "index": 0 },
+				"emissiveFactor": [1.0, 0.5, 0.0],
+				"alphaMode": "MASK",
+				"alphaCutoff": 0.3,
+				"doubleSided": true
+			},
+			{}
+		],
+		"textures": [{ "source": 1 }, { "source": 0 }],
+		"images": [{ "uri": "a.png" }, { "uri": "b.png" }]
+	}"#;
+	let doc = GltfDoc::from_slice(json.as_bytes(), "materials.gltf").unwrap();
+	let materials = byd::gltf::load_materials(&doc).unwrap();
+
+	let material = &materials[0];
+	assert_eq!(material.base_color, Color::new(0.5, 0.25, 1.0, 0.75));
+	assert_eq!(material.base_color_texture, Some(Handle::from_index(1)));
+	assert_eq!(material.metallic, 0.2);
+	assert_eq!(material.roughness, 0.6);
+	assert_eq!(
+		material.metallic_roughness_texture,
+		Some(Handle::from_index(0))
+	);
+	assert_eq!(material.normal_texture, Some(Handle::from_index(0)));
+	assert_eq!(material.normal_scale, 0.5);
+	assert_eq!(material.occlusion_texture, Some(Handle::from_index(0)));
+	assert_eq!(material.occlusion_strength, 0.25);
+	assert_eq!(material.emissive, Vector3::new(1.0, 0.5, 0.0));
+	assert_eq!(material.emissive_texture, Some(Handle::from_index(1)));
+	assert_eq!(material.alpha_mode, AlphaMode::Mask);
+	assert_eq!(material.alpha_cutoff, 0.3);
+	assert!(material.double_sided);
+
+	// Defaults from the glTF spec
+	let default = &materials[1];
+	let expected = PbrMaterial::default();
+	assert_eq!(default.base_color, expected.base_color);
+	assert_eq!(default.base_color_texture, None);
+	assert_eq!(default.metallic, 1.0);
+	assert_eq!(default.roughness, 1.0);
+	assert_eq!(default.alpha_mode, AlphaMode::Opaque);
+	assert_eq!(default.alpha_cutoff, 0.5);
+	assert!(!default.double_sided);
+}
+
+#[test]
+fn primitives_use_their_material() {
+	let mut bin = triangle_buffer();
+	bin.extend(png());
+	let json = triangle_json("", bin.len(), r#"{ "bufferView": 2 }"#).replace(
+		r#""materials": [{ "pbrMetallicRoughness": { "baseColorTexture": { "index": 0 } } }]"#,
+		r#""materials": [{
+			"pbrMetallicRoughness": { "baseColorFactor": [1, 0, 0, 1] },
+			"normalTexture": { "index": 0 },
+			"alphaMode": "BLEND"
+		}]"#,
+	);
+
+	let path = temp_file("material.glb", &glb(&json, &bin));
+	let gltf = Gltf::load(path.to_str().unwrap()).expect("Failed to load GLB");
+
+	let material = gltf.meshes[0]
+		.material
+		.downcast_ref::<PbrMaterial>()
+		.expect("Mesh should have a PbrMaterial");
+	assert_eq!(material.base_color, Color::new(1.0, 0.0, 0.0, 1.0));
+	assert_eq!(material.normal_texture, Some(Handle::from_index(0)));
+	assert_eq!(material.alpha_mode, AlphaMode::Blend);
+	// Normal maps hold linear data
+	assert!(!gltf.textures[0].is_srgb());
+	assert!(gltf.mesh_textures.is_empty());
+}
+
+#[test]
+fn unknown_material_texture_is_an_error() {
+	let json = r#"{
+		"asset": { "version": "2.0" },
+		"materials": [{ "emissiveTexture": { "index": 3 } }]
+	}"#;
+	let doc = GltfDoc::from_slice(json.as_bytes(), "materials.gltf").unwrap();
+
+	assert!(matches!(
+		byd::gltf::load_materials(&doc),
+		Err(GltfError::InvalidMaterial(0, _))
+	));
+}
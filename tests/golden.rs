@@ -0,0 +1,892 @@
+mod common;
+
+use byd::{
+	AlphaMode, BasicMaterial, Camera, Color, CustomMaterial, DebugNormals, Geometry, Indices,
+	Instance, InstancedMesh, Light, Mesh, OrthographicCamera, PbrMaterial, PostEffect, Renderer,
+	RendererError, Scene, SimpleProgram, SimpleVertex, TargetFormat, Texture, TextureMaterial,
+	Tonemapping, Vertex, Viewport,
+};
+use byd_derive::CastBytes;
+use cgmath::{Deg, Euler, Matrix4, Point3, Rad, Vector3};
+use common::*;
+use futures::executor::block_on;
+use image::{DynamicImage, Rgba, RgbaImage};
+use std::{f32::consts::PI, mem::size_of, rc::Rc};
+use wgpu::VertexFormat::{Float32x3, Float32x4};
+
+#[derive(Copy, Clone, Debug, CastBytes)]
+#[repr(C)]
+struct ColorVertex {
+	position: Point3<f32>,
+	color: Color,
+}
+
+impl Vertex for ColorVertex {
+	fn buffer_layout<'a>() -> wgpu::VertexBufferLayout<'a> {
+		wgpu::VertexBufferLayout {
+			array_stride: size_of::<Self>() as _,
+			step_mode: wgpu::VertexStepMode::Vertex,
+			attributes: &[
+				wgpu::VertexAttribute {
+					offset: 0,
+					shader_location: 0,
+					format: Float32x3,
+				},
+				wgpu::VertexAttribute {
+					offset: size_of::<Point3<f32>>() as _,
+					shader_location: 1,
+					format: Float32x4,
+				},
+			],
+		}
+	}
+}
+
+impl From<&[f32; 3]> for ColorVertex {
+	fn from(position: &[f32; 3]) -> Self {
+		Self {
+			position: Point3::new(position[0], position[1], position[2]),
+			color: Color::new(
+				position[0] * 0.5 + 0.5,
+				position[1] * 0.5 + 0.5,
+				position[2] * 0.5 + 0.5,
+				1.0,
+			),
+		}
+	}
+}
+
+fn cube_transform(x: f32) -> Matrix4<f32> {
+	Matrix4::from_translation(Vector3::new(x, 0.0, 8.0))
+		* Matrix4::from(Euler::new(Rad(0.5), Rad(0.7), Rad(0.2)))
+}
+
+fn shaded_cube() -> Geometry<SimpleVertex> {
+	let mut geometry: Geometry<SimpleVertex> = Geometry::cube();
+	for tri in geometry.vertices_mut().chunks_mut(3) {
+		let normal = (tri[1].position - tri[0].position).cross(tri[2].position - tri[0].position);
+		tri[0].normal = normal;
+		tri[1].normal = normal;
+		tri[2].normal = normal;
+	}
+	geometry
+}
+
+fn checker() -> Texture {
+	let pixels = RgbaImage::from_fn(16, 16, |x, y| {
+		if (x / 4 + y / 4) % 2 == 0 {
+			Rgba([255, 255, 255, 255])
+		} else {
+			Rgba([40, 80, 200, 255])
+		}
+	});
+	Texture::from_image(DynamicImage::ImageRgba8(pixels))
+}
+
+#[test]
+#[ignore = "needs a GPU adapter, run with --ignored"]
+fn primitive_pipeline() {
+	let mut renderer = renderer();
+	let mut scene = Scene::new();
+
+	let mut cube = Mesh::new(
+		shaded_cube(),
+		BasicMaterial::new(Color::new(0.9, 0.3, 0.1, 1.0)),
+	);
+	cube.transform = cube_transform(0.0);
+	scene.add(cube);
+
+	let frame = render(&mut renderer, &mut scene, &camera());
+	assert_golden("primitive_pipeline", &frame);
+}
+
+#[test]
+#[ignore = "needs a GPU adapter, run with --ignored"]
+fn line_pipeline() {
+	let mut renderer = renderer();
+	let mut scene = Scene::new();
+
+	let geometry = shaded_cube();
+	let mut normals = DebugNormals::new();
+	normals.set_vertices(geometry.vertices());
+	normals.transform = cube_transform(0.0);
+	scene.add(normals);
+
+	let frame = render(&mut renderer, &mut scene, &camera());
+	assert_golden("line_pipeline", &frame);
+}
+
+#[test]
+#[ignore = "needs a GPU adapter, run with --ignored"]
+fn simple_program() {
+	let mut renderer = renderer();
+	let mut scene = Scene::new();
+
+	let program: SimpleProgram<ColorVertex> =
+		SimpleProgram::new().shader(include_str!("./shaders/color.wgsl"));
+	let program_id = scene.add_program(program);
+	let mut cube: Mesh<ColorVertex> = Mesh::new(Geometry::cube(), CustomMaterial::new(program_id));
+	cube.transform = cube_transform(0.0);
+	scene.add(cube);
+
+	let frame = render(&mut renderer, &mut scene, &camera());
+	assert_golden("simple_program", &frame);
+}
+
+#[test]
+#[ignore = "needs a GPU adapter, run with --ignored"]
+fn texture_material() {
+	let mut renderer = renderer();
+	let mut scene = Scene::new();
+
+	let texture_id = scene.add_texture(checker());
+	let mut cube = Mesh::new(shaded_cube(), TextureMaterial::new(texture_id));
+	cube.transform = cube_transform(0.0);
+	scene.add(cube);
+
+	let frame = render(&mut renderer, &mut scene, &camera());
+	assert_golden("texture_material", &frame);
+}
+
+#[test]
+#[ignore = "needs a GPU adapter, run with --ignored"]
+fn pbr_material() {
+	let mut renderer = renderer();
+	let mut scene = Scene::new();
+
+	let texture_id = scene.add_texture(checker());
+	let mut rough = Mesh::new(
+		shaded_cube(),
+		PbrMaterial {
+			base_color_texture: Some(texture_id),
+			metallic: 0.0,
+			roughness: 0.8,
+			..PbrMaterial::default()
+		},
+	);
+	rough.transform = cube_transform(-2.5) * Matrix4::from_scale(0.8);
+	scene.add(rough);
+
+	let mut shiny = Mesh::new(
+		shaded_cube(),
+		PbrMaterial {
+			metallic: 1.0,
+			roughness: 0.3,
+			emissive: Vector3::new(0.1, 0.0, 0.0),
+			..PbrMaterial::new(Color::new(0.9, 0.6, 0.2, 1.0))
+		},
+	);
+	shiny.transform = cube_transform(2.5) * Matrix4::from_scale(0.8);
+	scene.add(shiny);
+
+	let frame = render(&mut renderer, &mut scene, &camera());
+	assert_golden("pbr_material", &frame);
+}
+
+#[test]
+#[ignore = "needs a GPU adapter, run with --ignored"]
+fn multiple_lights() {
+	let mut renderer = renderer();
+	let mut scene = Scene::new();
+	scene.set_ambient_light(Vector3::new(0.05, 0.05, 0.05));
+
+	scene.add_light(Light::directional(
+		Vector3::new(0.0, -1.0, 1.0),
+		Vector3::new(1.0, 1.0, 1.0),
+		0.5,
+	));
+	scene.add_light(
+		Light::point(
+			Point3::new(-3.0, 2.0, 2.0),
+			Vector3::new(1.0, 0.2, 0.2),
+			30.0,
+		)
+		.with_range(8.0),
+	);
+	scene.add_light(Light::spot(
+		Point3::new(3.0, 0.0, 0.0),
+		Vector3::new(-0.2, 0.0, 1.0),
+		Deg(25.0),
+		Vector3::new(0.2, 0.4, 1.0),
+		60.0,
+	));
+
+	for x in [-2.5, 0.0, 2.5] {
+		let mut cube = Mesh::new(
+			shaded_cube(),
+			PbrMaterial {
+				metallic: 0.2,
+				roughness: 0.5,
+				..PbrMaterial::new(Color::new(0.8, 0.8, 0.8, 1.0))
+			},
+		);
+		cube.transform = cube_transform(x) * Matrix4::from_scale(0.8);
+		scene.add(cube);
+	}
+
+	let frame = render(&mut renderer, &mut scene, &camera());
+	assert_golden("multiple_lights", &frame);
+}
+
+#[test]
+#[ignore = "needs a GPU adapter, run with --ignored"]
+fn shadows() {
+	let mut renderer = renderer();
+	let mut scene = Scene::new();
+	scene.add_light(
+		Light::directional(
+			Vector3::new(0.4, -1.0, 0.3),
+			Vector3::new(1.0, 1.0, 1.0),
+			2.5,
+		)
+		.with_shadows(),
+	);
+
+	let mut ground = Mesh::new(
+		shaded_cube(),
+		BasicMaterial::new(Color::new(0.8, 0.8, 0.8, 1.0)),
+	);
+	ground.transform = Matrix4::from_translation(Vector3::new(0.0, -2.0, 10.0))
+		* Matrix4::from_nonuniform_scale(8.0, 0.2, 8.0);
+	scene.add(ground);
+
+	let mut caster = Mesh::new(
+		shaded_cube(),
+		BasicMaterial::new(Color::new(0.9, 0.3, 0.2, 1.0)),
+	);
+	caster.transform = cube_transform(-1.5) * Matrix4::from_scale(0.7);
+	scene.add(caster);
+
+	// Neither casts nor receives shadows
+	let mut ghost = Mesh::new(
+		shaded_cube(),
+		BasicMaterial::new(Color::new(0.2, 0.5, 0.9, 1.0)),
+	);
+	ghost.transform = cube_transform(1.5) * Matrix4::from_scale(0.7);
+	ghost.cast_shadows = false;
+	ghost.receive_shadows = false;
+	scene.add(ghost);
+
+	let frame = render(&mut renderer, &mut scene, &camera());
+	assert_golden("shadows", &frame);
+}
+
+#[test]
+#[ignore = "needs a GPU adapter, run with --ignored"]
+fn many_objects() {
+	let mut renderer = renderer();
+	let mut scene = Scene::new();
+
+	// Churn through more objects than used to fit in the uniform buffers
+	let mut ids = vec![];
+	for i in 0..3000 {
+		let mut cube = Mesh::new(
+			shaded_cube(),
+			BasicMaterial::new(Color::new(0.2, 0.8, 0.3, 1.0)),
+		);
+		cube.transform = cube_transform((i % 5) as f32 - 2.0) * Matrix4::from_scale(0.3);
+		ids.push(scene.add(cube));
+	}
+	render(&mut renderer, &mut scene, &camera());
+	for id in ids.drain(..2995) {
+		scene.remove(id);
+	}
+	render(&mut renderer, &mut scene, &camera());
+
+	let mut cube = Mesh::new(
+		shaded_cube(),
+		BasicMaterial::new(Color::new(0.9, 0.3, 0.2, 1.0)),
+	);
+	cube.transform = cube_transform(0.0) * Matrix4::from_scale(0.6);
+	scene.add(cube);
+
+	let frame = render(&mut renderer, &mut scene, &camera());
+	assert_golden("many_objects", &frame);
+}
+
+#[test]
+#[ignore = "needs a GPU adapter, run with --ignored"]
+fn batched_draws() {
+	let mut renderer = renderer();
+	let mut scene = Scene::new();
+
+	// Alternate between two textures, so the draws only share state once they're sorted
+	let textures = [scene.add_texture(checker()), scene.add_texture(checker())];
+	let geometry = Rc::new(shaded_cube());
+	for i in 0..6 {
+		let mut cube = Mesh::new(geometry.clone(), TextureMaterial::new(textures[i % 2]));
+		cube.transform = cube_transform(i as f32 - 2.5) * Matrix4::from_scale(0.4);
+		scene.add(cube);
+	}
+
+	let frame = render(&mut renderer, &mut scene, &camera());
+	let stats = scene.render_stats();
+	assert_eq!(stats.draw_calls, 6);
+	assert_eq!(stats.pipeline_changes, 1);
+	// Each object's uniforms, plus one change per texture
+	assert_eq!(stats.bind_group_changes, 6 + 2);
+	// The cubes share one geometry, so its buffer is only bound once
+	assert_eq!(stats.buffer_changes, 1);
+	assert_golden("batched_draws", &frame);
+}
+
+fn instances() -> Vec<Instance> {
+	(0..5)
+		.map(|i| {
+			let x = i as f32 - 2.0;
+			Instance::new(cube_transform(x) * Matrix4::from_scale(0.3)).with_color(Color::new(
+				1.0 - i as f32 * 0.2,
+				0.5,
+				i as f32 * 0.2,
+				1.0,
+			))
+		})
+		.collect()
+}
+
+#[test]
+#[ignore = "needs a GPU adapter, run with --ignored"]
+fn instanced_mesh() {
+	let mut renderer = renderer();
+	let mut scene = Scene::new();
+
+	let mut cubes = InstancedMesh::new(
+		shaded_cube(),
+		BasicMaterial::new(Color::new(1.0, 1.0, 1.0, 1.0)),
+		instances(),
+	);
+	cubes.transform = Matrix4::from_translation(Vector3::new(0.0, 0.5, 0.0));
+	scene.add(cubes);
+
+	let frame = render(&mut renderer, &mut scene, &camera());
+	assert_golden("instanced_mesh", &frame);
+}
+
+#[test]
+#[ignore = "needs a GPU adapter, run with --ignored"]
+fn instanced_program() {
+	let mut renderer = renderer();
+	let mut scene = Scene::new();
+
+	let program: SimpleProgram<ColorVertex> = SimpleProgram::new()
+		.shader(include_str!("./shaders/color.wgsl"))
+		.instanced();
+	let program_id = scene.add_program(program);
+	let cubes: InstancedMesh<ColorVertex> = InstancedMesh::new(
+		Geometry::cube(),
+		CustomMaterial::new(program_id),
+		instances(),
+	);
+	let cubes = scene.add(cubes);
+
+	let frame = render(&mut renderer, &mut scene, &camera());
+	assert_golden("instanced_program", &frame);
+
+	// Programs without an instanced variant skip instanced meshes rather than failing
+	scene.remove(cubes);
+	let program: SimpleProgram<ColorVertex> =
+		SimpleProgram::new().shader(include_str!("./shaders/color.wgsl"));
+	let program_id = scene.add_program(program);
+	scene.add(InstancedMesh::<ColorVertex>::new(
+		Geometry::cube(),
+		CustomMaterial::new(program_id),
+		instances(),
+	));
+	let frame = render(&mut renderer, &mut scene, &camera());
+	let background = *frame.get_pixel(0, 0);
+	assert!(frame.pixels().all(|pixel| *pixel == background));
+}
+
+#[test]
+#[ignore = "needs a GPU adapter, run with --ignored"]
+fn frustum_culling() {
+	let mut renderer = renderer();
+	let mut scene = Scene::new();
+
+	// One cube in view, and three behind or beside the camera
+	for position in [
+		Vector3::new(0.0, 0.0, 8.0),
+		Vector3::new(0.0, 0.0, -8.0),
+		Vector3::new(40.0, 0.0, 8.0),
+		Vector3::new(0.0, -40.0, 8.0),
+	] {
+		let mut cube = Mesh::new(
+			shaded_cube(),
+			BasicMaterial::new(Color::new(0.9, 0.3, 0.1, 1.0)),
+		);
+		cube.transform = Matrix4::from_translation(position);
+		scene.add(cube);
+	}
+
+	let frame = render(&mut renderer, &mut scene, &camera());
+	assert_eq!(scene.culled_objects(), 3);
+	assert_golden("frustum_culling", &frame);
+}
+
+#[test]
+#[ignore = "needs a GPU adapter, run with --ignored"]
+fn alpha_blending() {
+	let mut renderer = renderer();
+	let mut scene = Scene::new();
+
+	let mut wall = Mesh::new(
+		shaded_cube(),
+		BasicMaterial::new(Color::new(0.8, 0.8, 0.8, 1.0)),
+	);
+	wall.transform =
+		Matrix4::from_translation(Vector3::new(0.0, 0.0, 14.0)) * Matrix4::from_scale(4.0);
+	scene.add(wall);
+
+	// Added nearest first, so they're only drawn correctly if they're sorted
+	for (x, z, color) in [
+		(-0.5, 5.0, Color::new(0.9, 0.2, 0.2, 0.5)),
+		(0.5, 8.0, Color::new(0.2, 0.3, 0.9, 0.5)),
+	] {
+		let mut cube = Mesh::new(
+			shaded_cube(),
+			BasicMaterial {
+				alpha_mode: AlphaMode::Blend,
+				..BasicMaterial::new(color)
+			},
+		);
+		cube.transform = Matrix4::from_translation(Vector3::new(x, 0.0, z));
+		scene.add(cube);
+	}
+
+	// Checker with transparent squares, which should be cut out
+	let pixels = RgbaImage::from_fn(16, 16, |x, y| {
+		if (x / 4 + y / 4) % 2 == 0 {
+			Rgba([255, 255, 255, 255])
+		} else {
+			Rgba([0, 0, 0, 0])
+		}
+	});
+	let texture_id = scene.add_texture(Texture::from_image(DynamicImage::ImageRgba8(pixels)));
+	let mut cube = Mesh::new(
+		shaded_cube(),
+		TextureMaterial {
+			alpha_mode: AlphaMode::Mask,
+			..TextureMaterial::new(texture_id)
+		},
+	);
+	cube.transform = Matrix4::from_translation(Vector3::new(-3.0, 1.5, 8.0));
+	scene.add(cube);
+
+	let frame = render(&mut renderer, &mut scene, &camera());
+	assert_golden("alpha_blending", &frame);
+}
+
+#[test]
+#[ignore = "needs a GPU adapter, run with --ignored"]
+fn indexed_geometry() {
+	let mut renderer = renderer();
+	let mut scene = Scene::new();
+
+	let corners = [
+		[-1.0, -1.0, 0.0],
+		[-1.0, 1.0, 0.0],
+		[1.0, -1.0, 0.0],
+		[1.0, 1.0, 0.0],
+	];
+	let vertices = corners
+		.iter()
+		.map(|corner| SimpleVertex {
+			normal: Vector3::new(0.0, 0.0, -1.0),
+			..SimpleVertex::from(corner)
+		})
+		.collect();
+	let indices: Vec<u16> = vec![0, 1, 2, 3, 2, 1];
+	let mut quad = Mesh::new(
+		Geometry::indexed(vertices, indices),
+		BasicMaterial::new(Color::new(0.2, 0.8, 0.3, 1.0)),
+	);
+	quad.transform = cube_transform(0.0);
+	let id = scene.add(quad);
+
+	let frame = render(&mut renderer, &mut scene, &camera());
+	assert_golden("indexed_geometry", &frame);
+
+	// Changing the indices rebuilds the buffers before the next frame, leaving one triangle
+	scene.with_object_mut(id, |quad: &mut Mesh<SimpleVertex>| {
+		quad.geometry_mut()
+			.set_indices(Some(Indices::new(vec![0, 1, 2], 4)));
+	});
+	let triangle = render(&mut renderer, &mut scene, &camera());
+	let background = *frame.get_pixel(0, 0);
+	let drawn = |frame: &RgbaImage| frame.pixels().filter(|&&pixel| pixel != background).count();
+	assert!(drawn(&triangle) > 0);
+	assert!(drawn(&triangle) < drawn(&frame));
+}
+
+#[test]
+#[ignore = "needs a GPU adapter, run with --ignored"]
+fn pick_object() {
+	let mut renderer = renderer();
+	let mut scene = Scene::new();
+
+	let mut near = Mesh::new(
+		shaded_cube(),
+		BasicMaterial::new(Color::new(0.9, 0.3, 0.1, 1.0)),
+	);
+	near.transform = cube_transform(0.0);
+	let near = scene.add(near);
+	let mut far = Mesh::new(
+		shaded_cube(),
+		BasicMaterial::new(Color::new(0.2, 0.5, 0.9, 1.0)),
+	);
+	far.transform = Matrix4::from_translation(Vector3::new(0.0, 0.0, 20.0));
+	scene.add(far);
+
+	let camera = camera();
+	render(&mut renderer, &mut scene, &camera);
+	let (x, y) = (WIDTH as f32 / 2.0, HEIGHT as f32 / 2.0);
+	let picked = renderer.pick(&mut scene, &camera, x, y).unwrap();
+	assert_eq!(picked, Some(near));
+	assert_eq!(renderer.pick(&mut scene, &camera, 1.0, 1.0).unwrap(), None);
+	assert_eq!(renderer.pick(&mut scene, &camera, -1.0, y).unwrap(), None);
+}
+
+#[test]
+#[ignore = "needs a GPU adapter, run with --ignored"]
+fn multisampling() {
+	let mut renderer = renderer();
+	assert!(renderer.set_sample_count(3).is_err());
+	// Render passes only accept 1 or 4 samples, whatever the adapter supports
+	assert!(renderer.set_sample_count(2).is_err());
+	assert!(renderer.set_sample_count(8).is_err());
+	assert_eq!(renderer.sample_count(), 1);
+
+	let mut scene = Scene::new();
+	let mut cube = Mesh::new(
+		shaded_cube(),
+		BasicMaterial::new(Color::new(0.9, 0.3, 0.1, 1.0)),
+	);
+	cube.transform = cube_transform(0.0) * Matrix4::from_angle_z(Deg(30.0));
+	let cube = scene.add(cube);
+	let camera = camera();
+
+	// Edges blend the cube into the background, so there are more distinct colors
+	let colors = |frame: &RgbaImage| {
+		let mut colors: Vec<_> = frame.pixels().map(|pixel| pixel.0).collect();
+		colors.sort_unstable();
+		colors.dedup();
+		colors.len()
+	};
+	let aliased = render(&mut renderer, &mut scene, &camera);
+	// The GL backend only draws with 1 sample
+	if !renderer.supports_sample_count(4) {
+		assert!(renderer.set_sample_count(4).is_err());
+		return;
+	}
+	renderer.set_sample_count(4).unwrap();
+	let frame = render(&mut renderer, &mut scene, &camera);
+	assert!(colors(&frame) > colors(&aliased));
+
+	// Picking draws without multisampling
+	let (x, y) = (WIDTH as f32 / 2.0, HEIGHT as f32 / 2.0);
+	assert_eq!(
+		renderer.pick(&mut scene, &camera, x, y).unwrap(),
+		Some(cube)
+	);
+}
+
+#[test]
+#[ignore = "needs a GPU adapter, run with --ignored"]
+fn builder_reports_unsupported_requests() {
+	let builder = || Renderer::builder(WIDTH, HEIGHT).fallback_adapter(true);
+
+	match block_on(builder().features(wgpu::Features::all()).build()) {
+		Err(RendererError::MissingFeatures(_, missing)) => assert!(!missing.is_empty()),
+		other => panic!("Expected missing features, got {:?}", other.err()),
+	}
+
+	let limits = wgpu::Limits {
+		max_texture_dimension_2d: u32::MAX,
+		..wgpu::Limits::downlevel_defaults()
+	};
+	match block_on(builder().limits(limits).build()) {
+		Err(RendererError::UnsupportedLimits(_, limits)) => {
+			assert_eq!(limits, "max_texture_dimension_2d")
+		}
+		other => panic!("Expected unsupported limits, got {:?}", other.err()),
+	}
+
+	// Snorm formats can only be rendered to with adapter specific format features
+	match block_on(
+		builder()
+			.screen_format(wgpu::TextureFormat::Rgba8Snorm)
+			.build(),
+	) {
+		Err(RendererError::UnsupportedFormat(_, wgpu::TextureFormat::Rgba8Snorm)) => {}
+		other => panic!("Expected unsupported format, got {:?}", other.err()),
+	}
+
+	match block_on(builder().sample_count(3).build()) {
+		Err(RendererError::UnsupportedSampleCount(_, 3)) => {}
+		other => panic!("Expected unsupported sample count, got {:?}", other.err()),
+	}
+}
+
+#[test]
+#[ignore = "needs a GPU adapter, run with --ignored"]
+fn clear_color_and_screen_format() {
+	let camera = camera();
+	for format in [
+		wgpu::TextureFormat::Rgba8UnormSrgb,
+		wgpu::TextureFormat::Bgra8UnormSrgb,
+	] {
+		let mut renderer = block_on(
+			Renderer::builder(WIDTH, HEIGHT)
+				.fallback_adapter(true)
+				.screen_format(format)
+				.clear_color(Color::new(1.0, 0.0, 0.0, 1.0))
+				.build(),
+		)
+		.unwrap();
+		assert_eq!(renderer.screen_format(), format);
+
+		let frame = render(&mut renderer, &mut Scene::new(), &camera);
+		assert_eq!(
+			*frame.get_pixel(0, 0),
+			Rgba([255, 0, 0, 255]),
+			"{:?}",
+			format
+		);
+	}
+}
+
+#[test]
+#[ignore = "needs a GPU adapter, run with --ignored"]
+fn post_effects() {
+	let mut renderer = renderer();
+	renderer.set_clear_color(Color::new(0.5, 0.5, 0.5, 1.0));
+	let mut scene = Scene::new();
+	let camera = camera();
+	let plain = render(&mut renderer, &mut scene, &camera);
+
+	let vignette = renderer.add_post_effect(PostEffect::vignette(0.8, 0.3));
+	let frame = render(&mut renderer, &mut scene, &camera);
+	let centre = frame.get_pixel(WIDTH / 2, HEIGHT / 2);
+	let corner = frame.get_pixel(0, 0);
+	assert_eq!(centre, plain.get_pixel(WIDTH / 2, HEIGHT / 2));
+	assert!(corner[0] < centre[0] / 2, "{:?} {:?}", corner, centre);
+
+	// Effects run in order, each reading the one before
+	renderer.set_clear_color(Color::new(1.0, 0.0, 0.0, 1.0));
+	let invert = renderer.add_post_effect(PostEffect::new(
+		"
+		[[stage(fragment)]]
+		fn fs_main(in: PostInput) -> [[location(0)]] vec4<f32> {
+			let color = sample_color(in.uv);
+			return vec4<f32>(1.0 - color.rgb, color.a);
+		}
+		",
+	));
+	renderer.post_effect_mut(vignette).unwrap().enabled = false;
+	let frame = render(&mut renderer, &mut scene, &camera);
+	assert_eq!(*frame.get_pixel(0, 0), Rgba([0, 255, 255, 255]));
+
+	assert!(renderer.remove_post_effect(invert).is_some());
+	assert!(renderer.post_effect(invert).is_none());
+	let frame = render(&mut renderer, &mut scene, &camera);
+	assert_eq!(*frame.get_pixel(0, 0), Rgba([255, 0, 0, 255]));
+}
+
+#[test]
+#[ignore = "needs a GPU adapter, run with --ignored"]
+fn post_effect_reads_depth() {
+	let mut renderer = renderer();
+	let mut scene = Scene::new();
+	let mut cube = Mesh::new(
+		shaded_cube(),
+		BasicMaterial::new(Color::new(0.9, 0.3, 0.1, 1.0)),
+	);
+	cube.transform = cube_transform(0.0);
+	scene.add(cube);
+	let camera = camera();
+
+	// White wherever something was drawn in front of the far plane
+	renderer.add_post_effect(PostEffect::new(
+		"
+		[[stage(fragment)]]
+		fn fs_main(in: PostInput) -> [[location(0)]] vec4<f32> {
+			let drawn = select(0.0, 1.0, load_depth(in.uv) < 1.0);
+			return vec4<f32>(vec3<f32>(drawn), 1.0);
+		}
+		",
+	));
+	let sample_counts: Vec<_> = [1, 4]
+		.into_iter()
+		.filter(|&count| renderer.supports_sample_count(count))
+		.collect();
+	for sample_count in sample_counts {
+		renderer.set_sample_count(sample_count).unwrap();
+		let frame = render(&mut renderer, &mut scene, &camera);
+		let white = Rgba([255, 255, 255, 255]);
+		let black = Rgba([0, 0, 0, 255]);
+		assert_eq!(*frame.get_pixel(WIDTH / 2, HEIGHT / 2), white);
+		assert_eq!(*frame.get_pixel(0, 0), black);
+	}
+}
+
+#[test]
+#[ignore = "needs a GPU adapter, run with --ignored"]
+fn hdr_tonemapping() {
+	let mut renderer = block_on(
+		Renderer::builder(WIDTH, HEIGHT)
+			.fallback_adapter(true)
+			.hdr()
+			.clear_color(Color::new(4.0, 1.0, 0.25, 1.0))
+			.build(),
+	)
+	.unwrap();
+	assert_eq!(renderer.screen_format(), TargetFormat::HDR_FORMAT);
+	assert_eq!(renderer.tonemapping(), Tonemapping::Aces);
+	let mut scene = Scene::new();
+	let camera = camera();
+
+	// Without tonemapping everything brighter than white clips
+	renderer.set_tonemapping(Tonemapping::None);
+	let pixel = *render(&mut renderer, &mut scene, &camera).get_pixel(0, 0);
+	assert_eq!(pixel[0], 255);
+	assert_eq!(pixel[1], 255);
+
+	renderer.set_tonemapping(Tonemapping::Reinhard);
+	let pixel = *render(&mut renderer, &mut scene, &camera).get_pixel(0, 0);
+	assert!(pixel[0] < 255, "{:?}", pixel);
+	assert!(pixel[0] > pixel[1] && pixel[1] > pixel[2], "{:?}", pixel);
+
+	// Exposure scales colors before tonemapping
+	renderer.set_tonemapping(Tonemapping::None);
+	renderer.set_exposure(0.25);
+	let pixel = *render(&mut renderer, &mut scene, &camera).get_pixel(0, 0);
+	assert_eq!(pixel[0], 255);
+	assert!(pixel[1] < 255, "{:?}", pixel);
+}
+
+#[test]
+#[ignore = "needs a GPU adapter, run with --ignored"]
+fn render_target_texture() {
+	let mut renderer = renderer();
+	let camera = camera();
+	let target = renderer.create_render_target(32, 32);
+	assert_eq!(target.target().format, renderer.screen_format());
+
+	// Red from the scene drawn into the target, on a blue background. The cube is lit, so the
+	// checks only look at which channel dominates.
+	renderer.set_clear_color(Color::new(1.0, 0.0, 0.0, 1.0));
+	renderer
+		.render_to_target(&mut Scene::new(), &camera, &target)
+		.unwrap();
+	let mut scene = Scene::new();
+	let texture_id = scene.add_texture(Texture::from_render_target(&target));
+	let mut cube = Mesh::new(shaded_cube(), TextureMaterial::new(texture_id));
+	cube.transform = cube_transform(0.0);
+	scene.add(cube);
+	renderer.set_clear_color(Color::new(0.0, 0.0, 1.0, 1.0));
+	let frame = render(&mut renderer, &mut scene, &camera);
+	let centre = *frame.get_pixel(WIDTH / 2, HEIGHT / 2);
+	assert!(
+		centre[0] > 2 * centre[1] && centre[0] > 2 * centre[2],
+		"{:?}",
+		centre
+	);
+	assert_eq!(*frame.get_pixel(0, 0), Rgba([0, 0, 255, 255]));
+
+	// Drawing the scene into its own target leaves out the cube showing it
+	renderer.set_clear_color(Color::new(0.0, 1.0, 0.0, 1.0));
+	renderer
+		.render_to_target(&mut scene, &camera, &target)
+		.unwrap();
+	renderer.set_clear_color(Color::new(0.0, 0.0, 1.0, 1.0));
+	let frame = render(&mut renderer, &mut scene, &camera);
+	let centre = *frame.get_pixel(WIDTH / 2, HEIGHT / 2);
+	assert!(
+		centre[1] > 2 * centre[0] && centre[1] > 2 * centre[2],
+		"{:?}",
+		centre
+	);
+}
+
+#[test]
+#[ignore = "needs a GPU adapter, run with --ignored"]
+fn split_screen_viewports() {
+	let mut renderer = renderer();
+	let mut scene = Scene::new();
+	let mut cube = Mesh::new(
+		shaded_cube(),
+		BasicMaterial::new(Color::new(1.0, 1.0, 1.0, 1.0)),
+	);
+	cube.transform = cube_transform(0.0);
+	scene.add(cube);
+	renderer.set_clear_color(Color::new(0.0, 0.0, 1.0, 1.0));
+
+	// The left view looks at the cube and the right one away from it
+	let mut left = camera();
+	let mut right = camera();
+	right.rotate(0.0, PI, 0.0);
+	let [left_viewport, right_viewport]: [Viewport; 2] =
+		Viewport::grid(WIDTH, HEIGHT, 2, 1).try_into().unwrap();
+	for _ in 0..3 {
+		let mut frame = renderer.begin_frame();
+		frame.render(&mut scene, &mut left, left_viewport).unwrap();
+		frame
+			.render(&mut scene, &mut right, right_viewport)
+			.unwrap();
+	}
+	assert_eq!(left.width(), (WIDTH / 2) as f32);
+	assert_eq!(left.height(), HEIGHT as f32);
+
+	let frame = renderer.capture_frame().unwrap();
+	let background = Rgba([0, 0, 255, 255]);
+	assert_ne!(*frame.get_pixel(WIDTH / 4, HEIGHT / 2), background);
+	assert_eq!(*frame.get_pixel(WIDTH * 3 / 4, HEIGHT / 2), background);
+	// Nothing is drawn past the edge of the left viewport
+	assert_eq!(*frame.get_pixel(WIDTH / 2, 0), background);
+}
+
+#[test]
+#[ignore = "needs a GPU adapter, run with --ignored"]
+fn reverse_z_and_orthographic_cameras() {
+	let mut renderer = renderer();
+	// A red cube in front of a larger green one
+	let mut scene = Scene::new();
+	let mut red = Mesh::new(
+		shaded_cube(),
+		BasicMaterial::new(Color::new(1.0, 0.0, 0.0, 1.0)),
+	);
+	red.transform = cube_transform(0.0);
+	let red_id = scene.add(red);
+	let mut green = Mesh::new(
+		shaded_cube(),
+		BasicMaterial::new(Color::new(0.0, 1.0, 0.0, 1.0)),
+	);
+	green.transform =
+		Matrix4::from_translation(Vector3::new(0.0, 0.0, 20.0)) * Matrix4::from_scale(4.0);
+	scene.add(green);
+	// Both are lit, so the checks only look at which channel dominates
+	let is_red = |pixel: Rgba<u8>| pixel[0] > 2 * pixel[1];
+
+	let camera = camera()
+		.with_clip_planes(0.1, f32::INFINITY)
+		.with_reverse_z(true);
+	let frame = render(&mut renderer, &mut scene, &camera);
+	let centre = *frame.get_pixel(WIDTH / 2, HEIGHT / 2);
+	assert!(is_red(centre), "{:?}", centre);
+	let picked = renderer
+		.pick(&mut scene, &camera, (WIDTH / 2) as _, (HEIGHT / 2) as _)
+		.unwrap();
+	assert_eq!(picked, Some(red_id));
+
+	let mut camera = OrthographicCamera::new().with_view_height(12.0);
+	camera.resize(WIDTH as _, HEIGHT as _);
+	for _ in 0..3 {
+		renderer.render_to_buffer(&mut scene, &camera).unwrap();
+	}
+	let frame = renderer.capture_frame().unwrap();
+	let centre = *frame.get_pixel(WIDTH / 2, HEIGHT / 2);
+	assert!(is_red(centre), "{:?}", centre);
+	// The green cube isn't shrunk by distance, so it shows well around the red one
+	let edge = *frame.get_pixel(WIDTH * 3 / 10, HEIGHT / 2);
+	assert!(edge[1] > 2 * edge[0], "{:?}", edge);
+}
@@ -0,0 +1,111 @@
+use byd::{
+	BasicMaterial, Color, Geometry, HandleAllocator, Light, Mesh, Scene, SimpleProgram,
+	SimpleVertex, Texture, DEFAULT_TEXTURE,
+};
+use cgmath::Vector3;
+
+fn cube() -> Mesh<SimpleVertex> {
+	Mesh::new(
+		Geometry::cube(),
+		BasicMaterial::new(Color::new(1.0, 1.0, 1.0, 1.0)),
+	)
+}
+
+#[test]
+fn freed_handles_are_stale() {
+	let mut handles = HandleAllocator::new();
+	let a = handles.allocate();
+	let b = handles.allocate();
+	assert_eq!(handles.len(), 2);
+
+	assert!(handles.free(a));
+	assert!(!handles.contains(a));
+	assert!(!handles.free(a));
+
+	// The index is reused, but the handle is different
+	let c = handles.allocate();
+	assert_eq!(c.index(), a.index());
+	assert_ne!(c, a);
+	assert!(handles.contains(b) && handles.contains(c));
+	assert_eq!(handles.len(), 2);
+}
+
+#[test]
+fn scenes_allocate_their_own_ids() {
+	let mut main = Scene::new();
+	let mut preview = Scene::new();
+
+	let main_texture =
+		main.add_texture(Texture::from_image_bytes(include_bytes!("../assets/pixel.png")).unwrap());
+	let preview_texture = preview
+		.add_texture(Texture::from_image_bytes(include_bytes!("../assets/pixel.png")).unwrap());
+	assert_ne!(main_texture, DEFAULT_TEXTURE);
+	assert_eq!(main_texture, preview_texture);
+
+	assert_eq!(main.add(cube()), preview.add(cube()));
+}
+
+#[test]
+fn removed_object_ids_are_not_reused() {
+	let mut scene = Scene::new();
+	let old = scene.add(cube());
+	scene.remove(old);
+	assert!(!scene.contains(old));
+
+	let new = scene.add(cube());
+	assert!(scene.contains(new));
+	assert!(!scene.contains(old));
+	assert!(scene.get(old).is_none());
+
+	// Removing a stale ID leaves the new object alone
+	scene.remove(old);
+	assert!(scene.contains(new));
+}
+
+#[test]
+fn removed_light_ids_are_not_reused() {
+	let mut scene = Scene::new();
+	let light = Light::directional(
+		Vector3::new(0.0, -1.0, 0.0),
+		Vector3::new(1.0, 1.0, 1.0),
+		1.0,
+	);
+	let old = scene.add_light(light);
+	scene.remove_light(old);
+
+	let new = scene.add_light(light);
+	assert!(scene.light(old).is_none());
+	assert!(scene.light(new).is_some());
+	assert!(scene.remove_light(old).is_none());
+}
+
+#[test]
+fn removed_texture_ids_are_not_reused() {
+	let mut scene = Scene::new();
+	let texture = || Texture::from_image_bytes(include_bytes!("../assets/pixel.png")).unwrap();
+	let old = scene.add_texture(texture());
+	assert!(scene.remove_texture(old).is_some());
+	assert!(scene.remove_texture(old).is_none());
+
+	let new = scene.add_texture(texture());
+	assert_eq!(new.index(), old.index());
+	assert_ne!(new, old);
+	assert!(scene.remove_texture(old).is_none());
+
+	// Materials fall back to the default texture, so it stays
+	assert!(scene.remove_texture(DEFAULT_TEXTURE).is_none());
+}
+
+#[test]
+fn removed_program_ids_are_not_reused() {
+	let mut scene = Scene::new();
+	let old = scene.add_program(SimpleProgram::<SimpleVertex>::new());
+	assert!(scene.remove_program(old).is_some());
+	assert!(scene.remove_program(old).is_none());
+
+	let new = scene.add_program(SimpleProgram::<SimpleVertex>::new());
+	assert_eq!(new.index(), old.index());
+	assert_ne!(new, old);
+	assert!(scene.remove_program(old).is_none());
+	assert!(scene.remove_program(new).is_some());
+}
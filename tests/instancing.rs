@@ -0,0 +1,45 @@
+use byd::{
+	BasicMaterial, Color, Geometry, Instance, InstancedMesh, Mesh, SceneObject, SimpleVertex,
+};
+use cgmath::{Matrix4, Vector3};
+use std::mem::size_of;
+
+#[test]
+fn instance_layout_follows_vertex_attributes() {
+	let layout = Instance::buffer_layout();
+	assert_eq!(layout.array_stride, size_of::<Instance>() as u64);
+	assert_eq!(layout.step_mode, wgpu::VertexStepMode::Instance);
+
+	let locations: Vec<_> = layout
+		.attributes
+		.iter()
+		.map(|a| a.shader_location)
+		.collect();
+	assert_eq!(locations, vec![8, 9, 10, 11, 12]);
+	let offsets: Vec<_> = layout.attributes.iter().map(|a| a.offset).collect();
+	assert_eq!(offsets, vec![0, 16, 32, 48, 64]);
+}
+
+#[test]
+fn instanced_mesh_tracks_instances() {
+	let material = BasicMaterial::new(Color::new(1.0, 1.0, 1.0, 1.0));
+	let mut mesh: InstancedMesh<SimpleVertex> =
+		InstancedMesh::new(Geometry::cube(), material, vec![Instance::default(); 3]);
+	assert!(mesh.instanced());
+	assert_eq!(mesh.instances().len(), 3);
+
+	let transform = Matrix4::from_translation(Vector3::new(1.0, 2.0, 3.0));
+	mesh.instances_mut()
+		.push(Instance::new(transform).with_color(Color::new(1.0, 0.0, 0.0, 1.0)));
+	assert_eq!(mesh.instances().len(), 4);
+	assert_eq!(mesh.instances()[3].transform, transform);
+
+	mesh.set_instances(vec![]);
+	assert!(mesh.instances().is_empty());
+
+	let plain = Mesh::new(
+		Geometry::<SimpleVertex>::cube(),
+		BasicMaterial::new(Color::new(1.0, 1.0, 1.0, 1.0)),
+	);
+	assert!(!plain.instanced());
+}
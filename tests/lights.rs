@@ -0,0 +1,197 @@
+use byd::pipelines::{LightUniform, LightsUniform, MAX_LIGHTS, MAX_SHADOW_MAPS};
+use byd::{Camera, FreeCamera, Light, LightKind, Scene, SHADOW_CASCADES};
+use cgmath::{Deg, Matrix4, Point3, Rad, Vector3, Vector4};
+
+fn white() -> Vector3<f32> {
+	Vector3::new(1.0, 1.0, 1.0)
+}
+
+/// Camera at the origin looking down +Z.
+fn camera() -> FreeCamera {
+	let mut camera = FreeCamera::new();
+	camera.resize(160.0, 120.0);
+	camera
+}
+
+/// Whether a point lands inside a shadow map.
+fn in_shadow_map(matrix: Matrix4<f32>, point: Point3<f32>) -> bool {
+	let clip = matrix * point.to_homogeneous();
+	let ndc = clip.truncate() / clip.w;
+	ndc.x.abs() <= 1.0 && ndc.y.abs() <= 1.0 && (0.0..=1.0).contains(&ndc.z)
+}
+
+#[test]
+fn directional_light_is_normalized() {
+	let light = Light::directional(Vector3::new(0.0, -2.0, 0.0), white(), 1.0);
+	assert_eq!(
+		light.kind,
+		LightKind::Directional {
+			direction: Vector3::new(0.0, -1.0, 0.0)
+		}
+	);
+}
+
+#[test]
+fn range_only_applies_to_positioned_lights() {
+	let point = Light::point(Point3::new(1.0, 2.0, 3.0), white(), 1.0).with_range(5.0);
+	assert!(matches!(point.kind, LightKind::Point { range, .. } if range == 5.0));
+
+	let directional = Light::directional(Vector3::new(1.0, 0.0, 0.0), white(), 1.0);
+	assert_eq!(directional.with_range(5.0), directional);
+}
+
+#[test]
+fn light_uniform_packing() {
+	let point = LightUniform::from(
+		&Light::point(Point3::new(1.0, 2.0, 3.0), Vector3::new(1.0, 0.5, 0.0), 2.0)
+			.with_range(10.0),
+	);
+	assert_eq!(point.position, Vector4::new(1.0, 2.0, 3.0, 1.0));
+	assert_eq!(point.direction.w, 10.0);
+	assert_eq!(point.color, Vector4::new(2.0, 1.0, 0.0, 1.0));
+
+	let spot = LightUniform::from(&Light::spot(
+		Point3::new(0.0, 0.0, 0.0),
+		Vector3::new(0.0, 0.0, 3.0),
+		Rad(0.5),
+		white(),
+		1.0,
+	));
+	assert_eq!(spot.position.w, 2.0);
+	assert_eq!(spot.direction, Vector4::new(0.0, 0.0, 1.0, 0.0));
+	assert!((spot.cone.x - 0.45f32.cos()).abs() < 1e-6);
+	assert!((spot.cone.y - 0.5f32.cos()).abs() < 1e-6);
+}
+
+#[test]
+fn lights_uniform_ignores_extra_lights() {
+	let lights = vec![Light::point(Point3::new(0.0, 0.0, 0.0), white(), 1.0); MAX_LIGHTS + 4];
+	let uniform = LightsUniform::new(Vector3::new(0.1, 0.2, 0.3), &lights, &camera(), 100.0);
+	assert_eq!(uniform.count as usize, MAX_LIGHTS);
+	assert_eq!(uniform.ambient, Vector4::new(0.1, 0.2, 0.3, 1.0));
+}
+
+#[test]
+fn shadow_maps_are_assigned_in_order() {
+	let sun = Light::directional(Vector3::new(0.0, -1.0, 0.5), white(), 1.0).with_shadows();
+	let spot = Light::spot(
+		Point3::new(0.0, 5.0, 5.0),
+		Vector3::new(0.0, -1.0, 0.0),
+		Deg(30.0),
+		white(),
+		1.0,
+	)
+	.with_shadows();
+	let point = Light::point(Point3::new(0.0, 1.0, 0.0), white(), 1.0).with_shadows();
+	let unshadowed = Light::directional(Vector3::new(1.0, -1.0, 0.0), white(), 1.0);
+
+	let lights = [sun, spot, point, unshadowed, sun];
+	let uniform = LightsUniform::new(Vector3::new(0.0, 0.0, 0.0), &lights, &camera(), 50.0);
+	let shadows: Vec<_> = uniform.lights[..lights.len()]
+		.iter()
+		.map(|light| (light.shadow.x, light.shadow.y))
+		.collect();
+	let cascades = SHADOW_CASCADES as f32;
+	assert_eq!(
+		shadows,
+		vec![
+			(0.0, cascades),
+			(cascades, 1.0),
+			(-1.0, 0.0),
+			(-1.0, 0.0),
+			(cascades + 1.0, cascades),
+		]
+	);
+	assert_eq!(uniform.shadow_maps as usize, 2 * SHADOW_CASCADES + 1);
+	assert!(uniform.shadow_maps as usize <= MAX_SHADOW_MAPS);
+
+	// Lights that don't fit are left without shadows
+	let uniform = LightsUniform::new(Vector3::new(0.0, 0.0, 0.0), &[sun; 4], &camera(), 50.0);
+	assert_eq!(
+		uniform.lights[MAX_SHADOW_MAPS / SHADOW_CASCADES].shadow.x,
+		-1.0
+	);
+}
+
+#[test]
+fn cascades_cover_the_view() {
+	let camera = camera();
+	let direction = Vector3::new(0.3, -1.0, 0.2);
+	let sun = Light::directional(direction, white(), 1.0);
+	let cascades = sun.shadow_matrices(&camera, 60.0);
+	assert_eq!(cascades.len(), SHADOW_CASCADES);
+
+	let near = Point3::new(0.0, 0.0, 2.0);
+	let far = Point3::new(0.0, 0.0, 55.0);
+	assert!(in_shadow_map(cascades[0], near));
+	assert!(!in_shadow_map(cascades[0], far));
+	assert!(in_shadow_map(cascades[SHADOW_CASCADES - 1], far));
+
+	// Casters between the light and the view are kept in the map
+	let above = near - direction * 30.0;
+	assert!(in_shadow_map(cascades[0], above));
+
+	let spot = Light::spot(
+		Point3::new(0.0, 5.0, 0.0),
+		Vector3::new(0.0, -1.0, 0.0),
+		Deg(30.0),
+		white(),
+		1.0,
+	);
+	let matrices = spot.shadow_matrices(&camera, 60.0);
+	assert_eq!(matrices.len(), 1);
+	assert!(in_shadow_map(matrices[0], Point3::new(0.5, 0.0, 0.5)));
+	assert!(!in_shadow_map(matrices[0], Point3::new(5.0, 0.0, 0.0)));
+}
+
+#[test]
+fn cascades_cover_an_infinite_reverse_z_view() {
+	let camera = camera()
+		.with_clip_planes(0.1, f32::INFINITY)
+		.with_reverse_z(true);
+	let direction = Vector3::new(0.3, -1.0, 0.2);
+	let sun = Light::directional(direction, white(), 1.0);
+	let cascades = sun.shadow_matrices(&camera, 60.0);
+	assert!(cascades
+		.iter()
+		.flat_map(|matrix| matrix.as_ref() as &[f32; 16])
+		.all(|value| value.is_finite()));
+
+	let near = Point3::new(0.0, 0.0, 2.0);
+	let far = Point3::new(0.0, 0.0, 55.0);
+	assert!(in_shadow_map(cascades[0], near));
+	assert!(!in_shadow_map(cascades[0], far));
+	assert!(in_shadow_map(cascades[SHADOW_CASCADES - 1], far));
+}
+
+#[test]
+fn scene_lights() {
+	let mut scene = Scene::new();
+	assert_eq!(scene.lights().count(), 0);
+
+	let sun = scene.add_light(Light::directional(
+		Vector3::new(0.0, -1.0, 0.0),
+		white(),
+		1.0,
+	));
+	let lamp = scene.add_light(Light::spot(
+		Point3::new(0.0, 3.0, 0.0),
+		Vector3::new(0.0, -1.0, 0.0),
+		Deg(30.0),
+		white(),
+		5.0,
+	));
+	let ids: Vec<_> = scene.lights().map(|(id, _)| id).collect();
+	assert_eq!(ids, vec![sun, lamp]);
+
+	scene.light_mut(lamp).unwrap().intensity = 10.0;
+	assert_eq!(scene.light(lamp).unwrap().intensity, 10.0);
+
+	assert!(scene.remove_light(sun).is_some());
+	assert!(scene.remove_light(sun).is_none());
+	assert!(scene.light(sun).is_none());
+	assert_eq!(scene.lights().count(), 1);
+
+	scene.set_ambient_light(Vector3::new(0.0, 0.0, 0.0));
+	assert_eq!(scene.ambient_light(), Vector3::new(0.0, 0.0, 0.0));
+}
@@ -0,0 +1,103 @@
+use byd::{
+	BasicMaterial, BoundingBox, Camera, Color, FreeCamera, Geometry, Instance, InstancedMesh, Mesh,
+	Ray, Scene, SimpleVertex,
+};
+use cgmath::{InnerSpace, Matrix4, Point3, Vector3};
+
+fn cube(position: Vector3<f32>) -> Mesh<SimpleVertex> {
+	let mut cube = Mesh::new(
+		Geometry::cube(),
+		BasicMaterial::new(Color::new(1.0, 1.0, 1.0, 1.0)),
+	);
+	cube.transform = Matrix4::from_translation(position);
+	cube
+}
+
+#[test]
+fn ray_hits_triangles_and_boxes() {
+	let ray = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 2.0));
+	assert_eq!(ray.direction, Vector3::unit_z());
+
+	let triangle = [
+		Point3::new(-1.0, -1.0, 0.0),
+		Point3::new(1.0, -1.0, 0.0),
+		Point3::new(0.0, 1.0, 0.0),
+	];
+	assert_eq!(
+		ray.intersect_triangle(triangle).map(|hit| hit.distance),
+		Some(5.0)
+	);
+	let behind = Ray::new(Point3::new(0.0, 0.0, 5.0), Vector3::unit_z());
+	assert_eq!(behind.intersect_triangle(triangle), None);
+	let beside = Ray::new(Point3::new(3.0, 0.0, -5.0), Vector3::unit_z());
+	assert_eq!(beside.intersect_triangle(triangle), None);
+
+	let bounds = BoundingBox::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+	assert_eq!(ray.intersect_box(&bounds), Some(4.0));
+	assert_eq!(beside.intersect_box(&bounds), None);
+	let inside = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::unit_x());
+	assert_eq!(inside.intersect_box(&bounds), Some(0.0));
+}
+
+#[test]
+fn screen_ray_goes_through_the_pixel() {
+	let mut camera = FreeCamera::new();
+	camera.resize(800.0, 600.0);
+
+	// The camera looks down +Z
+	let center = Ray::from_screen(&camera, 400.0, 300.0, 800.0, 600.0);
+	assert!((center.direction - Vector3::unit_z()).magnitude() < 1e-4);
+
+	let top_left = Ray::from_screen(&camera, 0.0, 0.0, 800.0, 600.0);
+	assert!(top_left.direction.x < 0.0);
+	assert!(top_left.direction.y > 0.0);
+}
+
+#[test]
+fn raycast_finds_the_nearest_object() {
+	let mut scene = Scene::new();
+	let far = scene.add(cube(Vector3::new(0.0, 0.0, 20.0)));
+	let near = scene.add(cube(Vector3::new(0.0, 0.0, 10.0)));
+	scene.add(cube(Vector3::new(5.0, 0.0, 5.0)));
+
+	let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::unit_z());
+	let hit = scene.raycast(&ray).unwrap();
+	assert_eq!(hit.object, near);
+	assert!((hit.distance - 9.0).abs() < 1e-4);
+	assert!((hit.position - Point3::new(0.0, 0.0, 9.0)).magnitude() < 1e-4);
+
+	scene.remove(near);
+	assert_eq!(scene.raycast(&ray).unwrap().object, far);
+
+	let miss = Ray::new(Point3::new(0.0, 10.0, 0.0), Vector3::unit_z());
+	assert_eq!(scene.raycast(&miss), None);
+}
+
+#[test]
+fn raycast_uses_world_transforms() {
+	let mut scene = Scene::new();
+	let parent = scene.add(cube(Vector3::new(0.0, 0.0, 10.0)));
+	let mut child = cube(Vector3::new(0.0, 5.0, 0.0));
+	child.transform = child.transform * Matrix4::from_scale(2.0);
+	let child = scene.add_child(parent, child).unwrap();
+
+	// The child's cube spans y 3..7 and z 8..12, so the ray hits its front at z = 8
+	let ray = Ray::new(Point3::new(0.0, 6.5, 0.0), Vector3::unit_z());
+	let hit = scene.raycast(&ray).unwrap();
+	assert_eq!(hit.object, child);
+	assert!((hit.distance - 8.0).abs() < 1e-4);
+
+	let instances = vec![
+		Instance::new(Matrix4::from_translation(Vector3::new(-3.0, 0.0, 0.0))),
+		Instance::new(Matrix4::from_translation(Vector3::new(3.0, 0.0, 0.0))),
+	];
+	let mesh = scene.add(InstancedMesh::new(
+		Geometry::<SimpleVertex>::cube(),
+		BasicMaterial::new(Color::new(1.0, 1.0, 1.0, 1.0)),
+		instances,
+	));
+	let ray = Ray::new(Point3::new(3.0, 0.0, -10.0), Vector3::unit_z());
+	let hit = scene.raycast(&ray).unwrap();
+	assert_eq!(hit.object, mesh);
+	assert!((hit.distance - 9.0).abs() < 1e-4);
+}
@@ -0,0 +1,170 @@
+use byd::{
+	BasicMaterial, Bvh, Camera, Color, FreeCamera, Geometry, Mesh, Ray, Scene, SimpleVertex,
+};
+use cgmath::{EuclideanSpace, InnerSpace, Matrix4, Point3, Rad, Vector3};
+use std::rc::Rc;
+
+/// A bumpy grid of `size` by `size` quads in the XZ plane.
+fn terrain(size: u32) -> Vec<[Point3<f32>; 3]> {
+	let point = |x: u32, z: u32| {
+		let (x, z) = (x as f32, z as f32);
+		Point3::new(x, (x * 0.7).sin() + (z * 0.4).cos(), z)
+	};
+	let mut triangles = Vec::new();
+	for x in 0..size {
+		for z in 0..size {
+			triangles.push([point(x, z), point(x, z + 1), point(x + 1, z)]);
+			triangles.push([point(x + 1, z), point(x, z + 1), point(x + 1, z + 1)]);
+		}
+	}
+	triangles
+}
+
+#[test]
+fn bvh_matches_testing_every_triangle() {
+	let triangles = terrain(24);
+	let bvh = Bvh::new(triangles.clone());
+	assert_eq!(bvh.triangles().len(), triangles.len());
+
+	for i in 0..50 {
+		let t = i as f32;
+		let ray = Ray::new(
+			Point3::new(t * 0.47 % 24.0, 10.0, t * 0.31 % 24.0),
+			Vector3::new((t * 0.9).sin(), -2.0, (t * 1.3).cos()),
+		);
+		let expected = triangles
+			.iter()
+			.filter_map(|&triangle| ray.intersect_triangle(triangle))
+			.map(|hit| hit.distance)
+			.min_by(f32::total_cmp);
+		let hit = bvh.raycast(&ray);
+		assert_eq!(hit.map(|hit| hit.distance), expected, "ray {}", i);
+		// The index is the triangle's place in the list, not in the BVH
+		if let Some(hit) = hit {
+			assert_eq!(triangles[hit.index], hit.triangle, "ray {}", i);
+		}
+	}
+
+	let miss = Ray::new(Point3::new(-5.0, 10.0, -5.0), Vector3::unit_y());
+	assert_eq!(bvh.raycast(&miss), None);
+	assert_eq!(Bvh::new(vec![]).raycast(&miss), None);
+}
+
+#[test]
+fn hits_have_normals_and_barycentric_coordinates() {
+	let vertex = |x, y| SimpleVertex {
+		position: Point3::new(x, y, 0.0),
+		..Default::default()
+	};
+	let mut triangle = Mesh::new(
+		Geometry::new(vec![vertex(0.0, 0.0), vertex(0.0, 2.0), vertex(2.0, 0.0)]),
+		BasicMaterial::new(Color::new(1.0, 1.0, 1.0, 1.0)),
+	);
+	// Turned to face +X, and moved to x = 10
+	triangle.transform = Matrix4::from_translation(Vector3::new(10.0, 0.0, 0.0))
+		* Matrix4::from_angle_y(Rad(std::f32::consts::FRAC_PI_2));
+	let mut scene = Scene::new();
+	let id = scene.add(triangle);
+
+	let ray = Ray::new(Point3::new(0.0, 0.5, -0.5), Vector3::unit_x());
+	let hit = scene.raycast(&ray).unwrap();
+	assert_eq!(hit.object, id);
+	assert!((hit.distance - 10.0).abs() < 1e-4);
+	assert!((hit.position - Point3::new(10.0, 0.5, -0.5)).magnitude() < 1e-4);
+	assert!((hit.normal - -Vector3::unit_x()).magnitude() < 1e-4);
+	assert!((hit.barycentric - Vector3::new(0.5, 0.25, 0.25)).magnitude() < 1e-4);
+
+	// Hit from the other side, the normal still faces the ray
+	let back = Ray::new(Point3::new(20.0, 0.5, -0.5), -Vector3::unit_x());
+	let hit = scene.raycast(&back).unwrap();
+	assert!((hit.normal - Vector3::unit_x()).magnitude() < 1e-4);
+}
+
+#[test]
+fn small_triangles_and_zero_directions() {
+	// Small enough that an unscaled epsilon would treat every ray as parallel
+	let size = 1e-4;
+	let triangle = [
+		Point3::new(0.0, 0.0, 0.0),
+		Point3::new(0.0, size, 0.0),
+		Point3::new(size, 0.0, 0.0),
+	];
+	let ray = Ray::new(Point3::new(size / 4.0, size / 4.0, -1.0), Vector3::unit_z());
+	let hit = ray.intersect_triangle(triangle).unwrap();
+	assert!((hit.distance - 1.0).abs() < 1e-4);
+
+	let parallel = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::unit_x());
+	assert_eq!(parallel.intersect_triangle(triangle), None);
+
+	let zero = Ray::new(
+		Point3::new(size / 4.0, size / 4.0, 0.0),
+		Vector3::new(0.0, 0.0, 0.0),
+	);
+	assert_eq!(zero.direction, Vector3::new(0.0, 0.0, 0.0));
+	assert_eq!(zero.intersect_triangle(triangle), None);
+}
+
+#[test]
+fn ground_height_from_a_downward_ray() {
+	let vertices = terrain(16)
+		.into_iter()
+		.flatten()
+		.map(|position| SimpleVertex {
+			position,
+			..Default::default()
+		})
+		.collect();
+	let mut scene = Scene::new();
+	scene.add(Mesh::new(
+		Geometry::new(vertices),
+		BasicMaterial::new(Color::new(1.0, 1.0, 1.0, 1.0)),
+	));
+
+	// Directly on a grid point, so the height is exact
+	let ray = Ray::new(Point3::new(4.0, 100.0, 6.0), -Vector3::unit_y());
+	let hit = scene.raycast(&ray).unwrap();
+	let height = (4.0f32 * 0.7).sin() + (6.0f32 * 0.4).cos();
+	assert!((hit.position.y - height).abs() < 1e-4);
+	assert!(hit.normal.y > 0.0);
+}
+
+#[test]
+fn hits_name_the_triangle_that_was_hit() {
+	let vertices = terrain(16)
+		.into_iter()
+		.flatten()
+		.map(|position| SimpleVertex {
+			position,
+			..Default::default()
+		})
+		.collect();
+	let geometry = Rc::new(Geometry::new(vertices));
+	let mut scene = Scene::new();
+	scene.add(Mesh::new(
+		geometry.clone(),
+		BasicMaterial::new(Color::new(1.0, 1.0, 1.0, 1.0)),
+	));
+
+	// The triangle and weights give back the point that was hit
+	let ray = Ray::new(Point3::new(7.3, 100.0, 2.6), -Vector3::unit_y());
+	let hit = scene.raycast(&ray).unwrap();
+	let corners = geometry.triangle(hit.triangle).unwrap();
+	let position = corners
+		.iter()
+		.zip([hit.barycentric.x, hit.barycentric.y, hit.barycentric.z])
+		.map(|(corner, weight)| corner.to_vec() * weight)
+		.sum::<Vector3<f32>>();
+	assert!((position - hit.position.to_vec()).magnitude() < 1e-4);
+}
+
+#[test]
+fn camera_screen_ray() {
+	let mut camera = FreeCamera::new();
+	camera.resize(640.0, 480.0);
+	camera.translate(1.0, 2.0, 3.0);
+
+	let ray = camera.screen_ray(320.0, 240.0);
+	assert!((ray.origin - Point3::new(1.0, 2.0, 3.0)).magnitude() < 0.5);
+	assert!((ray.direction - Vector3::unit_z()).magnitude() < 1e-4);
+	assert_eq!(ray, Ray::from_screen(&camera, 320.0, 240.0, 640.0, 480.0));
+}
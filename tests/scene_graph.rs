@@ -0,0 +1,95 @@
+use byd::{FreeCamera, Group, Scene, SceneError};
+use cgmath::{assert_relative_eq, Matrix4, Vector3};
+
+fn translation(x: f32) -> Matrix4<f32> {
+	Matrix4::from_translation(Vector3::new(x, 0.0, 0.0))
+}
+
+#[test]
+fn world_transform_combines_ancestors() {
+	let mut scene = Scene::new();
+	let tank = scene.add(Group::new(translation(10.0)));
+	let turret = scene.add_child(tank, Group::new(translation(1.0))).unwrap();
+	let barrel = scene
+		.add_child(turret, Group::new(Matrix4::from_scale(2.0)))
+		.unwrap();
+
+	assert_eq!(scene.parent(barrel), Some(turret));
+	assert_eq!(scene.children(tank), &[turret]);
+	assert_relative_eq!(
+		scene.world_transform(barrel).unwrap(),
+		translation(11.0) * Matrix4::from_scale(2.0)
+	);
+
+	// Moving the turret moves everything under it
+	scene.with_object_mut(turret, |turret: &mut Group| {
+		turret.transform = translation(3.0);
+	});
+	assert_relative_eq!(
+		scene.world_transform(barrel).unwrap(),
+		translation(13.0) * Matrix4::from_scale(2.0)
+	);
+	assert_relative_eq!(scene.world_transform(tank).unwrap(), translation(10.0));
+}
+
+#[test]
+fn reparenting_and_detaching() {
+	let mut scene = Scene::new();
+	let a = scene.add(Group::new(translation(1.0)));
+	let b = scene.add(Group::new(translation(2.0)));
+	let child = scene.add_child(a, Group::new(translation(5.0))).unwrap();
+
+	scene.set_parent(child, Some(b)).unwrap();
+	assert!(scene.children(a).is_empty());
+	assert_eq!(scene.children(b), &[child]);
+	assert_relative_eq!(scene.world_transform(child).unwrap(), translation(7.0));
+
+	scene.detach(child).unwrap();
+	assert_eq!(scene.parent(child), None);
+	assert!(scene.children(b).is_empty());
+	assert_relative_eq!(scene.world_transform(child).unwrap(), translation(5.0));
+}
+
+#[test]
+fn parenting_errors() {
+	let mut scene = Scene::new();
+	let a = scene.add(Group::default());
+	let b = scene.add_child(a, Group::default()).unwrap();
+
+	assert_eq!(scene.set_parent(a, Some(b)), Err(SceneError::Cycle(a)));
+	assert_eq!(scene.set_parent(a, Some(a)), Err(SceneError::Cycle(a)));
+
+	let removed = scene.add(Group::default());
+	scene.remove(removed);
+	assert_eq!(
+		scene.set_parent(b, Some(removed)),
+		Err(SceneError::MissingObject(removed))
+	);
+	assert!(scene.add_child(removed, Group::default()).is_err());
+	assert_eq!(scene.parent(b), Some(a));
+}
+
+#[test]
+fn remove_is_recursive() {
+	let mut scene = Scene::new();
+	let root = scene.add(Group::default());
+	let keep = scene.add_child(root, Group::default()).unwrap();
+	let branch = scene.add_child(root, Group::default()).unwrap();
+	let leaf = scene.add_child(branch, Group::default()).unwrap();
+
+	scene.remove(branch);
+	assert!(!scene.contains(branch));
+	assert!(!scene.contains(leaf));
+	assert_eq!(scene.children(root), &[keep]);
+}
+
+#[test]
+fn groups_are_not_drawn() {
+	let mut scene = Scene::new();
+	let group = scene.add(Group::default());
+	scene
+		.add_child(group, Group::new(translation(1.0)))
+		.unwrap();
+
+	assert!(scene.draw_order(&FreeCamera::new()).is_empty());
+}
@@ -0,0 +1,56 @@
+struct Camera {
+	view: mat4x4<f32>;
+	projection: mat4x4<f32>;
+};
+
+struct Actor {
+	color: vec4<f32>;
+	model: mat4x4<f32>;
+};
+
+struct VertexOutput {
+	[[builtin(position)]] position: vec4<f32>;
+	[[location(0)]] color: vec4<f32>;
+};
+
+[[group(0), binding(0)]]
+var<uniform> camera: Camera;
+[[group(0), binding(1)]]
+var<uniform> actor: Actor;
+
+[[stage(vertex)]]
+fn vs_main(
+	[[location(0)]] position: vec3<f32>,
+	[[location(1)]] color: vec4<f32>,
+) -> VertexOutput {
+	var out: VertexOutput;
+
+	out.position = camera.projection * camera.view * actor.model * vec4<f32>(position, 1.0);
+	out.color = color;
+
+	return out;
+}
+
+[[stage(vertex)]]
+fn vs_instanced(
+	[[location(0)]] position: vec3<f32>,
+	[[location(1)]] color: vec4<f32>,
+	[[location(8)]] model_0: vec4<f32>,
+	[[location(9)]] model_1: vec4<f32>,
+	[[location(10)]] model_2: vec4<f32>,
+	[[location(11)]] model_3: vec4<f32>,
+	[[location(12)]] instance_color: vec4<f32>,
+) -> VertexOutput {
+	var out: VertexOutput;
+
+	var model = actor.model * mat4x4<f32>(model_0, model_1, model_2, model_3);
+	out.position = camera.projection * camera.view * model * vec4<f32>(position, 1.0);
+	out.color = color * instance_color;
+
+	return out;
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	return in.color;
+}
@@ -0,0 +1,34 @@
+use byd::SlotAllocator;
+
+#[test]
+fn slots_are_reused_after_being_freed() {
+	let mut slots = SlotAllocator::new();
+	assert!(slots.is_empty());
+
+	let a = slots.allocate();
+	let b = slots.allocate();
+	let c = slots.allocate();
+	assert_eq!((a, b, c), (0, 1, 2));
+
+	slots.free(b);
+	assert_eq!(slots.allocate(), b);
+	assert_eq!(slots.allocate(), 3);
+	assert_eq!(slots.len(), 4);
+}
+
+#[test]
+fn churn_doesnt_grow_slots() {
+	let mut slots = SlotAllocator::new();
+	let mut live: Vec<_> = (0..100).map(|_| slots.allocate()).collect();
+
+	// Far more adds and removes than the old fixed limit of 2048 objects
+	for _ in 0..10_000 {
+		slots.free(live.remove(0));
+		live.push(slots.allocate());
+	}
+
+	assert_eq!(slots.len(), 100);
+	live.sort_unstable();
+	live.dedup();
+	assert_eq!(live.len(), 100);
+}
@@ -0,0 +1,78 @@
+use byd::{
+	AlphaMode, BasicMaterial, Camera, Color, CustomMaterial, FreeCamera, Geometry, Handle,
+	LineMaterial, Material, Mesh, PbrMaterial, Scene, SimpleVertex, TextureMaterial,
+	DEFAULT_TEXTURE,
+};
+use cgmath::{Matrix4, Point3, Vector3};
+
+#[test]
+fn materials_default_to_opaque() {
+	let materials: [Box<dyn Material>; 5] = [
+		Box::new(BasicMaterial::new(Color::new(1.0, 1.0, 1.0, 0.5))),
+		Box::new(TextureMaterial::new(DEFAULT_TEXTURE)),
+		Box::new(PbrMaterial::default()),
+		Box::new(LineMaterial::new()),
+		Box::new(CustomMaterial::new(Handle::FIRST)),
+	];
+	for material in &materials {
+		assert_eq!(material.alpha_mode(), AlphaMode::Opaque);
+	}
+}
+
+#[test]
+fn materials_report_their_alpha_mode() {
+	let basic = BasicMaterial {
+		alpha_mode: AlphaMode::Blend,
+		..BasicMaterial::new(Color::new(1.0, 1.0, 1.0, 0.5))
+	};
+	let texture = TextureMaterial {
+		alpha_mode: AlphaMode::Mask,
+		..TextureMaterial::new(DEFAULT_TEXTURE)
+	};
+	let pbr = PbrMaterial {
+		alpha_mode: AlphaMode::Blend,
+		..PbrMaterial::default()
+	};
+	assert_eq!(Material::alpha_mode(&basic), AlphaMode::Blend);
+	assert_eq!(Material::alpha_mode(&texture), AlphaMode::Mask);
+	assert_eq!(Material::alpha_mode(&pbr), AlphaMode::Blend);
+}
+
+/// A small triangle `z` units in front of the default camera.
+fn triangle(z: f32, alpha_mode: AlphaMode) -> Mesh<SimpleVertex> {
+	let vertex = |x, y| SimpleVertex {
+		position: Point3::new(x, y, 0.0),
+		..Default::default()
+	};
+	let mut mesh = Mesh::new(
+		Geometry::new(vec![vertex(0.0, 0.0), vertex(0.0, 0.5), vertex(0.5, 0.0)]),
+		BasicMaterial {
+			alpha_mode,
+			..BasicMaterial::new(Color::new(1.0, 1.0, 1.0, 0.5))
+		},
+	);
+	mesh.transform = Matrix4::from_translation(Vector3::new(0.0, 0.0, z));
+	mesh
+}
+
+#[test]
+fn opaque_objects_draw_before_blended_objects_back_to_front() {
+	let mut scene = Scene::new();
+	let near = scene.add(triangle(2.0, AlphaMode::Blend));
+	let opaque_near = scene.add(triangle(3.0, AlphaMode::Opaque));
+	let far = scene.add(triangle(8.0, AlphaMode::Blend));
+	let middle = scene.add(triangle(5.0, AlphaMode::Blend));
+	let opaque_far = scene.add(triangle(10.0, AlphaMode::Opaque));
+	// Behind the camera, so it's culled
+	scene.add(triangle(-5.0, AlphaMode::Blend));
+
+	let mut camera = FreeCamera::new();
+	camera.resize(160.0, 120.0);
+	let order = scene.draw_order(&camera);
+
+	assert_eq!(order.len(), 5);
+	// Opaque objects sharing state may be drawn in either order
+	assert!(order[..2].contains(&opaque_near));
+	assert!(order[..2].contains(&opaque_far));
+	assert_eq!(order[2..], [far, middle, near]);
+}
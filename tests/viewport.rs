@@ -0,0 +1,34 @@
+use byd::Viewport;
+
+#[test]
+fn viewport_grid() {
+	let viewports = Viewport::grid(101, 50, 2, 2);
+	assert_eq!(
+		viewports,
+		vec![
+			Viewport::new(0, 0, 50, 25),
+			Viewport::new(50, 0, 51, 25),
+			Viewport::new(0, 25, 50, 25),
+			Viewport::new(50, 25, 51, 25),
+		]
+	);
+	assert!(viewports[1].contains(60.0, 10.0));
+	assert!(!viewports[1].contains(60.0, 30.0));
+
+	assert_eq!(
+		Viewport::new(80, 40, 50, 50).clamp(100, 60),
+		Some(Viewport::new(80, 40, 20, 20))
+	);
+	assert_eq!(Viewport::new(100, 0, 10, 10).clamp(100, 60), None);
+}
+
+#[test]
+fn viewport_edges_past_u32_max_saturate() {
+	let viewport = Viewport::new(u32::MAX - 10, u32::MAX - 10, 100, 100);
+	assert!(!viewport.contains(0.0, 0.0));
+	assert_eq!(
+		viewport.clamp(u32::MAX, u32::MAX),
+		Some(Viewport::new(u32::MAX - 10, u32::MAX - 10, 10, 10))
+	);
+	assert_eq!(viewport.clamp(100, 100), None);
+}